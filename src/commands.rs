@@ -0,0 +1,434 @@
+//! Scripting subcommands - search, status, clean and ls-files, and the indexing dry run
+//!
+//! Each command returns a report that serializes to the `--json` output and displays as
+//! the plain-text output. None of the local commands (`status`, `clean`, `ls-files`,
+//! `--index-only --dry-run`) create `.ace-tool/` or need API credentials.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::enhancer::session_store::SESSIONS_DIR_NAME;
+use crate::enhancer::templates::ENHANCE_TEMPLATE_FILE_NAME;
+use crate::index::upload_policy::UPLOAD_AUDIT_LOG_FILE_NAME;
+use crate::index::{
+    dry_run, list_files, DryRunEntry, FileListEntry, IndexManager, IndexSummary,
+    INDEX_LOCK_FILE_NAME,
+};
+use crate::mcp::prompts::PROMPTS_DIR_NAME;
+use crate::mcp::resources::has_index;
+use crate::tools::search_context::SearchContextArgs;
+use crate::tools::{SearchContextTool, ToolError};
+use crate::utils::project_detector::{ace_dir_path, ACE_DIR_NAME, INDEX_FILE_NAME};
+
+/// Hand-written files in `.ace-tool/` that `clean` keeps unless `--all` is given
+pub const PRESERVED_ENTRIES: [&str; 2] = [PROMPTS_DIR_NAME, ENHANCE_TEMPLATE_FILE_NAME];
+
+/// Whether `clean` keeps an entry of `.ace-tool/` unless `--all` is given: hand-written
+/// files, pending review sessions, and the upload audit log with its rotated files
+fn is_preserved(name: &str) -> bool {
+    PRESERVED_ENTRIES.contains(&name)
+        || name == SESSIONS_DIR_NAME
+        || name
+            .strip_prefix(UPLOAD_AUDIT_LOG_FILE_NAME)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+fn display_root(project_root: &Path) -> String {
+    project_root.to_string_lossy().replace('\\', "/")
+}
+
+/// A failed command, as reported in `--json` output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandError {
+    pub code: &'static str,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl From<&ToolError> for CommandError {
+    fn from(err: &ToolError) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+            retryable: err.retryable(),
+        }
+    }
+}
+
+/// Result of `search`
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchReport {
+    pub project_root: String,
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CommandError>,
+}
+
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.context, &self.error) {
+            (_, Some(error)) => write!(f, "Error: {}", error.message),
+            (Some(context), None) => f.write_str(context),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Index the project if needed, then search it
+pub async fn search(config: Arc<Config>, project_root: &Path, query: &str) -> SearchReport {
+    let project_root = display_root(project_root);
+    let result = SearchContextTool::new(config)
+        .execute(SearchContextArgs {
+            project_root_path: Some(project_root.clone()),
+            query: Some(query.to_string()),
+        })
+        .await;
+
+    let (context, error) = match result.error {
+        Some(ref err) => (None, Some(CommandError::from(err))),
+        None => (Some(result.text), None),
+    };
+    SearchReport {
+        project_root,
+        query: query.to_string(),
+        context,
+        error,
+    }
+}
+
+/// Result of `status`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub project_root: String,
+    /// Whether `.ace-tool/index.bin` exists
+    pub indexed: bool,
+    /// Totals of the index; empty when it was built with different chunking settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexSummary>,
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Project root: {}", self.project_root)?;
+        let Some(index) = self.index.as_ref().filter(|_| self.indexed) else {
+            return write!(f, "Not indexed yet");
+        };
+
+        writeln!(
+            f,
+            "Indexed files: {} ({} blobs, {} chunked files)",
+            index.files, index.blobs, index.chunked_files
+        )?;
+        writeln!(f, "Total size: {}KB", index.total_bytes / 1024)?;
+        if let Some(time) = index
+            .last_modified_secs
+            .and_then(|secs| Local.timestamp_opt(secs as i64, 0).single())
+        {
+            writeln!(
+                f,
+                "Last modified file: {}",
+                time.format("%Y-%m-%d %H:%M:%S")
+            )?;
+        }
+
+        let mut extensions: Vec<_> = index.extensions.iter().collect();
+        extensions.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let extensions: Vec<String> = extensions
+            .into_iter()
+            .map(|(ext, count)| {
+                let ext = if ext.is_empty() { "(none)" } else { ext };
+                format!("{} {}", ext, count)
+            })
+            .collect();
+        write!(f, "Extensions: {}", extensions.join(", "))
+    }
+}
+
+/// Index statistics of the project, without creating an index
+pub fn status(config: &Arc<Config>, project_root: &Path) -> Result<StatusReport> {
+    if !has_index(project_root) {
+        return Ok(StatusReport {
+            project_root: display_root(project_root),
+            indexed: false,
+            index: None,
+        });
+    }
+
+    let manager = IndexManager::new(config.clone(), project_root.to_path_buf())?;
+    let index = manager.load_index();
+    Ok(StatusReport {
+        project_root: display_root(manager.project_root()),
+        indexed: true,
+        index: Some(index.summary(manager.project_root())),
+    })
+}
+
+/// Result of `clean`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanReport {
+    pub project_root: String,
+    /// Removed paths, relative to the project root
+    pub removed: Vec<String>,
+    /// Hand-written files left in place, relative to the project root
+    pub kept: Vec<String>,
+}
+
+impl fmt::Display for CleanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.removed.is_empty() {
+            write!(f, "Nothing to clean in {}", self.project_root)?;
+        } else {
+            write!(f, "Removed:")?;
+            for path in &self.removed {
+                write!(f, "\n  {}", path)?;
+            }
+        }
+        if !self.kept.is_empty() {
+            write!(f, "\nKept (use --all to remove):")?;
+            for path in &self.kept {
+                write!(f, "\n  {}", path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remove the project's `.ace-tool/` data
+///
+/// The index, caches and logs are removed; custom prompts, the enhancement template and
+/// review sessions are kept unless `all` is set. Fails while another process holds the
+/// index lock. `index.lock` itself is never removed, so the directory stays once created.
+pub fn clean(project_root: &Path, all: bool) -> Result<CleanReport> {
+    let ace_dir = ace_dir_path(project_root);
+    let mut report = CleanReport {
+        project_root: display_root(project_root),
+        ..Default::default()
+    };
+    if !ace_dir.is_dir() {
+        return Ok(report);
+    }
+
+    let _lock = IndexManager::try_lock_index(&ace_dir.join(INDEX_FILE_NAME))?.ok_or_else(|| {
+        anyhow!(
+            "The index is being updated by another ace-tool process; try again once it finishes"
+        )
+    })?;
+
+    let mut names: Vec<_> = fs::read_dir(&ace_dir)
+        .map_err(|e| anyhow!("Failed to read {:?}: {}", ace_dir, e))?
+        .collect::<std::io::Result<_>>()?;
+    names.sort_by_key(|entry| entry.file_name());
+
+    for entry in names {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == INDEX_LOCK_FILE_NAME {
+            continue;
+        }
+        let rel_path = format!("{}/{}", ACE_DIR_NAME, name);
+        if !all && is_preserved(&name) {
+            report.kept.push(rel_path);
+            continue;
+        }
+
+        let path = entry.path();
+        let removed = if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.map_err(|e| anyhow!("Failed to remove {:?}: {}", path, e))?;
+        report.removed.push(rel_path);
+    }
+
+    Ok(report)
+}
+
+/// Result of `ls-files`
+#[derive(Debug, Clone, Serialize)]
+pub struct LsFilesReport {
+    pub project_root: String,
+    /// Number of files that would be indexed
+    pub included: usize,
+    /// Number of excluded files and directories
+    pub excluded: usize,
+    pub files: Vec<FileListEntry>,
+}
+
+impl fmt::Display for LsFilesReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.files {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            match &entry.skipped {
+                None => writeln!(f, "+ {}{}", entry.path, suffix)?,
+                Some(reason) => writeln!(f, "- {}{}\t{}", entry.path, suffix, reason)?,
+            }
+        }
+        write!(
+            f,
+            "{} files would be indexed, {} entries excluded",
+            self.included, self.excluded
+        )
+    }
+}
+
+/// Files that would be indexed, and the excluded files and directories with the reason
+pub fn ls_files(config: &Config, project_root: &Path) -> Result<LsFilesReport> {
+    let files = list_files(config, project_root)?;
+    let included = files.iter().filter(|entry| entry.included).count();
+    Ok(LsFilesReport {
+        project_root: display_root(project_root),
+        included,
+        excluded: files.len() - included,
+        files,
+    })
+}
+
+/// Result of `--index-only --dry-run`
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub project_root: String,
+    /// Number of files that would be indexed
+    pub included: usize,
+    /// Number of skipped files and excluded directories
+    pub skipped: usize,
+    /// Blobs that would be uploaded
+    pub chunks: usize,
+    /// Bytes that would be uploaded
+    pub bytes: u64,
+    /// Secrets that would be redacted before upload
+    pub redacted: usize,
+    /// Skipped entries per reason code
+    pub by_reason: BTreeMap<&'static str, usize>,
+    pub files: Vec<DryRunEntry>,
+}
+
+impl DryRunReport {
+    /// The per-file report as CSV, one row per file or excluded directory
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("path,included,chunks,bytes,encoding,redacted,reason,detail\n");
+        for entry in &self.files {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            let fields = [
+                format!("{}{}", entry.path, suffix),
+                entry.included.to_string(),
+                entry.chunks.to_string(),
+                entry.bytes.to_string(),
+                entry.encoding.unwrap_or_default().to_string(),
+                entry.redacted.to_string(),
+                entry
+                    .skipped
+                    .as_ref()
+                    .map(|reason| reason.code().to_string())
+                    .unwrap_or_default(),
+                entry
+                    .skipped
+                    .as_ref()
+                    .map(|reason| reason.to_string())
+                    .unwrap_or_default(),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl fmt::Display for DryRunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.files {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            match &entry.skipped {
+                None => {
+                    write!(
+                        f,
+                        "+ {}\t{} chunk(s), {}",
+                        entry.path,
+                        entry.chunks,
+                        entry.encoding.unwrap_or_default()
+                    )?;
+                    if entry.redacted > 0 {
+                        write!(f, ", {} secret(s) redacted", entry.redacted)?;
+                    }
+                    writeln!(f)?;
+                }
+                Some(reason) => writeln!(f, "- {}{}\t{}", entry.path, suffix, reason)?,
+            }
+        }
+        write!(
+            f,
+            "{} files would be indexed as {} chunks ({}KB), {} entries skipped",
+            self.included,
+            self.chunks,
+            self.bytes / 1024,
+            self.skipped
+        )?;
+        if self.redacted > 0 {
+            write!(f, "\n{} secret(s) would be redacted", self.redacted)?;
+        }
+        if self
+            .files
+            .iter()
+            .any(|e| e.skipped.as_ref().is_some_and(|r| r.aborts_indexing()))
+        {
+            write!(
+                f,
+                "\nIndexing would abort: a file matches a secret detector with the abort policy"
+            )?;
+        }
+        for (reason, count) in &self.by_reason {
+            write!(f, "\n  {}: {}", reason, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collect and process files as indexing would, without uploading anything
+pub fn index_dry_run(config: &Config, project_root: &Path) -> Result<DryRunReport> {
+    let files = dry_run(config, project_root)?;
+    let mut report = DryRunReport {
+        project_root: display_root(project_root),
+        included: 0,
+        skipped: 0,
+        chunks: 0,
+        bytes: 0,
+        redacted: 0,
+        by_reason: BTreeMap::new(),
+        files: Vec::new(),
+    };
+    for entry in &files {
+        match &entry.skipped {
+            None => {
+                report.included += 1;
+                report.chunks += entry.chunks;
+                report.bytes += entry.bytes;
+                report.redacted += entry.redacted;
+            }
+            Some(reason) => {
+                report.skipped += 1;
+                *report.by_reason.entry(reason.code()).or_default() += 1;
+            }
+        }
+    }
+    report.files = files;
+    Ok(report)
+}
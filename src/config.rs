@@ -1,9 +1,62 @@
 //! Configuration module - CLI arguments and settings
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
 use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 
+use crate::index::{SecretScanSettings, SecretScanner, UploadPolicy, UploadPolicySettings};
+use crate::service::GenerationSettings;
+use crate::utils::project_detector::{get_user_config_dir, ACE_DIR_NAME};
+
+/// User config file name, read from the user config directory
+pub const USER_CONFIG_FILE_NAME: &str = "config.json";
+
+/// Default time to wait for a review in the Web UI or `$EDITOR` (8 minutes)
+pub const DEFAULT_WEBUI_TIMEOUT_SECS: u64 = 8 * 60;
+
+/// The user config file, parsed once at startup
+///
+/// Every section is optional. The `enhancer` section is validated once the
+/// `PROMPT_ENHANCER_*` environment overrides are applied to it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UserConfigFile {
+    /// Generation settings for third-party enhancer calls
+    pub enhancer: GenerationSettings,
+    /// Secret scanning before upload
+    pub secrets: SecretScanSettings,
+    /// Upload allowlist/denylist and audit log
+    pub upload: UploadPolicySettings,
+}
+
+impl UserConfigFile {
+    /// Read and validate a user config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let file: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        SecretScanner::new(&file.secrets)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        UploadPolicy::new(&file.upload, Path::new(""))
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        Ok(file)
+    }
+
+    /// Load the file from the user config directory, or the defaults when there is none
+    pub fn load() -> Result<Self> {
+        match get_user_config_dir() {
+            Some(dir) if dir.join(USER_CONFIG_FILE_NAME).is_file() => {
+                Self::from_file(&dir.join(USER_CONFIG_FILE_NAME))
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+}
+
 /// CLI override configuration for upload parameters
 #[derive(Debug, Clone, Default)]
 pub struct CliOverrides {
@@ -24,6 +77,18 @@ pub struct ConfigOptions {
     pub force_xdg_open: bool,
     /// Custom bind address for the web UI server (e.g., "127.0.0.1:8754", "0.0.0.0:3456")
     pub webui_addr: Option<String>,
+    /// PEM certificate chain for serving the web UI over HTTPS
+    pub webui_tls_cert: Option<String>,
+    /// PEM private key for serving the web UI over HTTPS
+    pub webui_tls_key: Option<String>,
+    /// Seconds to wait for a Web UI review before returning the latest enhanced prompt
+    pub webui_timeout: Option<u64>,
+    /// Secret scanning before upload (default: built-in detectors, redact)
+    pub secret_scan: Option<SecretScanSettings>,
+    /// Upload allowlist/denylist and audit log (default: no restriction, audit on)
+    pub upload_policy: Option<UploadPolicySettings>,
+    /// Third-party generation settings from the user config file (default: provider defaults)
+    pub generation: Option<GenerationSettings>,
 }
 
 /// Main configuration struct
@@ -39,10 +104,22 @@ pub struct Config {
     pub force_xdg_open: bool,
     /// Custom bind address for the web UI server
     pub webui_addr: Option<String>,
+    /// PEM certificate chain for serving the web UI over HTTPS
+    pub webui_tls_cert: Option<String>,
+    /// PEM private key for serving the web UI over HTTPS
+    pub webui_tls_key: Option<String>,
+    /// Seconds to wait for a Web UI review before returning the latest enhanced prompt
+    pub webui_timeout_secs: u64,
     pub cli_overrides: CliOverrides,
     pub text_extensions: HashSet<String>,
     pub text_filenames: HashSet<String>,
     pub exclude_patterns: Vec<String>,
+    /// Secret scanning before upload
+    pub secret_scan: SecretScanSettings,
+    /// Which paths may be uploaded, and the audit log of uploads
+    pub upload_policy: UploadPolicySettings,
+    /// Third-party generation settings from the user config file, before environment overrides
+    pub generation: GenerationSettings,
 }
 
 /// Upload strategy based on project scale
@@ -77,6 +154,16 @@ impl Config {
             return Err(anyhow!("token cannot be empty"));
         }
 
+        if options.webui_timeout == Some(0) {
+            return Err(anyhow!("webui_timeout must be greater than 0"));
+        }
+
+        if options.webui_tls_cert.is_some() != options.webui_tls_key.is_some() {
+            return Err(anyhow!(
+                "webui_tls_cert and webui_tls_key must be provided together"
+            ));
+        }
+
         Ok(Arc::new(Self {
             base_url,
             token,
@@ -86,6 +173,9 @@ impl Config {
             no_webbrowser_enhance_prompt: options.no_webbrowser_enhance_prompt,
             force_xdg_open: options.force_xdg_open,
             webui_addr: options.webui_addr,
+            webui_tls_cert: options.webui_tls_cert,
+            webui_tls_key: options.webui_tls_key,
+            webui_timeout_secs: options.webui_timeout.unwrap_or(DEFAULT_WEBUI_TIMEOUT_SECS),
             cli_overrides: CliOverrides {
                 upload_timeout_secs: options.upload_timeout,
                 upload_concurrency: options.upload_concurrency,
@@ -93,6 +183,9 @@ impl Config {
             text_extensions: default_text_extensions(),
             text_filenames: default_text_filenames(),
             exclude_patterns: default_exclude_patterns(),
+            secret_scan: options.secret_scan.unwrap_or_default(),
+            upload_policy: options.upload_policy.unwrap_or_default(),
+            generation: options.generation.unwrap_or_default(),
         }))
     }
 
@@ -108,12 +201,36 @@ impl Config {
             no_webbrowser_enhance_prompt: true,
             force_xdg_open: false,
             webui_addr: None,
+            webui_tls_cert: None,
+            webui_tls_key: None,
+            webui_timeout_secs: DEFAULT_WEBUI_TIMEOUT_SECS,
             cli_overrides: CliOverrides::default(),
             text_extensions: default_text_extensions(),
             text_filenames: default_text_filenames(),
             exclude_patterns: default_exclude_patterns(),
+            secret_scan: SecretScanSettings::default(),
+            upload_policy: UploadPolicySettings::default(),
+            generation: GenerationSettings::default(),
         })
     }
+
+    /// Create a config for commands that only inspect the local project
+    /// (`status`, `clean`, `ls-files`, `--index-only --dry-run`), where no API credentials
+    /// are needed
+    pub fn new_local(
+        max_lines_per_blob: Option<usize>,
+        secret_scan: SecretScanSettings,
+        upload_policy: UploadPolicySettings,
+    ) -> Arc<Self> {
+        let mut config = Self::new_for_third_party_enhancer();
+        let local = Arc::make_mut(&mut config);
+        if let Some(max_lines) = max_lines_per_blob {
+            local.max_lines_per_blob = max_lines;
+        }
+        local.secret_scan = secret_scan;
+        local.upload_policy = upload_policy;
+        config
+    }
 }
 
 /// Get adaptive upload strategy based on blob count
@@ -403,7 +520,7 @@ fn default_exclude_patterns() -> Vec<String> {
         "*.sqlite",
         "*.sqlite3",
         // Ace-tool directory
-        ".ace-tool",
+        ACE_DIR_NAME,
     ]
     .iter()
     .map(|s| s.to_string())
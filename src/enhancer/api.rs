@@ -0,0 +1,213 @@
+//! Web UI JSON API - versioned request/response types for the enhancer server
+//! Lets editor plugins drive reviews without a browser
+//!
+//! Every endpoint is served under `/api/v1/` (and the unversioned `/api/` used by the
+//! bundled Web UI). Calls carry a token in the `X-Ace-Session-Token` header or the
+//! `token` query parameter: the per-session token for one session, or the dashboard
+//! token from the discovery file (`.ace-tool/webui.json`) for the listing endpoints.
+//!
+//! | Method | Path | Token | Request | Response |
+//! |--------|------|-------|---------|----------|
+//! | GET | `/api/v1/session?session=ID` | session | - | [`SessionResponse`] |
+//! | POST | `/api/v1/submit` | session | [`SubmitRequest`] | [`SuccessResponse`] |
+//! | POST | `/api/v1/re-enhance` | session | [`ReEnhanceRequest`] | [`ReEnhanceResponse`] |
+//! | POST | `/api/v1/diff` | session | [`DiffRequest`] | [`DiffResponse`] |
+//! | POST | `/api/v1/cancel` | session or dashboard | [`CancelRequest`] | [`SuccessResponse`] |
+//! | GET | `/api/v1/sessions` | dashboard | - | [`SessionListResponse`] |
+//! | GET | `/api/v1/sessions/wait?cursor=N&timeout=S` | dashboard | - | [`SessionListResponse`] |
+//!
+//! Errors are returned as [`ErrorResponse`] with a 4xx/5xx status.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::diff::DiffSegment;
+use super::server::{Revision, SessionStatus};
+use crate::utils::project_detector::{ace_dir_path, get_ace_dir};
+
+/// Current API version, also sent in the `X-Ace-Api-Version` response header
+pub const API_VERSION: u32 = 1;
+
+/// Path prefix of the versioned API
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Response header carrying [`API_VERSION`]
+pub const API_VERSION_HEADER: &str = "X-Ace-Api-Version";
+
+/// Discovery file name (inside `.ace-tool/`)
+pub const DISCOVERY_FILE_NAME: &str = "webui.json";
+
+/// Default and maximum wait for `/sessions/wait`, in seconds
+pub const DEFAULT_WAIT_SECS: u64 = 30;
+pub const MAX_WAIT_SECS: u64 = 120;
+
+/// `GET /session` - state of one review session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    /// Latest enhanced prompt
+    pub enhanced_prompt: String,
+    pub status: SessionStatus,
+    /// Creation time in milliseconds since the Unix epoch
+    pub created_at: u64,
+    /// How long the agent waits for the review
+    pub timeout_ms: u64,
+    /// Enhanced prompt revisions, oldest first
+    pub revisions: Vec<Revision>,
+}
+
+/// `POST /submit` - finish a review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitRequest {
+    pub session_id: String,
+    /// Prompt text returned to the agent
+    pub content: String,
+    /// `"use_original"` or `"end_conversation"`; other values submit `content`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Revision the submitted content was based on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision_index: Option<usize>,
+}
+
+/// `POST /re-enhance` - enhance the current text again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReEnhanceRequest {
+    pub session_id: String,
+    pub current_prompt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReEnhanceResponse {
+    pub enhanced_prompt: String,
+    pub revision: Revision,
+    pub revision_index: usize,
+}
+
+/// `POST /diff` - word-level diff against the original prompt or a revision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffRequest {
+    pub session_id: String,
+    /// Text to diff; defaults to the latest enhanced prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_prompt: Option<String>,
+    /// Compare against this revision instead of the original prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_revision: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffResponse {
+    pub original: String,
+    pub enhanced: String,
+    pub segments: Vec<DiffSegment>,
+}
+
+/// `POST /cancel` - cancel an open session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelRequest {
+    pub session_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuccessResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// One open session in [`SessionListResponse`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub id: String,
+    pub status: SessionStatus,
+    /// Project root the session was started from, when known
+    pub project: Option<String>,
+    pub original_prompt: String,
+    pub created_at: u64,
+    pub age_ms: u64,
+    /// Review page path, including the session token
+    pub url: String,
+    /// Per-session token for the session endpoints
+    pub token: String,
+}
+
+/// `GET /sessions` and `GET /sessions/wait` - open sessions, newest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionSummary>,
+    pub timeout_ms: u64,
+    /// Change counter; pass it to `/sessions/wait` to block until the list changes
+    pub cursor: u64,
+}
+
+/// Contents of `.ace-tool/webui.json`, written while the Web UI server is running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryInfo {
+    pub api_version: u32,
+    /// Base URL, e.g. `http://127.0.0.1:3000`
+    pub url: String,
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    /// Dashboard token for `/sessions`, `/sessions/wait` and `/cancel`
+    pub token: String,
+    /// Process serving the API; the file is stale once it has exited
+    pub pid: u32,
+}
+
+/// Get the discovery file path for a project
+pub fn get_discovery_file(project_root: &Path) -> PathBuf {
+    get_ace_dir(project_root).join(DISCOVERY_FILE_NAME)
+}
+
+/// Write the discovery file atomically; readable only by the owner since it holds a token
+pub fn write_discovery_file(project_root: &Path, info: &DiscoveryInfo) -> Result<PathBuf> {
+    let path = get_discovery_file(project_root);
+    let tmp_path = path.with_extension("json.tmp");
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&tmp_path)
+        .with_context(|| format!("Failed to write discovery file {}", tmp_path.display()))?;
+    file.write_all(&serde_json::to_vec_pretty(info)?)?;
+    drop(file);
+
+    fs::rename(&tmp_path, &path)
+        .with_context(|| format!("Failed to save discovery file {}", path.display()))?;
+    Ok(path)
+}
+
+/// Read the discovery file; `Ok(None)` when no server has written one
+pub fn read_discovery_file(project_root: &Path) -> Result<Option<DiscoveryInfo>> {
+    let path = ace_dir_path(project_root).join(DISCOVERY_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read discovery file {}", path.display()))?;
+    let info = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid discovery file {}", path.display()))?;
+    Ok(Some(info))
+}
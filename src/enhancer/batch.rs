@@ -0,0 +1,165 @@
+//! Batch enhancement - enhance many prompts non-interactively from JSONL
+//! Used by evaluation pipelines through `--enhance-batch`
+
+use std::future::Future;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::service::ConversationHistory;
+
+/// Default number of enhancements run at the same time
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+/// One input line: `{"prompt": ..., "conversation_history": ..., "project_root": ...}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BatchRequest {
+    pub prompt: String,
+    /// Text history or an array of `{role, content}` messages, with roles normalized
+    #[serde(default, deserialize_with = "deserialize_conversation_history")]
+    pub conversation_history: ConversationHistory,
+    /// Project to take code context from; relative paths resolve against the working directory
+    #[serde(default)]
+    pub project_root: Option<PathBuf>,
+    /// Caller-supplied identifier, echoed in the result
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+fn deserialize_conversation_history<'de, D>(
+    deserializer: D,
+) -> Result<ConversationHistory, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    ConversationHistory::deserialize(deserializer)?
+        .normalized()
+        .map_err(de::Error::custom)
+}
+
+/// One output line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// 1-based input line number
+    pub line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub prompt: String,
+    pub enhanced: Option<String>,
+    pub error: Option<String>,
+    /// Enhancer endpoint (e.g. "new", "claude")
+    pub provider: String,
+    pub model: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Enhancer endpoint and model recorded in every result
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProvider {
+    pub provider: String,
+    pub model: Option<String>,
+}
+
+/// Read a whole input source; `-` means stdin
+pub fn read_input(source: &str) -> Result<String> {
+    if source == "-" {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read stdin")?;
+        Ok(input)
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))
+    }
+}
+
+/// Read a single prompt from a file or stdin (`-`), rejecting empty input
+pub fn read_prompt(source: &str) -> Result<String> {
+    let prompt = read_input(source)?.trim().to_string();
+    if prompt.is_empty() {
+        let name = if source == "-" { "stdin" } else { source };
+        return Err(anyhow!("No prompt found in {}", name));
+    }
+    Ok(prompt)
+}
+
+/// Parse JSONL input into `(line number, request)` pairs, skipping blank lines.
+/// Malformed lines are kept as errors so they show up in the output.
+pub fn parse_batch(input: &str) -> Vec<(usize, Result<BatchRequest, String>)> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let request = serde_json::from_str::<BatchRequest>(line)
+                .map_err(|e| format!("Invalid batch line: {}", e))
+                .and_then(|request| {
+                    if request.prompt.trim().is_empty() {
+                        Err("Prompt is empty".to_string())
+                    } else {
+                        Ok(request)
+                    }
+                });
+            (i + 1, request)
+        })
+        .collect()
+}
+
+/// Resolve a request's project root against `cwd` (defaulting to `cwd`)
+pub fn resolve_project_root(request: &BatchRequest, cwd: &Path) -> PathBuf {
+    match &request.project_root {
+        Some(root) => cwd.join(root),
+        None => cwd.to_path_buf(),
+    }
+}
+
+/// Run `enhance` over every entry with at most `concurrency` calls in flight.
+/// Results are yielded in input order as soon as each is ready.
+pub fn enhance_batch<'a, F, Fut>(
+    entries: Vec<(usize, Result<BatchRequest, String>)>,
+    concurrency: usize,
+    provider: &'a BatchProvider,
+    enhance: F,
+) -> impl Stream<Item = BatchResult> + 'a
+where
+    F: Fn(BatchRequest) -> Fut + 'a,
+    Fut: Future<Output = Result<String>> + 'a,
+{
+    stream::iter(entries)
+        .map(move |(line, request)| {
+            let call = request.map(|request| {
+                let prompt = request.prompt.clone();
+                let id = request.id.clone();
+                (prompt, id, enhance(request))
+            });
+            async move {
+                let start = Instant::now();
+                let (prompt, id, outcome) = match call {
+                    Ok((prompt, id, future)) => {
+                        (prompt, id, future.await.map_err(|e| e.to_string()))
+                    }
+                    Err(e) => (String::new(), None, Err(e)),
+                };
+                let (enhanced, error) = match outcome {
+                    Ok(text) => (Some(text), None),
+                    Err(e) => (None, Some(e)),
+                };
+                BatchResult {
+                    line,
+                    id,
+                    prompt,
+                    enhanced,
+                    error,
+                    provider: provider.provider.clone(),
+                    model: provider.model.clone(),
+                    duration_ms: start.elapsed().as_millis() as u64,
+                }
+            }
+        })
+        .buffered(concurrency.max(1))
+}
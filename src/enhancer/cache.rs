@@ -0,0 +1,297 @@
+//! Enhancement cache - reuse enhanced prompts for identical requests
+//!
+//! Entries live in `.ace-tool/enhance-cache/<key>.json`. The key hashes everything
+//! that shapes the LLM request: prompt, history, endpoint, model, generation settings,
+//! template, guidelines, token budgets and the index state. Entries expire after
+//! `PROMPT_ENHANCER_CACHE_TTL_SECS` (0 disables the cache) and the oldest entries are
+//! evicted once the directory grows past `PROMPT_ENHANCER_CACHE_MAX_BYTES`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use super::guidelines::ProjectGuidelines;
+use crate::service::{ConversationHistory, GenerationSettings};
+use crate::utils::project_detector::get_ace_dir;
+
+/// Cache directory name (inside `.ace-tool/`)
+pub const CACHE_DIR_NAME: &str = "enhance-cache";
+
+/// Environment variable for the entry lifetime in seconds; `0` disables the cache
+pub const ENV_CACHE_TTL_SECS: &str = "PROMPT_ENHANCER_CACHE_TTL_SECS";
+
+/// Environment variable for the total cache size limit in bytes
+pub const ENV_CACHE_MAX_BYTES: &str = "PROMPT_ENHANCER_CACHE_MAX_BYTES";
+
+/// Default entry lifetime (24 hours)
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Default total cache size (16 MiB)
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Everything that determines the enhancer's output
+#[derive(Debug, Clone, Copy)]
+pub struct CacheKeyInput<'a> {
+    pub prompt: &'a str,
+    pub conversation_history: &'a ConversationHistory,
+    /// Enhancer endpoint (e.g. "new", "claude")
+    pub endpoint: &'a str,
+    pub base_url: &'a str,
+    pub model: &'a str,
+    pub generation: Option<&'a GenerationSettings>,
+    /// [`EnhanceTemplate::fingerprint`](super::EnhanceTemplate::fingerprint)
+    pub template: &'a str,
+    pub guidelines: &'a ProjectGuidelines,
+    /// [`calculate_config_hash`](crate::index::calculate_config_hash)
+    pub index_config_hash: &'a str,
+    /// Sorted blob hashes from the project's index; change whenever the indexed content does
+    pub blob_names: &'a [String],
+    pub include_search_context: bool,
+    /// Input token budget the history was packed into
+    pub budget_tokens: usize,
+    /// Tokens left for search context after packing
+    pub search_budget: usize,
+}
+
+impl CacheKeyInput<'_> {
+    /// Hex SHA-256 over the length-prefixed fields
+    pub fn key(&self) -> String {
+        let mut hasher = Sha256::new();
+        let mut field = |value: &str| {
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        };
+
+        field("v3");
+        field(self.prompt);
+        field(&serde_json::to_string(self.conversation_history).unwrap_or_default());
+        field(self.endpoint);
+        field(self.base_url);
+        field(self.model);
+        field(
+            &self
+                .generation
+                .and_then(|g| serde_json::to_string(g).ok())
+                .unwrap_or_default(),
+        );
+        field(self.template);
+        field(&self.guidelines.user);
+        field(&self.guidelines.workspace);
+        field(&self.guidelines.rules.len().to_string());
+        for rule in &self.guidelines.rules {
+            field(rule);
+        }
+        field(self.index_config_hash);
+        field(&self.blob_names.len().to_string());
+        for name in self.blob_names {
+            field(name);
+        }
+        field(if self.include_search_context {
+            "1"
+        } else {
+            "0"
+        });
+        field(&self.budget_tokens.to_string());
+        field(&self.search_budget.to_string());
+
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// One cached enhancement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    pub enhanced_prompt: String,
+    pub endpoint: String,
+    pub model: Option<String>,
+    /// Creation time in milliseconds since the Unix epoch
+    pub created_at: u64,
+}
+
+impl CacheEntry {
+    pub fn new(
+        key: String,
+        enhanced_prompt: String,
+        endpoint: String,
+        model: Option<String>,
+    ) -> Self {
+        Self {
+            key,
+            enhanced_prompt,
+            endpoint,
+            model,
+            created_at: now_millis(),
+        }
+    }
+}
+
+/// A cache lookup that found a live entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHit {
+    pub entry: CacheEntry,
+    pub age: Duration,
+}
+
+/// How the last enhancement used the cache, reported in the tool result's `_meta`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CacheStatus {
+    /// Served from the cache without calling the enhancer
+    Hit { key: String, age_secs: u64 },
+    /// Enhancer was called and the result stored
+    Miss { key: String },
+    /// Skipped because the caller passed `no_cache`
+    Bypassed,
+    /// Cache turned off with `PROMPT_ENHANCER_CACHE_TTL_SECS=0`
+    Disabled,
+}
+
+/// On-disk enhancement cache for one project
+#[derive(Debug, Clone)]
+pub struct EnhanceCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl EnhanceCache {
+    pub fn new(dir: PathBuf, ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            ttl,
+            max_bytes,
+        }
+    }
+
+    /// Cache for a project (defaulting to the current directory) configured from the
+    /// environment; `None` when the cache is disabled
+    pub fn for_project(project_root: Option<&Path>) -> Option<Self> {
+        let ttl_secs = env_u64(ENV_CACHE_TTL_SECS).unwrap_or(DEFAULT_CACHE_TTL_SECS);
+        if ttl_secs == 0 {
+            return None;
+        }
+        let max_bytes = env_u64(ENV_CACHE_MAX_BYTES).unwrap_or(DEFAULT_CACHE_MAX_BYTES);
+
+        let root = match project_root {
+            Some(root) => root.to_path_buf(),
+            None => std::env::current_dir().ok()?,
+        };
+        Some(Self::new(
+            get_ace_dir(&root).join(CACHE_DIR_NAME),
+            Duration::from_secs(ttl_secs),
+            max_bytes,
+        ))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Look up a live entry; expired or unreadable entries are removed
+    pub fn get(&self, key: &str) -> Option<CacheHit> {
+        let path = self.entry_path(key);
+        let content = fs::read_to_string(&path).ok()?;
+
+        let entry = match serde_json::from_str::<CacheEntry>(&content) {
+            Ok(entry) if entry.key == key => entry,
+            _ => {
+                warn!(
+                    "Removing invalid enhancement cache entry {}",
+                    path.display()
+                );
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        let age = Duration::from_millis(now_millis().saturating_sub(entry.created_at));
+        if age >= self.ttl {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(CacheHit { entry, age })
+    }
+
+    /// Store an entry atomically, then prune expired and excess entries
+    pub fn put(&self, entry: &CacheEntry) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let path = self.entry_path(&entry.key);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(entry)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to save {}", path.display()))?;
+
+        self.prune()
+    }
+
+    /// Remove expired entries, then the oldest entries until the cache fits `max_bytes`
+    pub fn prune(&self) -> Result<()> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).context("Failed to read enhancement cache"),
+        };
+
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        for item in read_dir.flatten() {
+            let path = item.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Ok(metadata) = item.metadata() else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            let age = now.duration_since(modified).unwrap_or_default();
+            if age >= self.ttl {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            entries.push((modified, metadata.len(), path));
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(len);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    let value = std::env::var(name).ok()?;
+    match value.trim().parse() {
+        Ok(n) => Some(n),
+        Err(_) => {
+            warn!("Ignoring invalid {}={:?}", name, value);
+            None
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,151 @@
+//! Word-level diff between the original and enhanced prompt
+//! Used by the Web UI to show what the enhancer changed
+
+use serde::{Deserialize, Serialize};
+
+/// Largest LCS table (in cells) computed before falling back to a whole-text replacement
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Kind of change for a diff segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of text with the same diff operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{ac00}'..='\u{d7af}'
+            | '\u{f900}'..='\u{faff}')
+}
+
+#[derive(PartialEq)]
+enum TokenKind {
+    Word,
+    Space,
+    Single,
+}
+
+fn token_kind(c: char) -> TokenKind {
+    if c.is_whitespace() {
+        TokenKind::Space
+    } else if (c.is_alphanumeric() || c == '_') && !is_cjk(c) {
+        TokenKind::Word
+    } else {
+        // CJK characters and punctuation are compared one character at a time
+        TokenKind::Single
+    }
+}
+
+/// Split text into words, whitespace runs and single CJK/punctuation characters
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let kind = token_kind(c);
+        let end = i + c.len_utf8();
+        let joins_next = |next: char| kind != TokenKind::Single && token_kind(next) == kind;
+        if !chars.peek().is_some_and(|&(_, next)| joins_next(next)) {
+            tokens.push(&text[start..end]);
+            start = end;
+        }
+    }
+    tokens
+}
+
+fn push_segment(segments: &mut Vec<DiffSegment>, op: DiffOp, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match segments.last_mut() {
+        Some(last) if last.op == op => last.text.push_str(text),
+        _ => segments.push(DiffSegment {
+            op,
+            text: text.to_string(),
+        }),
+    }
+}
+
+/// Compute a word-level diff from `original` to `enhanced`
+///
+/// Adjacent segments with the same operation are merged. Very large inputs fall back to a
+/// single delete and insert.
+pub fn word_diff(original: &str, enhanced: &str) -> Vec<DiffSegment> {
+    let old = tokenize(original);
+    let new = tokenize(enhanced);
+    let mut segments = Vec::new();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    for token in &old[..prefix] {
+        push_segment(&mut segments, DiffOp::Equal, token);
+    }
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    let (n, m) = (old_mid.len(), new_mid.len());
+
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        push_segment(&mut segments, DiffOp::Delete, &old_mid.concat());
+        push_segment(&mut segments, DiffOp::Insert, &new_mid.concat());
+    } else {
+        // lcs[i][j] = LCS length of old_mid[i..] and new_mid[j..]
+        let width = m + 1;
+        let mut lcs = vec![0u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if old_mid[i] == new_mid[j] {
+                push_segment(&mut segments, DiffOp::Equal, old_mid[i]);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                push_segment(&mut segments, DiffOp::Delete, old_mid[i]);
+                i += 1;
+            } else {
+                push_segment(&mut segments, DiffOp::Insert, new_mid[j]);
+                j += 1;
+            }
+        }
+        for token in &old_mid[i..] {
+            push_segment(&mut segments, DiffOp::Delete, token);
+        }
+        for token in &new_mid[j..] {
+            push_segment(&mut segments, DiffOp::Insert, token);
+        }
+    }
+
+    for token in &old[old.len() - suffix..] {
+        push_segment(&mut segments, DiffOp::Equal, token);
+    }
+    segments
+}
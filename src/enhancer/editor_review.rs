@@ -0,0 +1,176 @@
+//! Terminal review - edit the enhanced prompt in `$EDITOR` instead of the Web UI
+//! Useful on headless SSH sessions where no browser can be opened
+
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::process::Command;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::tools::error::ToolError;
+
+#[cfg(windows)]
+const FALLBACK_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const FALLBACK_EDITOR: &str = "vi";
+
+/// Editor command from `$VISUAL`, then `$EDITOR`, falling back to vi (notepad on Windows).
+/// The value is split on whitespace so commands like `code --wait` work.
+pub fn resolve_editor() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|parts| !parts.is_empty())
+        .unwrap_or_else(|| vec![FALLBACK_EDITOR.to_string()])
+}
+
+/// Open `enhanced_prompt` in the editor and return the saved text once it exits.
+///
+/// Mirrors the Web UI session outcomes: saved text completes the review, an empty file
+/// cancels it, and if the editor is still open after `timeout` it is killed and the last
+/// saved text is returned with a note that it was not reviewed.
+///
+/// The review file lives in a private (0700) directory and is created 0600 on unix, since
+/// the prompt may carry project code.
+pub async fn review_in_editor(
+    editor: &[String],
+    enhanced_prompt: &str,
+    timeout: Duration,
+) -> Result<String> {
+    let (program, args) = editor
+        .split_first()
+        .ok_or_else(|| anyhow!("No editor configured"))?;
+
+    let dir = std::env::temp_dir().join(format!("ace-tool-review-{}", Uuid::new_v4()));
+    create_private_dir(&dir)
+        .map_err(|e| anyhow!("Failed to create review directory {}: {}", dir.display(), e))?;
+    let path = dir.join("enhanced-prompt.md");
+
+    let result = match write_private_file(&path, enhanced_prompt) {
+        Ok(()) => run_editor(program, args, &path, enhanced_prompt, timeout).await,
+        Err(e) => Err(anyhow!(
+            "Failed to write review file {}: {}",
+            path.display(),
+            e
+        )),
+    };
+    if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+        warn!("Failed to remove review directory {}: {}", dir.display(), e);
+    }
+    result
+}
+
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+/// Note appended to a prompt whose editor review timed out
+fn editor_timeout_note(timeout: Duration) -> String {
+    format!(
+        "\n\n---\n[ace-tool] The editor review timed out after {} seconds, so the prompt \
+         above is the last saved version without reviewer approval.",
+        timeout.as_secs()
+    )
+}
+
+async fn run_editor(
+    program: &str,
+    args: &[String],
+    path: &Path,
+    enhanced_prompt: &str,
+    timeout: Duration,
+) -> Result<String> {
+    let mut command = Command::new(program);
+    command.args(args).arg(path).kill_on_drop(true);
+    attach_terminal(&mut command);
+
+    info!("Opening enhanced prompt in {} for review", program);
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("Failed to start editor '{}': {}", program, e))?;
+
+    let status = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => status.map_err(|e| anyhow!("Failed to wait for editor: {}", e))?,
+        Err(_) => {
+            let _ = child.kill().await;
+            warn!(
+                "Editor review timed out after {} seconds; using the last saved text",
+                timeout.as_secs()
+            );
+            let saved = tokio::fs::read_to_string(path).await.unwrap_or_default();
+            let saved = match saved.trim() {
+                "" => enhanced_prompt,
+                saved => saved,
+            };
+            return Ok(format!("{}{}", saved, editor_timeout_note(timeout)));
+        }
+    };
+
+    if !status.success() {
+        return Err(ToolError::Cancelled(format!(
+            "Editor exited with {}, review cancelled",
+            status
+        ))
+        .into());
+    }
+
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| anyhow!("Failed to read review file {}: {}", path.display(), e))?;
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(ToolError::Cancelled("User cancelled the enhancement".to_string()).into());
+    }
+    Ok(content.to_string())
+}
+
+/// Connect the editor to the controlling terminal so it works even when stdout is piped
+/// (e.g. `$(ace-tool-rs --enhance-prompt ...)`)
+#[cfg(unix)]
+fn attach_terminal(command: &mut Command) {
+    let input = std::fs::File::open("/dev/tty");
+    let output = std::fs::OpenOptions::new().write(true).open("/dev/tty");
+    match (input, output) {
+        (Ok(input), Ok(output)) => {
+            command
+                .stdin(Stdio::from(input))
+                .stdout(Stdio::from(output));
+        }
+        // No terminal (e.g. GUI editor): keep editor output out of the enhanced prompt on stdout
+        _ => {
+            command.stdout(Stdio::null());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn attach_terminal(command: &mut Command) {
+    command.stdin(Stdio::inherit()).stdout(Stdio::inherit());
+}
@@ -0,0 +1,188 @@
+//! Project guidelines discovery
+//! Collects coding guidelines and rules files so enhanced prompts respect them
+
+use std::fs;
+use std::path::Path;
+
+use tracing::{info, warn};
+
+use crate::utils::project_detector::get_user_config_dir;
+
+/// Environment variable to disable guideline injection (enabled by default)
+pub const ENV_ENHANCER_INCLUDE_GUIDELINES: &str = "PROMPT_ENHANCER_INCLUDE_GUIDELINES";
+
+/// Workspace guideline files, read from the project root in this order
+pub const WORKSPACE_GUIDELINE_FILES: &[&str] = &[".augment-guidelines", "AGENTS.md", "CLAUDE.md"];
+
+/// Rule files, read from the project root in this order
+pub const RULE_FILES: &[&str] = &[".cursorrules"];
+
+/// Directory of additional rule files (`*.md`) under the project root
+pub const RULES_DIR: &str = ".augment/rules";
+
+/// User guideline file in the user config directory
+pub const USER_GUIDELINES_FILE_NAME: &str = "guidelines.md";
+
+/// Maximum bytes read from a single guideline file (16KB)
+const MAX_GUIDELINE_FILE_BYTES: usize = 16 * 1024;
+
+/// Maximum bytes of guidelines across all files (48KB)
+const MAX_GUIDELINES_TOTAL_BYTES: usize = 48 * 1024;
+
+/// Maximum number of files read from the rules directory
+const MAX_RULE_DIR_FILES: usize = 20;
+
+/// Guidelines discovered for a project
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectGuidelines {
+    /// Per-user guidelines from the user config directory
+    pub user: String,
+    /// Workspace guidelines (`.augment-guidelines`, `AGENTS.md`, `CLAUDE.md`)
+    pub workspace: String,
+    /// Individual rule files (`.cursorrules`, `.augment/rules/*.md`)
+    pub rules: Vec<String>,
+}
+
+impl ProjectGuidelines {
+    pub fn is_empty(&self) -> bool {
+        self.user.is_empty() && self.workspace.is_empty() && self.rules.is_empty()
+    }
+
+    /// Render guidelines as a system prompt for third-party providers
+    pub fn to_system_prompt(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut prompt = String::from(
+            "The user works in a project with the following guidelines. Make sure the enhanced prompt is consistent with them, and mention the relevant conventions where they affect the request.",
+        );
+        if !self.user.is_empty() {
+            prompt.push_str(&format!(
+                "\n\n<user_guidelines>\n{}\n</user_guidelines>",
+                self.user
+            ));
+        }
+        if !self.workspace.is_empty() {
+            prompt.push_str(&format!(
+                "\n\n<workspace_guidelines>\n{}\n</workspace_guidelines>",
+                self.workspace
+            ));
+        }
+        for rule in &self.rules {
+            prompt.push_str(&format!("\n\n<rule>\n{}\n</rule>", rule));
+        }
+        Some(prompt)
+    }
+}
+
+fn should_include_guidelines() -> bool {
+    !matches!(
+        std::env::var(ENV_ENHANCER_INCLUDE_GUIDELINES)
+            .ok()
+            .map(|v| v.trim().to_ascii_lowercase())
+            .as_deref(),
+        Some("0" | "false" | "no" | "off")
+    )
+}
+
+/// Tracks the remaining byte budget while reading guideline files
+struct GuidelineReader {
+    remaining: usize,
+}
+
+impl GuidelineReader {
+    /// Read a guideline file, truncated to the per-file and remaining total limits
+    fn read(&mut self, path: &Path) -> Option<String> {
+        if self.remaining == 0 || !path.is_file() {
+            return None;
+        }
+
+        let content = match fs::read(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                warn!("Failed to read guideline file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let content = content.trim();
+        if content.is_empty() {
+            return None;
+        }
+
+        let limit = MAX_GUIDELINE_FILE_BYTES.min(self.remaining);
+        let text = truncate_guideline(content, limit);
+        self.remaining = self.remaining.saturating_sub(text.len());
+        info!("Loaded guidelines from {}", path.display());
+        Some(text)
+    }
+}
+
+/// Truncate at a UTF-8 boundary, appending a notice when content was cut
+fn truncate_guideline(content: &str, max_bytes: usize) -> String {
+    if content.len() <= max_bytes {
+        return content.to_string();
+    }
+
+    let mut end = max_bytes;
+    while end > 0 && !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n[guidelines truncated for length]", &content[..end])
+}
+
+/// Discover guideline and rule files for a project
+///
+/// Returns empty guidelines when disabled via `PROMPT_ENHANCER_INCLUDE_GUIDELINES`.
+pub fn load_project_guidelines(project_root: Option<&Path>) -> ProjectGuidelines {
+    let mut guidelines = ProjectGuidelines::default();
+    if !should_include_guidelines() {
+        return guidelines;
+    }
+
+    let mut reader = GuidelineReader {
+        remaining: MAX_GUIDELINES_TOTAL_BYTES,
+    };
+
+    if let Some(dir) = get_user_config_dir() {
+        if let Some(text) = reader.read(&dir.join(USER_GUIDELINES_FILE_NAME)) {
+            guidelines.user = text;
+        }
+    }
+
+    let Some(root) = project_root else {
+        return guidelines;
+    };
+
+    let workspace: Vec<String> = WORKSPACE_GUIDELINE_FILES
+        .iter()
+        .filter_map(|name| {
+            reader
+                .read(&root.join(name))
+                .map(|text| format!("# {}\n{}", name, text))
+        })
+        .collect();
+    guidelines.workspace = workspace.join("\n\n");
+
+    for name in RULE_FILES {
+        if let Some(text) = reader.read(&root.join(name)) {
+            guidelines.rules.push(text);
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(root.join(RULES_DIR)) {
+        let mut rule_paths: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        rule_paths.sort();
+        for path in rule_paths.into_iter().take(MAX_RULE_DIR_FILES) {
+            if let Some(text) = reader.read(&path) {
+                guidelines.rules.push(text);
+            }
+        }
+    }
+
+    guidelines
+}
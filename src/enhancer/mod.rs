@@ -1,10 +1,20 @@
 //! Prompt Enhancer module
 //! Enhances user prompts using codebase context and conversation history
 
+pub mod api;
+pub mod batch;
+pub mod cache;
+pub mod diff;
+pub mod editor_review;
+pub mod guidelines;
 pub mod prompt_enhancer;
 pub mod server;
+pub mod session_store;
 pub mod templates;
 
+pub use guidelines::{load_project_guidelines, ProjectGuidelines};
 pub use prompt_enhancer::PromptEnhancer;
 pub use server::EnhancerServer;
-pub use templates::{ENHANCER_UI_HTML, ENHANCE_PROMPT_TEMPLATE};
+pub use templates::{
+    load_enhance_template, EnhanceTemplate, ENHANCER_UI_HTML, ENHANCE_PROMPT_TEMPLATE,
+};
@@ -11,23 +11,32 @@
 //! - `codex`: Uses Codex API (OpenAI Responses API)
 
 use std::net::SocketAddr;
-use std::path::Path;
-use std::sync::{Arc, OnceLock};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use reqwest::Client;
-use tracing::{error, info, warn};
+use tracing::{info, warn};
 
 use crate::config::Config;
-use crate::index::IndexManager;
+use crate::http_logger;
+use crate::index::{calculate_config_hash, IndexManager, IndexRegistry};
+use crate::service::generation::DEFAULT_MAX_TOKENS;
 use crate::service::{
     call_claude_endpoint, call_codex_endpoint, call_gemini_endpoint, call_new_endpoint,
-    call_old_endpoint, call_openai_endpoint, get_third_party_config, EnhancerEndpoint,
+    call_old_endpoint, call_openai_endpoint, format_chat_message, get_third_party_config,
+    parse_chat_history, ChatMessage, ConversationHistory, EnhanceInput, EnhancerEndpoint,
+    ThirdPartyConfig, DEFAULT_MODEL,
 };
-use crate::utils::project_detector::get_index_file_path;
+use crate::tools::error::ToolError;
 
-use super::server::EnhancerServer;
+use super::api::write_discovery_file;
+use super::cache::{CacheEntry, CacheKeyInput, CacheStatus, EnhanceCache};
+use super::guidelines::load_project_guidelines;
+use super::server::{EnhancerServer, SessionOutcome, SessionStatus};
+use super::session_store::{discard_session, get_sessions_dir, load_session};
+use super::templates::load_enhance_template;
 
 /// Singleton EnhancerServer shared across all PromptEnhancer instances to prevent port leaks.
 /// Each `EnhancerServer::start()` binds a new port (3000-3099); without sharing, repeated
@@ -43,7 +52,28 @@ pub const ENV_ENHANCER_ENDPOINT_LEGACY: &str = "ACE_ENHANCER_ENDPOINT";
 /// Environment variable to include search_context results in third-party enhancement
 pub const ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT: &str = "PROMPT_ENHANCER_INCLUDE_SEARCH_CONTEXT";
 
-const SEARCH_CONTEXT_CHAR_LIMIT: usize = 12_000;
+/// Environment variable to override the model context window size (in tokens)
+pub const ENV_ENHANCER_CONTEXT_TOKENS: &str = "PROMPT_ENHANCER_CONTEXT_TOKENS";
+
+/// Environment variable to cap the tokens spent on search context
+pub const ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS: &str =
+    "PROMPT_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS";
+
+/// Default cap on search context tokens, regardless of how large the context window is
+pub const DEFAULT_MAX_SEARCH_CONTEXT_TOKENS: usize = 16_000;
+
+/// Context window assumed for models not in the table below
+const DEFAULT_CONTEXT_TOKENS: usize = 32_000;
+
+/// Share of the context window kept free for tokenizer estimation error
+const CONTEXT_SAFETY_MARGIN_PERCENT: usize = 10;
+
+/// Share of the input budget given to conversation history when search context is enabled
+const HISTORY_BUDGET_PERCENT: usize = 40;
+
+/// Smallest remainder worth keeping as a truncated history message
+const MIN_PARTIAL_MESSAGE_TOKENS: usize = 64;
+
 const NO_RELEVANT_CODE_CONTEXT: &str = "No relevant code context found for your query.";
 
 /// Get the configured enhancer endpoint type
@@ -67,15 +97,328 @@ fn should_include_search_context() -> bool {
     )
 }
 
-fn truncate_by_chars(text: &str, max_chars: usize) -> String {
-    let char_count = text.chars().count();
-    if char_count <= max_chars {
+/// Estimate the token count of text
+///
+/// CJK characters count as one token each, everything else as four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    let quarters: usize = text.chars().map(char_token_quarters).sum();
+    quarters.div_ceil(4)
+}
+
+/// Token cost of a character in quarter tokens
+fn char_token_quarters(c: char) -> usize {
+    let is_cjk = matches!(c,
+        '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{ac00}'..='\u{d7af}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{ff00}'..='\u{ffef}');
+    if is_cjk {
+        4
+    } else {
+        1
+    }
+}
+
+/// Context window size in tokens for a model name
+pub fn model_context_tokens(model: &str) -> usize {
+    if let Some(tokens) = std::env::var(ENV_ENHANCER_CONTEXT_TOKENS)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|&v| v > 0)
+    {
+        return tokens;
+    }
+
+    let model = model.trim().to_ascii_lowercase();
+    if model.contains("gemini") || model.starts_with("gpt-4.1") {
+        1_000_000
+    } else if model.starts_with("gpt-5") {
+        400_000
+    } else if model.starts_with("claude")
+        || model.starts_with("o1")
+        || model.starts_with("o3")
+        || model.starts_with("o4")
+    {
+        200_000
+    } else if model.starts_with("gpt-4o") {
+        128_000
+    } else {
+        DEFAULT_CONTEXT_TOKENS
+    }
+}
+
+/// Upper bound on search context tokens, from `PROMPT_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS`
+pub fn max_search_context_tokens() -> usize {
+    std::env::var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|&v| v > 0)
+        .unwrap_or(DEFAULT_MAX_SEARCH_CONTEXT_TOKENS)
+}
+
+/// Input token budget for a model after reserving room for the response
+pub fn input_token_budget(model: &str, max_output_tokens: u32) -> usize {
+    let context = model_context_tokens(model);
+    let margin = context * CONTEXT_SAFETY_MARGIN_PERCENT / 100;
+    context
+        .saturating_sub(max_output_tokens as usize)
+        .saturating_sub(margin)
+}
+
+/// Keep the head of `text` within `max_tokens`, appending `notice` when cut
+fn truncate_to_tokens(text: &str, max_tokens: usize, notice: &str) -> String {
+    if estimate_tokens(text) <= max_tokens {
         return text.to_string();
     }
 
-    let mut truncated: String = text.chars().take(max_chars).collect();
-    truncated.push_str("\n\n[codebase_context truncated for length]");
-    truncated
+    let limit = (max_tokens * 4).saturating_sub(estimate_tokens(notice) * 4 + 8);
+    let mut used = 0;
+    let end = text
+        .char_indices()
+        .find(|&(_, c)| {
+            used += char_token_quarters(c);
+            used > limit
+        })
+        .map_or(text.len(), |(i, _)| i);
+
+    format!("{}\n\n{}", &text[..end], notice)
+}
+
+/// Estimate the token count of conversation history (messages by their JSON encoding)
+fn history_tokens(history: &ConversationHistory) -> usize {
+    match history {
+        ConversationHistory::Text(text) => estimate_tokens(text),
+        ConversationHistory::Messages(messages) => {
+            estimate_tokens(&serde_json::to_string(messages).unwrap_or_default())
+        }
+    }
+}
+
+/// Pack conversation history into `max_tokens`, keeping the newest messages
+///
+/// Older messages are dropped first; the oldest kept message may be truncated.
+/// Structured history stays structured.
+fn pack_conversation_history(
+    history: &ConversationHistory,
+    max_tokens: usize,
+) -> ConversationHistory {
+    if history_tokens(history) <= max_tokens {
+        return history.clone();
+    }
+
+    let (messages, is_structured) = match history {
+        ConversationHistory::Messages(messages) => (messages.clone(), true),
+        ConversationHistory::Text(text) => (parse_chat_history(text), false),
+    };
+    if let (ConversationHistory::Text(text), true) = (history, messages.is_empty()) {
+        // Unstructured history: keep the most recent text
+        let chars: Vec<char> = text.chars().collect();
+        let mut used = 0;
+        let start = chars
+            .iter()
+            .rposition(|&c| {
+                used += char_token_quarters(c);
+                used > max_tokens * 4
+            })
+            .map_or(0, |i| i + 1);
+        return ConversationHistory::Text(chars[start..].iter().collect());
+    }
+
+    let message_tokens = |message: &ChatMessage| {
+        let text = if is_structured {
+            serde_json::to_string(message).unwrap_or_default()
+        } else {
+            format_chat_message(message)
+        };
+        estimate_tokens(&text) + 1
+    };
+
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for message in messages.iter().rev() {
+        let cost = message_tokens(message);
+        if used + cost <= max_tokens {
+            kept.push(message.clone());
+            used += cost;
+            continue;
+        }
+
+        let overhead = message_tokens(&ChatMessage {
+            role: message.role.clone(),
+            content: String::new(),
+        });
+        let remaining = (max_tokens - used).saturating_sub(overhead);
+        if remaining >= MIN_PARTIAL_MESSAGE_TOKENS {
+            kept.push(ChatMessage {
+                role: message.role.clone(),
+                content: truncate_to_tokens(
+                    &message.content,
+                    remaining,
+                    "[message truncated for length]",
+                ),
+            });
+        }
+        break;
+    }
+
+    info!(
+        "Packed conversation history: kept {} of {} messages",
+        kept.len(),
+        messages.len()
+    );
+    kept.reverse();
+    if is_structured {
+        ConversationHistory::Messages(kept)
+    } else {
+        ConversationHistory::Text(
+            kept.iter()
+                .map(format_chat_message)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+/// Split search context into ranked sections (one per `Path:` block, or per paragraph)
+fn split_search_context(context: &str) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in context.lines() {
+        if line.starts_with("Path:") || sections.is_empty() {
+            sections.push(String::new());
+        }
+        let section = sections.last_mut().expect("sections is not empty");
+        if !section.is_empty() {
+            section.push('\n');
+        }
+        section.push_str(line);
+    }
+
+    if sections.len() > 1 {
+        return sections;
+    }
+    context
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Pack search context into `max_tokens`, dropping the lowest-ranked sections first
+fn pack_search_context(context: &str, max_tokens: usize) -> String {
+    if estimate_tokens(context) <= max_tokens {
+        return context.to_string();
+    }
+
+    let sections = split_search_context(context);
+    let mut kept: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for section in &sections {
+        let cost = estimate_tokens(section) + 1;
+        if used + cost > max_tokens {
+            break;
+        }
+        kept.push(section);
+        used += cost;
+    }
+
+    if kept.is_empty() {
+        return truncate_to_tokens(
+            context,
+            max_tokens,
+            "[codebase_context truncated for length]",
+        );
+    }
+
+    let omitted = sections.len() - kept.len();
+    info!(
+        "Packed search context: kept {} of {} sections",
+        kept.len(),
+        sections.len()
+    );
+    format!(
+        "{}\n\n[{} lower-ranked codebase_context sections omitted for length]",
+        kept.join("\n\n"),
+        omitted
+    )
+}
+
+/// Pack conversation history into the input budget and return the tokens left for search context
+///
+/// The rendered template and system prompt are fixed costs. History keeps its newest messages
+/// within `HISTORY_BUDGET_PERCENT` of the remainder when search context will also be added.
+fn pack_conversation_into_budget(
+    input: &mut EnhanceInput,
+    budget_tokens: usize,
+    extra_system_prompt: Option<&str>,
+    reserve_for_search: bool,
+) -> usize {
+    let history = std::mem::take(&mut input.conversation_history);
+    let fixed = estimate_tokens(&input.render_prompt())
+        + input
+            .guidelines
+            .to_system_prompt()
+            .map_or(0, |s| estimate_tokens(&s))
+        + extra_system_prompt.map_or(0, estimate_tokens);
+    let available = budget_tokens.saturating_sub(fixed);
+
+    let history_cap = if reserve_for_search {
+        available * HISTORY_BUDGET_PERCENT / 100
+    } else {
+        available
+    };
+    // History is sent as chat messages and, when the template uses it, rendered again
+    let copies = if input.template.uses("conversation_history") {
+        2
+    } else {
+        1
+    };
+    input.conversation_history = pack_conversation_history(&history, history_cap / copies);
+
+    available.saturating_sub(history_tokens(&input.conversation_history) * copies)
+}
+
+/// Enhancement input with its history packed into the model's input budget
+struct PackedInput {
+    input: EnhanceInput,
+    /// Input tokens the model accepts after reserving room for the response
+    budget_tokens: usize,
+    /// Tokens left for search context, capped by `max_search_context_tokens`
+    search_budget: usize,
+}
+
+/// Build the enhancement input and pack its history for the endpoint's model
+fn pack_enhance_input(
+    third_party_config: Option<&ThirdPartyConfig>,
+    original_prompt: &str,
+    conversation_history: &ConversationHistory,
+    project_root: Option<&Path>,
+) -> Result<PackedInput> {
+    let mut input = EnhanceInput {
+        project_name: project_root
+            .and_then(|root| root.file_name())
+            .map(|name| name.to_string_lossy().into_owned()),
+        template: load_enhance_template(project_root)?,
+        guidelines: load_project_guidelines(project_root),
+        ..EnhanceInput::new(original_prompt, conversation_history.clone())
+    };
+
+    let budget_tokens = match third_party_config {
+        Some(tp) => input_token_budget(&tp.model, tp.generation.reserved_output_tokens()),
+        None => input_token_budget(DEFAULT_MODEL, DEFAULT_MAX_TOKENS),
+    };
+    let search_budget = pack_conversation_into_budget(
+        &mut input,
+        budget_tokens,
+        third_party_config.and_then(|tp| tp.generation.system_prompt.as_deref()),
+        should_include_search_context(),
+    );
+    Ok(PackedInput {
+        input,
+        budget_tokens,
+        search_budget: search_budget.min(max_search_context_tokens()),
+    })
 }
 
 fn normalize_search_context(search_context: &str) -> Option<String> {
@@ -84,7 +427,7 @@ fn normalize_search_context(search_context: &str) -> Option<String> {
         return None;
     }
 
-    Some(truncate_by_chars(trimmed, SEARCH_CONTEXT_CHAR_LIMIT))
+    Some(trimmed.to_string())
 }
 
 fn build_prompt_with_search_context(original_prompt: &str, search_context: Option<&str>) -> String {
@@ -97,14 +440,22 @@ fn build_prompt_with_search_context(original_prompt: &str, search_context: Optio
     )
 }
 
+/// Fetch search_context results into the enhancement input when enabled.
+///
+/// Searches run through `registry` when the caller has one, so they share the server's
+/// index managers. The result is packed into `max_tokens`. The NEW endpoint receives it as a separate node,
+/// templates that reference `{search_context}` receive it through that placeholder, and
+/// otherwise the original prompt is wrapped with it.
 async fn maybe_inject_search_context(
     config: &Config,
+    registry: Option<&IndexRegistry>,
     endpoint: EnhancerEndpoint,
-    original_prompt: &str,
+    input: &mut EnhanceInput,
     project_root: Option<&Path>,
-) -> Result<String> {
-    if !endpoint.is_third_party() || !should_include_search_context() {
-        return Ok(original_prompt.to_string());
+    max_tokens: usize,
+) -> Result<()> {
+    if !should_include_search_context() {
+        return Ok(());
     }
 
     let project_root = project_root.ok_or_else(|| {
@@ -123,15 +474,31 @@ async fn maybe_inject_search_context(
         ));
     }
 
-    info!("Injecting search_context into third-party prompt enhancement");
-    let manager = IndexManager::new(Arc::new(config.clone()), project_root.to_path_buf())?;
-    let search_context = manager.search_context(original_prompt).await?;
-    let normalized = normalize_search_context(&search_context);
-
-    Ok(build_prompt_with_search_context(
-        original_prompt,
-        normalized.as_deref(),
-    ))
+    info!(
+        "Injecting search_context into '{}' prompt enhancement",
+        endpoint
+    );
+    let search_context = match registry {
+        Some(registry) => {
+            registry
+                .search_context(project_root, &input.original_prompt)
+                .await?
+        }
+        None => {
+            let manager = IndexManager::new(Arc::new(config.clone()), project_root.to_path_buf())?;
+            manager.search_context(&input.original_prompt).await?
+        }
+    };
+    let normalized = normalize_search_context(&search_context)
+        .map(|context| pack_search_context(&context, max_tokens));
+
+    if endpoint == EnhancerEndpoint::New || input.template.uses("search_context") {
+        input.search_context = normalized;
+    } else {
+        input.original_prompt =
+            build_prompt_with_search_context(&input.original_prompt, normalized.as_deref());
+    }
+    Ok(())
 }
 
 /// Prompt Enhancer
@@ -139,6 +506,10 @@ pub struct PromptEnhancer {
     config: Arc<Config>,
     client: Client,
     server: Arc<EnhancerServer>,
+    use_cache: bool,
+    cache_status: Mutex<Option<CacheStatus>>,
+    /// Index managers of a long-lived server, used for search context
+    registry: Option<Arc<IndexRegistry>>,
 }
 
 impl PromptEnhancer {
@@ -147,16 +518,47 @@ impl PromptEnhancer {
         let client = Client::builder().timeout(Duration::from_secs(60)).build()?;
 
         let server = SHARED_SERVER
-            .get_or_init(|| Arc::new(EnhancerServer::new()))
+            .get_or_init(|| {
+                Arc::new(EnhancerServer::with_timeout(
+                    config.webui_timeout_secs.saturating_mul(1000),
+                ))
+            })
             .clone();
 
         Ok(Self {
             config,
             client,
             server,
+            use_cache: true,
+            cache_status: Mutex::new(None),
+            registry: None,
         })
     }
 
+    /// Search through the shared managers of a long-lived server
+    pub fn with_registry(mut self, registry: Arc<IndexRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Enable or skip the enhancement cache (enabled by default)
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.use_cache = enabled;
+        self
+    }
+
+    /// How the most recent enhancement used the cache
+    pub fn cache_status(&self) -> Option<CacheStatus> {
+        self.cache_status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn set_cache_status(&self, status: CacheStatus) {
+        *self.cache_status.lock().unwrap_or_else(|e| e.into_inner()) = Some(status);
+    }
+
     /// Enhance a prompt with codebase context and conversation history
     ///
     /// # Arguments
@@ -169,7 +571,7 @@ impl PromptEnhancer {
     pub async fn enhance(
         &self,
         original_prompt: &str,
-        conversation_history: &str,
+        conversation_history: &ConversationHistory,
         project_root: Option<&Path>,
     ) -> Result<String> {
         info!("Starting prompt enhancement...");
@@ -188,27 +590,7 @@ impl PromptEnhancer {
         }
 
         // Set up enhance callback for re-enhancement
-        let config = self.config.clone();
-        let client = self.client.clone();
-        let callback_project_root = project_root.map(|p| p.to_path_buf());
-        let callback = Arc::new(move |prompt: String, history: String, blobs: Vec<String>| {
-            let config = config.clone();
-            let client = client.clone();
-            let project_root = callback_project_root.clone();
-            Box::pin(async move {
-                call_prompt_enhancer_api_static(
-                    &client,
-                    &config,
-                    &prompt,
-                    &history,
-                    &blobs,
-                    project_root.as_deref(),
-                )
-                .await
-            })
-                as std::pin::Pin<Box<dyn std::future::Future<Output = Result<String>> + Send>>
-        });
-        self.server.set_enhance_callback(callback).await;
+        self.install_enhance_callback(project_root).await;
 
         // Call prompt-enhancer API
         info!("Calling prompt-enhancer API...");
@@ -230,6 +612,7 @@ impl PromptEnhancer {
                 original_prompt,
                 conversation_history,
                 &blob_names,
+                project_root,
             )
             .await?;
 
@@ -237,14 +620,38 @@ impl PromptEnhancer {
         Ok(final_prompt)
     }
 
-    /// Interact with user through Web UI
-    async fn interact_with_user(
-        &self,
-        enhanced_prompt: &str,
-        original_prompt: &str,
-        conversation_history: &str,
-        blob_names: &[String],
-    ) -> Result<String> {
+    /// Set the server's re-enhance callback for sessions of this project
+    async fn install_enhance_callback(&self, project_root: Option<&Path>) {
+        let config = self.config.clone();
+        let client = self.client.clone();
+        let registry = self.registry.clone();
+        let callback_project_root = project_root.map(|p| p.to_path_buf());
+        let callback = Arc::new(
+            move |prompt: String, history: ConversationHistory, blobs: Vec<String>| {
+                let config = config.clone();
+                let client = client.clone();
+                let registry = registry.clone();
+                let project_root = callback_project_root.clone();
+                Box::pin(async move {
+                    call_prompt_enhancer_api_static(
+                        &client,
+                        &config,
+                        registry.as_deref(),
+                        &prompt,
+                        &history,
+                        &blobs,
+                        project_root.as_deref(),
+                    )
+                    .await
+                })
+                    as std::pin::Pin<Box<dyn std::future::Future<Output = Result<String>> + Send>>
+            },
+        );
+        self.server.set_enhance_callback(callback).await;
+    }
+
+    /// Apply --webui-addr / TLS settings and start the shared Web UI server
+    async fn ensure_server_started(&self, project_root: Option<&Path>) -> Result<()> {
         // Set custom bind address if configured
         if let Some(ref addr_str) = self.config.webui_addr {
             let addr: SocketAddr = addr_str
@@ -252,9 +659,46 @@ impl PromptEnhancer {
                 .map_err(|e| anyhow!("Invalid --webui-addr '{}': {}", addr_str, e))?;
             self.server.set_bind_addr(addr).await;
         }
+        if let (Some(cert), Some(key)) = (&self.config.webui_tls_cert, &self.config.webui_tls_key) {
+            self.server.set_tls(Path::new(cert), Path::new(key)).await?;
+        }
 
-        // Start server
         self.server.start().await?;
+        self.write_discovery_file(project_root).await;
+        Ok(())
+    }
+
+    /// Publish the server's address and dashboard token for editor plugins
+    async fn write_discovery_file(&self, project_root: Option<&Path>) {
+        let Some(info) = self.server.discovery_info().await else {
+            return;
+        };
+        let root = match project_root {
+            Some(root) => root.to_path_buf(),
+            None => match std::env::current_dir() {
+                Ok(dir) => dir,
+                Err(_) => return,
+            },
+        };
+        if let Err(e) = write_discovery_file(&root, &info) {
+            warn!("Failed to write Web UI discovery file: {}", e);
+        }
+    }
+
+    /// Interact with user through Web UI
+    ///
+    /// The session is persisted under `.ace-tool/sessions/`. If nobody submits before the
+    /// timeout, the latest enhanced prompt is returned with a note on how to fetch the
+    /// reviewed version later.
+    async fn interact_with_user(
+        &self,
+        enhanced_prompt: &str,
+        original_prompt: &str,
+        conversation_history: &ConversationHistory,
+        blob_names: &[String],
+        project_root: Option<&Path>,
+    ) -> Result<String> {
+        self.ensure_server_started(project_root).await?;
 
         // Create session (responder is registered at creation time to prevent race conditions)
         let (session_id, rx) = self
@@ -262,16 +706,26 @@ impl PromptEnhancer {
             .create_session(
                 enhanced_prompt.to_string(),
                 original_prompt.to_string(),
-                conversation_history.to_string(),
+                conversation_history.clone(),
                 blob_names.to_vec(),
             )
             .await;
 
-        // Build URL
-        let port = self.server.get_port().await;
-        let host = self.server.get_host().await;
-        let url = format!("http://{}:{}/enhance?session={}", host, port, session_id);
+        if let Err(e) = self
+            .server
+            .persist_session(&session_id, sessions_dir(project_root)?)
+            .await
+        {
+            warn!("Session {} will not survive a timeout: {}", session_id, e);
+        }
+
+        // Build URL (carries the session's secret token)
+        let url =
+            self.server.session_url(&session_id).await.ok_or_else(|| {
+                anyhow!("Session {} disappeared before it was opened", session_id)
+            })?;
         info!("Please open in browser: {}", url);
+        info!("All pending reviews: {}", self.server.dashboard_url().await);
 
         // Try to open browser
         self.open_browser(&url);
@@ -280,22 +734,95 @@ impl PromptEnhancer {
         match self
             .server
             .wait_for_session_with_receiver(&session_id, rx)
-            .await
+            .await?
         {
-            Ok(result) => {
+            SessionOutcome::Completed(result) => {
                 if result.is_empty() {
-                    Err(anyhow!("User cancelled the enhancement"))
+                    Err(ToolError::Cancelled("User cancelled the enhancement".to_string()).into())
                 } else {
                     Ok(result)
                 }
             }
-            Err(e) => {
-                if e.to_string().contains("timeout") {
-                    error!("User interaction timeout (8 minutes)");
-                }
-                Err(e)
+            SessionOutcome::TimedOut(latest) => {
+                warn!(
+                    "User interaction timeout ({} seconds), returning latest enhanced prompt",
+                    self.server.timeout_ms / 1000
+                );
+                Ok(format!(
+                    "{}{}",
+                    latest,
+                    pending_review_note(&session_id, &url, Some(self.server.timeout_ms))
+                ))
+            }
+        }
+    }
+
+    /// Fetch the result of a review session, e.g. after `enhance_prompt` timed out
+    ///
+    /// Returns the submitted text once the reviewer has finished, and deletes the session.
+    /// Otherwise the session is re-opened in the Web UI (if this process no longer has it)
+    /// and the latest enhanced prompt is returned with a note.
+    pub async fn resume_session(
+        &self,
+        session_id: &str,
+        project_root: Option<&Path>,
+    ) -> Result<String> {
+        if let Some(session) = self.server.get_session(session_id).await {
+            if session.status == SessionStatus::Cancelled {
+                self.server.discard_session(session_id).await;
+                return Err(
+                    ToolError::Cancelled("User cancelled the enhancement".to_string()).into(),
+                );
+            }
+            if let Some(result) = session.result {
+                self.server.discard_session(session_id).await;
+                return Ok(result);
             }
+            let url = self
+                .server
+                .session_url(session_id)
+                .await
+                .unwrap_or_default();
+            return Ok(format!(
+                "{}{}",
+                session.enhanced_prompt,
+                pending_review_note(session_id, &url, None)
+            ));
+        }
+
+        let dir = sessions_dir(project_root)?;
+        let persisted = load_session(&dir, session_id)?.ok_or_else(|| {
+            ToolError::InvalidArguments(format!("Session not found: {}", session_id))
+        })?;
+        if persisted.status == SessionStatus::Cancelled || persisted.result.is_some() {
+            if let Err(e) = discard_session(dir, session_id.to_string()).await {
+                warn!("Failed to remove session file for {}: {}", session_id, e);
+            }
+            return match persisted.result {
+                Some(result) => Ok(result),
+                None => {
+                    Err(ToolError::Cancelled("User cancelled the enhancement".to_string()).into())
+                }
+            };
         }
+
+        // Re-open the session in this process so the reviewer can still finish it
+        self.ensure_server_started(project_root).await?;
+        self.install_enhance_callback(project_root).await;
+        let latest = persisted.enhanced_prompt.clone();
+        self.server.restore_session(persisted, dir).await;
+        let url = self
+            .server
+            .session_url(session_id)
+            .await
+            .unwrap_or_default();
+        info!("Review session re-opened: {}", url);
+
+        Ok(format!(
+            "{}{}",
+            latest,
+            pending_review_note(session_id, &url, None)
+        ))
     }
 
     /// Open browser
@@ -332,46 +859,119 @@ impl PromptEnhancer {
         }
     }
 
-    /// Load blob names from index file
+    /// Load the blob hashes of the project's index, sorted so they can key the cache
     fn load_blob_names(&self, project_root: &Path) -> Vec<String> {
-        let index_file_path = get_index_file_path(project_root);
-
-        if !index_file_path.exists() {
-            return Vec::new();
-        }
-
-        match std::fs::read_to_string(&index_file_path) {
-            Ok(content) => match serde_json::from_str(&content) {
-                Ok(names) => names,
-                Err(e) => {
-                    warn!("Failed to parse index file: {}", e);
-                    Vec::new()
-                }
-            },
+        let manager = match IndexManager::new(self.config.clone(), project_root.to_path_buf()) {
+            Ok(manager) => manager,
             Err(e) => {
-                warn!("Failed to read index file: {}", e);
-                Vec::new()
+                warn!("Failed to open index: {}", e);
+                return Vec::new();
             }
-        }
+        };
+        let mut blob_names = manager.load_index().get_all_blob_hashes();
+        blob_names.sort_unstable();
+        blob_names
     }
 
-    /// Call prompt-enhancer API
+    /// Call prompt-enhancer API, serving identical requests from the enhancement cache
     async fn call_prompt_enhancer_api(
         &self,
         original_prompt: &str,
-        conversation_history: &str,
+        conversation_history: &ConversationHistory,
         blob_names: &[String],
         project_root: Option<&Path>,
     ) -> Result<String> {
-        call_prompt_enhancer_api_static(
+        let cache = if self.use_cache {
+            EnhanceCache::for_project(project_root)
+        } else {
+            None
+        };
+        let Some(cache) = cache else {
+            self.set_cache_status(if self.use_cache {
+                CacheStatus::Disabled
+            } else {
+                CacheStatus::Bypassed
+            });
+            return call_prompt_enhancer_api_static(
+                &self.client,
+                &self.config,
+                self.registry.as_deref(),
+                original_prompt,
+                conversation_history,
+                blob_names,
+                project_root,
+            )
+            .await;
+        };
+
+        let endpoint = get_enhancer_endpoint();
+        let third_party_config = if endpoint.is_third_party() {
+            Some(get_third_party_config(endpoint, &self.config.generation)?)
+        } else {
+            None
+        };
+        let packed = pack_enhance_input(
+            third_party_config.as_ref(),
+            original_prompt,
+            conversation_history,
+            project_root,
+        )?;
+        let template = packed.input.template.fingerprint();
+        let guidelines = packed.input.guidelines;
+        let index_config_hash =
+            calculate_config_hash(self.config.max_lines_per_blob, &self.config.secret_scan);
+        let endpoint_name = endpoint.to_string();
+        let key = CacheKeyInput {
+            prompt: original_prompt,
+            conversation_history,
+            endpoint: &endpoint_name,
+            base_url: third_party_config
+                .as_ref()
+                .map_or(self.config.base_url.as_str(), |tp| tp.base_url.as_str()),
+            model: third_party_config
+                .as_ref()
+                .map_or("", |tp| tp.model.as_str()),
+            generation: third_party_config.as_ref().map(|tp| &tp.generation),
+            template: &template,
+            guidelines: &guidelines,
+            index_config_hash: &index_config_hash,
+            blob_names,
+            include_search_context: should_include_search_context(),
+            budget_tokens: packed.budget_tokens,
+            search_budget: packed.search_budget,
+        }
+        .key();
+
+        if let Some(hit) = cache.get(&key) {
+            let age_secs = hit.age.as_secs();
+            info!("Using cached enhancement ({}s old)", age_secs);
+            http_logger::log_cache_hit(project_root, &hit.entry.endpoint, &key, age_secs);
+            self.set_cache_status(CacheStatus::Hit { key, age_secs });
+            return Ok(hit.entry.enhanced_prompt);
+        }
+
+        let enhanced_prompt = call_prompt_enhancer_api_static(
             &self.client,
             &self.config,
+            self.registry.as_deref(),
             original_prompt,
             conversation_history,
             blob_names,
             project_root,
         )
-        .await
+        .await?;
+
+        let entry = CacheEntry::new(
+            key.clone(),
+            enhanced_prompt.clone(),
+            endpoint_name,
+            third_party_config.map(|tp| tp.model),
+        );
+        if let Err(e) = cache.put(&entry) {
+            warn!("Failed to cache enhanced prompt: {}", e);
+        }
+        self.set_cache_status(CacheStatus::Miss { key });
+        Ok(enhanced_prompt)
     }
 
     /// Simple enhancement without Web UI interaction
@@ -379,7 +979,7 @@ impl PromptEnhancer {
     pub async fn enhance_simple(
         &self,
         original_prompt: &str,
-        conversation_history: &str,
+        conversation_history: &ConversationHistory,
         project_root: Option<&Path>,
     ) -> Result<String> {
         info!("Starting simple prompt enhancement (no Web UI)...");
@@ -413,78 +1013,93 @@ impl PromptEnhancer {
     }
 }
 
+/// Sessions directory for a project, defaulting to the current directory
+fn sessions_dir(project_root: Option<&Path>) -> Result<PathBuf> {
+    match project_root {
+        Some(root) => Ok(get_sessions_dir(root)),
+        None => Ok(get_sessions_dir(&std::env::current_dir()?)),
+    }
+}
+
+/// Note appended to an enhanced prompt the reviewer has not submitted yet
+pub fn pending_review_note(session_id: &str, url: &str, timeout_ms: Option<u64>) -> String {
+    let reason = match timeout_ms {
+        Some(ms) => format!("The review timed out after {} seconds", ms / 1000),
+        None => "The review has not been submitted yet".to_string(),
+    };
+    format!(
+        "\n\n---\n[ace-tool] {}, so the prompt above is the latest enhanced version without \
+         reviewer approval. The review is still open at {}. Once it is submitted, call \
+         `get_enhancement_result` with session_id \"{}\" to fetch the final prompt.",
+        reason, url, session_id
+    )
+}
+
 /// Static function to call prompt-enhancer API (used for callback)
 async fn call_prompt_enhancer_api_static(
     client: &Client,
     config: &Config,
+    registry: Option<&IndexRegistry>,
     original_prompt: &str,
-    conversation_history: &str,
+    conversation_history: &ConversationHistory,
     blob_names: &[String],
     project_root: Option<&Path>,
 ) -> Result<String> {
     let endpoint = get_enhancer_endpoint();
-    let enriched_prompt =
-        maybe_inject_search_context(config, endpoint, original_prompt, project_root).await?;
+    let third_party_config = if endpoint.is_third_party() {
+        Some(get_third_party_config(endpoint, &config.generation)?)
+    } else {
+        None
+    };
+    let PackedInput {
+        mut input,
+        search_budget,
+        ..
+    } = pack_enhance_input(
+        third_party_config.as_ref(),
+        original_prompt,
+        conversation_history,
+        project_root,
+    )?;
+    maybe_inject_search_context(
+        config,
+        registry,
+        endpoint,
+        &mut input,
+        project_root,
+        search_budget,
+    )
+    .await?;
 
+    // Third-party arms reuse the config loaded for the budget
+    let third_party_config = move || match third_party_config {
+        Some(third_party_config) => Ok(third_party_config),
+        None => get_third_party_config(endpoint, &config.generation),
+    };
     match endpoint {
         EnhancerEndpoint::New => {
             info!("Using NEW prompt-enhancer endpoint");
-            call_new_endpoint(client, config, original_prompt, conversation_history).await
+            call_new_endpoint(client, config, &input).await
         }
         EnhancerEndpoint::Old => {
             info!("Using OLD chat-stream endpoint");
-            call_old_endpoint(
-                client,
-                config,
-                original_prompt,
-                conversation_history,
-                blob_names,
-            )
-            .await
+            call_old_endpoint(client, config, &input, blob_names).await
         }
         EnhancerEndpoint::Claude => {
             info!("Using Claude API endpoint");
-            let third_party_config = get_third_party_config(endpoint)?;
-            call_claude_endpoint(
-                client,
-                &third_party_config,
-                &enriched_prompt,
-                conversation_history,
-            )
-            .await
+            call_claude_endpoint(client, &third_party_config()?, &input).await
         }
         EnhancerEndpoint::OpenAI => {
             info!("Using OpenAI API endpoint");
-            let third_party_config = get_third_party_config(endpoint)?;
-            call_openai_endpoint(
-                client,
-                &third_party_config,
-                &enriched_prompt,
-                conversation_history,
-            )
-            .await
+            call_openai_endpoint(client, &third_party_config()?, &input).await
         }
         EnhancerEndpoint::Gemini => {
             info!("Using Gemini API endpoint");
-            let third_party_config = get_third_party_config(endpoint)?;
-            call_gemini_endpoint(
-                client,
-                &third_party_config,
-                &enriched_prompt,
-                conversation_history,
-            )
-            .await
+            call_gemini_endpoint(client, &third_party_config()?, &input).await
         }
         EnhancerEndpoint::Codex => {
             info!("Using Codex API endpoint");
-            let third_party_config = get_third_party_config(endpoint)?;
-            call_codex_endpoint(
-                client,
-                &third_party_config,
-                &enriched_prompt,
-                conversation_history,
-            )
-            .await
+            call_codex_endpoint(client, &third_party_config()?, &input).await
         }
     }
 }
@@ -561,27 +1176,235 @@ mod tests {
     }
 
     #[test]
-    fn test_truncate_by_chars_appends_notice() {
-        let result = truncate_by_chars("abcdef", 3);
-        assert!(result.starts_with("abc"));
-        assert!(result.contains("truncated for length"));
+    fn test_truncate_to_tokens_appends_notice() {
+        let text = "abcdefgh".repeat(100);
+        let result = truncate_to_tokens(&text, 50, "[truncated for length]");
+        assert!(result.starts_with("abcdefgh"));
+        assert!(result.ends_with("[truncated for length]"));
+        assert!(estimate_tokens(&result) <= 50);
+
+        assert_eq!(truncate_to_tokens("short", 50, "[cut]"), "short");
     }
 
     #[test]
-    fn test_maybe_inject_search_context_skips_for_non_third_party() {
+    fn test_estimate_tokens_counts_cjk_per_char() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("重构登录"), 4);
+    }
+
+    #[test]
+    fn test_model_context_tokens_by_model() {
+        let _guard = ENV_MUTEX.lock().unwrap();
+        let original = std::env::var(ENV_ENHANCER_CONTEXT_TOKENS).ok();
+        std::env::remove_var(ENV_ENHANCER_CONTEXT_TOKENS);
+
+        assert_eq!(model_context_tokens("claude-sonnet-4-5"), 200_000);
+        assert_eq!(model_context_tokens("gpt-5.2"), 400_000);
+        assert_eq!(model_context_tokens("gpt-4o-mini"), 128_000);
+        assert_eq!(model_context_tokens("gemini-3-flash-preview"), 1_000_000);
+        assert_eq!(
+            model_context_tokens("my-local-model"),
+            DEFAULT_CONTEXT_TOKENS
+        );
+
+        std::env::set_var(ENV_ENHANCER_CONTEXT_TOKENS, "8000");
+        assert_eq!(model_context_tokens("claude-sonnet-4-5"), 8000);
+        assert_eq!(input_token_budget("claude-sonnet-4-5", 1000), 6200);
+
+        match original {
+            Some(v) => std::env::set_var(ENV_ENHANCER_CONTEXT_TOKENS, v),
+            None => std::env::remove_var(ENV_ENHANCER_CONTEXT_TOKENS),
+        }
+    }
+
+    #[test]
+    fn test_max_search_context_tokens_override() {
+        let _guard = ENV_MUTEX.lock().unwrap();
+        let original = std::env::var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS).ok();
+
+        std::env::remove_var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS);
+        assert_eq!(
+            max_search_context_tokens(),
+            DEFAULT_MAX_SEARCH_CONTEXT_TOKENS
+        );
+
+        std::env::set_var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS, "4000");
+        assert_eq!(max_search_context_tokens(), 4000);
+        std::env::set_var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS, "0");
+        assert_eq!(
+            max_search_context_tokens(),
+            DEFAULT_MAX_SEARCH_CONTEXT_TOKENS
+        );
+
+        match original {
+            Some(v) => std::env::set_var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS, v),
+            None => std::env::remove_var(ENV_ENHANCER_MAX_SEARCH_CONTEXT_TOKENS),
+        }
+    }
+
+    #[test]
+    fn test_pack_conversation_history_keeps_newest() {
+        let history = (1..=20)
+            .map(|i| {
+                format!(
+                    "User: question {} {}\nAssistant: answer {}",
+                    i,
+                    "x".repeat(200),
+                    i
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let history = ConversationHistory::Text(history);
+
+        let packed = pack_conversation_history(&history, 200);
+        assert!(history_tokens(&packed) <= 200);
+        let ConversationHistory::Text(text) = &packed else {
+            panic!("text history should stay text");
+        };
+        assert!(text.ends_with("Assistant: answer 20"));
+        assert!(!text.contains("question 1 "));
+
+        assert_eq!(pack_conversation_history(&history, usize::MAX), history);
+    }
+
+    #[test]
+    fn test_pack_conversation_history_keeps_json_structure() {
+        let messages: Vec<ChatMessage> = (1..=20)
+            .flat_map(|i| {
+                [
+                    ChatMessage {
+                        role: "user".to_string(),
+                        content: format!("question {}\nUser: {}", i, "x".repeat(200)),
+                    },
+                    ChatMessage {
+                        role: "assistant".to_string(),
+                        content: format!("answer {}", i),
+                    },
+                ]
+            })
+            .collect();
+        let history = ConversationHistory::Messages(messages.clone());
+
+        let packed = pack_conversation_history(&history, 200);
+        assert!(history_tokens(&packed) <= 200);
+        let ConversationHistory::Messages(kept) = packed else {
+            panic!("structured history should stay structured");
+        };
+        assert_eq!(kept.last(), messages.last());
+        assert!(kept.len() < messages.len());
+        assert!(kept.iter().all(|m| !m.content.starts_with("question 1\n")));
+    }
+
+    #[test]
+    fn test_pack_conversation_history_unstructured_keeps_tail() {
+        let history = ConversationHistory::Text(format!("{}latest", "old ".repeat(500)));
+        let packed = pack_conversation_history(&history, 10);
+        assert!(packed.display().ends_with("latest"));
+        assert!(history_tokens(&packed) <= 10);
+    }
+
+    #[test]
+    fn test_pack_search_context_drops_lowest_ranked_sections() {
+        let context = (1..=5)
+            .map(|i| format!("Path: src/file{}.rs\n{}", i, "code ".repeat(100)))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let packed = pack_search_context(&context, 300);
+        assert!(packed.contains("Path: src/file1.rs"));
+        assert!(packed.contains("Path: src/file2.rs"));
+        assert!(!packed.contains("Path: src/file3.rs"));
+        assert!(packed.ends_with("[3 lower-ranked codebase_context sections omitted for length]"));
+
+        let packed = pack_search_context(&context, 50);
+        assert!(packed.starts_with("Path: src/file1.rs"));
+        assert!(packed.ends_with("[codebase_context truncated for length]"));
+    }
+
+    #[test]
+    fn test_pack_conversation_into_budget_splits_with_search() {
+        let history = (1..=50)
+            .map(|i| format!("User: q{} {}\nAssistant: a{}", i, "y".repeat(400), i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let history = ConversationHistory::Text(history);
+
+        let mut input = EnhanceInput::new("Add login", history.clone());
+        let fixed = estimate_tokens(
+            &EnhanceInput::new("Add login", ConversationHistory::default()).render_prompt(),
+        );
+        let budget = fixed + 2000;
+        let search_budget = pack_conversation_into_budget(&mut input, budget, None, true);
+
+        let packed_tokens = history_tokens(&input.conversation_history);
+        assert!(packed_tokens <= 800);
+        assert!(input
+            .conversation_history
+            .display()
+            .ends_with("Assistant: a50"));
+        assert_eq!(search_budget, 2000 - packed_tokens);
+
+        let mut input = EnhanceInput::new("Add login", history);
+        let search_budget = pack_conversation_into_budget(&mut input, budget, None, false);
+        assert!(history_tokens(&input.conversation_history) > 800);
+        assert!(search_budget < 1200);
+    }
+
+    #[test]
+    fn test_maybe_inject_search_context_skips_when_disabled() {
         let _guard = ENV_MUTEX.lock().unwrap();
         let original = std::env::var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT).ok();
-        std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, "1");
+        std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, "0");
 
         let config = Config::new_for_third_party_enhancer();
-        let result = block_on(maybe_inject_search_context(
-            &config,
-            EnhancerEndpoint::New,
-            "test",
-            None,
-        ))
+        for endpoint in [EnhancerEndpoint::New, EnhancerEndpoint::Claude] {
+            let mut input = EnhanceInput::new("test", ConversationHistory::default());
+            block_on(maybe_inject_search_context(
+                &config,
+                None,
+                endpoint,
+                &mut input,
+                None,
+                usize::MAX,
+            ))
+            .unwrap();
+            assert_eq!(input.original_prompt, "test");
+            assert!(input.search_context.is_none());
+        }
+
+        match original {
+            Some(v) => std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, v),
+            None => std::env::remove_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT),
+        }
+    }
+
+    #[test]
+    fn test_maybe_inject_search_context_applies_to_augment_endpoints() {
+        let _guard = ENV_MUTEX.lock().unwrap();
+        let original = std::env::var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT).ok();
+        std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, "1");
+
+        let config = Config::new(
+            "https://api.example.com".to_string(),
+            "test-token".to_string(),
+            ConfigOptions::default(),
+        )
         .unwrap();
-        assert_eq!(result, "test");
+        for endpoint in [EnhancerEndpoint::New, EnhancerEndpoint::Old] {
+            let err = block_on(maybe_inject_search_context(
+                &config,
+                None,
+                endpoint,
+                &mut EnhanceInput::new("test", ConversationHistory::default()),
+                None,
+                usize::MAX,
+            ))
+            .unwrap_err();
+            assert!(err.to_string().contains("requires project_root"));
+        }
 
         match original {
             Some(v) => std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, v),
@@ -604,9 +1427,11 @@ mod tests {
 
         let err = block_on(maybe_inject_search_context(
             &config,
+            None,
             EnhancerEndpoint::Claude,
-            "test",
+            &mut EnhanceInput::new("test", ConversationHistory::default()),
             None,
+            usize::MAX,
         ))
         .unwrap_err();
         assert!(err.to_string().contains("requires project_root"));
@@ -627,9 +1452,11 @@ mod tests {
         let temp_dir = tempdir().unwrap();
         let err = block_on(maybe_inject_search_context(
             &config,
+            None,
             EnhancerEndpoint::Claude,
-            "test",
+            &mut EnhanceInput::new("test", ConversationHistory::default()),
             Some(temp_dir.path()),
+            usize::MAX,
         ))
         .unwrap_err();
         assert!(err
@@ -1,8 +1,9 @@
 //! Enhancer Server - HTTP server and Session management
 //! Provides Web UI interaction interface
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -13,23 +14,52 @@ use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Method, Request, Response, StatusCode};
 use hyper_util::rt::TokioIo;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::net::TcpListener;
-use tokio::sync::{oneshot, Mutex, RwLock};
+use tokio::sync::{oneshot, watch, Mutex, RwLock};
+use tokio_rustls::rustls::{self, ServerConfig};
+use tokio_rustls::TlsAcceptor;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use super::templates::ENHANCER_UI_HTML;
+use super::api::{
+    CancelRequest, DiffRequest, DiffResponse, DiscoveryInfo, ReEnhanceRequest, ReEnhanceResponse,
+    SessionListResponse, SessionResponse, SessionSummary, SubmitRequest, SuccessResponse,
+    API_PREFIX, API_VERSION, API_VERSION_HEADER, DEFAULT_WAIT_SECS, MAX_WAIT_SECS,
+};
+use super::diff::word_diff;
+use super::prompt_enhancer::get_enhancer_endpoint;
+use super::session_store::{
+    discard_session, prune_sessions, remove_session, PersistedSession, SessionSnapshot,
+};
+use super::templates::{DASHBOARD_HTML, ENHANCER_UI_HTML};
+use crate::config::DEFAULT_WEBUI_TIMEOUT_SECS;
+use crate::service::ConversationHistory;
 
 /// Maximum request body size (1MB)
 const MAX_BODY_SIZE: usize = 1024 * 1024;
 
+/// Default time to wait for the reviewer, in milliseconds
+pub const DEFAULT_TIMEOUT_MS: u64 = DEFAULT_WEBUI_TIMEOUT_SECS * 1000;
+
+/// Request header carrying the per-session secret token
+pub const SESSION_TOKEN_HEADER: &str = "X-Ace-Session-Token";
+
+/// How often the sweeper looks for expired sessions
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a timed-out session stays open in memory for a late reviewer; persisted
+/// sessions not written for this long are deleted
+const TIMED_OUT_SESSION_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// Callback type for re-enhancement
 pub type EnhanceCallback = Arc<
     dyn Fn(
             String,
-            String,
+            ConversationHistory,
             Vec<String>,
         ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<String>> + Send>>
         + Send
@@ -42,18 +72,123 @@ pub struct SessionData {
     pub id: String,
     pub enhanced_prompt: String,
     pub original_prompt: String,
-    pub conversation_history: String,
+    pub conversation_history: ConversationHistory,
     pub blob_names: Vec<String>,
     pub status: SessionStatus,
     pub created_at: Instant,
     pub created_at_ms: u64,
+    /// Per-session secret embedded in the Web UI URL, required on every `/api/*` call
+    pub token: String,
+    /// Enhanced prompt revisions, oldest first (initial enhancement, then each re-enhance)
+    pub revisions: Vec<Revision>,
+    /// Revision the user submitted from, recorded by `/api/submit`
+    pub selected_revision: Option<usize>,
+    /// Final text for the agent, set once the reviewer submits
+    pub result: Option<String>,
+    /// Directory the session is persisted to (see `session_store`), if any
+    pub store_dir: Option<PathBuf>,
+}
+
+impl SessionData {
+    /// Project the session was started from, derived from its store directory
+    pub fn project_root(&self) -> Option<&Path> {
+        self.store_dir.as_deref()?.parent()?.parent()
+    }
+
+    /// Capture the session for its store directory, if it has one; write it with
+    /// `persist` once the sessions lock is released
+    fn snapshot(&self) -> Option<SessionSnapshot> {
+        let dir = self.store_dir.clone()?;
+        Some(SessionSnapshot::new(
+            dir,
+            PersistedSession::from_session(self),
+        ))
+    }
+}
+
+/// Write a session snapshot taken under the sessions lock
+async fn persist(snapshot: Option<SessionSnapshot>) {
+    if let Some(snapshot) = snapshot {
+        let id = snapshot.session_id().to_string();
+        if let Err(e) = snapshot.write().await {
+            warn!("Failed to persist session {}: {}", id, e);
+        }
+    }
+}
+
+/// Delete a session's file once its result has been handed to the agent
+async fn discard(session: Option<SessionData>) {
+    if let Some(SessionData {
+        id,
+        store_dir: Some(dir),
+        ..
+    }) = session
+    {
+        if let Err(e) = discard_session(dir, id.clone()).await {
+            warn!("Failed to remove session file for {}: {}", id, e);
+        }
+    }
+}
+
+/// How waiting for a review session ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionOutcome {
+    /// The reviewer submitted; carries the text to return
+    Completed(String),
+    /// Nobody submitted in time; carries the latest enhanced prompt. The session stays
+    /// open so a late reviewer can still submit.
+    TimedOut(String),
+}
+
+/// One enhanced prompt produced during a session
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    pub text: String,
+    #[serde(rename = "createdAt")]
+    pub created_at_ms: u64,
+    /// Enhancer endpoint that produced the revision (e.g. "new", "claude")
+    pub provider: String,
+    /// User-edited prompt sent for re-enhancement, when it differed from the previous revision
+    #[serde(rename = "userEdit")]
+    pub user_edit: Option<String>,
+}
+
+impl Revision {
+    /// Create a revision produced now by the configured enhancer endpoint
+    pub fn new(text: String, user_edit: Option<String>) -> Self {
+        Self {
+            text,
+            created_at_ms: now_ms(),
+            provider: get_enhancer_endpoint().to_string(),
+            user_edit,
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// Current time in milliseconds since the Unix epoch
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SessionStatus {
     Pending,
     Completed,
+    /// The waiting tool call gave up; the reviewer may still submit
     Timeout,
+    /// Cancelled from the dashboard; the waiting tool call reports a cancellation
+    Cancelled,
+}
+
+impl SessionStatus {
+    /// Whether the session can no longer be submitted or re-enhanced
+    pub fn is_finished(&self) -> bool {
+        matches!(self, SessionStatus::Completed | SessionStatus::Cancelled)
+    }
 }
 
 /// Session response sender
@@ -71,18 +206,32 @@ pub struct EnhancerServer {
     enhance_callback: Arc<RwLock<Option<EnhanceCallback>>>,
     /// Custom bind address requested via --webui-addr (set before start)
     bind_addr: Arc<RwLock<Option<SocketAddr>>>,
+    /// TLS acceptor configured via --webui-tls-cert/--webui-tls-key (set before start)
+    tls: Arc<RwLock<Option<TlsAcceptor>>>,
+    /// Server-wide secret for the sessions dashboard (`/` and `/api/sessions`)
+    dashboard_token: Arc<String>,
+    /// Change counter for the open session list, watched by `/api/sessions/wait`
+    events: Arc<watch::Sender<u64>>,
     pub timeout_ms: u64,
 }
 
 impl EnhancerServer {
     pub fn new() -> Self {
+        Self::with_timeout(DEFAULT_TIMEOUT_MS)
+    }
+
+    /// Create a server that waits `timeout_ms` for each review
+    pub fn with_timeout(timeout_ms: u64) -> Self {
         Self {
             actual_addr: Arc::new(RwLock::new(None)),
             sessions: Arc::new(RwLock::new(HashMap::new())),
             responders: Arc::new(Mutex::new(HashMap::new())),
             enhance_callback: Arc::new(RwLock::new(None)),
             bind_addr: Arc::new(RwLock::new(None)),
-            timeout_ms: 8 * 60 * 1000, // 8 minutes
+            tls: Arc::new(RwLock::new(None)),
+            dashboard_token: Arc::new(generate_session_token()),
+            events: Arc::new(watch::channel(0).0),
+            timeout_ms,
         }
     }
 
@@ -105,6 +254,24 @@ impl EnhancerServer {
         // Both locks are dropped here
     }
 
+    /// Serve the Web UI over HTTPS using a PEM certificate chain and private key.
+    /// Must be called before `start()`. Has no effect if the server is already running.
+    pub async fn set_tls(&self, cert_path: &Path, key_path: &Path) -> Result<()> {
+        let acceptor = load_tls_acceptor(cert_path, key_path)?;
+        let actual = self.actual_addr.read().await;
+        if actual.is_some() {
+            warn!("Server is already running, ignoring set_tls");
+            return Ok(());
+        }
+        *self.tls.write().await = Some(acceptor);
+        Ok(())
+    }
+
+    /// Whether the server serves HTTPS
+    pub async fn is_tls(&self) -> bool {
+        self.tls.read().await.is_some()
+    }
+
     /// Start HTTP server.
     ///
     /// Holds the `actual_addr` write lock through the bind phase so that concurrent
@@ -121,12 +288,14 @@ impl EnhancerServer {
         let custom_addr = *self.bind_addr.read().await;
         let listener: TcpListener;
 
+        let tls = self.tls.read().await.clone();
+
         if let Some(addr) = custom_addr {
-            // Warn if binding to a non-loopback address (security risk)
-            if !addr.ip().is_loopback() {
+            // Warn if binding to a non-loopback address without TLS (tokens travel in clear text)
+            if !addr.ip().is_loopback() && tls.is_none() {
                 warn!(
-                    "Binding to non-loopback address {}. The Web UI has no authentication \
-                     and will be accessible from the network.",
+                    "Binding to non-loopback address {} without TLS. Session tokens will be \
+                     sent unencrypted; consider --webui-tls-cert/--webui-tls-key.",
                     addr
                 );
             }
@@ -176,13 +345,40 @@ impl EnhancerServer {
         // Release the write lock now — concurrent callers can proceed
         drop(actual);
 
-        info!("Enhancer server started: http://{}", local_addr);
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        info!("Enhancer server started: {}://{}", scheme, local_addr);
+
+        // Shared state for the server task
+        let ctx = Arc::new(RequestContext {
+            sessions: self.sessions.clone(),
+            responders: self.responders.clone(),
+            enhance_callback: self.enhance_callback.clone(),
+            timeout_ms: self.timeout_ms,
+            cors: CorsPolicy {
+                scheme,
+                bind_origin: format!(
+                    "{}://{}:{}",
+                    scheme,
+                    url_host(local_addr),
+                    local_addr.port()
+                ),
+            },
+            dashboard_token: self.dashboard_token.clone(),
+            events: self.events.clone(),
+        });
 
-        // Clone references for the server task
-        let sessions = self.sessions.clone();
-        let responders = self.responders.clone();
-        let enhance_callback = self.enhance_callback.clone();
-        let timeout_ms = self.timeout_ms;
+        // Periodically expire sessions nobody is waiting for any more
+        {
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    sweep_sessions(&ctx.sessions, &ctx.responders, &ctx.events, ctx.timeout_ms)
+                        .await;
+                }
+            });
+        }
 
         // Spawn server task
         tokio::spawn(async move {
@@ -195,23 +391,35 @@ impl EnhancerServer {
                     }
                 };
 
-                let io = TokioIo::new(stream);
-                let sessions = sessions.clone();
-                let responders = responders.clone();
-                let enhance_callback = enhance_callback.clone();
+                let ctx = ctx.clone();
+                let tls = tls.clone();
 
                 tokio::spawn(async move {
                     let service = service_fn(|req| {
-                        let sessions = sessions.clone();
-                        let responders = responders.clone();
-                        let enhance_callback = enhance_callback.clone();
-                        async move {
-                            handle_request(req, sessions, responders, enhance_callback, timeout_ms)
+                        let ctx = ctx.clone();
+                        async move { handle_request(req, &ctx).await }
+                    });
+
+                    let result = match tls {
+                        Some(acceptor) => match acceptor.accept(stream).await {
+                            Ok(tls_stream) => {
+                                http1::Builder::new()
+                                    .serve_connection(TokioIo::new(tls_stream), service)
+                                    .await
+                            }
+                            Err(e) => {
+                                warn!("TLS handshake failed: {}", e);
+                                return;
+                            }
+                        },
+                        None => {
+                            http1::Builder::new()
+                                .serve_connection(TokioIo::new(stream), service)
                                 .await
                         }
-                    });
+                    };
 
-                    if let Err(e) = http1::Builder::new().serve_connection(io, service).await {
+                    if let Err(e) = result {
                         if !e.to_string().contains("connection closed") {
                             error!("Error serving connection: {}", e);
                         }
@@ -236,35 +444,71 @@ impl EnhancerServer {
     /// - IPv6 addresses are wrapped in brackets for URL compatibility
     pub async fn get_host(&self) -> String {
         match *self.actual_addr.read().await {
-            Some(addr) => {
-                let ip = addr.ip();
-                if ip.is_unspecified() {
-                    "localhost".to_string()
-                } else if ip.is_ipv6() {
-                    format!("[{}]", ip)
-                } else {
-                    ip.to_string()
-                }
-            }
+            Some(addr) => url_host(addr),
             None => "localhost".to_string(),
         }
     }
 
+    /// Browser URL for a session, including its secret token
+    pub async fn session_url(&self, session_id: &str) -> Option<String> {
+        let token = self.sessions.read().await.get(session_id)?.token.clone();
+        let scheme = if self.is_tls().await { "https" } else { "http" };
+        Some(format!(
+            "{}://{}:{}/enhance?session={}&token={}",
+            scheme,
+            self.get_host().await,
+            self.get_port().await,
+            session_id,
+            token
+        ))
+    }
+
+    /// Connection details for editor plugins, written to `.ace-tool/webui.json`.
+    /// `None` until the server is started.
+    pub async fn discovery_info(&self) -> Option<DiscoveryInfo> {
+        let addr = (*self.actual_addr.read().await)?;
+        let tls = self.is_tls().await;
+        let host = url_host(addr);
+        Some(DiscoveryInfo {
+            api_version: API_VERSION,
+            url: format!(
+                "{}://{}:{}",
+                if tls { "https" } else { "http" },
+                host,
+                addr.port()
+            ),
+            host,
+            port: addr.port(),
+            tls,
+            token: self.dashboard_token.to_string(),
+            pid: std::process::id(),
+        })
+    }
+
+    /// Browser URL for the dashboard listing every open session
+    pub async fn dashboard_url(&self) -> String {
+        let scheme = if self.is_tls().await { "https" } else { "http" };
+        format!(
+            "{}://{}:{}/?token={}",
+            scheme,
+            self.get_host().await,
+            self.get_port().await,
+            self.dashboard_token
+        )
+    }
+
     /// Create new session and return a receiver for the result
     /// The responder is registered at creation time to prevent race conditions
     pub async fn create_session(
         &self,
         enhanced_prompt: String,
         original_prompt: String,
-        conversation_history: String,
+        conversation_history: ConversationHistory,
         blob_names: Vec<String>,
     ) -> (String, oneshot::Receiver<String>) {
         let session_id = Uuid::new_v4().to_string();
         let now = Instant::now();
-        let created_at_ms = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_millis() as u64)
-            .unwrap_or(0);
+        let initial_revision = Revision::new(enhanced_prompt.clone(), None);
 
         let session = SessionData {
             id: session_id.clone(),
@@ -274,7 +518,12 @@ impl EnhancerServer {
             blob_names,
             status: SessionStatus::Pending,
             created_at: now,
-            created_at_ms,
+            created_at_ms: initial_revision.created_at_ms,
+            token: generate_session_token(),
+            revisions: vec![initial_revision],
+            selected_revision: None,
+            result: None,
+            store_dir: None,
         };
 
         // Create oneshot channel and register responder BEFORE inserting session
@@ -290,52 +539,121 @@ impl EnhancerServer {
             let mut sessions = self.sessions.write().await;
             sessions.insert(session_id.clone(), session);
         }
+        notify_session_change(&self.events);
 
         info!("Created session: {}", session_id);
         (session_id, rx)
     }
 
+    /// Persist a session to `store_dir` now and after every later change
+    pub async fn persist_session(&self, session_id: &str, store_dir: PathBuf) -> Result<()> {
+        let snapshot = {
+            let mut sessions = self.sessions.write().await;
+            let session = sessions
+                .get_mut(session_id)
+                .ok_or_else(|| anyhow!("Session not found: {}", session_id))?;
+            session.store_dir = Some(store_dir);
+            session.snapshot()
+        };
+        match snapshot {
+            Some(snapshot) => snapshot.write().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Re-open a persisted session so a late reviewer can finish it in the Web UI
+    pub async fn restore_session(&self, persisted: PersistedSession, store_dir: PathBuf) {
+        let session = persisted.into_session(store_dir);
+        info!("Restored session: {}", session.id);
+        self.sessions
+            .write()
+            .await
+            .insert(session.id.clone(), session);
+        notify_session_change(&self.events);
+    }
+
     /// Wait for session completion using a pre-created receiver
+    ///
+    /// On timeout the session is marked `Timeout` but kept (and persisted), so the
+    /// reviewer can still submit and the result can be fetched later. A completed
+    /// session is removed, together with its file.
     pub async fn wait_for_session_with_receiver(
         &self,
         session_id: &str,
         rx: oneshot::Receiver<String>,
-    ) -> Result<String> {
+    ) -> Result<SessionOutcome> {
         // Set up timeout
         let timeout = Duration::from_millis(self.timeout_ms);
 
         match tokio::time::timeout(timeout, rx).await {
             Ok(Ok(result)) => {
                 // Clean up session
-                {
-                    let mut sessions = self.sessions.write().await;
-                    sessions.remove(session_id);
-                }
-                Ok(result)
+                let session = self.sessions.write().await.remove(session_id);
+                discard(session).await;
+                Ok(SessionOutcome::Completed(result))
             }
             Ok(Err(_)) => {
-                // Channel closed - clean up session
-                {
-                    let mut sessions = self.sessions.write().await;
-                    sessions.remove(session_id);
+                // The sweeper expires sessions by dropping their responder
+                let mut sessions = self.sessions.write().await;
+                if let Some(session) = sessions.get(session_id) {
+                    if session.status == SessionStatus::Timeout {
+                        return Ok(SessionOutcome::TimedOut(session.enhanced_prompt.clone()));
+                    }
                 }
+                // Channel closed - clean up session
+                sessions.remove(session_id);
                 Err(anyhow!("Session channel closed unexpectedly"))
             }
             Err(_) => {
-                // Timeout - clean up session and responder
-                {
-                    let mut sessions = self.sessions.write().await;
-                    sessions.remove(session_id);
-                }
+                // Timeout - stop waiting but keep the session open for a late submit
                 {
                     let mut responders = self.responders.lock().await;
                     responders.remove(session_id);
                 }
-                Err(anyhow!("User interaction timeout (8 minutes)"))
+                let mut sessions = self.sessions.write().await;
+                let session = sessions
+                    .get_mut(session_id)
+                    .ok_or_else(|| anyhow!("Session not found: {}", session_id))?;
+                if let Some(result) = session.result.clone() {
+                    // Submitted just as the wait expired
+                    let session = sessions.remove(session_id);
+                    drop(sessions);
+                    discard(session).await;
+                    return Ok(SessionOutcome::Completed(result));
+                }
+                session.status = SessionStatus::Timeout;
+                let latest = session.enhanced_prompt.clone();
+                let snapshot = session.snapshot();
+                drop(sessions);
+                persist(snapshot).await;
+                Ok(SessionOutcome::TimedOut(latest))
             }
         }
     }
 
+    /// Get a snapshot of a session, if it still exists
+    pub async fn get_session(&self, session_id: &str) -> Option<SessionData> {
+        self.sessions.read().await.get(session_id).cloned()
+    }
+
+    /// Drop a finished session whose result was handed to the agent, and delete its file
+    pub async fn discard_session(&self, session_id: &str) {
+        let session = self.sessions.write().await.remove(session_id);
+        discard(session).await;
+    }
+
+    /// Mark pending sessions past the timeout as `Timeout` and free their responders.
+    /// Runs periodically once the server is started; returns the number of expired sessions.
+    pub async fn sweep_expired_sessions(&self) -> usize {
+        sweep_sessions(
+            &self.sessions,
+            &self.responders,
+            &self.events,
+            self.timeout_ms,
+        )
+        .await
+    }
+
     /// Set enhance callback
     pub async fn set_enhance_callback(&self, callback: EnhanceCallback) {
         let mut cb = self.enhance_callback.write().await;
@@ -349,17 +667,30 @@ impl Default for EnhancerServer {
     }
 }
 
-/// Handle HTTP request
-async fn handle_request(
-    req: Request<Incoming>,
+/// State shared by every request of a running server
+struct RequestContext {
     sessions: Arc<RwLock<HashMap<String, SessionData>>>,
     responders: Arc<Mutex<HashMap<String, SessionResponder>>>,
     enhance_callback: Arc<RwLock<Option<EnhanceCallback>>>,
     timeout_ms: u64,
+    cors: CorsPolicy,
+    dashboard_token: Arc<String>,
+    events: Arc<watch::Sender<u64>>,
+}
+
+/// Handle HTTP request
+async fn handle_request(
+    req: Request<Incoming>,
+    ctx: &RequestContext,
 ) -> Result<Response<Full<Bytes>>, hyper::Error> {
     let method = req.method().clone();
-    let path = req.uri().path().to_string();
     let query = req.uri().query().map(|s| s.to_string());
+    let origin = ctx.cors.allowed_origin(&req);
+    // `/api/v1/...` is the versioned form of `/api/...`
+    let path = match req.uri().path().strip_prefix(API_PREFIX) {
+        Some(rest) if rest.starts_with('/') => format!("/api{}", rest),
+        _ => req.uri().path().to_string(),
+    };
 
     // Handle CORS preflight
     if method == Method::OPTIONS {
@@ -368,15 +699,59 @@ async fn handle_request(
                 .status(StatusCode::OK)
                 .body(Full::new(Bytes::new()))
                 .unwrap(),
+            &origin,
         ));
     }
 
+    // Every API call must carry the session token from the Web UI URL
+    let token = request_token(&req, query.as_deref());
+    if path.starts_with("/api/") && token.is_empty() {
+        return Ok(api_response(cors_response(
+            json_error_response(StatusCode::UNAUTHORIZED, "Session token is required"),
+            &origin,
+        )));
+    }
+
+    let sessions = ctx.sessions.clone();
+    let dashboard_token = ctx.dashboard_token.as_str();
     let response = match (method, path.as_str()) {
+        (Method::GET, "/") => serve_html(DASHBOARD_HTML),
         (Method::GET, "/enhance") => serve_enhancer_ui(),
-        (Method::GET, "/api/session") => get_session_data(query, sessions, timeout_ms).await,
-        (Method::POST, "/api/submit") => handle_submit(req, sessions, responders).await,
+        (Method::GET, "/api/session") => {
+            get_session_data(query, sessions, ctx.timeout_ms, &token).await
+        }
+        (Method::GET, "/api/sessions") => {
+            if !secret_eq(dashboard_token, &token) {
+                dashboard_forbidden_response()
+            } else {
+                let cursor = *ctx.events.borrow();
+                list_sessions(&sessions, ctx.timeout_ms, cursor).await
+            }
+        }
+        (Method::GET, "/api/sessions/wait") => {
+            if !secret_eq(dashboard_token, &token) {
+                dashboard_forbidden_response()
+            } else {
+                wait_for_sessions(query.as_deref(), ctx).await
+            }
+        }
+        (Method::POST, "/api/submit") => {
+            handle_submit(req, sessions, ctx.responders.clone(), &token, &ctx.events).await
+        }
         (Method::POST, "/api/re-enhance") => {
-            handle_re_enhance(req, sessions, enhance_callback).await
+            handle_re_enhance(req, sessions, ctx.enhance_callback.clone(), &token).await
+        }
+        (Method::POST, "/api/diff") => handle_diff(req, sessions, &token).await,
+        (Method::POST, "/api/cancel") => {
+            handle_cancel(
+                req,
+                sessions,
+                ctx.responders.clone(),
+                &token,
+                dashboard_token,
+                &ctx.events,
+            )
+            .await
         }
         _ => Response::builder()
             .status(StatusCode::NOT_FOUND)
@@ -385,52 +760,376 @@ async fn handle_request(
             .unwrap(),
     };
 
-    Ok(cors_response(response))
+    let response = cors_response(response, &origin);
+    Ok(if path.starts_with("/api/") {
+        api_response(response)
+    } else {
+        response
+    })
 }
 
-/// Add CORS headers (restricted to localhost only)
-pub fn cors_response(mut response: Response<Full<Bytes>>) -> Response<Full<Bytes>> {
+/// Tag an API response with the API version
+fn api_response(mut response: Response<Full<Bytes>>) -> Response<Full<Bytes>> {
+    response
+        .headers_mut()
+        .insert(API_VERSION_HEADER, API_VERSION.into());
+    response
+}
+
+/// Allowed CORS origin, derived from the address the server is bound to
+struct CorsPolicy {
+    scheme: &'static str,
+    /// Origin built from the bind address (unspecified addresses map to localhost)
+    bind_origin: String,
+}
+
+impl CorsPolicy {
+    /// Reflect the request's Origin when it matches the Host it was sent to (e.g. a remote
+    /// or devcontainer hostname for a 0.0.0.0 bind), otherwise allow only the bind origin
+    fn allowed_origin<B>(&self, req: &Request<B>) -> String {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        match (header("Origin"), header("Host")) {
+            (Some(origin), Some(host)) if origin == format!("{}://{}", self.scheme, host) => {
+                origin.to_string()
+            }
+            _ => self.bind_origin.clone(),
+        }
+    }
+}
+
+/// Add CORS headers allowing only `origin`
+pub fn cors_response(mut response: Response<Full<Bytes>>, origin: &str) -> Response<Full<Bytes>> {
     let headers = response.headers_mut();
-    headers.insert(
-        "Access-Control-Allow-Origin",
-        "http://localhost".parse().unwrap(),
-    );
+    if let Ok(value) = origin.parse() {
+        headers.insert("Access-Control-Allow-Origin", value);
+    }
+    headers.insert("Vary", "Origin".parse().unwrap());
     headers.insert(
         "Access-Control-Allow-Methods",
         "GET, POST, OPTIONS".parse().unwrap(),
     );
     headers.insert(
         "Access-Control-Allow-Headers",
-        "Content-Type".parse().unwrap(),
+        format!("Content-Type, {}", SESSION_TOKEN_HEADER)
+            .parse()
+            .unwrap(),
     );
     response
 }
 
+/// Host part of a browser URL for a bound address.
+/// Unspecified addresses map to "localhost"; IPv6 addresses are bracketed.
+fn url_host(addr: SocketAddr) -> String {
+    let ip = addr.ip();
+    if ip.is_unspecified() {
+        "localhost".to_string()
+    } else if ip.is_ipv6() {
+        format!("[{}]", ip)
+    } else {
+        ip.to_string()
+    }
+}
+
+/// Build a TLS acceptor from a PEM certificate chain and private key
+pub fn load_tls_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            anyhow!(
+                "Failed to read TLS certificate {}: {}",
+                cert_path.display(),
+                e
+            )
+        })?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found in {}", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| {
+        anyhow!(
+            "Failed to read TLS private key {}: {}",
+            key_path.display(),
+            e
+        )
+    })?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| anyhow!("Invalid TLS certificate/key: {}", e))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Generate a random per-session token (two v4 UUIDs, 244 random bits)
+fn generate_session_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Session token from the `X-Ace-Session-Token` header or the `token` query parameter
+fn request_token<B>(req: &Request<B>, query: Option<&str>) -> String {
+    if let Some(token) = req
+        .headers()
+        .get(SESSION_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        return token.to_string();
+    }
+    query
+        .and_then(|q| query_param(q, "token"))
+        .unwrap_or_default()
+}
+
+/// Value of a query string parameter (values are used as-is, without percent-decoding)
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|param| {
+        let mut parts = param.splitn(2, '=');
+        if parts.next()? == name {
+            Some(parts.next()?.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Bump the session list change counter, waking `/api/sessions/wait` callers
+fn notify_session_change(events: &watch::Sender<u64>) {
+    events.send_modify(|cursor| *cursor += 1);
+}
+
+/// Compare a presented token with the session's token in constant time
+fn token_matches(session: &SessionData, token: &str) -> bool {
+    secret_eq(&session.token, token)
+}
+
+/// Constant-time string comparison for secrets
+pub(crate) fn secret_eq(expected: &str, presented: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), presented.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Response for a request whose token does not match the session
+fn forbidden_response() -> Response<Full<Bytes>> {
+    json_error_response(StatusCode::FORBIDDEN, "Invalid session token")
+}
+
+/// Response for a listing request without the dashboard token
+fn dashboard_forbidden_response() -> Response<Full<Bytes>> {
+    json_error_response(StatusCode::FORBIDDEN, "Invalid dashboard token")
+}
+
 /// Serve Web UI HTML
 pub fn serve_enhancer_ui() -> Response<Full<Bytes>> {
+    serve_html(ENHANCER_UI_HTML)
+}
+
+fn serve_html(html: &'static str) -> Response<Full<Bytes>> {
     Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "text/html; charset=utf-8")
-        .body(Full::new(Bytes::from(ENHANCER_UI_HTML)))
+        .body(Full::new(Bytes::from(html)))
         .unwrap()
 }
 
+/// Expire pending sessions past `timeout_ms` and evict finished or long-expired ones.
+///
+/// Expired sessions are marked `Timeout` (and persisted) but stay open for a late submit;
+/// dropping their responder wakes any tool call still waiting on them.
+async fn sweep_sessions(
+    sessions: &RwLock<HashMap<String, SessionData>>,
+    responders: &Mutex<HashMap<String, SessionResponder>>,
+    events: &watch::Sender<u64>,
+    timeout_ms: u64,
+) -> usize {
+    let timeout = Duration::from_millis(timeout_ms);
+    let mut expired = Vec::new();
+    let mut snapshots = Vec::new();
+    let mut abandoned = Vec::new();
+    let mut store_dirs = HashSet::new();
+    {
+        let mut sessions = sessions.write().await;
+        for session in sessions.values_mut() {
+            if session.status == SessionStatus::Pending && session.created_at.elapsed() >= timeout {
+                session.status = SessionStatus::Timeout;
+                snapshots.extend(session.snapshot());
+                expired.push(session.id.clone());
+            }
+            store_dirs.extend(session.store_dir.clone());
+        }
+        // Finished sessions are only kept until their waiter has picked up the result;
+        // their files stay for `get_enhancement_result` until pruned by age
+        sessions.retain(|_, session| {
+            let age = session.created_at.elapsed();
+            match session.status {
+                SessionStatus::Pending => true,
+                SessionStatus::Timeout => {
+                    let keep = age < TIMED_OUT_SESSION_RETENTION;
+                    if !keep {
+                        abandoned.push((session.store_dir.clone(), session.id.clone()));
+                    }
+                    keep
+                }
+                SessionStatus::Completed | SessionStatus::Cancelled => {
+                    age < timeout + SWEEP_INTERVAL
+                }
+            }
+        });
+    }
+
+    if !expired.is_empty() || !abandoned.is_empty() {
+        notify_session_change(events);
+    }
+    for snapshot in snapshots {
+        persist(Some(snapshot)).await;
+    }
+    let pruned = tokio::task::spawn_blocking(move || {
+        for (dir, id) in &abandoned {
+            if let Some(dir) = dir {
+                if let Err(e) = remove_session(dir, id) {
+                    warn!("Failed to remove session file for {}: {}", id, e);
+                }
+            }
+        }
+        store_dirs
+            .iter()
+            .map(|dir| prune_sessions(dir, TIMED_OUT_SESSION_RETENTION).unwrap_or(0))
+            .sum::<usize>()
+    })
+    .await
+    .unwrap_or(0);
+    if pruned > 0 {
+        info!("Removed {} stale session file(s)", pruned);
+    }
+    if !expired.is_empty() {
+        let mut responders = responders.lock().await;
+        for id in &expired {
+            responders.remove(id);
+        }
+        info!("Expired {} review session(s)", expired.len());
+    }
+    expired.len()
+}
+
+/// List open (pending or timed-out) sessions for the dashboard, newest first
+async fn list_sessions(
+    sessions: &RwLock<HashMap<String, SessionData>>,
+    timeout_ms: u64,
+    cursor: u64,
+) -> Response<Full<Bytes>> {
+    let sessions = sessions.read().await;
+    let mut list: Vec<SessionSummary> = sessions
+        .values()
+        .filter(|s| !s.status.is_finished())
+        .map(|s| SessionSummary {
+            id: s.id.clone(),
+            status: s.status.clone(),
+            project: s.project_root().map(|p| p.display().to_string()),
+            original_prompt: s.original_prompt.clone(),
+            created_at: s.created_at_ms,
+            age_ms: s.created_at.elapsed().as_millis() as u64,
+            url: format!("/enhance?session={}&token={}", s.id, s.token),
+            token: s.token.clone(),
+        })
+        .collect();
+    list.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+
+    let resp = SessionListResponse {
+        sessions: list,
+        timeout_ms,
+        cursor,
+    };
+    json_response(StatusCode::OK, &serde_json::to_string(&resp).unwrap())
+}
+
+/// Long-poll: list open sessions once the change counter moves past `cursor`,
+/// or after `timeout` seconds (default 30, max 120) with the list unchanged
+async fn wait_for_sessions(query: Option<&str>, ctx: &RequestContext) -> Response<Full<Bytes>> {
+    let param = |name| query.and_then(|q| query_param(q, name));
+    let cursor = match param("cursor").map(|c| c.parse::<u64>()) {
+        Some(Ok(c)) => c,
+        Some(Err(_)) => return json_error_response(StatusCode::BAD_REQUEST, "Invalid cursor"),
+        None => 0,
+    };
+    let wait_secs = param("timeout")
+        .and_then(|t| t.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_WAIT_SECS)
+        .min(MAX_WAIT_SECS);
+
+    let mut rx = ctx.events.subscribe();
+    let changed = rx.wait_for(|current| *current > cursor);
+    let _ = tokio::time::timeout(Duration::from_secs(wait_secs), changed).await;
+
+    let current = *ctx.events.borrow();
+    list_sessions(&ctx.sessions, ctx.timeout_ms, current).await
+}
+
+/// Cancel an open session; accepts the session token or the dashboard token.
+/// A tool call still waiting on the session reports the enhancement as cancelled.
+async fn handle_cancel(
+    req: Request<Incoming>,
+    sessions: Arc<RwLock<HashMap<String, SessionData>>>,
+    responders: Arc<Mutex<HashMap<String, SessionResponder>>>,
+    token: &str,
+    dashboard_token: &str,
+    events: &watch::Sender<u64>,
+) -> Response<Full<Bytes>> {
+    let body = match read_body_with_limit(req, MAX_BODY_SIZE).await {
+        Ok(b) => b,
+        Err(e) => {
+            return json_error_response(StatusCode::BAD_REQUEST, &e);
+        }
+    };
+
+    let cancel: CancelRequest = match serde_json::from_slice(&body) {
+        Ok(c) => c,
+        Err(_) => {
+            return json_error_response(StatusCode::BAD_REQUEST, "Invalid request body");
+        }
+    };
+
+    let snapshot = {
+        let mut sessions = sessions.write().await;
+        let session = match sessions.get_mut(&cancel.session_id) {
+            Some(s) => s,
+            None => {
+                return json_error_response(StatusCode::NOT_FOUND, "Session not found");
+            }
+        };
+        if !token_matches(session, token) && !secret_eq(dashboard_token, token) {
+            return forbidden_response();
+        }
+        if session.status.is_finished() {
+            return json_error_response(StatusCode::BAD_REQUEST, "Session already completed");
+        }
+        session.status = SessionStatus::Cancelled;
+        session.snapshot()
+    };
+    persist(snapshot).await;
+    notify_session_change(events);
+
+    // An empty result tells the waiting tool call the user cancelled
+    {
+        let mut responders = responders.lock().await;
+        if let Some(responder) = responders.remove(&cancel.session_id) {
+            let _ = responder.sender.send(String::new());
+        }
+    }
+
+    info!("Session {} cancelled", cancel.session_id);
+    json_response(
+        StatusCode::OK,
+        &serde_json::to_string(&SuccessResponse { success: true }).unwrap(),
+    )
+}
+
 /// Get session data
 async fn get_session_data(
     query: Option<String>,
     sessions: Arc<RwLock<HashMap<String, SessionData>>>,
     timeout_ms: u64,
+    token: &str,
 ) -> Response<Full<Bytes>> {
-    let session_id = query.and_then(|q| {
-        q.split('&').find_map(|param| {
-            let mut parts = param.splitn(2, '=');
-            if parts.next()? == "session" {
-                Some(parts.next()?.to_string())
-            } else {
-                None
-            }
-        })
-    });
+    let session_id = query.and_then(|q| query_param(&q, "session"));
 
     let session_id = match session_id {
         Some(id) => id,
@@ -449,28 +1148,16 @@ async fn get_session_data(
             return json_response(StatusCode::NOT_FOUND, r#"{"error":"Session not found"}"#);
         }
     };
-
-    #[derive(Serialize)]
-    struct SessionResponse {
-        #[serde(rename = "enhancedPrompt")]
-        enhanced_prompt: String,
-        status: String,
-        #[serde(rename = "createdAt")]
-        created_at: u64,
-        #[serde(rename = "timeoutMs")]
-        timeout_ms: u64,
+    if !token_matches(session, token) {
+        return forbidden_response();
     }
 
     let resp = SessionResponse {
         enhanced_prompt: session.enhanced_prompt.clone(),
-        status: match session.status {
-            SessionStatus::Pending => "pending",
-            SessionStatus::Completed => "completed",
-            SessionStatus::Timeout => "timeout",
-        }
-        .to_string(),
+        status: session.status.clone(),
         created_at: session.created_at_ms,
         timeout_ms,
+        revisions: session.revisions.clone(),
     };
 
     json_response(StatusCode::OK, &serde_json::to_string(&resp).unwrap())
@@ -481,6 +1168,8 @@ async fn handle_submit(
     req: Request<Incoming>,
     sessions: Arc<RwLock<HashMap<String, SessionData>>>,
     responders: Arc<Mutex<HashMap<String, SessionResponder>>>,
+    token: &str,
+    events: &watch::Sender<u64>,
 ) -> Response<Full<Bytes>> {
     let body = match read_body_with_limit(req, MAX_BODY_SIZE).await {
         Ok(b) => b,
@@ -489,15 +1178,6 @@ async fn handle_submit(
         }
     };
 
-    #[derive(Deserialize)]
-    struct SubmitRequest {
-        #[serde(rename = "sessionId")]
-        session_id: String,
-        content: String,
-        #[serde(default)]
-        action: Option<String>,
-    }
-
     let submit: SubmitRequest = match serde_json::from_slice(&body) {
         Ok(s) => s,
         Err(_) => {
@@ -505,8 +1185,8 @@ async fn handle_submit(
         }
     };
 
-    // Get session and update status
-    let original_prompt = {
+    // Get session, resolve the result and update status
+    let (result, snapshot) = {
         let mut sessions = sessions.write().await;
         let session = match sessions.get_mut(&submit.session_id) {
             Some(s) => s,
@@ -514,41 +1194,58 @@ async fn handle_submit(
                 return json_error_response(StatusCode::NOT_FOUND, "Session not found");
             }
         };
-
-        if session.status != SessionStatus::Pending {
-            return json_error_response(
-                StatusCode::BAD_REQUEST,
-                "Session already completed or timed out",
-            );
+        if !token_matches(session, token) {
+            return forbidden_response();
         }
 
-        session.status = SessionStatus::Completed;
-        session.original_prompt.clone()
-    };
-
-    // Determine what to send back - check action field first, then fallback to magic strings
-    let result = match submit.action.as_deref() {
-        Some("use_original") => {
-            info!("User chose to use original prompt");
-            original_prompt
+        // Timed-out sessions stay open so a late reviewer can still finish
+        if session.status.is_finished() {
+            return json_error_response(StatusCode::BAD_REQUEST, "Session already completed");
         }
-        Some("end_conversation") => {
-            info!("User chose to end conversation");
-            "__END_CONVERSATION__".to_string()
+
+        if let Some(index) = submit.revision_index {
+            if index >= session.revisions.len() {
+                return json_error_response(StatusCode::BAD_REQUEST, "Invalid revision index");
+            }
+            info!(
+                "Session {} submitted from revision {} of {}",
+                submit.session_id,
+                index,
+                session.revisions.len()
+            );
         }
-        _ => {
-            // Fallback to magic strings for backward compatibility
-            if submit.content == "__USE_ORIGINAL__" {
+
+        // Determine what to send back - check action field first, then fallback to magic strings
+        let result = match submit.action.as_deref() {
+            Some("use_original") => {
                 info!("User chose to use original prompt");
-                original_prompt
-            } else if submit.content == "__END_CONVERSATION__" {
+                session.original_prompt.clone()
+            }
+            Some("end_conversation") => {
                 info!("User chose to end conversation");
                 "__END_CONVERSATION__".to_string()
-            } else {
-                submit.content
             }
-        }
+            _ => {
+                // Fallback to magic strings for backward compatibility
+                if submit.content == "__USE_ORIGINAL__" {
+                    info!("User chose to use original prompt");
+                    session.original_prompt.clone()
+                } else if submit.content == "__END_CONVERSATION__" {
+                    info!("User chose to end conversation");
+                    "__END_CONVERSATION__".to_string()
+                } else {
+                    submit.content
+                }
+            }
+        };
+
+        session.status = SessionStatus::Completed;
+        session.selected_revision = submit.revision_index;
+        session.result = Some(result.clone());
+        (result, session.snapshot())
     };
+    persist(snapshot).await;
+    notify_session_change(events);
 
     // Send result through channel
     {
@@ -561,7 +1258,7 @@ async fn handle_submit(
     info!("Session {} completed", submit.session_id);
     json_response(
         StatusCode::OK,
-        &serde_json::to_string(&json!({"success": true})).unwrap(),
+        &serde_json::to_string(&SuccessResponse { success: true }).unwrap(),
     )
 }
 
@@ -570,6 +1267,7 @@ async fn handle_re_enhance(
     req: Request<Incoming>,
     sessions: Arc<RwLock<HashMap<String, SessionData>>>,
     enhance_callback: Arc<RwLock<Option<EnhanceCallback>>>,
+    token: &str,
 ) -> Response<Full<Bytes>> {
     let body = match read_body_with_limit(req, MAX_BODY_SIZE).await {
         Ok(b) => b,
@@ -578,14 +1276,6 @@ async fn handle_re_enhance(
         }
     };
 
-    #[derive(Deserialize)]
-    struct ReEnhanceRequest {
-        #[serde(rename = "sessionId")]
-        session_id: String,
-        #[serde(rename = "currentPrompt")]
-        current_prompt: String,
-    }
-
     let req_data: ReEnhanceRequest = match serde_json::from_slice(&body) {
         Ok(r) => r,
         Err(_) => {
@@ -594,7 +1284,7 @@ async fn handle_re_enhance(
     };
 
     // Get session data
-    let (conversation_history, blob_names, status) = {
+    let (conversation_history, blob_names, status, previous_text) = {
         let sessions = sessions.read().await;
         let session = match sessions.get(&req_data.session_id) {
             Some(s) => s,
@@ -602,18 +1292,19 @@ async fn handle_re_enhance(
                 return json_error_response(StatusCode::NOT_FOUND, "Session not found");
             }
         };
+        if !token_matches(session, token) {
+            return forbidden_response();
+        }
         (
             session.conversation_history.clone(),
             session.blob_names.clone(),
             session.status.clone(),
+            session.enhanced_prompt.clone(),
         )
     };
 
-    if status != SessionStatus::Pending {
-        return json_error_response(
-            StatusCode::BAD_REQUEST,
-            "Session already completed or timed out",
-        );
+    if status.is_finished() {
+        return json_error_response(StatusCode::BAD_REQUEST, "Session already completed");
     }
 
     // Get callback
@@ -634,21 +1325,33 @@ async fn handle_re_enhance(
 
     info!("Re-enhancing session: {}", req_data.session_id);
 
+    let user_edit = (req_data.current_prompt.trim() != previous_text.trim())
+        .then(|| req_data.current_prompt.clone());
+
     // Call enhance callback
     match callback(req_data.current_prompt, conversation_history, blob_names).await {
         Ok(enhanced) => {
-            // Update session
-            {
+            // Update session and record the new revision
+            let revision = Revision::new(enhanced.clone(), user_edit);
+            let (revision_index, snapshot) = {
                 let mut sessions = sessions.write().await;
-                if let Some(session) = sessions.get_mut(&req_data.session_id) {
-                    session.enhanced_prompt = enhanced.clone();
+                match sessions.get_mut(&req_data.session_id) {
+                    Some(session) => {
+                        session.enhanced_prompt = enhanced.clone();
+                        session.revisions.push(revision.clone());
+                        (session.revisions.len() - 1, session.snapshot())
+                    }
+                    None => (0, None),
                 }
-            }
+            };
+            persist(snapshot).await;
 
-            json_response(
-                StatusCode::OK,
-                &serde_json::to_string(&json!({"enhancedPrompt": enhanced})).unwrap(),
-            )
+            let resp = ReEnhanceResponse {
+                enhanced_prompt: enhanced,
+                revision,
+                revision_index,
+            };
+            json_response(StatusCode::OK, &serde_json::to_string(&resp).unwrap())
         }
         Err(e) => {
             error!("Re-enhancement failed: {}", e);
@@ -660,6 +1363,67 @@ async fn handle_re_enhance(
     }
 }
 
+/// Handle diff request: word-level diff from the original prompt to the current text
+///
+/// Uses `currentPrompt` when given (so unsaved edits are included), otherwise the
+/// session's latest enhanced prompt. `baseRevision` compares against a revision instead
+/// of the original prompt.
+async fn handle_diff(
+    req: Request<Incoming>,
+    sessions: Arc<RwLock<HashMap<String, SessionData>>>,
+    token: &str,
+) -> Response<Full<Bytes>> {
+    let body = match read_body_with_limit(req, MAX_BODY_SIZE).await {
+        Ok(b) => b,
+        Err(e) => {
+            return json_error_response(StatusCode::BAD_REQUEST, &e);
+        }
+    };
+
+    let req_data: DiffRequest = match serde_json::from_slice(&body) {
+        Ok(r) => r,
+        Err(_) => {
+            return json_error_response(StatusCode::BAD_REQUEST, "Invalid request body");
+        }
+    };
+
+    let (original, enhanced) = {
+        let sessions = sessions.read().await;
+        let session = match sessions.get(&req_data.session_id) {
+            Some(s) => s,
+            None => {
+                return json_error_response(StatusCode::NOT_FOUND, "Session not found");
+            }
+        };
+        if !token_matches(session, token) {
+            return forbidden_response();
+        }
+        let original = match req_data.base_revision {
+            Some(index) => match session.revisions.get(index) {
+                Some(revision) => revision.text.clone(),
+                None => {
+                    return json_error_response(StatusCode::BAD_REQUEST, "Invalid revision index");
+                }
+            },
+            None => session.original_prompt.clone(),
+        };
+        (
+            original,
+            req_data
+                .current_prompt
+                .unwrap_or_else(|| session.enhanced_prompt.clone()),
+        )
+    };
+
+    let segments = word_diff(&original, &enhanced);
+    let resp = DiffResponse {
+        original,
+        enhanced,
+        segments,
+    };
+    json_response(StatusCode::OK, &serde_json::to_string(&resp).unwrap())
+}
+
 /// Read request body with size limit (streaming enforcement to prevent memory exhaustion)
 async fn read_body_with_limit(req: Request<Incoming>, max_size: usize) -> Result<Bytes, String> {
     let limited = Limited::new(req.into_body(), max_size);
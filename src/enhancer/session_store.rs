@@ -0,0 +1,215 @@
+//! Session persistence - Web UI review sessions saved under `.ace-tool/sessions/`
+//! Lets a reviewer finish after the tool call timed out, and the agent fetch the result later
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::server::{Revision, SessionData, SessionStatus};
+use crate::service::ConversationHistory;
+use crate::utils::project_detector::ace_dir_path;
+
+/// Directory (inside `.ace-tool/`) holding persisted sessions
+pub const SESSIONS_DIR_NAME: &str = "sessions";
+
+/// Get the persisted sessions directory for a project (not created; `save_session` creates it)
+pub fn get_sessions_dir(project_root: &Path) -> PathBuf {
+    ace_dir_path(project_root).join(SESSIONS_DIR_NAME)
+}
+
+/// On-disk form of a review session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedSession {
+    pub id: String,
+    pub token: String,
+    pub status: SessionStatus,
+    pub original_prompt: String,
+    pub enhanced_prompt: String,
+    pub conversation_history: ConversationHistory,
+    pub blob_names: Vec<String>,
+    pub revisions: Vec<Revision>,
+    pub selected_revision: Option<usize>,
+    /// Final text returned to the agent, set once the reviewer submits
+    pub result: Option<String>,
+    pub created_at: u64,
+}
+
+impl PersistedSession {
+    pub fn from_session(session: &SessionData) -> Self {
+        Self {
+            id: session.id.clone(),
+            token: session.token.clone(),
+            status: session.status.clone(),
+            original_prompt: session.original_prompt.clone(),
+            enhanced_prompt: session.enhanced_prompt.clone(),
+            conversation_history: session.conversation_history.clone(),
+            blob_names: session.blob_names.clone(),
+            revisions: session.revisions.clone(),
+            selected_revision: session.selected_revision,
+            result: session.result.clone(),
+            created_at: session.created_at_ms,
+        }
+    }
+
+    /// Rebuild an in-memory session that keeps persisting to `store_dir`
+    pub fn into_session(self, store_dir: PathBuf) -> SessionData {
+        SessionData {
+            id: self.id,
+            enhanced_prompt: self.enhanced_prompt,
+            original_prompt: self.original_prompt,
+            conversation_history: self.conversation_history,
+            blob_names: self.blob_names,
+            status: self.status,
+            created_at: Instant::now(),
+            created_at_ms: self.created_at,
+            token: self.token,
+            revisions: self.revisions,
+            selected_revision: self.selected_revision,
+            result: self.result,
+            store_dir: Some(store_dir),
+        }
+    }
+}
+
+/// Session IDs are UUIDs; rejecting anything else keeps IDs from escaping the directory
+fn session_file(dir: &Path, session_id: &str) -> Result<PathBuf> {
+    Uuid::parse_str(session_id).map_err(|_| anyhow!("Invalid session ID: {}", session_id))?;
+    Ok(dir.join(format!("{}.json", session_id)))
+}
+
+/// Write a session file atomically (temp file + rename)
+pub fn save_session(dir: &Path, session: &PersistedSession) -> Result<()> {
+    let path = session_file(dir, &session.id)?;
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create sessions directory {}", dir.display()))?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_vec_pretty(session)?)
+        .with_context(|| format!("Failed to write session file {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &path)
+        .with_context(|| format!("Failed to save session file {}", path.display()))?;
+    Ok(())
+}
+
+/// Delete a session file; a missing file is not an error
+pub fn remove_session(dir: &Path, session_id: &str) -> Result<()> {
+    let path = session_file(dir, session_id)?;
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove session file {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Delete session files not written for `max_age`; returns how many were removed
+pub fn prune_sessions(dir: &Path, max_age: Duration) -> Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read sessions directory {}", dir.display()))
+        }
+    };
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Session files and temp files left by an interrupted save
+        if path
+            .extension()
+            .is_none_or(|ext| ext != "json" && ext != "tmp")
+        {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() >= max_age);
+        if stale && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Sequence number of the last snapshot written per session file; `u64::MAX` once deleted
+static WRITTEN: LazyLock<Mutex<HashMap<PathBuf, u64>>> = LazyLock::new(Default::default);
+
+/// Order in which snapshots were taken
+static NEXT_SNAPSHOT: AtomicU64 = AtomicU64::new(0);
+
+/// A session's state captured while the sessions lock is held, written after it is released
+///
+/// Writes run on the blocking pool. A snapshot older than the one already on disk is
+/// dropped, so writes finishing out of order never roll a session back, and a deleted
+/// session is not written again.
+#[derive(Debug)]
+pub struct SessionSnapshot {
+    dir: PathBuf,
+    session: PersistedSession,
+    seq: u64,
+}
+
+impl SessionSnapshot {
+    pub fn new(dir: PathBuf, session: PersistedSession) -> Self {
+        Self {
+            dir,
+            session,
+            seq: NEXT_SNAPSHOT.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session.id
+    }
+
+    /// Write the snapshot unless a newer one was already written
+    pub async fn write(self) -> Result<()> {
+        tokio::task::spawn_blocking(move || {
+            let path = session_file(&self.dir, &self.session.id)?;
+            let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+            if written.get(&path).is_some_and(|&seq| seq >= self.seq) {
+                return Ok(());
+            }
+            save_session(&self.dir, &self.session)?;
+            written.insert(path, self.seq);
+            Ok(())
+        })
+        .await?
+    }
+}
+
+/// Delete a session file once its result has been picked up
+pub async fn discard_session(dir: PathBuf, session_id: String) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let path = session_file(&dir, &session_id)?;
+        let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+        written.insert(path, u64::MAX);
+        remove_session(&dir, &session_id)
+    })
+    .await?
+}
+
+/// Load a session file; `Ok(None)` when the session was never persisted
+pub fn load_session(dir: &Path, session_id: &str) -> Result<Option<PersistedSession>> {
+    let path = session_file(dir, session_id)?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read session file {}", path.display()))?;
+    let session = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid session file {}", path.display()))?;
+    Ok(Some(session))
+}
@@ -1,6 +1,15 @@
 //! Web UI templates for the Prompt Enhancer
 //! Based on Augment VSCode plugin official templates
 
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::utils::project_detector::{ace_dir_path, get_user_config_dir};
+
 /// Prompt enhancement template
 /// Copied from augment.mjs YDn function - must stay in sync
 /// Contains placeholder for original prompt only
@@ -157,6 +166,93 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
       box-shadow: 0 2px 8px rgba(0, 0, 0, 0.1);
     }
 
+    .section-header {
+      display: flex;
+      align-items: center;
+      justify-content: space-between;
+      margin-bottom: 10px;
+    }
+
+    .section-header .section-title {
+      margin-bottom: 0;
+    }
+
+    .link-btn {
+      padding: 4px 10px;
+      font-size: 12px;
+      font-weight: 500;
+      background: white;
+      color: #333;
+      border: 1px solid #ccc;
+      border-radius: 6px;
+    }
+
+    .link-btn:hover {
+      background: #f5f5f5;
+    }
+
+    .revision-bar {
+      display: flex;
+      align-items: center;
+      gap: 8px;
+      margin-bottom: 10px;
+      font-size: 12px;
+      color: #666;
+    }
+
+    .revision-bar select {
+      flex: 1;
+      padding: 4px 8px;
+      font-size: 12px;
+      border: 1px solid #ccc;
+      border-radius: 6px;
+      background: white;
+    }
+
+    .diff-view {
+      display: none;
+      grid-template-columns: 1fr 1fr;
+      gap: 12px;
+      margin-top: 12px;
+    }
+
+    .diff-view.active {
+      display: grid;
+    }
+
+    .diff-pane {
+      border: 2px solid #e0e0e0;
+      border-radius: 8px;
+      padding: 12px 16px;
+      background: #fafafa;
+      font-family: 'SF Mono', 'Monaco', 'Menlo', 'Consolas', monospace;
+      font-size: 13px;
+      line-height: 1.6;
+      white-space: pre-wrap;
+      word-break: break-word;
+      max-height: 350px;
+      overflow-y: auto;
+    }
+
+    .diff-pane-title {
+      font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+      font-size: 12px;
+      font-weight: 600;
+      color: #666;
+      margin-bottom: 8px;
+    }
+
+    .diff-pane del {
+      background: #f8d7da;
+      color: #721c24;
+    }
+
+    .diff-pane ins {
+      background: #d4edda;
+      color: #155724;
+      text-decoration: none;
+    }
+
     .info-box {
       background: #f9f9f9;
       border-left: 4px solid #333;
@@ -340,6 +436,10 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
         flex-direction: column-reverse;
       }
 
+      .diff-view.active {
+        grid-template-columns: 1fr;
+      }
+
       button {
         width: 100%;
         justify-content: center;
@@ -377,7 +477,16 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
         </div>
 
         <div class="section">
-          <div class="section-title">Enhanced Prompt</div>
+          <div class="section-header">
+            <div class="section-title">Enhanced Prompt</div>
+            <button class="link-btn" id="diffBtn" onclick="toggleDiff()">Show Changes</button>
+          </div>
+          <div class="revision-bar">
+            <label for="revisionSelect">Revision</label>
+            <select id="revisionSelect"></select>
+            <button class="link-btn" onclick="restoreRevision()">Restore</button>
+            <button class="link-btn" onclick="compareRevision()">Compare</button>
+          </div>
           <div class="editor-wrapper">
             <textarea
               id="promptText"
@@ -386,6 +495,16 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
             ></textarea>
             <div class="char-count" id="charCount">0 chars</div>
           </div>
+          <div class="diff-view" id="diffView">
+            <div class="diff-pane">
+              <div class="diff-pane-title" id="diffBaseTitle">Original</div>
+              <div id="diffOriginal"></div>
+            </div>
+            <div class="diff-pane">
+              <div class="diff-pane-title">Enhanced</div>
+              <div id="diffEnhanced"></div>
+            </div>
+          </div>
         </div>
 
         <div class="buttons">
@@ -430,6 +549,14 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
   <script>
     const urlParams = new URLSearchParams(window.location.search);
     const sessionId = urlParams.get('session');
+    const sessionToken = urlParams.get('token') || '';
+
+    // All API calls carry the session token from the page URL
+    function apiFetch(url, options) {
+      const opts = options || {};
+      opts.headers = Object.assign({}, opts.headers, { 'X-Ace-Session-Token': sessionToken });
+      return fetch(url, opts);
+    }
     const promptText = document.getElementById('promptText');
     const charCount = document.getElementById('charCount');
     const loading = document.getElementById('loading');
@@ -448,6 +575,118 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
 
     promptText.addEventListener('input', updateCharCount);
 
+    // Diff view: word-level changes from the original prompt, computed server-side
+    const diffView = document.getElementById('diffView');
+    const diffBtn = document.getElementById('diffBtn');
+
+    function renderDiff(segments) {
+      const originalPane = document.getElementById('diffOriginal');
+      const enhancedPane = document.getElementById('diffEnhanced');
+      originalPane.textContent = '';
+      enhancedPane.textContent = '';
+
+      segments.forEach(seg => {
+        if (seg.op === 'equal') {
+          originalPane.appendChild(document.createTextNode(seg.text));
+          enhancedPane.appendChild(document.createTextNode(seg.text));
+        } else if (seg.op === 'delete') {
+          const el = document.createElement('del');
+          el.textContent = seg.text;
+          originalPane.appendChild(el);
+        } else if (seg.op === 'insert') {
+          const el = document.createElement('ins');
+          el.textContent = seg.text;
+          enhancedPane.appendChild(el);
+        }
+      });
+    }
+
+    // Revision compared against in the diff view; null compares against the original prompt
+    let diffBaseRevision = null;
+
+    function refreshDiff() {
+      const body = { sessionId: sessionId, currentPrompt: promptText.value };
+      if (diffBaseRevision !== null) {
+        body.baseRevision = diffBaseRevision;
+      }
+      document.getElementById('diffBaseTitle').textContent =
+        diffBaseRevision === null ? 'Original' : 'Revision ' + (diffBaseRevision + 1);
+      return apiFetch('/api/diff', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+      })
+      .then(r => r.json())
+      .then(data => {
+        if (data.error) {
+          throw new Error(data.error);
+        }
+        renderDiff(data.segments);
+      })
+      .catch(err => {
+        showStatus('Diff failed: ' + err.message, 'error');
+      });
+    }
+
+    function showDiff() {
+      return refreshDiff().then(() => {
+        diffView.classList.add('active');
+        diffBtn.textContent = 'Hide Changes';
+      });
+    }
+
+    function toggleDiff() {
+      if (diffView.classList.contains('active')) {
+        diffView.classList.remove('active');
+        diffBtn.textContent = 'Show Changes';
+        return;
+      }
+      diffBaseRevision = null;
+      showDiff();
+    }
+
+    // Revision history: every enhancement round is kept so reviewers can go back
+    const revisionSelect = document.getElementById('revisionSelect');
+    let revisions = [];
+    let currentRevision = 0;
+
+    function renderRevisions() {
+      revisionSelect.textContent = '';
+      revisions.forEach((rev, i) => {
+        const option = document.createElement('option');
+        const time = new Date(rev.createdAt).toLocaleTimeString();
+        const edited = rev.userEdit ? ', edited' : '';
+        option.value = i;
+        option.textContent = '#' + (i + 1) + ' ' + time + ' (' + rev.provider + edited + ')';
+        revisionSelect.appendChild(option);
+      });
+      revisionSelect.value = currentRevision;
+    }
+
+    function restoreRevision() {
+      const index = parseInt(revisionSelect.value, 10);
+      if (isNaN(index) || !revisions[index]) return;
+      if (promptText.value !== revisions[currentRevision].text &&
+          !confirm('Discard your edits and restore revision #' + (index + 1) + '?')) {
+        revisionSelect.value = currentRevision;
+        return;
+      }
+      currentRevision = index;
+      promptText.value = revisions[index].text;
+      updateCharCount();
+      if (diffView.classList.contains('active')) {
+        refreshDiff();
+      }
+      showStatus('Restored revision #' + (index + 1), 'success');
+    }
+
+    function compareRevision() {
+      const index = parseInt(revisionSelect.value, 10);
+      if (isNaN(index) || !revisions[index]) return;
+      diffBaseRevision = index;
+      showDiff();
+    }
+
     // Format time display
     function formatTime(ms) {
       const totalSeconds = Math.floor(ms / 1000);
@@ -465,7 +704,8 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
       const remaining = sessionTimeoutMs - elapsed;
 
       if (remaining <= 0) {
-        countdownEl.textContent = 'Timed out';
+        // The agent stopped waiting, but a late Send is saved for get_enhancement_result
+        countdownEl.textContent = 'Timed out - Send still saves your edits';
         countdownEl.className = 'countdown danger';
         if (countdownInterval) {
           clearInterval(countdownInterval);
@@ -521,7 +761,7 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
     } else {
       loading.classList.add('active');
 
-      fetch('/api/session?session=' + encodeURIComponent(sessionId))
+      apiFetch('/api/session?session=' + encodeURIComponent(sessionId))
         .then(r => r.json())
         .then(data => {
           if (data.error) {
@@ -530,6 +770,9 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
 
           promptText.value = data.enhancedPrompt;
           updateCharCount();
+          revisions = data.revisions || [];
+          currentRevision = Math.max(revisions.length - 1, 0);
+          renderRevisions();
           loading.classList.remove('active');
           mainContent.style.display = 'block';
           promptText.focus();
@@ -561,7 +804,7 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
       sendBtn.disabled = true;
       reEnhanceBtn.innerHTML = '<div class="spinner" style="width: 16px; height: 16px; border-width: 2px; margin: 0;"></div> Enhancing...';
 
-      fetch('/api/re-enhance', {
+      apiFetch('/api/re-enhance', {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({
@@ -577,6 +820,14 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
 
         promptText.value = data.enhancedPrompt;
         updateCharCount();
+        if (data.revision) {
+          revisions.push(data.revision);
+          currentRevision = data.revisionIndex;
+          renderRevisions();
+        }
+        if (diffView.classList.contains('active')) {
+          refreshDiff();
+        }
         showStatus('Enhancement successful! You can continue editing or send.', 'success');
 
         reEnhanceBtn.disabled = false;
@@ -606,10 +857,15 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
       reEnhanceBtn.disabled = true;
       sendBtn.innerHTML = '<div class="spinner" style="width: 16px; height: 16px; border-width: 2px; margin: 0;"></div> Sending...';
 
-      fetch('/api/submit', {
+      apiFetch('/api/submit', {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
-        body: JSON.stringify({ sessionId: sessionId, content: content, action: 'send' })
+        body: JSON.stringify({
+          sessionId: sessionId,
+          content: content,
+          action: 'send',
+          revisionIndex: revisions.length ? currentRevision : undefined
+        })
       })
       .then(r => r.json())
       .then(data => {
@@ -629,7 +885,7 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
 
     function useOriginal() {
       if (confirm('Are you sure you want to use the original prompt?')) {
-        fetch('/api/submit', {
+        apiFetch('/api/submit', {
           method: 'POST',
           headers: { 'Content-Type': 'application/json' },
           body: JSON.stringify({ sessionId: sessionId, content: '', action: 'use_original' })
@@ -650,7 +906,7 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
 
     function endConversation() {
       if (confirm('Are you sure you want to end this conversation?')) {
-        fetch('/api/submit', {
+        apiFetch('/api/submit', {
           method: 'POST',
           headers: { 'Content-Type': 'application/json' },
           body: JSON.stringify({ sessionId: sessionId, content: '', action: 'end_conversation' })
@@ -677,3 +933,480 @@ pub const ENHANCER_UI_HTML: &str = r#"<!DOCTYPE html>
   </script>
 </body>
 </html>"#;
+
+/// Web UI HTML for the sessions dashboard served at `/`
+pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="UTF-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1.0">
+  <title>Pending Reviews - ACE Tool</title>
+  <style>
+    * {
+      margin: 0;
+      padding: 0;
+      box-sizing: border-box;
+    }
+
+    body {
+      font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', 'Roboto', 'Helvetica Neue', sans-serif;
+      background: #f5f5f5;
+      min-height: 100vh;
+      padding: 20px;
+      color: #333;
+    }
+
+    .container {
+      background: white;
+      border-radius: 8px;
+      box-shadow: 0 2px 8px rgba(0, 0, 0, 0.1);
+      border: 1px solid #e0e0e0;
+      max-width: 1000px;
+      margin: 0 auto;
+      overflow: hidden;
+    }
+
+    .header {
+      padding: 24px 30px;
+      border-bottom: 1px solid #e0e0e0;
+      display: flex;
+      justify-content: space-between;
+      align-items: center;
+    }
+
+    .header h1 {
+      font-size: 20px;
+      font-weight: 600;
+    }
+
+    .summary {
+      font-size: 13px;
+      color: #666;
+    }
+
+    table {
+      width: 100%;
+      border-collapse: collapse;
+      font-size: 14px;
+    }
+
+    th, td {
+      text-align: left;
+      padding: 12px 16px;
+      border-bottom: 1px solid #eee;
+      vertical-align: top;
+    }
+
+    th {
+      font-size: 12px;
+      color: #666;
+      font-weight: 600;
+      background: #fafafa;
+    }
+
+    .prompt {
+      max-width: 420px;
+      white-space: pre-wrap;
+      word-break: break-word;
+    }
+
+    .project {
+      font-family: 'SF Mono', Monaco, Consolas, monospace;
+      font-size: 12px;
+      color: #555;
+      word-break: break-all;
+    }
+
+    .badge {
+      display: inline-block;
+      padding: 2px 8px;
+      border-radius: 10px;
+      font-size: 12px;
+      background: #e8f0fe;
+      color: #1a73e8;
+    }
+
+    .badge.timeout {
+      background: #fff4e5;
+      color: #b26a00;
+    }
+
+    .actions a, .actions button {
+      font-size: 13px;
+      margin-right: 8px;
+    }
+
+    .actions button {
+      background: none;
+      border: 1px solid #d93025;
+      color: #d93025;
+      border-radius: 4px;
+      padding: 2px 8px;
+      cursor: pointer;
+    }
+
+    .empty, .error {
+      padding: 40px;
+      text-align: center;
+      color: #888;
+    }
+
+    .error {
+      color: #d93025;
+    }
+  </style>
+</head>
+<body>
+  <div class="container">
+    <div class="header">
+      <h1>Pending Reviews</h1>
+      <span class="summary" id="summary"></span>
+    </div>
+    <table>
+      <thead>
+        <tr>
+          <th>Project</th>
+          <th>Original Prompt</th>
+          <th>Age</th>
+          <th>Status</th>
+          <th></th>
+        </tr>
+      </thead>
+      <tbody id="sessions"></tbody>
+    </table>
+    <div id="message" class="empty"></div>
+  </div>
+
+  <script>
+    const dashboardToken = new URLSearchParams(window.location.search).get('token') || '';
+    const tbody = document.getElementById('sessions');
+    const message = document.getElementById('message');
+
+    // All API calls carry the dashboard token from the page URL
+    function apiFetch(url, options) {
+      const opts = options || {};
+      opts.headers = Object.assign({}, opts.headers, { 'X-Ace-Session-Token': dashboardToken });
+      return fetch(url, opts);
+    }
+
+    function formatAge(ms) {
+      const totalSeconds = Math.floor(ms / 1000);
+      if (totalSeconds < 60) return totalSeconds + 's';
+      const minutes = Math.floor(totalSeconds / 60);
+      if (minutes < 60) return minutes + 'm ' + (totalSeconds % 60) + 's';
+      return Math.floor(minutes / 60) + 'h ' + (minutes % 60) + 'm';
+    }
+
+    function cell(text, className) {
+      const td = document.createElement('td');
+      if (className) td.className = className;
+      td.textContent = text;
+      return td;
+    }
+
+    function renderSessions(sessions) {
+      tbody.textContent = '';
+      document.getElementById('summary').textContent = sessions.length + ' open';
+      message.textContent = sessions.length ? '' : 'No pending reviews';
+      sessions.forEach(function(s) {
+        const tr = document.createElement('tr');
+        tr.appendChild(cell(s.project || '-', 'project'));
+        const prompt = s.originalPrompt.length > 300
+          ? s.originalPrompt.slice(0, 300) + '…'
+          : s.originalPrompt;
+        tr.appendChild(cell(prompt, 'prompt'));
+        tr.appendChild(cell(formatAge(s.ageMs)));
+
+        const statusTd = document.createElement('td');
+        const badge = document.createElement('span');
+        badge.className = 'badge ' + s.status;
+        badge.textContent = s.status;
+        statusTd.appendChild(badge);
+        tr.appendChild(statusTd);
+
+        const actions = document.createElement('td');
+        actions.className = 'actions';
+        const link = document.createElement('a');
+        link.href = s.url;
+        link.target = '_blank';
+        link.textContent = 'Review';
+        actions.appendChild(link);
+        const cancel = document.createElement('button');
+        cancel.textContent = 'Cancel';
+        cancel.onclick = function() { cancelSession(s.id); };
+        actions.appendChild(cancel);
+        tr.appendChild(actions);
+
+        tbody.appendChild(tr);
+      });
+    }
+
+    function loadSessions() {
+      apiFetch('/api/sessions')
+        .then(function(res) {
+          return res.json().then(function(data) {
+            if (!res.ok) throw new Error(data.error || res.statusText);
+            return data;
+          });
+        })
+        .then(function(data) {
+          message.className = 'empty';
+          renderSessions(data.sessions);
+        })
+        .catch(function(err) {
+          tbody.textContent = '';
+          message.className = 'error';
+          message.textContent = 'Failed to load sessions: ' + err.message;
+        });
+    }
+
+    function cancelSession(id) {
+      if (!confirm('Cancel this review? The waiting agent will be told it was cancelled.')) return;
+      apiFetch('/api/cancel', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ sessionId: id })
+      }).then(loadSessions);
+    }
+
+    loadSessions();
+    setInterval(loadSessions, 5000);
+  </script>
+</body>
+</html>"#;
+
+/// File name of a custom enhancement template (in `.ace-tool/` or the user config dir)
+pub const ENHANCE_TEMPLATE_FILE_NAME: &str = "enhance_template.md";
+
+/// Placeholder names recognized in enhancement templates
+pub const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "original_prompt",
+    "search_context",
+    "language",
+    "project_name",
+    "conversation_history",
+];
+
+/// Values substituted into an enhancement template
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemplateVars<'a> {
+    pub original_prompt: &'a str,
+    pub search_context: &'a str,
+    pub language: &'a str,
+    pub project_name: &'a str,
+    pub conversation_history: &'a str,
+}
+
+impl TemplateVars<'_> {
+    fn get(&self, name: &str) -> &str {
+        match name {
+            "original_prompt" => self.original_prompt,
+            "search_context" => self.search_context,
+            "language" => self.language,
+            "project_name" => self.project_name,
+            "conversation_history" => self.conversation_history,
+            _ => "",
+        }
+    }
+}
+
+/// Literal text or a `{name}` placeholder, as split by [`parse_placeholders`]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PlaceholderSegment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// Split text into literal and `{name}` segments
+///
+/// `{{` and `}}` produce literal braces and a `{` not followed by an identifier is literal
+/// text. An identifier without its closing `}` is an error. Shared by enhancement templates
+/// and MCP prompt files so both read placeholders the same way.
+pub(crate) fn parse_placeholders(text: &str) -> Result<Vec<PlaceholderSegment>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        literal.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            literal.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+
+        if let Some(after) = tail.strip_prefix('}') {
+            literal.push('}');
+            rest = after;
+            continue;
+        }
+
+        // `{` followed by an identifier is a placeholder; anything else is literal text
+        let ident_len = tail[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(tail.len() - 1);
+        if ident_len == 0 {
+            literal.push('{');
+            rest = &tail[1..];
+            continue;
+        }
+
+        let name = &tail[1..1 + ident_len];
+        if !tail[1 + ident_len..].starts_with('}') {
+            return Err(anyhow!("Unterminated placeholder '{{{}'", name));
+        }
+
+        if !literal.is_empty() {
+            segments.push(PlaceholderSegment::Text(std::mem::take(&mut literal)));
+        }
+        segments.push(PlaceholderSegment::Placeholder(name.to_string()));
+        rest = &tail[ident_len + 2..];
+    }
+
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        segments.push(PlaceholderSegment::Text(literal));
+    }
+    Ok(segments)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateSegment {
+    Text(String),
+    Placeholder(&'static str),
+}
+
+/// A validated enhancement template
+///
+/// Templates use `{name}` placeholders (see [`TEMPLATE_PLACEHOLDERS`]); `{{` and `}}`
+/// produce literal braces. The template is parsed once, so substituted values are
+/// never re-scanned and placeholder-like text in user input stays intact.
+#[derive(Debug, Clone)]
+pub struct EnhanceTemplate {
+    segments: Vec<TemplateSegment>,
+    source: Option<PathBuf>,
+}
+
+static BUILTIN_TEMPLATE: LazyLock<EnhanceTemplate> = LazyLock::new(|| {
+    EnhanceTemplate::parse(ENHANCE_PROMPT_TEMPLATE).expect("built-in template is valid")
+});
+
+impl EnhanceTemplate {
+    /// The built-in template (`ENHANCE_PROMPT_TEMPLATE`)
+    pub fn builtin() -> Self {
+        BUILTIN_TEMPLATE.clone()
+    }
+
+    /// Parse and validate template text
+    ///
+    /// Fails on unknown or unterminated placeholders, or when `{original_prompt}` is missing.
+    pub fn parse(text: &str) -> Result<Self> {
+        let segments = parse_placeholders(text)?
+            .into_iter()
+            .map(|segment| match segment {
+                PlaceholderSegment::Text(text) => Ok(TemplateSegment::Text(text)),
+                PlaceholderSegment::Placeholder(name) => TEMPLATE_PLACEHOLDERS
+                    .iter()
+                    .find(|p| **p == name)
+                    .map(|known| TemplateSegment::Placeholder(known))
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Unknown template placeholder '{{{}}}' (supported: {})",
+                            name,
+                            TEMPLATE_PLACEHOLDERS.join(", ")
+                        )
+                    }),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let template = Self {
+            segments,
+            source: None,
+        };
+        if !template.uses("original_prompt") {
+            return Err(anyhow!(
+                "Template is missing the {{original_prompt}} placeholder"
+            ));
+        }
+        Ok(template)
+    }
+
+    /// Load and validate a template file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read template {}: {}", path.display(), e))?;
+        let mut template = Self::parse(&text)
+            .map_err(|e| anyhow!("Invalid template {}: {}", path.display(), e))?;
+        template.source = Some(path.to_path_buf());
+        Ok(template)
+    }
+
+    /// Check whether the template references a placeholder
+    pub fn uses(&self, name: &str) -> bool {
+        self.segments
+            .iter()
+            .any(|s| matches!(s, TemplateSegment::Placeholder(p) if *p == name))
+    }
+
+    /// File the template was loaded from (`None` for the built-in template)
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// Hash of the parsed template, used to key cached enhancements
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        for segment in &self.segments {
+            let (kind, value) = match segment {
+                TemplateSegment::Text(text) => (b'T', text.as_str()),
+                TemplateSegment::Placeholder(name) => (b'P', *name),
+            };
+            hasher.update([kind]);
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// Render the template with the given values
+    pub fn render(&self, vars: &TemplateVars<'_>) -> String {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                TemplateSegment::Text(text) => rendered.push_str(text),
+                TemplateSegment::Placeholder(name) => rendered.push_str(vars.get(name)),
+            }
+        }
+        rendered
+    }
+}
+
+impl Default for EnhanceTemplate {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Resolve the enhancement template for a project
+///
+/// Looks for `.ace-tool/enhance_template.md` in the project root, then
+/// `enhance_template.md` in the user config directory, and falls back to the
+/// built-in template. A template file that exists but fails validation is an error.
+pub fn load_enhance_template(project_root: Option<&Path>) -> Result<EnhanceTemplate> {
+    let candidates = project_root
+        .map(|root| ace_dir_path(root).join(ENHANCE_TEMPLATE_FILE_NAME))
+        .into_iter()
+        .chain(get_user_config_dir().map(|dir| dir.join(ENHANCE_TEMPLATE_FILE_NAME)));
+
+    for path in candidates {
+        if path.is_file() {
+            let template = EnhanceTemplate::from_file(&path)?;
+            info!("Using custom enhancement template: {}", path.display());
+            return Ok(template);
+        }
+    }
+
+    Ok(EnhanceTemplate::builtin())
+}
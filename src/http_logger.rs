@@ -11,6 +11,8 @@ use std::sync::{Mutex, OnceLock};
 use chrono::Local;
 use tracing::warn;
 
+use crate::utils::project_detector::{ace_dir_path, ACE_DIR_NAME};
+
 /// Environment variable to control HTTP logging
 const ENV_HTTP_LOG: &str = "ACE_HTTP_LOG";
 
@@ -50,7 +52,7 @@ pub fn is_enabled() -> bool {
 /// Get log file path for a project
 fn get_log_file_path(project_root: Option<&std::path::Path>) -> PathBuf {
     if let Some(root) = project_root {
-        let ace_tool_dir = root.join(".ace-tool");
+        let ace_tool_dir = ace_dir_path(root);
         if !ace_tool_dir.exists() {
             if let Err(e) = fs::create_dir_all(&ace_tool_dir) {
                 warn!("Failed to create .ace-tool directory: {}", e);
@@ -59,7 +61,7 @@ fn get_log_file_path(project_root: Option<&std::path::Path>) -> PathBuf {
         ace_tool_dir.join(LOG_FILE_NAME)
     } else {
         // Default to current directory's .ace-tool folder
-        let ace_tool_dir = PathBuf::from(".ace-tool");
+        let ace_tool_dir = PathBuf::from(ACE_DIR_NAME);
         if !ace_tool_dir.exists() {
             if let Err(e) = fs::create_dir_all(&ace_tool_dir) {
                 warn!("Failed to create .ace-tool directory: {}", e);
@@ -153,6 +155,34 @@ pub fn log_request(
     }
 }
 
+/// Log an enhancement served from the cache in place of an HTTP call
+pub fn log_cache_hit(
+    project_root: Option<&std::path::Path>,
+    endpoint: &str,
+    key: &str,
+    age_secs: u64,
+) {
+    if !is_enabled() {
+        return;
+    }
+
+    let log_path = get_log_file_path(project_root);
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+    let separator = "=".repeat(80);
+    let log_content = format!(
+        "\n{sep}\n[{}] CACHE HIT enhance ({})\n{sep}\nKey: {}\nAge: {}s\n\n{sep}\n",
+        timestamp,
+        endpoint,
+        key,
+        age_secs,
+        sep = separator
+    );
+
+    if let Err(e) = write_log(&log_path, &log_content) {
+        warn!("Failed to write HTTP log: {}", e);
+    }
+}
+
 /// Write log content to file (thread-safe)
 fn write_log(path: &PathBuf, content: &str) -> std::io::Result<()> {
     // Acquire lock to prevent interleaved writes from concurrent requests
@@ -1,6 +1,6 @@
 //! Index manager - Core indexing and search logic
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -11,24 +11,29 @@ use bincode::Options;
 use encoding_rs::{GB18030, GBK, UTF_8, WINDOWS_1252};
 use futures::stream::{FuturesUnordered, StreamExt};
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
 use rayon::prelude::*;
 use regex::Regex;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 use walkdir::WalkDir;
 
+use super::secrets::{SecretPolicy, SecretScanSettings, SecretScanner, SECRETS_LOG_FILE_NAME};
+use super::selection::{upload_policy_allows, SkipReason};
+use super::upload_policy::{UploadAuditLog, UploadPolicy, UPLOAD_AUDIT_LOG_FILE_NAME};
 use crate::config::Config;
 use crate::http_logger::{self, HttpRequestLog, HttpResponseLog};
 use crate::strategy::{AdaptiveStrategy, ErrorType};
+use crate::tools::error::ToolError;
 use crate::utils::path_normalizer::{normalize_path, normalize_relative_path, RuntimeEnv};
 use crate::utils::project_detector::get_index_file_path;
 use crate::USER_AGENT;
 
 /// Maximum blob size in bytes (128KB, aligned with official augment.mjs)
-const MAX_BLOB_SIZE: usize = 128 * 1024;
+pub(super) const MAX_BLOB_SIZE: usize = 128 * 1024;
 
 /// Maximum batch size in bytes (1MB, aligned with official augment.mjs)
 const MAX_BATCH_SIZE: usize = 1024 * 1024;
@@ -39,6 +44,13 @@ const MAX_INDEX_BYTES: u64 = 256 * 1024 * 1024;
 /// Current index format version
 const CURRENT_INDEX_VERSION: u32 = 2;
 
+/// Lock file next to `index.bin` that serializes index writers; never deleted, so every
+/// process locks the same inode
+pub const INDEX_LOCK_FILE_NAME: &str = "index.lock";
+
+/// Encoding name reported when no encoding decodes a file cleanly
+pub(super) const LOSSY_UTF_8: &str = "UTF-8 (lossy)";
+
 /// Generate a unique request ID
 fn generate_request_id() -> String {
     Uuid::new_v4().to_string()
@@ -77,6 +89,50 @@ impl IndexData {
             .flat_map(|e| e.blob_hashes.iter().cloned())
             .collect()
     }
+
+    /// File, blob and size totals of the index
+    pub fn summary(&self, project_root: &Path) -> IndexSummary {
+        let mut extensions: BTreeMap<String, usize> = BTreeMap::new();
+        for rel_path in self.entries.keys() {
+            let ext = Path::new(rel_path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            *extensions.entry(ext).or_default() += 1;
+        }
+
+        IndexSummary {
+            project_root: project_root.to_string_lossy().replace('\\', "/"),
+            index_version: self.version,
+            config_hash: self.config_hash.clone(),
+            files: self.entries.len(),
+            blobs: self.entries.values().map(|e| e.blob_hashes.len()).sum(),
+            chunked_files: self
+                .entries
+                .values()
+                .filter(|e| e.blob_hashes.len() > 1)
+                .count(),
+            total_bytes: self.entries.values().map(|e| e.size).sum(),
+            last_modified_secs: self.entries.values().map(|e| e.mtime_secs).max(),
+            extensions,
+        }
+    }
+}
+
+/// Totals of an index, keyed by lower-case file extension (without the dot)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexSummary {
+    pub project_root: String,
+    pub index_version: u32,
+    pub config_hash: String,
+    pub files: usize,
+    pub blobs: usize,
+    /// Files split into more than one blob
+    pub chunked_files: usize,
+    pub total_bytes: u64,
+    /// Latest modification time of an indexed file (seconds since UNIX epoch)
+    pub last_modified_secs: Option<u64>,
+    pub extensions: BTreeMap<String, usize>,
 }
 
 /// Single file index entry
@@ -94,16 +150,20 @@ pub struct FileEntry {
 
 /// Result of processing a single file
 #[derive(Debug)]
-struct ProcessedFile {
+pub(super) struct ProcessedFile {
     /// Normalized relative path
-    rel_path: String,
+    pub(super) rel_path: String,
     /// Processing result
-    result: ProcessedResult,
+    pub(super) result: ProcessedResult,
+    /// Encoding the content was decoded with (None for cache hits)
+    pub(super) encoding: Option<&'static str>,
+    /// Number of secrets redacted from the content
+    pub(super) redacted: usize,
 }
 
 /// Processing result variants
 #[derive(Debug)]
-enum ProcessedResult {
+pub(super) enum ProcessedResult {
     /// Cache hit - reuse existing entry
     Cached { entry: FileEntry },
     /// New or modified file - contains blobs to upload
@@ -145,6 +205,8 @@ struct BatchUploadResult {
     latency_ms: u64,
     error_type: Option<ErrorType>,
     success: bool,
+    /// Request ID of the attempt that succeeded
+    request_id: Option<String>,
 }
 
 /// Search request payload
@@ -173,12 +235,17 @@ struct SearchResponse {
 
 /// Calculate configuration fingerprint for detecting index-affecting config changes
 ///
-/// Note: Currently only max_lines_per_blob affects blob splitting and hash calculation.
-/// If new config options affecting indexing are added, they must be included here.
-fn calculate_config_hash(max_lines_per_blob: usize) -> String {
+/// Note: max_lines_per_blob affects blob splitting and secret scanning settings affect
+/// blob content. If new config options affecting indexing are added, they must be
+/// included here.
+pub fn calculate_config_hash(
+    max_lines_per_blob: usize,
+    secret_scan: &SecretScanSettings,
+) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(b"v1:");
+    hasher.update(b"v2:");
     hasher.update(max_lines_per_blob.to_le_bytes());
+    hasher.update(secret_scan.fingerprint().as_bytes());
     hex::encode(&hasher.finalize()[..8])
 }
 
@@ -191,6 +258,9 @@ pub struct IndexManager {
     text_filenames: HashSet<String>,
     max_lines_per_blob: usize,
     compiled_patterns: Vec<(String, Option<Regex>)>,
+    scanner: Arc<SecretScanner>,
+    upload_policy: UploadPolicy,
+    audit_log: Option<UploadAuditLog>,
     index_file_path: PathBuf,
     client: Client,
     runtime_env: RuntimeEnv,
@@ -214,20 +284,18 @@ impl IndexManager {
         let index_file_path = get_index_file_path(&project_root);
 
         // Precompile exclude patterns to regex
-        let compiled_patterns: Vec<(String, Option<Regex>)> = config
-            .exclude_patterns
-            .iter()
-            .map(|pattern| {
-                let regex_pattern = pattern
-                    .replace('.', "\\.")
-                    .replace('*', ".*")
-                    .replace('?', ".");
-                let regex = Regex::new(&format!("^{}$", regex_pattern)).ok();
-                (pattern.clone(), regex)
-            })
-            .collect();
+        let compiled_patterns = compile_exclude_patterns(&config.exclude_patterns);
 
-        let config_hash = calculate_config_hash(config.max_lines_per_blob);
+        let scanner = SecretScanner::new(&config.secret_scan)?
+            .with_log(index_file_path.with_file_name(SECRETS_LOG_FILE_NAME));
+
+        let upload_policy = UploadPolicy::new(&config.upload_policy, &project_root)?;
+        let audit_log = UploadAuditLog::new(
+            index_file_path.with_file_name(UPLOAD_AUDIT_LOG_FILE_NAME),
+            &config.upload_policy.audit,
+        );
+
+        let config_hash = calculate_config_hash(config.max_lines_per_blob, &config.secret_scan);
 
         Ok(Self {
             project_root,
@@ -237,6 +305,9 @@ impl IndexManager {
             text_filenames: config.text_filenames.clone(),
             max_lines_per_blob: config.max_lines_per_blob,
             compiled_patterns,
+            scanner: Arc::new(scanner),
+            upload_policy,
+            audit_log,
             index_file_path,
             client,
             runtime_env,
@@ -393,62 +464,91 @@ impl IndexManager {
         }
     }
 
-    /// Save index data to file (atomic write, bincode format)
+    /// Open the lock file guarding `index.bin` against concurrent writers
+    fn open_index_lock(index_file_path: &Path) -> Result<fs::File> {
+        let lock_path = index_file_path.with_file_name(INDEX_LOCK_FILE_NAME);
+        fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| anyhow!("Failed to open index lock {:?}: {}", lock_path, e))
+    }
+
+    /// Take the exclusive lock on `.ace-tool/index.lock`, waiting for another process
+    /// to finish its update. The lock is released when the returned file is dropped.
+    fn lock_index(index_file_path: &Path) -> Result<fs::File> {
+        let lock = Self::open_index_lock(index_file_path)?;
+        lock.lock()
+            .map_err(|e| anyhow!("Failed to lock index file: {}", e))?;
+        Ok(lock)
+    }
+
+    /// Take the index lock without waiting; `None` while another process holds it
+    pub fn try_lock_index(index_file_path: &Path) -> Result<Option<fs::File>> {
+        let lock = Self::open_index_lock(index_file_path)?;
+        match lock.try_lock() {
+            Ok(()) => Ok(Some(lock)),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => Err(anyhow!("Failed to lock index file: {}", e)),
+        }
+    }
+
+    /// Save index data to file (atomic write, bincode format) under the index lock
     pub fn save_index(&self, data: &IndexData) -> Result<()> {
+        let _lock = Self::lock_index(&self.index_file_path)?;
+        self.write_index(data)
+    }
+
+    /// Write the index; the caller holds the index lock
+    fn write_index(&self, data: &IndexData) -> Result<()> {
         let options = bincode::DefaultOptions::new().with_limit(MAX_INDEX_BYTES);
         let bytes = options.serialize(data)?;
-        let tmp_path = self.index_file_path.with_extension("bin.tmp");
 
+        // Per-process temporary file, so a crashed writer never clobbers another's
+        let tmp_path = self
+            .index_file_path
+            .with_extension(format!("bin.{}.tmp", std::process::id()));
+
+        let result = Self::replace_file(&tmp_path, &self.index_file_path, &bytes);
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Write `bytes` to `tmp_path`, then atomically rename it over `target`
+    fn replace_file(tmp_path: &Path, target: &Path, bytes: &[u8]) -> Result<()> {
         // Write to temporary file
-        fs::write(&tmp_path, &bytes)?;
+        fs::write(tmp_path, bytes)?;
 
         // Atomic rename (on Windows, need to remove target first)
         #[cfg(windows)]
-        if self.index_file_path.exists() {
-            fs::remove_file(&self.index_file_path)?;
+        if target.exists() {
+            fs::remove_file(target)?;
         }
 
-        fs::rename(&tmp_path, &self.index_file_path)?;
+        fs::rename(tmp_path, target)?;
         Ok(())
     }
 
     /// Read file with encoding detection (avoids updating file access time on Windows)
     fn read_file_with_encoding(path: &Path) -> Result<String> {
         let bytes = Self::read_file_bytes(path)?;
-
-        // Try different encodings
-        let encodings = [UTF_8, GBK, GB18030, WINDOWS_1252];
-
-        for encoding in encodings {
-            let (content, _, had_errors) = encoding.decode(&bytes);
-            if !had_errors {
-                let content_str = content.to_string();
-                // Check for replacement characters
-                let replacement_count = content_str.matches('\u{FFFD}').count();
-                let threshold = if content_str.len() < 100 {
-                    5
-                } else {
-                    (content_str.len() as f64 * 0.05) as usize
-                };
-
-                if replacement_count <= threshold {
-                    return Ok(content_str);
-                }
-            }
-        }
-
-        // Fallback to UTF-8 with lossy conversion
-        Ok(String::from_utf8_lossy(&bytes).to_string())
+        Ok(Self::detect_encoding(&bytes).0)
     }
 
-    /// Decode bytes with encoding detection (for use when bytes are already read)
-    fn decode_bytes_with_encoding(bytes: &[u8]) -> Result<String> {
+    /// Decode bytes with the first encoding that fits, falling back to lossy UTF-8
+    ///
+    /// Returns the content and the name of the encoding used.
+    pub(super) fn detect_encoding(bytes: &[u8]) -> (String, &'static str) {
         let encodings = [UTF_8, GBK, GB18030, WINDOWS_1252];
 
         for encoding in encodings {
             let (content, _, had_errors) = encoding.decode(bytes);
             if !had_errors {
                 let content_str = content.to_string();
+                // Check for replacement characters
                 let replacement_count = content_str.matches('\u{FFFD}').count();
                 let threshold = if content_str.len() < 100 {
                     5
@@ -457,12 +557,12 @@ impl IndexManager {
                 };
 
                 if replacement_count <= threshold {
-                    return Ok(content_str);
+                    return (content_str, encoding.name());
                 }
             }
         }
 
-        Ok(String::from_utf8_lossy(bytes).to_string())
+        (String::from_utf8_lossy(bytes).to_string(), LOSSY_UTF_8)
     }
 
     /// Read file bytes
@@ -540,6 +640,21 @@ impl IndexManager {
         blobs
     }
 
+    /// Read a project file the way indexing does: encoding detection, binary check,
+    /// sanitizing and secret redaction
+    pub fn read_indexable_content(&self, rel_path: &str) -> Result<String> {
+        let content = Self::read_file_with_encoding(&self.project_root.join(rel_path))?;
+        if Self::is_binary_content(&content) {
+            return Err(anyhow!("File appears to be binary: {}", rel_path));
+        }
+        let content = Self::sanitize_content(&content);
+        let findings = self.scanner.scan(&content);
+        if findings.iter().any(|f| f.policy != SecretPolicy::Redact) {
+            return Err(anyhow!("File contains secrets: {}", rel_path));
+        }
+        Ok(SecretScanner::redact(&content, &findings))
+    }
+
     /// Collect all text files
     pub fn collect_files(&self) -> Result<Vec<Blob>> {
         let mut blobs = Vec::new();
@@ -585,6 +700,10 @@ impl IndexManager {
                 continue;
             }
 
+            if !upload_policy_allows(&self.upload_policy, path, &self.project_root) {
+                continue;
+            }
+
             // Check file size before reading to avoid memory spikes
             match fs::metadata(path) {
                 Ok(metadata) => {
@@ -645,6 +764,14 @@ impl IndexManager {
                     .to_string_lossy(),
             );
 
+            let clean_content = match self.scanner.apply(&relative_path, clean_content) {
+                Ok((content, _)) => content,
+                Err(reason) if reason.aborts_indexing() => {
+                    return Err(anyhow!("Indexing aborted: {}: {}", relative_path, reason));
+                }
+                Err(_) => continue,
+            };
+
             let file_blobs = self.split_file_content(&relative_path, &clean_content);
             blobs.extend(file_blobs);
         }
@@ -722,7 +849,7 @@ impl IndexManager {
                     timeout_ms,
                 )
                 .await;
-                (index, result)
+                (index, batch, result)
             }
         };
 
@@ -746,11 +873,18 @@ impl IndexManager {
             }
 
             // 2. Wait for the next task to complete
-            if let Some((i, result)) = active_tasks.next().await {
+            if let Some((i, batch, result)) = active_tasks.next().await {
                 // 3. Record outcome and adjust strategy
                 strategy.record_outcome(result.success, result.latency_ms, result.error_type);
 
                 if result.success {
+                    if let Some(audit_log) = &self.audit_log {
+                        audit_log.record(
+                            &self.base_url,
+                            result.request_id.as_deref().unwrap_or_default(),
+                            &batch,
+                        );
+                    }
                     uploaded_blob_names.extend(result.blob_names);
                 } else {
                     error!("Batch {} upload failed", i + 1);
@@ -778,6 +912,7 @@ impl IndexManager {
                 latency_ms: 0,
                 error_type: Some(ErrorType::ClientError),
                 success: false,
+                request_id: None,
             };
         }
 
@@ -869,6 +1004,7 @@ impl IndexManager {
                             latency_ms: total_latency_ms,
                             error_type: Some(ErrorType::ClientError),
                             success: false,
+                            request_id: None,
                         };
                     }
 
@@ -893,6 +1029,7 @@ impl IndexManager {
                             latency_ms: total_latency_ms,
                             error_type: Some(ErrorType::ClientError),
                             success: false,
+                            request_id: None,
                         };
                     }
 
@@ -918,6 +1055,7 @@ impl IndexManager {
                                 latency_ms: total_latency_ms,
                                 error_type: None,
                                 success: true,
+                                request_id: Some(request_id),
                             };
                         }
                     }
@@ -1002,6 +1140,7 @@ impl IndexManager {
                             Some(ErrorType::ClientError)
                         },
                         success: false,
+                        request_id: None,
                     };
                 }
                 Err(e) => {
@@ -1045,6 +1184,7 @@ impl IndexManager {
             latency_ms: total_latency_ms,
             error_type: last_error_type,
             success: false,
+            request_id: None,
         }
     }
 
@@ -1059,7 +1199,7 @@ impl IndexManager {
         let text_filenames_scan = self.text_filenames.clone();
         let compiled_patterns_scan = self.compiled_patterns.clone();
 
-        let file_paths = tokio::task::spawn_blocking(move || {
+        let mut file_paths = tokio::task::spawn_blocking(move || {
             collect_file_paths_standalone(
                 &project_root_scan,
                 &text_extensions_scan,
@@ -1082,41 +1222,105 @@ impl IndexManager {
             };
         }
 
+        // Enforce the upload allowlist/denylist before any file is read
+        file_paths.retain(|path| {
+            let allowed = upload_policy_allows(&self.upload_policy, path, &self.project_root);
+            if !allowed {
+                debug!("Skipping {:?}: refused by upload policy", path);
+            }
+            allowed
+        });
+
+        if file_paths.is_empty() {
+            warn!("Upload policy refused every indexable file");
+            return IndexResult {
+                status: "error".to_string(),
+                message: "No files allowed by the upload policy".to_string(),
+                stats: None,
+            };
+        }
+
         info!("Found {} files to process", file_paths.len());
 
-        // Step 2: Load old index
+        // Step 2: Load old index. The index lock is held until the new index is saved, so
+        // a concurrent run in another process can't load the same old index and then
+        // overwrite this run's entries with its own.
+        let index_file_path = self.index_file_path.clone();
+        let index_lock =
+            match tokio::task::spawn_blocking(move || Self::lock_index(&index_file_path)).await {
+                Ok(Ok(lock)) => lock,
+                Ok(Err(e)) => {
+                    error!("{}", e);
+                    return IndexResult {
+                        status: "error".to_string(),
+                        message: e.to_string(),
+                        stats: None,
+                    };
+                }
+                Err(e) => {
+                    error!("Index lock task failed: {}", e);
+                    return IndexResult {
+                        status: "error".to_string(),
+                        message: format!("Failed to lock index file: {}", e),
+                        stats: None,
+                    };
+                }
+            };
         let old_index = self.load_index();
 
         // Step 3: Process files in parallel using rayon (via spawn_blocking)
         let old_index_arc = Arc::new(old_index);
         let project_root = self.project_root.clone();
-        let text_extensions = self.text_extensions.clone();
-        let text_filenames = self.text_filenames.clone();
-        let compiled_patterns = self.compiled_patterns.clone();
+        let scanner = self.scanner.clone();
         let max_lines_per_blob = self.max_lines_per_blob;
 
-        let results: Vec<ProcessedFile> = tokio::task::spawn_blocking(move || {
-            file_paths
-                .par_iter()
-                .filter_map(|path| {
-                    // We need to inline the processing logic here since we can't capture &self
-                    process_file_standalone(
-                        path,
-                        &old_index_arc,
-                        &project_root,
-                        &text_extensions,
-                        &text_filenames,
-                        &compiled_patterns,
-                        max_lines_per_blob,
-                    )
-                })
-                .collect()
-        })
-        .await
-        .unwrap_or_else(|e| {
-            error!("Parallel processing failed: {}", e);
-            Vec::new()
-        });
+        let outcomes: Vec<std::result::Result<ProcessedFile, (PathBuf, SkipReason)>> =
+            tokio::task::spawn_blocking(move || {
+                file_paths
+                    .par_iter()
+                    .map(|path| {
+                        // We need to inline the processing logic here since we can't capture &self
+                        process_file_standalone(
+                            path,
+                            &old_index_arc,
+                            &project_root,
+                            &scanner,
+                            max_lines_per_blob,
+                        )
+                        .map_err(|reason| (path.clone(), reason))
+                    })
+                    .collect()
+            })
+            .await
+            .unwrap_or_else(|e| {
+                error!("Parallel processing failed: {}", e);
+                Vec::new()
+            });
+
+        let mut results: Vec<ProcessedFile> = Vec::with_capacity(outcomes.len());
+        for outcome in outcomes {
+            match outcome {
+                Ok(processed) => results.push(processed),
+                // Nothing has been uploaded or saved yet
+                Err((path, reason)) if reason.aborts_indexing() => {
+                    let rel_path = path.strip_prefix(&self.project_root).unwrap_or(&path);
+                    error!(
+                        "Indexing aborted by secret scan: {:?}: {}",
+                        rel_path, reason
+                    );
+                    return IndexResult {
+                        status: "error".to_string(),
+                        message: format!(
+                            "Indexing aborted: {} contains a secret ({})",
+                            rel_path.display(),
+                            reason
+                        ),
+                        stats: None,
+                    };
+                }
+                Err((path, reason)) => debug!("Skipping {:?}: {}", path, reason),
+            }
+        }
 
         if results.is_empty() {
             warn!("No files were successfully processed");
@@ -1182,12 +1386,13 @@ impl IndexManager {
 
         // Step 6: Save new index (atomic write)
         let total_blobs = cached_count + uploaded_blob_names.len();
-        let save_failed = if let Err(e) = self.save_index(&new_index) {
+        let save_failed = if let Err(e) = self.write_index(&new_index) {
             error!("Failed to save index: {}", e);
             true
         } else {
             false
         };
+        drop(index_lock);
 
         info!(
             "Indexing complete: {} files, {} total blobs",
@@ -1250,7 +1455,11 @@ impl IndexManager {
         // Auto-index first
         let index_result = self.index_project().await;
         if index_result.status == "error" {
-            return Err(anyhow!("Failed to index project: {}", index_result.message));
+            return Err(ToolError::IndexFailed(format!(
+                "Failed to index project: {}",
+                index_result.message
+            ))
+            .into());
         }
         if index_result.status == "partial" {
             warn!(
@@ -1263,7 +1472,7 @@ impl IndexManager {
         let index_data = self.load_index();
         let blob_names = index_data.get_all_blob_hashes();
         if blob_names.is_empty() {
-            return Err(anyhow!("No blobs found after indexing"));
+            return Err(ToolError::IndexFailed("No blobs found after indexing".to_string()).into());
         }
 
         // Execute search
@@ -1345,7 +1554,13 @@ impl IndexManager {
                             Some(&format!("Search failed: {} - {}", status, text)),
                         );
                     }
-                    return Err(anyhow!("Search failed: {} - {}", status, text));
+                    let message = format!("Search failed: {} - {}", status, text);
+                    return Err(match status.as_u16() {
+                        401 | 403 => ToolError::AuthFailed(message),
+                        408 | 504 => ToolError::UpstreamTimeout(message),
+                        _ => ToolError::Upstream(message),
+                    }
+                    .into());
                 }
 
                 let body_text = resp.text().await.unwrap_or_default();
@@ -1388,7 +1603,7 @@ impl IndexManager {
                         Some(&error_msg),
                     );
                 }
-                Err(anyhow!("Search request failed: {}", error_msg))
+                Err(ToolError::request_failed("Search request failed", &e))
             }
         }
     }
@@ -1396,23 +1611,23 @@ impl IndexManager {
 
 /// Standalone file processing function for use in parallel context
 /// (cannot capture &self in spawn_blocking closure)
-fn process_file_standalone(
+///
+/// Returns why the file is skipped when it cannot be indexed and has no usable old entry.
+pub(super) fn process_file_standalone(
     path: &Path,
     old_index: &IndexData,
     project_root: &Path,
-    _text_extensions: &HashSet<String>,
-    _text_filenames: &HashSet<String>,
-    _compiled_patterns: &[(String, Option<Regex>)],
+    scanner: &SecretScanner,
     max_lines_per_blob: usize,
-) -> Option<ProcessedFile> {
+) -> std::result::Result<ProcessedFile, SkipReason> {
     // Calculate relative path
     let rel_path = match path.strip_prefix(project_root) {
         Ok(p) => normalize_relative_path(&p.to_string_lossy()),
-        Err(_) => return None,
+        Err(_) => return Err(SkipReason::OutsideRoot),
     };
 
     // Helper to preserve old entry on transient errors
-    let preserve_old = || -> Option<ProcessedFile> {
+    let preserve_old = |error: String| -> std::result::Result<ProcessedFile, SkipReason> {
         old_index
             .entries
             .get(&rel_path)
@@ -1421,25 +1636,30 @@ fn process_file_standalone(
                 result: ProcessedResult::Cached {
                     entry: cached.clone(),
                 },
+                encoding: None,
+                redacted: 0,
             })
+            .ok_or(SkipReason::Unreadable { error })
     };
 
     // Get metadata
     // NotFound = file deleted, don't preserve; other errors = transient, preserve old entry
     let metadata = match fs::metadata(path) {
         Ok(m) => m,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
-        Err(_) => return preserve_old(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(SkipReason::Removed),
+        Err(e) => return preserve_old(e.to_string()),
     };
 
     // Check file size before reading
     if metadata.len() > MAX_BLOB_SIZE as u64 {
-        return None;
+        return Err(SkipReason::TooLarge {
+            size: metadata.len(),
+        });
     }
 
     let mtime = match metadata.modified() {
         Ok(t) => t,
-        Err(_) => return preserve_old(),
+        Err(e) => return preserve_old(e.to_string()),
     };
 
     let duration = mtime.duration_since(UNIX_EPOCH).unwrap_or_default();
@@ -1455,59 +1675,58 @@ fn process_file_standalone(
         {
             // High precision: mtime_nanos match confirms cache hit
             if mtime_nanos != 0 && cached.mtime_nanos == mtime_nanos {
-                return Some(ProcessedFile {
+                return Ok(ProcessedFile {
                     rel_path,
                     result: ProcessedResult::Cached {
                         entry: cached.clone(),
                     },
+                    encoding: None,
+                    redacted: 0,
                 });
             }
             // Low precision (mtime_nanos=0): read file, compute hash, compare with cached
             // If hash matches, it's a cache hit (avoid re-upload)
             if mtime_nanos == 0 {
-                if let Ok(content) = IndexManager::read_file_with_encoding(path) {
-                    if !IndexManager::is_binary_content(&content) {
-                        let clean_content = IndexManager::sanitize_content(&content);
-                        if clean_content.len() <= MAX_BLOB_SIZE {
-                            let blobs = split_file_content_standalone(
-                                &rel_path,
-                                &clean_content,
-                                max_lines_per_blob,
-                            );
-                            let new_hashes: Vec<String> = blobs
-                                .iter()
-                                .map(|b| IndexManager::calculate_blob_name(&b.path, &b.content))
-                                .collect();
-                            // Hash match = content unchanged, use cached entry with updated mtime
-                            if new_hashes == cached.blob_hashes {
-                                let updated_entry = FileEntry {
-                                    mtime_secs,
-                                    mtime_nanos: 0,
-                                    size,
-                                    blob_hashes: cached.blob_hashes.clone(),
-                                };
-                                return Some(ProcessedFile {
-                                    rel_path,
-                                    result: ProcessedResult::Cached {
-                                        entry: updated_entry,
-                                    },
-                                });
-                            }
-                            // Hash mismatch = content changed, return as new
-                            return Some(ProcessedFile {
-                                rel_path,
-                                result: ProcessedResult::New {
-                                    blobs,
-                                    entry: FileEntry {
-                                        mtime_secs,
-                                        mtime_nanos: 0,
-                                        size,
-                                        blob_hashes: new_hashes,
-                                    },
-                                },
-                            });
-                        }
+                if let Ok(bytes) = fs::read(path) {
+                    let (content, encoding) = IndexManager::detect_encoding(&bytes);
+                    let (blobs, redacted) =
+                        prepare_blobs(&rel_path, &content, scanner, max_lines_per_blob)?;
+                    let new_hashes: Vec<String> = blobs
+                        .iter()
+                        .map(|b| IndexManager::calculate_blob_name(&b.path, &b.content))
+                        .collect();
+                    // Hash match = content unchanged, use cached entry with updated mtime
+                    if new_hashes == cached.blob_hashes {
+                        let updated_entry = FileEntry {
+                            mtime_secs,
+                            mtime_nanos: 0,
+                            size,
+                            blob_hashes: cached.blob_hashes.clone(),
+                        };
+                        return Ok(ProcessedFile {
+                            rel_path,
+                            result: ProcessedResult::Cached {
+                                entry: updated_entry,
+                            },
+                            encoding: Some(encoding),
+                            redacted,
+                        });
                     }
+                    // Hash mismatch = content changed, return as new
+                    return Ok(ProcessedFile {
+                        rel_path,
+                        result: ProcessedResult::New {
+                            blobs,
+                            entry: FileEntry {
+                                mtime_secs,
+                                mtime_nanos: 0,
+                                size,
+                                blob_hashes: new_hashes,
+                            },
+                        },
+                        encoding: Some(encoding),
+                        redacted,
+                    });
                 }
             }
         }
@@ -1515,30 +1734,14 @@ fn process_file_standalone(
 
     // Cache miss - read and process file
     // Try to read file; handle deletion that may occur between metadata check and read
-    let content = match fs::read(path) {
-        Ok(bytes) => {
-            // Decode with encoding detection
-            match IndexManager::decode_bytes_with_encoding(&bytes) {
-                Ok(c) => c,
-                Err(_) => return preserve_old(),
-            }
-        }
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
-        Err(_) => return preserve_old(), // Other read errors: preserve old entry
+    let (content, encoding) = match fs::read(path) {
+        // Decode with encoding detection
+        Ok(bytes) => IndexManager::detect_encoding(&bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(SkipReason::Removed),
+        Err(e) => return preserve_old(e.to_string()), // Other read errors: preserve old entry
     };
 
-    // Skip binary files
-    if IndexManager::is_binary_content(&content) {
-        return None;
-    }
-
-    let clean_content = IndexManager::sanitize_content(&content);
-
-    if clean_content.len() > MAX_BLOB_SIZE {
-        return None;
-    }
-
-    let blobs = split_file_content_standalone(&rel_path, &clean_content, max_lines_per_blob);
+    let (blobs, redacted) = prepare_blobs(&rel_path, &content, scanner, max_lines_per_blob)?;
     let blob_hashes: Vec<String> = blobs
         .iter()
         .map(|b| IndexManager::calculate_blob_name(&b.path, &b.content))
@@ -1551,12 +1754,43 @@ fn process_file_standalone(
         blob_hashes,
     };
 
-    Some(ProcessedFile {
+    Ok(ProcessedFile {
         rel_path,
         result: ProcessedResult::New { blobs, entry },
+        encoding: Some(encoding),
+        redacted,
     })
 }
 
+/// Turn decoded file content into blobs: binary check, sanitizing, size check and
+/// secret scanning, then splitting
+///
+/// Also returns the number of redacted secrets.
+fn prepare_blobs(
+    rel_path: &str,
+    content: &str,
+    scanner: &SecretScanner,
+    max_lines_per_blob: usize,
+) -> std::result::Result<(Vec<Blob>, usize), SkipReason> {
+    // Skip binary files
+    if IndexManager::is_binary_content(content) {
+        return Err(SkipReason::Binary);
+    }
+
+    let clean_content = IndexManager::sanitize_content(content);
+
+    // Decoding to UTF-8 can grow the content past the on-disk size
+    if clean_content.len() > MAX_BLOB_SIZE {
+        return Err(SkipReason::TooLarge {
+            size: clean_content.len() as u64,
+        });
+    }
+
+    let (clean_content, redacted) = scanner.apply(rel_path, clean_content)?;
+    let blobs = split_file_content_standalone(rel_path, &clean_content, max_lines_per_blob);
+    Ok((blobs, redacted))
+}
+
 /// Standalone file content splitting for use in parallel context
 fn split_file_content_standalone(
     file_path: &str,
@@ -1598,7 +1832,22 @@ fn split_file_content_standalone(
     blobs
 }
 
-fn build_ignore_rules(project_root: &Path) -> Option<Gitignore> {
+/// Precompile exclude patterns (`*` and `?` wildcards) to anchored regexes
+pub(super) fn compile_exclude_patterns(patterns: &[String]) -> Vec<(String, Option<Regex>)> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let regex_pattern = pattern
+                .replace('.', "\\.")
+                .replace('*', ".*")
+                .replace('?', ".");
+            let regex = Regex::new(&format!("^{}$", regex_pattern)).ok();
+            (pattern.clone(), regex)
+        })
+        .collect()
+}
+
+pub(super) fn build_ignore_rules(project_root: &Path) -> Option<Gitignore> {
     let ignore_files = [".gitignore", ".aceignore"];
     let paths: Vec<_> = ignore_files
         .iter()
@@ -1630,7 +1879,7 @@ fn build_ignore_rules(project_root: &Path) -> Option<Gitignore> {
 }
 
 /// Standalone file path collection for use in spawn_blocking
-fn collect_file_paths_standalone(
+pub(super) fn collect_file_paths_standalone(
     project_root: &Path,
     text_extensions: &HashSet<String>,
     text_filenames: &HashSet<String>,
@@ -1665,6 +1914,17 @@ fn should_exclude_standalone(
     gitignore: Option<&Gitignore>,
     compiled_patterns: &[(String, Option<Regex>)],
 ) -> bool {
+    exclusion_reason_standalone(path, is_dir, project_root, gitignore, compiled_patterns).is_some()
+}
+
+/// Why a path is excluded by the ignore files or exclude patterns, if it is
+pub(super) fn exclusion_reason_standalone(
+    path: &Path,
+    is_dir: bool,
+    project_root: &Path,
+    gitignore: Option<&Gitignore>,
+    compiled_patterns: &[(String, Option<Regex>)],
+) -> Option<SkipReason> {
     let relative_path = match path.strip_prefix(project_root) {
         Ok(p) => p,
         Err(_) => {
@@ -1674,7 +1934,7 @@ fn should_exclude_standalone(
                 "Path prefix mismatch, excluding for safety: {:?} vs {:?}",
                 path, project_root
             );
-            return true;
+            return Some(SkipReason::OutsideRoot);
         }
     };
 
@@ -1682,43 +1942,40 @@ fn should_exclude_standalone(
 
     // Check gitignore
     if let Some(gi) = gitignore {
-        if gi.matched(&path_str, is_dir).is_ignore() {
-            return true;
+        if let Match::Ignore(glob) = gi.matched(&path_str, is_dir) {
+            return Some(SkipReason::Ignored {
+                source: glob
+                    .from()
+                    .and_then(|f| f.file_name())
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                pattern: glob.original().to_string(),
+            });
         }
     }
 
     // Check exclude patterns using precompiled regexes
     let path_parts: Vec<&str> = path_str.split('/').collect();
     for (pattern, compiled_regex) in compiled_patterns {
-        if let Some(regex) = compiled_regex {
-            // Check each path component
-            for part in &path_parts {
-                if regex.is_match(part) {
-                    return true;
-                }
-            }
-            // Check full path
-            if regex.is_match(&path_str) {
-                return true;
-            }
+        let matched = if let Some(regex) = compiled_regex {
+            // Check each path component, then the full path
+            path_parts.iter().any(|part| regex.is_match(part)) || regex.is_match(&path_str)
         } else {
             // Fallback to string matching if regex failed to compile
-            for part in &path_parts {
-                if *part == pattern {
-                    return true;
-                }
-            }
-            if path_str == *pattern {
-                return true;
-            }
+            path_parts.iter().any(|part| part == pattern) || path_str == *pattern
+        };
+        if matched {
+            return Some(SkipReason::ExcludePattern {
+                pattern: pattern.clone(),
+            });
         }
     }
 
-    false
+    None
 }
 
 /// Standalone indexable file check for use in spawn_blocking
-fn is_indexable_file_standalone(
+pub(super) fn is_indexable_file_standalone(
     path: &Path,
     text_extensions: &HashSet<String>,
     text_filenames: &HashSet<String>,
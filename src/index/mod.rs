@@ -1,5 +1,16 @@
 //! Index module
 
 mod manager;
+mod registry;
+pub mod secrets;
+pub mod selection;
+pub mod upload_policy;
 
-pub use manager::{Blob, FileEntry, IndexData, IndexManager, IndexResult, IndexStats};
+pub use manager::{
+    calculate_config_hash, Blob, FileEntry, IndexData, IndexManager, IndexResult, IndexStats,
+    IndexSummary, INDEX_LOCK_FILE_NAME,
+};
+pub use registry::IndexRegistry;
+pub use secrets::{SecretPolicy, SecretScanSettings, SecretScanner};
+pub use selection::{dry_run, list_files, DryRunEntry, FileListEntry, SkipReason};
+pub use upload_policy::{UploadAuditSettings, UploadPolicy, UploadPolicySettings};
@@ -0,0 +1,92 @@
+//! Index registry - one shared IndexManager per project root
+//!
+//! A long-lived process (the daemon, or one stdio server with several concurrent tool
+//! calls) keeps a single `IndexManager` per project and runs at most one indexing pass
+//! per project at a time. Concurrent searches on the same project queue up behind each
+//! other, so the second one finds the blobs the first one already uploaded.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Mutex as AsyncMutex;
+
+use super::manager::IndexManager;
+use crate::config::Config;
+
+/// A registered project: its manager and the lock serializing index passes
+struct RegistryEntry {
+    manager: Arc<IndexManager>,
+    index_lock: Arc<AsyncMutex<()>>,
+}
+
+/// Shared `IndexManager` instances keyed by project root
+pub struct IndexRegistry {
+    config: Arc<Config>,
+    entries: Mutex<HashMap<PathBuf, RegistryEntry>>,
+}
+
+impl IndexRegistry {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Canonical key of a project root (falls back to the path as given)
+    fn key(project_root: &Path) -> PathBuf {
+        project_root
+            .canonicalize()
+            .unwrap_or_else(|_| project_root.to_path_buf())
+    }
+
+    /// Manager and index lock of a project, created on first use
+    fn entry(&self, project_root: &Path) -> Result<(Arc<IndexManager>, Arc<AsyncMutex<()>>)> {
+        let key = Self::key(project_root);
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("Index registry lock poisoned"))?;
+
+        if let Some(entry) = entries.get(&key) {
+            return Ok((entry.manager.clone(), entry.index_lock.clone()));
+        }
+
+        let manager = Arc::new(IndexManager::new(
+            self.config.clone(),
+            project_root.to_path_buf(),
+        )?);
+        let index_lock = Arc::new(AsyncMutex::new(()));
+        entries.insert(
+            key,
+            RegistryEntry {
+                manager: manager.clone(),
+                index_lock: index_lock.clone(),
+            },
+        );
+        Ok((manager, index_lock))
+    }
+
+    /// Shared manager of a project, created on first use
+    pub fn manager(&self, project_root: &Path) -> Result<Arc<IndexManager>> {
+        self.entry(project_root).map(|(manager, _)| manager)
+    }
+
+    /// Index the project and search it, one pass per project at a time
+    pub async fn search_context(&self, project_root: &Path, query: &str) -> Result<String> {
+        let (manager, index_lock) = self.entry(project_root)?;
+        let _guard = index_lock.lock().await;
+        manager.search_context(query).await
+    }
+
+    /// Number of registered projects
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|e| e.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
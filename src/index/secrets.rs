@@ -0,0 +1,417 @@
+//! Secret scanning - finds credentials in file content before it is uploaded
+//!
+//! Runs after `sanitize_content` on every file that is about to be split into blobs.
+//! Each detector has a policy: redact the match, skip the whole file, or abort indexing.
+//! Findings are appended to `.ace-tool/secrets.log` (never the secret itself, only a
+//! short hash of it).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use super::selection::SkipReason;
+
+/// File in `.ace-tool/` that findings are appended to
+pub const SECRETS_LOG_FILE_NAME: &str = "secrets.log";
+
+/// Default minimum Shannon entropy (bits per character) of a high-entropy assignment
+pub const DEFAULT_ENTROPY_THRESHOLD: f64 = 3.5;
+
+/// Built-in detectors: name and regex
+///
+/// A `secret` capture group limits the match that is redacted; otherwise the whole
+/// match is. `high_entropy_assignment` matches are only reported when the value passes
+/// the entropy threshold.
+const BUILTIN_DETECTORS: &[(&str, &str)] = &[
+    (
+        "aws_access_key_id",
+        r"\b(?P<secret>(?:AKIA|ASIA|AGPA|AIDA|AROA|AIPA|ANPA|ANVA)[0-9A-Z]{16})\b",
+    ),
+    (
+        "aws_secret_access_key",
+        r#"(?i)aws_?secret_?(?:access_?)?key["']?\s*[:=]\s*["']?(?P<secret>[A-Za-z0-9/+=]{40})\b"#,
+    ),
+    (
+        "private_key",
+        r"(?s)-----BEGIN [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----.*?(?:-----END [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----|\z)",
+    ),
+    (
+        "jwt",
+        r"\b(?P<secret>eyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]*)",
+    ),
+    (
+        "high_entropy_assignment",
+        r#"(?im)[A-Za-z0-9_.-]*(?:secret|token|passw(?:or)?d|pwd|api_?key|access_?key|private_?key|credential|auth)[A-Za-z0-9_.-]*["']?\s*(?::=|=|:)\s*["']?(?P<secret>[A-Za-z0-9+/=_.~-]{16,})(?:["'\s,;]|$)"#,
+    ),
+];
+
+const HIGH_ENTROPY_DETECTOR: &str = "high_entropy_assignment";
+
+/// What to do with a file that contains a secret
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretPolicy {
+    /// Replace the secret with a `[REDACTED:<detector>]` marker and index the file
+    #[default]
+    Redact,
+    /// Leave the file out of the index
+    Skip,
+    /// Stop indexing without uploading anything
+    Abort,
+}
+
+impl std::fmt::Display for SecretPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SecretPolicy::Redact => "redact",
+            SecretPolicy::Skip => "skip",
+            SecretPolicy::Abort => "abort",
+        })
+    }
+}
+
+/// A detector defined in the user config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomSecretPattern {
+    pub name: String,
+    /// Regex; a `secret` capture group limits what is redacted
+    pub regex: String,
+    /// Policy for this detector (default: the global policy)
+    #[serde(default)]
+    pub policy: Option<SecretPolicy>,
+}
+
+/// Secret scanning settings, the `secrets` section of the user config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretScanSettings {
+    /// Scan files before upload (default: true)
+    pub enabled: bool,
+    /// Policy of detectors without an override (default: redact)
+    pub policy: SecretPolicy,
+    /// Policy overrides by built-in detector name
+    pub detectors: BTreeMap<String, SecretPolicy>,
+    /// Built-in detectors to turn off
+    pub disabled: Vec<String>,
+    /// Additional detectors
+    pub patterns: Vec<CustomSecretPattern>,
+    /// Minimum entropy (bits per character) of a high-entropy assignment
+    pub entropy_threshold: f64,
+}
+
+impl Default for SecretScanSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            policy: SecretPolicy::default(),
+            detectors: BTreeMap::new(),
+            disabled: Vec::new(),
+            patterns: Vec::new(),
+            entropy_threshold: DEFAULT_ENTROPY_THRESHOLD,
+        }
+    }
+}
+
+impl SecretScanSettings {
+    /// Names of the built-in detectors
+    pub fn builtin_detectors() -> impl Iterator<Item = &'static str> {
+        BUILTIN_DETECTORS.iter().map(|(name, _)| *name)
+    }
+
+    /// Stable fingerprint, part of the index config hash
+    pub fn fingerprint(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// One secret found in file content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretFinding {
+    pub detector: String,
+    pub policy: SecretPolicy,
+    /// 1-based line of the start of the match
+    pub line: usize,
+    /// Byte range of the secret in the content
+    pub start: usize,
+    pub end: usize,
+    /// First 12 hex digits of the SHA-256 of the secret
+    pub fingerprint: String,
+}
+
+/// A finding as written to the secrets log
+#[derive(Debug, Serialize)]
+struct SecretLogRecord<'a> {
+    timestamp: String,
+    path: &'a str,
+    line: usize,
+    detector: &'a str,
+    policy: SecretPolicy,
+    fingerprint: &'a str,
+}
+
+struct Detector {
+    name: String,
+    regex: Regex,
+    policy: SecretPolicy,
+}
+
+/// Compiled detectors of a `SecretScanSettings`
+pub struct SecretScanner {
+    detectors: Vec<Detector>,
+    entropy_threshold: f64,
+    log_path: Option<PathBuf>,
+    log_lock: Mutex<()>,
+}
+
+impl std::fmt::Debug for SecretScanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretScanner")
+            .field(
+                "detectors",
+                &self.detectors.iter().map(|d| &d.name).collect::<Vec<_>>(),
+            )
+            .field("log_path", &self.log_path)
+            .finish()
+    }
+}
+
+impl SecretScanner {
+    /// Compile the detectors, rejecting unknown detector names and invalid regexes
+    pub fn new(settings: &SecretScanSettings) -> Result<Self> {
+        let builtin: HashMap<&str, &str> = BUILTIN_DETECTORS.iter().copied().collect();
+        for name in settings.detectors.keys().chain(&settings.disabled) {
+            if !builtin.contains_key(name.as_str()) {
+                return Err(anyhow!(
+                    "Unknown secret detector '{}' (built-in detectors: {})",
+                    name,
+                    SecretScanSettings::builtin_detectors()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        }
+        if !(0.0..=8.0).contains(&settings.entropy_threshold) {
+            return Err(anyhow!(
+                "secrets.entropy_threshold must be between 0 and 8, got {}",
+                settings.entropy_threshold
+            ));
+        }
+
+        let mut detectors = Vec::new();
+        if settings.enabled {
+            for (name, pattern) in BUILTIN_DETECTORS {
+                if settings.disabled.iter().any(|d| d == name) {
+                    continue;
+                }
+                detectors.push(Detector {
+                    name: name.to_string(),
+                    regex: Regex::new(pattern).expect("built-in secret detector regex"),
+                    policy: settings
+                        .detectors
+                        .get(*name)
+                        .copied()
+                        .unwrap_or(settings.policy),
+                });
+            }
+            for pattern in &settings.patterns {
+                if pattern.name.trim().is_empty() {
+                    return Err(anyhow!("Custom secret pattern needs a name"));
+                }
+                if detectors.iter().any(|d| d.name == pattern.name)
+                    || builtin.contains_key(pattern.name.as_str())
+                {
+                    return Err(anyhow!("Duplicate secret detector name '{}'", pattern.name));
+                }
+                let regex = Regex::new(&pattern.regex).map_err(|e| {
+                    anyhow!("Invalid regex for secret pattern '{}': {}", pattern.name, e)
+                })?;
+                detectors.push(Detector {
+                    name: pattern.name.clone(),
+                    regex,
+                    policy: pattern.policy.unwrap_or(settings.policy),
+                });
+            }
+        }
+
+        Ok(Self {
+            detectors,
+            entropy_threshold: settings.entropy_threshold,
+            log_path: None,
+            log_lock: Mutex::new(()),
+        })
+    }
+
+    /// Append findings to this log file
+    pub fn with_log(mut self, path: PathBuf) -> Self {
+        self.log_path = Some(path);
+        self
+    }
+
+    /// Whether any detector is active
+    pub fn is_enabled(&self) -> bool {
+        !self.detectors.is_empty()
+    }
+
+    /// Find secrets, ordered by position; overlapping matches keep the strictest detector's
+    pub fn scan(&self, content: &str) -> Vec<SecretFinding> {
+        let mut findings = Vec::new();
+        for detector in &self.detectors {
+            for caps in detector.regex.captures_iter(content) {
+                let Some(secret) = caps.name("secret").or_else(|| caps.get(0)) else {
+                    continue;
+                };
+                if secret.as_str().is_empty() {
+                    continue;
+                }
+                if detector.name == HIGH_ENTROPY_DETECTOR
+                    && !is_high_entropy(secret.as_str(), self.entropy_threshold)
+                {
+                    continue;
+                }
+                findings.push(SecretFinding {
+                    detector: detector.name.clone(),
+                    policy: detector.policy,
+                    line: content[..secret.start()].matches('\n').count() + 1,
+                    start: secret.start(),
+                    end: secret.end(),
+                    fingerprint: hex::encode(&Sha256::digest(secret.as_str().as_bytes())[..6]),
+                });
+            }
+        }
+
+        // Overlapping matches keep the strictest policy, so a `skip` or `abort`
+        // detector can't be hidden behind a redacting one that matched the same text
+        findings.sort_by_key(|f| (f.start, std::cmp::Reverse(f.policy)));
+        let mut kept: Vec<SecretFinding> = Vec::with_capacity(findings.len());
+        for finding in findings {
+            match kept.last_mut() {
+                Some(last) if finding.start < last.end => {
+                    if finding.policy > last.policy {
+                        *last = finding;
+                    }
+                }
+                _ => kept.push(finding),
+            }
+        }
+        kept
+    }
+
+    /// Replace each finding with a `[REDACTED:<detector>]` marker, keeping line breaks
+    pub fn redact(content: &str, findings: &[SecretFinding]) -> String {
+        let mut redacted = String::with_capacity(content.len());
+        let mut pos = 0;
+        for finding in findings {
+            redacted.push_str(&content[pos..finding.start]);
+            redacted.push_str(&format!("[REDACTED:{}]", finding.detector));
+            let secret = &content[finding.start..finding.end];
+            redacted.extend(std::iter::repeat_n('\n', secret.matches('\n').count()));
+            pos = finding.end;
+        }
+        redacted.push_str(&content[pos..]);
+        redacted
+    }
+
+    /// Scan a file about to be indexed, log the findings and apply the strictest policy
+    ///
+    /// Returns the content to index (redacted if needed) and the number of redactions,
+    /// or the skip reason when a `skip` or `abort` detector matched.
+    pub fn apply(
+        &self,
+        rel_path: &str,
+        content: String,
+    ) -> std::result::Result<(String, usize), SkipReason> {
+        let findings = self.scan(&content);
+        let Some(strictest) = findings.iter().max_by_key(|f| f.policy) else {
+            return Ok((content, 0));
+        };
+
+        warn!(
+            "Secret scan: {} finding(s) in {} ({}), policy: {}",
+            findings.len(),
+            rel_path,
+            findings
+                .iter()
+                .map(|f| format!("{} line {}", f.detector, f.line))
+                .collect::<Vec<_>>()
+                .join(", "),
+            strictest.policy
+        );
+        self.log(rel_path, &findings);
+
+        match strictest.policy {
+            SecretPolicy::Redact => Ok((Self::redact(&content, &findings), findings.len())),
+            policy => Err(SkipReason::Secret {
+                detector: strictest.detector.clone(),
+                line: strictest.line,
+                policy,
+            }),
+        }
+    }
+
+    /// Append findings to the secrets log, best effort
+    fn log(&self, rel_path: &str, findings: &[SecretFinding]) {
+        let Some(path) = &self.log_path else {
+            return;
+        };
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let mut lines = String::new();
+        for finding in findings {
+            let record = SecretLogRecord {
+                timestamp: timestamp.clone(),
+                path: rel_path,
+                line: finding.line,
+                detector: &finding.detector,
+                policy: finding.policy,
+                fingerprint: &finding.fingerprint,
+            };
+            if let Ok(line) = serde_json::to_string(&record) {
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+        }
+
+        let _guard = self.log_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        if let Err(e) = written {
+            warn!("Failed to write secrets log {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Whether a value looks random: letters and digits, and enough entropy per character
+fn is_high_entropy(value: &str, threshold: f64) -> bool {
+    let has_digit = value.chars().any(|c| c.is_ascii_digit());
+    let has_letter = value.chars().any(|c| c.is_ascii_alphabetic());
+    has_digit && has_letter && shannon_entropy(value) >= threshold
+}
+
+/// Shannon entropy in bits per character
+fn shannon_entropy(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let len = value.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
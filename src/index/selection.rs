@@ -0,0 +1,344 @@
+//! File selection - which project files are indexed, and why the others are not
+//!
+//! `list_files` runs the same path checks as indexing (ignore files, exclude patterns,
+//! file type, upload policy and size) without reading file contents. `dry_run`
+//! additionally reads and chunks every candidate file through the indexing pipeline,
+//! without uploading. Neither creates `.ace-tool/`.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use super::manager::{
+    build_ignore_rules, collect_file_paths_standalone, compile_exclude_patterns,
+    exclusion_reason_standalone, is_indexable_file_standalone, process_file_standalone, IndexData,
+    ProcessedResult, MAX_BLOB_SIZE,
+};
+use super::secrets::{SecretPolicy, SecretScanner};
+use super::upload_policy::UploadPolicy;
+use crate::config::Config;
+use crate::utils::path_normalizer::normalize_relative_path;
+
+/// Why a file or directory is left out of the index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Matched a `.gitignore` / `.aceignore` rule
+    Ignored { source: String, pattern: String },
+    /// Matched a built-in exclude pattern
+    ExcludePattern { pattern: String },
+    /// Neither a known text extension nor a known file name
+    UnsupportedType,
+    /// Matched an upload denylist pattern
+    UploadDenied { pattern: String },
+    /// An upload allowlist is set and does not match
+    NotAllowlisted,
+    /// Larger than the maximum blob size, on disk or once decoded
+    TooLarge { size: u64 },
+    /// Content looks binary (too many control characters)
+    Binary,
+    /// A secret detector with the `skip` or `abort` policy matched
+    Secret {
+        detector: String,
+        line: usize,
+        policy: SecretPolicy,
+    },
+    /// Could not be read
+    Unreadable { error: String },
+    /// Deleted while the project was being scanned
+    Removed,
+    /// Path could not be resolved inside the project root
+    OutsideRoot,
+}
+
+impl SkipReason {
+    /// Whether indexing stops instead of skipping the file
+    pub fn aborts_indexing(&self) -> bool {
+        matches!(
+            self,
+            SkipReason::Secret {
+                policy: SecretPolicy::Abort,
+                ..
+            }
+        )
+    }
+
+    /// Stable reason code
+    pub fn code(&self) -> &'static str {
+        match self {
+            SkipReason::Ignored { .. } => "ignored",
+            SkipReason::ExcludePattern { .. } => "exclude_pattern",
+            SkipReason::UnsupportedType => "unsupported_type",
+            SkipReason::UploadDenied { .. } => "upload_denied",
+            SkipReason::NotAllowlisted => "not_allowlisted",
+            SkipReason::TooLarge { .. } => "too_large",
+            SkipReason::Binary => "binary",
+            SkipReason::Secret { .. } => "secret",
+            SkipReason::Unreadable { .. } => "unreadable",
+            SkipReason::Removed => "removed",
+            SkipReason::OutsideRoot => "outside_root",
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Ignored { source, pattern } => {
+                write!(f, "ignored by {} rule '{}'", source, pattern)
+            }
+            SkipReason::ExcludePattern { pattern } => {
+                write!(f, "matches exclude pattern '{}'", pattern)
+            }
+            SkipReason::UnsupportedType => f.write_str("not a recognized text file type"),
+            SkipReason::UploadDenied { pattern } => {
+                write!(f, "denied by upload policy '{}'", pattern)
+            }
+            SkipReason::NotAllowlisted => f.write_str("not in the upload allowlist"),
+            SkipReason::TooLarge { size } => write!(
+                f,
+                "too large ({}KB, limit {}KB)",
+                size / 1024,
+                MAX_BLOB_SIZE / 1024
+            ),
+            SkipReason::Binary => f.write_str("binary content"),
+            SkipReason::Secret {
+                detector,
+                line,
+                policy: SecretPolicy::Abort,
+            } => write!(f, "{} on line {} (aborts indexing)", detector, line),
+            SkipReason::Secret { detector, line, .. } => {
+                write!(f, "{} on line {}", detector, line)
+            }
+            SkipReason::Unreadable { error } => write!(f, "unreadable: {}", error),
+            SkipReason::Removed => f.write_str("removed during the scan"),
+            SkipReason::OutsideRoot => f.write_str("outside the project root"),
+        }
+    }
+}
+
+/// One file, or one excluded directory, of a project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileListEntry {
+    /// Path relative to the project root, with forward slashes
+    pub path: String,
+    pub is_dir: bool,
+    pub included: bool,
+    /// Set when the entry is not indexed; an excluded directory covers everything below it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<SkipReason>,
+}
+
+/// Walk the project and classify every file (and excluded directory), sorted by path
+pub fn list_files(config: &Config, project_root: &Path) -> Result<Vec<FileListEntry>> {
+    let upload_policy = UploadPolicy::new(&config.upload_policy, project_root)?;
+    let gitignore = build_ignore_rules(project_root);
+    let compiled_patterns = compile_exclude_patterns(&config.exclude_patterns);
+    let mut entries = Vec::new();
+
+    let mut walker = WalkDir::new(project_root)
+        .follow_links(false)
+        .sort_by_file_name()
+        .min_depth(1)
+        .into_iter();
+
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!("Failed to access entry during directory walk: {}", e);
+                continue;
+            }
+        };
+        let is_dir = entry.file_type().is_dir();
+        if !is_dir && !entry.file_type().is_file() {
+            // Symlinks and special files are never followed or indexed
+            continue;
+        }
+
+        let path = entry.path();
+        let rel_path = normalize_relative_path(
+            &path
+                .strip_prefix(project_root)
+                .unwrap_or(path)
+                .to_string_lossy(),
+        );
+
+        let skipped = exclusion_reason_standalone(
+            path,
+            is_dir,
+            project_root,
+            gitignore.as_ref(),
+            &compiled_patterns,
+        );
+        if is_dir {
+            if skipped.is_some() {
+                walker.skip_current_dir();
+                entries.push(FileListEntry {
+                    path: rel_path,
+                    is_dir,
+                    included: false,
+                    skipped,
+                });
+            }
+            continue;
+        }
+
+        let skipped = skipped.or_else(|| {
+            if !is_indexable_file_standalone(path, &config.text_extensions, &config.text_filenames)
+            {
+                return Some(SkipReason::UnsupportedType);
+            }
+            if let Some(reason) = upload_policy.check(&rel_path) {
+                return Some(reason);
+            }
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            (size > MAX_BLOB_SIZE as u64).then_some(SkipReason::TooLarge { size })
+        });
+
+        entries.push(FileListEntry {
+            path: rel_path,
+            is_dir,
+            included: skipped.is_none(),
+            skipped,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// One file, or one excluded directory, of a dry run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DryRunEntry {
+    /// Path relative to the project root, with forward slashes
+    pub path: String,
+    pub is_dir: bool,
+    pub included: bool,
+    /// Number of blobs the file is split into
+    pub chunks: usize,
+    /// Bytes that would be uploaded for the file
+    pub bytes: u64,
+    /// Encoding the file was decoded with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<&'static str>,
+    /// Number of secrets that would be redacted
+    pub redacted: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<SkipReason>,
+}
+
+impl From<FileListEntry> for DryRunEntry {
+    fn from(entry: FileListEntry) -> Self {
+        Self {
+            path: entry.path,
+            is_dir: entry.is_dir,
+            included: entry.included,
+            chunks: 0,
+            bytes: 0,
+            encoding: None,
+            redacted: 0,
+            skipped: entry.skipped,
+        }
+    }
+}
+
+/// Run file collection and processing as indexing would, without uploading, sorted by path
+///
+/// Every candidate the upload policy allows is read, decoded, scanned for secrets and
+/// chunked from scratch (the existing index is not consulted, and no secrets log is
+/// written); paths excluded before reading are reported as in `list_files`.
+pub fn dry_run(config: &Config, project_root: &Path) -> Result<Vec<DryRunEntry>> {
+    let scanner = SecretScanner::new(&config.secret_scan)?;
+    let upload_policy = UploadPolicy::new(&config.upload_policy, project_root)?;
+    let compiled_patterns = compile_exclude_patterns(&config.exclude_patterns);
+    let mut candidates = collect_file_paths_standalone(
+        project_root,
+        &config.text_extensions,
+        &config.text_filenames,
+        &compiled_patterns,
+    );
+    candidates.retain(|path| upload_policy_allows(&upload_policy, path, project_root));
+    let no_index = IndexData::default();
+
+    let mut entries: Vec<DryRunEntry> = candidates
+        .par_iter()
+        .map(|path| {
+            let processed = process_file_standalone(
+                path,
+                &no_index,
+                project_root,
+                &scanner,
+                config.max_lines_per_blob,
+            );
+            match processed {
+                Ok(processed) => {
+                    let (chunks, bytes) = match &processed.result {
+                        ProcessedResult::New { blobs, .. } => (
+                            blobs.len(),
+                            blobs.iter().map(|b| b.content.len() as u64).sum(),
+                        ),
+                        ProcessedResult::Cached { entry } => (entry.blob_hashes.len(), 0),
+                    };
+                    DryRunEntry {
+                        path: processed.rel_path,
+                        is_dir: false,
+                        included: true,
+                        chunks,
+                        bytes,
+                        encoding: processed.encoding,
+                        redacted: processed.redacted,
+                        skipped: None,
+                    }
+                }
+                Err(reason) => DryRunEntry {
+                    path: normalize_relative_path(
+                        &path
+                            .strip_prefix(project_root)
+                            .unwrap_or(path)
+                            .to_string_lossy(),
+                    ),
+                    is_dir: false,
+                    included: false,
+                    chunks: 0,
+                    bytes: 0,
+                    encoding: None,
+                    redacted: 0,
+                    skipped: Some(reason),
+                },
+            }
+        })
+        .collect();
+
+    // Paths that never reached processing: ignored, excluded, of an unsupported type or
+    // refused by the upload policy
+    let processed: HashSet<String> = entries.iter().map(|e| e.path.clone()).collect();
+    entries.extend(
+        list_files(config, project_root)?
+            .into_iter()
+            .filter(|entry| !entry.included && !processed.contains(&entry.path))
+            .map(DryRunEntry::from),
+    );
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Whether the upload policy lets a collected file through
+pub(super) fn upload_policy_allows(
+    policy: &UploadPolicy,
+    path: &Path,
+    project_root: &Path,
+) -> bool {
+    match path.strip_prefix(project_root) {
+        Ok(rel) => policy
+            .check(&normalize_relative_path(&rel.to_string_lossy()))
+            .is_none(),
+        // Reported as outside the root by processing
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,207 @@
+//! Upload policy - which paths may be sent to the indexing service, and a record of
+//! what was sent
+//!
+//! The allowlist and denylist use `.gitignore` syntax but are independent of the
+//! project's ignore files: a denied path is never uploaded, and when an allowlist is
+//! set, only paths it matches are. Every successfully uploaded blob is appended to
+//! `.ace-tool/upload_audit.log`, which is rotated by size. Unlike `ACE_HTTP_LOG`, the
+//! audit log is on by default and never contains file content.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::manager::{Blob, IndexManager};
+use super::selection::SkipReason;
+
+/// File in `.ace-tool/` that uploaded blobs are recorded in
+pub const UPLOAD_AUDIT_LOG_FILE_NAME: &str = "upload_audit.log";
+
+/// Default size at which the audit log is rotated (10MB)
+pub const DEFAULT_AUDIT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated audit logs kept
+pub const DEFAULT_AUDIT_MAX_FILES: usize = 5;
+
+/// Audit log settings, the `audit` part of the `upload` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UploadAuditSettings {
+    /// Record uploaded blobs (default: true)
+    pub enabled: bool,
+    /// Size in bytes at which the log is rotated
+    pub max_bytes: u64,
+    /// Rotated logs kept as `upload_audit.log.1` (newest) to `.N`
+    pub max_files: usize,
+}
+
+impl Default for UploadAuditSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_bytes: DEFAULT_AUDIT_MAX_BYTES,
+            max_files: DEFAULT_AUDIT_MAX_FILES,
+        }
+    }
+}
+
+/// Upload policy settings, the `upload` section of the user config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UploadPolicySettings {
+    /// Only upload paths matching one of these patterns (empty: no restriction)
+    pub allow: Vec<String>,
+    /// Never upload paths matching one of these patterns; wins over `allow`
+    pub deny: Vec<String>,
+    pub audit: UploadAuditSettings,
+}
+
+/// Compiled allowlist and denylist
+#[derive(Debug)]
+pub struct UploadPolicy {
+    allow: Option<Gitignore>,
+    deny: Gitignore,
+}
+
+impl UploadPolicy {
+    /// Compile the patterns, relative to the project root
+    pub fn new(settings: &UploadPolicySettings, project_root: &Path) -> Result<Self> {
+        if settings.audit.max_bytes == 0 {
+            return Err(anyhow!("upload.audit.max_bytes must be greater than 0"));
+        }
+        let allow = if settings.allow.is_empty() {
+            None
+        } else {
+            Some(compile_patterns(project_root, "allow", &settings.allow)?)
+        };
+        let deny = compile_patterns(project_root, "deny", &settings.deny)?;
+        Ok(Self { allow, deny })
+    }
+
+    /// Why a file may not be uploaded, given its path relative to the project root
+    pub fn check(&self, rel_path: &str) -> Option<SkipReason> {
+        if let Match::Ignore(glob) = self.deny.matched_path_or_any_parents(rel_path, false) {
+            return Some(SkipReason::UploadDenied {
+                pattern: glob.original().to_string(),
+            });
+        }
+        match &self.allow {
+            Some(allow)
+                if !allow
+                    .matched_path_or_any_parents(rel_path, false)
+                    .is_ignore() =>
+            {
+                Some(SkipReason::NotAllowlisted)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn compile_patterns(project_root: &Path, list: &str, patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(project_root);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| anyhow!("Invalid upload {} pattern '{}': {}", list, pattern, e))?;
+    }
+    builder
+        .build()
+        .map_err(|e| anyhow!("Invalid upload {} patterns: {}", list, e))
+}
+
+/// One line of the audit log
+#[derive(Serialize)]
+struct UploadAuditRecord<'a> {
+    timestamp: &'a str,
+    path: &'a str,
+    blob: String,
+    base_url: &'a str,
+    request_id: &'a str,
+}
+
+/// Size-rotated JSONL log of uploaded blobs
+#[derive(Debug)]
+pub struct UploadAuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    lock: Mutex<()>,
+}
+
+impl UploadAuditLog {
+    /// Audit log at `path`, or `None` when auditing is turned off
+    pub fn new(path: PathBuf, settings: &UploadAuditSettings) -> Option<Self> {
+        settings.enabled.then(|| Self {
+            path,
+            max_bytes: settings.max_bytes,
+            max_files: settings.max_files,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Append one record per blob of a successful upload request, best effort
+    pub fn record(&self, base_url: &str, request_id: &str, blobs: &[Blob]) {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let mut lines = String::new();
+        for blob in blobs {
+            let record = UploadAuditRecord {
+                timestamp: &timestamp,
+                path: &blob.path,
+                blob: IndexManager::calculate_blob_name(&blob.path, &blob.content),
+                base_url,
+                request_id,
+            };
+            if let Ok(line) = serde_json::to_string(&record) {
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+        }
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + lines.len() as u64 > self.max_bytes {
+            if let Err(e) = self.rotate() {
+                warn!("Failed to rotate upload audit log {:?}: {}", self.path, e);
+            }
+        }
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        if let Err(e) = written {
+            warn!("Failed to write upload audit log {:?}: {}", self.path, e);
+        }
+    }
+
+    /// Shift `log.N-1` to `log.N`, ..., `log` to `log.1`, dropping the oldest
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let rotated = |n: usize| {
+            let mut name = self.path.as_os_str().to_owned();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        match fs::remove_file(rotated(self.max_files)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        for n in (1..self.max_files).rev() {
+            match fs::rename(rotated(n), rotated(n + 1)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, rotated(1))
+    }
+}
@@ -1,5 +1,6 @@
 //! ace-tool library - MCP server for codebase indexing and semantic search
 
+pub mod commands;
 pub mod config;
 pub mod enhancer;
 pub mod http_logger;
@@ -1,13 +1,29 @@
 //! ace-tool - MCP server for codebase indexing and semantic search
 
-use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::commands;
+use ace_tool::config::{Config, ConfigOptions, UserConfigFile, DEFAULT_WEBUI_TIMEOUT_SECS};
+use ace_tool::enhancer::batch::{
+    enhance_batch, parse_batch, read_input, read_prompt, resolve_project_root, BatchProvider,
+    DEFAULT_BATCH_CONCURRENCY,
+};
+use ace_tool::enhancer::editor_review::{resolve_editor, review_in_editor};
 use ace_tool::enhancer::prompt_enhancer::{get_enhancer_endpoint, PromptEnhancer};
+use ace_tool::enhancer::templates::load_enhance_template;
 use ace_tool::index::IndexManager;
-use ace_tool::mcp::{McpServer, TransportMode};
-use ace_tool::service::get_third_party_config;
+use ace_tool::mcp::http::{
+    DEFAULT_HTTP_ADDR, DEFAULT_MAX_SESSIONS, DEFAULT_SESSION_IDLE_TIMEOUT, ENV_HTTP_TOKEN,
+    MCP_HTTP_PATH,
+};
+use ace_tool::mcp::{HttpTransportOptions, McpHttpServer, McpServer, TransportMode};
+use ace_tool::service::{get_third_party_config, ConversationHistory};
 use anyhow::{anyhow, Result};
-use clap::{Parser, ValueEnum};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
 use std::env;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -16,12 +32,47 @@ enum TransportArg {
     Auto,
     Lsp,
     Line,
+    /// MCP Streamable HTTP instead of stdio
+    Http,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum CliCommand {
+    /// Index the project if needed, search it and print the retrieved code context
+    Search {
+        /// Natural language description of the code to find
+        query: String,
+    },
+    /// Show index statistics of the project
+    Status,
+    /// Remove the project's .ace-tool data (custom prompts and templates are kept)
+    Clean {
+        /// Also remove custom prompts, the enhancement template and review sessions
+        #[arg(long, default_value = "false")]
+        all: bool,
+    },
+    /// List the files that would be indexed, and why other paths are excluded
+    LsFiles,
+    /// Run the MCP server (the default without a subcommand)
+    Serve,
 }
 
 #[derive(Parser, Debug)]
 #[command(name = "ace-tool")]
 #[command(about = "MCP server for codebase indexing and semantic search")]
+#[command(group(ArgGroup::new("single_prompt").args(["enhance_prompt", "enhance_prompt_file"])))]
 struct Args {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
+    /// Print subcommand and --dry-run output as JSON
+    #[arg(long, global = true, default_value = "false")]
+    json: bool,
+
+    /// Project root to work on (default: current directory)
+    #[arg(long, global = true)]
+    project_root: Option<PathBuf>,
+
     /// API base URL for the indexing service
     #[arg(long)]
     base_url: Option<String>,
@@ -30,10 +81,22 @@ struct Args {
     #[arg(long)]
     token: Option<String>,
 
-    /// Transport framing: auto, lsp, line
+    /// Transport: stdio framing (auto, lsp, line) or http (MCP Streamable HTTP)
     #[arg(long, value_enum, default_value = "auto")]
     transport: TransportArg,
 
+    /// Bind address for --transport http (default: 127.0.0.1:8765)
+    #[arg(long)]
+    http_addr: Option<String>,
+
+    /// Bearer token required by --transport http (default: ACE_MCP_HTTP_TOKEN)
+    #[arg(long)]
+    http_token: Option<String>,
+
+    /// Browser origin allowed to call --transport http besides loopback ones (repeatable)
+    #[arg(long = "http-allowed-origin", value_name = "ORIGIN")]
+    http_allowed_origins: Vec<String>,
+
     /// Maximum lines per blob (default: 800)
     #[arg(long)]
     max_lines_per_blob: Option<usize>,
@@ -65,18 +128,88 @@ struct Args {
 
     /// Bind address and port for the enhance_prompt Web UI server (e.g., "127.0.0.1:8754", "0.0.0.0:3456")
     /// If not specified, automatically selects an available port on 127.0.0.1.
-    /// WARNING: Binding to 0.0.0.0 or a non-loopback address exposes the unauthenticated
-    /// Web UI to the network. Only use this in trusted environments.
+    /// Each session URL carries a secret token; use --webui-tls-cert/--webui-tls-key
+    /// when binding to a non-loopback address so the token is not sent in clear text.
     #[arg(long)]
     webui_addr: Option<String>,
 
-    /// Index-only mode: index current directory and exit (no MCP server)
+    /// PEM certificate chain for serving the Web UI over HTTPS
+    #[arg(long, requires = "webui_tls_key")]
+    webui_tls_cert: Option<String>,
+
+    /// PEM private key for serving the Web UI over HTTPS
+    #[arg(long, requires = "webui_tls_cert")]
+    webui_tls_key: Option<String>,
+
+    /// Seconds to wait for a Web UI review before returning the latest enhanced prompt
+    /// (default: 480). The session stays open and can be fetched later.
+    #[arg(long)]
+    webui_timeout: Option<u64>,
+
+    /// Serve MCP on a Unix socket from one long-lived process shared by every editor
+    /// window (Unix only). Exits after --daemon-idle-timeout seconds without connections.
+    #[arg(long, default_value = "false", conflicts_with = "use_daemon")]
+    daemon: bool,
+
+    /// Relay stdio MCP to the shared daemon, starting it on first use (Unix only)
+    #[arg(long, default_value = "false")]
+    use_daemon: bool,
+
+    /// Unix socket of the daemon (default: derived from the other options)
+    #[arg(long)]
+    daemon_socket: Option<String>,
+
+    /// Seconds without connections before the daemon exits (default: 600)
+    #[arg(long)]
+    daemon_idle_timeout: Option<u64>,
+
+    /// Index-only mode: index the project root and exit (no MCP server)
     #[arg(long, default_value = "false")]
     index_only: bool,
 
-    /// Enhance a prompt and output the result to stdout, then exit
+    /// With --index-only: read and chunk files as indexing would, print a per-file report
+    /// and exit without uploading (no credentials needed)
+    #[arg(long, default_value = "false", requires = "index_only")]
+    dry_run: bool,
+
+    /// Print the --dry-run report as CSV
+    #[arg(
+        long,
+        default_value = "false",
+        requires = "dry_run",
+        conflicts_with = "json"
+    )]
+    csv: bool,
+
+    /// Enhance a prompt and output the result to stdout, then exit ("-" reads stdin)
     #[arg(long)]
     enhance_prompt: Option<String>,
+
+    /// Like --enhance-prompt, reading the prompt from a file ("-" reads stdin)
+    #[arg(long)]
+    enhance_prompt_file: Option<String>,
+
+    /// Enhance every line of a JSONL file ("-" reads stdin) and write JSONL results, then exit.
+    /// Each line is {"prompt": ..., "conversation_history": ..., "project_root": ...}.
+    #[arg(long, conflicts_with_all = ["single_prompt", "review_in_editor"])]
+    enhance_batch: Option<String>,
+
+    /// Number of batch enhancements run at the same time (default: 4)
+    #[arg(long, requires = "enhance_batch")]
+    batch_concurrency: Option<usize>,
+
+    /// Write batch results to this file instead of stdout
+    #[arg(long, requires = "enhance_batch")]
+    batch_output: Option<String>,
+
+    /// Review the enhanced prompt in $VISUAL/$EDITOR before printing it (--enhance-prompt mode).
+    /// Saving an empty file cancels the enhancement.
+    #[arg(long, default_value = "false", requires = "single_prompt")]
+    review_in_editor: bool,
+
+    /// Seconds to wait for the editor before giving up (default: 480)
+    #[arg(long, requires = "review_in_editor")]
+    review_timeout: Option<u64>,
 }
 
 #[tokio::main]
@@ -87,79 +220,101 @@ async fn main() -> Result<()> {
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let command = args.command.take();
+
+    if command.is_some()
+        && (args.index_only
+            || args.enhance_prompt.is_some()
+            || args.enhance_prompt_file.is_some()
+            || args.enhance_batch.is_some())
+    {
+        return Err(anyhow!(
+            "Subcommands cannot be combined with --index-only or --enhance-* modes"
+        ));
+    }
+
+    let project_root = resolve_root(args.project_root.as_deref())?;
+    let user_config = UserConfigFile::load()?;
+
+    // Local subcommands need no API credentials
+    match command {
+        Some(CliCommand::Status) => {
+            let config = Config::new_local(
+                args.max_lines_per_blob,
+                user_config.secrets.clone(),
+                user_config.upload.clone(),
+            );
+            return print_report(&commands::status(&config, &project_root)?, args.json);
+        }
+        Some(CliCommand::Clean { all }) => {
+            return print_report(&commands::clean(&project_root, all)?, args.json);
+        }
+        Some(CliCommand::LsFiles) => {
+            let config = Config::new_local(
+                args.max_lines_per_blob,
+                user_config.secrets.clone(),
+                user_config.upload.clone(),
+            );
+            return print_report(&commands::ls_files(&config, &project_root)?, args.json);
+        }
+        _ => {}
+    }
+
+    // Dry run: show what indexing would upload, and why other files are skipped
+    if args.index_only && args.dry_run {
+        let config = Config::new_local(
+            args.max_lines_per_blob,
+            user_config.secrets.clone(),
+            user_config.upload.clone(),
+        );
+        let report = commands::index_dry_run(&config, &project_root)?;
+        if args.csv {
+            print!("{}", report.to_csv());
+            return Ok(());
+        }
+        return print_report(&report, args.json);
+    }
 
-    // Enhance-prompt mode: enhance the prompt and output to stdout
-    if let Some(ref prompt) = args.enhance_prompt {
+    // Enhance-prompt mode: enhance the prompt(s) and output to stdout
+    if args.enhance_prompt.is_some()
+        || args.enhance_prompt_file.is_some()
+        || args.enhance_batch.is_some()
+    {
         info!("Enhance-prompt mode: enhancing prompt");
-        let project_root = env::current_dir()?;
         info!("Project root: {:?}", project_root);
 
-        // Check if using third-party endpoint (claude/openai/gemini)
-        let endpoint = get_enhancer_endpoint();
-        let config = if endpoint.is_third_party() {
-            // For third-party endpoints, base_url and token are not required from CLI
-            // They will be read from environment variables
-            // Validate early that required environment variables are set
-            let _ = get_third_party_config(endpoint)
-                .map_err(|e| anyhow!("Third-party endpoint configuration error: {}", e))?;
-            info!("Using third-party endpoint: {}", endpoint);
-            match (args.base_url.clone(), args.token.clone()) {
-                (Some(base_url), Some(token)) => {
-                    info!("Using CLI base_url/token to enable ACE search features");
-                    Config::new(
-                        base_url,
-                        token,
-                        ConfigOptions {
-                            max_lines_per_blob: args.max_lines_per_blob,
-                            upload_timeout: args.upload_timeout,
-                            upload_concurrency: args.upload_concurrency,
-                            retrieval_timeout: args.retrieval_timeout,
-                            no_adaptive: args.no_adaptive,
-                            no_webbrowser_enhance_prompt: args.no_webbrowser_enhance_prompt,
-                            force_xdg_open: args.force_xdg_open,
-                            webui_addr: args.webui_addr.clone(),
-                        },
-                    )?
-                }
-                (None, None) => Config::new_for_third_party_enhancer(),
-                _ => {
-                    return Err(anyhow!(
-                        "--base-url and --token must be provided together in third-party enhance-prompt mode"
-                    ));
-                }
-            }
-        } else {
-            // For new/old endpoints, base_url and token are required
-            let base_url = args
-                .base_url
-                .clone()
-                .ok_or_else(|| anyhow!("--base-url is required for '{}' endpoint", endpoint))?;
-            let token = args
-                .token
-                .clone()
-                .ok_or_else(|| anyhow!("--token is required for '{}' endpoint", endpoint))?;
-            Config::new(
-                base_url,
-                token,
-                ConfigOptions {
-                    max_lines_per_blob: args.max_lines_per_blob,
-                    upload_timeout: args.upload_timeout,
-                    upload_concurrency: args.upload_concurrency,
-                    retrieval_timeout: args.retrieval_timeout,
-                    no_adaptive: args.no_adaptive,
-                    no_webbrowser_enhance_prompt: args.no_webbrowser_enhance_prompt,
-                    force_xdg_open: args.force_xdg_open,
-                    webui_addr: args.webui_addr.clone(),
-                },
-            )?
-        };
+        // Fail fast on a malformed custom enhancement template
+        load_enhance_template(Some(&project_root))?;
 
-        let enhancer = PromptEnhancer::new(config.clone())?;
+        let enhancer = PromptEnhancer::new(enhance_mode_config(&args, &user_config)?)?;
+
+        if let Some(ref source) = args.enhance_batch {
+            return run_enhance_batch(&enhancer, source, &args, &project_root, &user_config).await;
+        }
+
+        let prompt = match (&args.enhance_prompt, &args.enhance_prompt_file) {
+            (Some(prompt), _) if prompt == "-" => read_prompt("-")?,
+            (Some(prompt), _) => prompt.clone(),
+            (None, Some(path)) => read_prompt(path)?,
+            (None, None) => unreachable!("checked above"),
+        };
         let enhanced = enhancer
-            .enhance_simple(prompt, "", Some(&project_root))
+            .enhance_simple(
+                &prompt,
+                &ConversationHistory::default(),
+                Some(&project_root),
+            )
             .await?;
 
+        let enhanced = if args.review_in_editor {
+            let timeout =
+                Duration::from_secs(args.review_timeout.unwrap_or(DEFAULT_WEBUI_TIMEOUT_SECS));
+            review_in_editor(&resolve_editor(), &enhanced, timeout).await?
+        } else {
+            enhanced
+        };
+
         // Output enhanced prompt to stdout
         println!("{}", enhanced);
         return Ok(());
@@ -184,16 +339,30 @@ async fn main() -> Result<()> {
             no_webbrowser_enhance_prompt: args.no_webbrowser_enhance_prompt,
             force_xdg_open: args.force_xdg_open,
             webui_addr: args.webui_addr,
+            webui_tls_cert: args.webui_tls_cert,
+            webui_tls_key: args.webui_tls_key,
+            webui_timeout: args.webui_timeout,
+            secret_scan: Some(user_config.secrets),
+            upload_policy: Some(user_config.upload),
+            generation: Some(user_config.enhancer),
         },
     )?;
 
-    // Index-only mode: index current directory and exit
+    if let Some(CliCommand::Search { query }) = &command {
+        let report = commands::search(config, &project_root, query).await;
+        print_report(&report, args.json)?;
+        if report.error.is_some() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Index-only mode: index the project root and exit
     if args.index_only {
-        info!("Index-only mode: indexing current directory");
-        let project_root = env::current_dir()?;
+        info!("Index-only mode: indexing project root");
         info!("Project root: {:?}", project_root);
 
-        let manager = IndexManager::new(config, project_root)?;
+        let manager = IndexManager::new(config, project_root.clone())?;
         let result = manager.index_project().await;
 
         match result.status.as_str() {
@@ -235,19 +404,352 @@ async fn main() -> Result<()> {
 
     info!("Starting ace-tool MCP server");
 
+    // Fail fast on a malformed custom enhancement template
+    load_enhance_template(Some(&project_root))?;
+
     let transport_mode = match args.transport {
-        TransportArg::Auto => None,
+        TransportArg::Auto | TransportArg::Http => None,
         TransportArg::Lsp => Some(TransportMode::Lsp),
         TransportArg::Line => Some(TransportMode::Line),
     };
 
+    if args.daemon || args.use_daemon {
+        if matches!(args.transport, TransportArg::Http) {
+            return Err(anyhow!(
+                "--daemon and --use-daemon cannot be combined with --transport http"
+            ));
+        }
+        let daemon = DaemonArgs {
+            front_end: args.use_daemon,
+            project_root: args.project_root.as_ref().map(|_| project_root.clone()),
+            json: args.json,
+            socket: args.daemon_socket.clone(),
+            idle_timeout: args.daemon_idle_timeout,
+        };
+        if let Err(e) = run_daemon_mode(daemon, config, transport_mode).await {
+            error!("Daemon error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Create and run MCP server
-    let server = McpServer::new(config, transport_mode);
+    let mut server = McpServer::new(config, transport_mode);
+    if args.project_root.is_some() {
+        server = server.with_project_root(project_root.clone());
+    }
+
+    let result = if matches!(args.transport, TransportArg::Http) {
+        let addr = args.http_addr.as_deref().unwrap_or(DEFAULT_HTTP_ADDR);
+        let options = HttpTransportOptions {
+            bind_addr: addr
+                .parse()
+                .map_err(|e| anyhow!("Invalid --http-addr '{}': {}", addr, e))?,
+            bearer_token: args
+                .http_token
+                .clone()
+                .or_else(|| env::var(ENV_HTTP_TOKEN).ok()),
+            session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            allowed_origins: args.http_allowed_origins.clone(),
+        };
+        let http = McpHttpServer::new(server, options);
+        if args.json {
+            serve_http_with_report(&http, &project_root).await
+        } else {
+            http.run().await
+        }
+    } else {
+        report_serving(
+            args.json,
+            serde_json::json!({ "transport": "stdio", "project_root": project_root }),
+        );
+        server.run().await
+    };
 
-    if let Err(e) = server.run().await {
+    if let Err(e) = result {
         error!("Server error: {}", e);
         std::process::exit(1);
     }
 
     Ok(())
 }
+
+/// Canonical project root from --project-root, or the current directory
+fn resolve_root(project_root: Option<&Path>) -> Result<PathBuf> {
+    let root = match project_root {
+        Some(root) => root.to_path_buf(),
+        None => return Ok(env::current_dir()?),
+    };
+    if !root.is_dir() {
+        return Err(anyhow!(
+            "--project-root is not a directory: {}",
+            root.display()
+        ));
+    }
+    root.canonicalize()
+        .map_err(|e| anyhow!("Invalid --project-root {}: {}", root.display(), e))
+}
+
+/// Print a subcommand report as JSON or plain text
+fn print_report<T>(report: &T, json: bool) -> Result<()>
+where
+    T: serde::Serialize + std::fmt::Display,
+{
+    if json {
+        println!("{}", serde_json::to_string_pretty(report)?);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+/// With --json, describe the server being started on one stderr line
+/// (stdout carries the MCP protocol in stdio mode)
+fn report_serving(json: bool, details: serde_json::Value) {
+    if json {
+        eprintln!("{}", details);
+    }
+}
+
+/// Serve MCP over HTTP until Ctrl-C, reporting the bound address as JSON
+async fn serve_http_with_report(http: &McpHttpServer, project_root: &Path) -> Result<()> {
+    let addr = http.start().await?;
+    info!("MCP Streamable HTTP server listening on http://{}", addr);
+    report_serving(
+        true,
+        serde_json::json!({
+            "transport": "http",
+            "url": format!("http://{}{}", addr, MCP_HTTP_PATH),
+            "project_root": project_root,
+        }),
+    );
+    tokio::signal::ctrl_c().await?;
+    info!("Shutting down MCP HTTP server");
+    Ok(())
+}
+
+/// Daemon options from the command line
+#[cfg_attr(not(unix), allow(dead_code))]
+struct DaemonArgs {
+    /// Relay stdio to the daemon (`--use-daemon`) instead of being the daemon
+    front_end: bool,
+    /// Explicit --project-root, used when a request names no project
+    project_root: Option<PathBuf>,
+    json: bool,
+    socket: Option<String>,
+    idle_timeout: Option<u64>,
+}
+
+/// Run the daemon (`--daemon`) or a stdio front-end of it (`--use-daemon`)
+#[cfg(unix)]
+async fn run_daemon_mode(
+    daemon: DaemonArgs,
+    config: Arc<Config>,
+    transport_mode: Option<TransportMode>,
+) -> Result<()> {
+    use ace_tool::mcp::daemon::{
+        daemon_command, run_proxy, server_args, socket_path_for, DEFAULT_IDLE_TIMEOUT_SECS,
+        ENV_DAEMON_SOCKET,
+    };
+    use ace_tool::mcp::{DaemonOptions, McpDaemon};
+
+    let server_args = server_args(env::args_os().skip(1));
+    let socket_path = match daemon
+        .socket
+        .or_else(|| env::var(ENV_DAEMON_SOCKET).ok())
+        .filter(|s| !s.is_empty())
+    {
+        Some(socket) => std::path::PathBuf::from(socket),
+        None => socket_path_for(&server_args, &env::current_dir()?),
+    };
+
+    report_serving(
+        daemon.json,
+        serde_json::json!({
+            "transport": if daemon.front_end { "daemon-client" } else { "daemon" },
+            "socket": socket_path,
+        }),
+    );
+
+    if daemon.front_end {
+        let start = daemon_command(&server_args, &socket_path)?;
+        return run_proxy(&socket_path, transport_mode, Some(start)).await;
+    }
+
+    info!("Starting ace-tool daemon");
+    let options = DaemonOptions {
+        socket_path,
+        idle_timeout: Duration::from_secs(daemon.idle_timeout.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS)),
+    };
+    let mut server = McpServer::new(config, None);
+    if let Some(root) = daemon.project_root {
+        server = server.with_project_root(root);
+    }
+    McpDaemon::new(server, options).run().await
+}
+
+#[cfg(not(unix))]
+async fn run_daemon_mode(
+    _daemon: DaemonArgs,
+    _config: Arc<Config>,
+    _transport_mode: Option<TransportMode>,
+) -> Result<()> {
+    Err(anyhow!(
+        "--daemon and --use-daemon need Unix domain sockets and are only supported on Unix"
+    ))
+}
+
+/// Configuration for the --enhance-prompt modes
+fn enhance_mode_config(args: &Args, user_config: &UserConfigFile) -> Result<Arc<Config>> {
+    // Check if using third-party endpoint (claude/openai/gemini)
+    let endpoint = get_enhancer_endpoint();
+    let config = if endpoint.is_third_party() {
+        // For third-party endpoints, base_url and token are not required from CLI
+        // They will be read from environment variables
+        // Validate early that required environment variables are set
+        let _ = get_third_party_config(endpoint, &user_config.enhancer)
+            .map_err(|e| anyhow!("Third-party endpoint configuration error: {}", e))?;
+        info!("Using third-party endpoint: {}", endpoint);
+        match (args.base_url.clone(), args.token.clone()) {
+            (Some(base_url), Some(token)) => {
+                info!("Using CLI base_url/token to enable ACE search features");
+                Config::new(
+                    base_url,
+                    token,
+                    ConfigOptions {
+                        max_lines_per_blob: args.max_lines_per_blob,
+                        upload_timeout: args.upload_timeout,
+                        upload_concurrency: args.upload_concurrency,
+                        retrieval_timeout: args.retrieval_timeout,
+                        no_adaptive: args.no_adaptive,
+                        no_webbrowser_enhance_prompt: args.no_webbrowser_enhance_prompt,
+                        force_xdg_open: args.force_xdg_open,
+                        webui_addr: args.webui_addr.clone(),
+                        webui_tls_cert: args.webui_tls_cert.clone(),
+                        webui_tls_key: args.webui_tls_key.clone(),
+                        webui_timeout: args.webui_timeout,
+                        secret_scan: Some(user_config.secrets.clone()),
+                        upload_policy: Some(user_config.upload.clone()),
+                        generation: Some(user_config.enhancer.clone()),
+                    },
+                )?
+            }
+            (None, None) => {
+                let mut config = Config::new_for_third_party_enhancer();
+                Arc::make_mut(&mut config).generation = user_config.enhancer.clone();
+                config
+            }
+            _ => {
+                return Err(anyhow!(
+                    "--base-url and --token must be provided together in third-party enhance-prompt mode"
+                ));
+            }
+        }
+    } else {
+        // For new/old endpoints, base_url and token are required
+        let base_url = args
+            .base_url
+            .clone()
+            .ok_or_else(|| anyhow!("--base-url is required for '{}' endpoint", endpoint))?;
+        let token = args
+            .token
+            .clone()
+            .ok_or_else(|| anyhow!("--token is required for '{}' endpoint", endpoint))?;
+        Config::new(
+            base_url,
+            token,
+            ConfigOptions {
+                max_lines_per_blob: args.max_lines_per_blob,
+                upload_timeout: args.upload_timeout,
+                upload_concurrency: args.upload_concurrency,
+                retrieval_timeout: args.retrieval_timeout,
+                no_adaptive: args.no_adaptive,
+                no_webbrowser_enhance_prompt: args.no_webbrowser_enhance_prompt,
+                force_xdg_open: args.force_xdg_open,
+                webui_addr: args.webui_addr.clone(),
+                webui_tls_cert: args.webui_tls_cert.clone(),
+                webui_tls_key: args.webui_tls_key.clone(),
+                webui_timeout: args.webui_timeout,
+                secret_scan: Some(user_config.secrets.clone()),
+                upload_policy: Some(user_config.upload.clone()),
+                generation: Some(user_config.enhancer.clone()),
+            },
+        )?
+    };
+    Ok(config)
+}
+
+/// Enhance every JSONL line from `source`, writing one JSONL result per line.
+/// Exits with status 2 if any line failed.
+async fn run_enhance_batch(
+    enhancer: &PromptEnhancer,
+    source: &str,
+    args: &Args,
+    cwd: &Path,
+    user_config: &UserConfigFile,
+) -> Result<()> {
+    let concurrency = args.batch_concurrency.unwrap_or(DEFAULT_BATCH_CONCURRENCY);
+    if concurrency == 0 {
+        return Err(anyhow!("--batch-concurrency must be at least 1"));
+    }
+
+    let entries = parse_batch(&read_input(source)?);
+    let endpoint = get_enhancer_endpoint();
+    let provider = BatchProvider {
+        provider: endpoint.to_string(),
+        model: if endpoint.is_third_party() {
+            get_third_party_config(endpoint, &user_config.enhancer)
+                .ok()
+                .map(|c| c.model)
+        } else {
+            None
+        },
+    };
+
+    let mut output: Box<dyn Write> = match args.batch_output {
+        Some(ref path) => Box::new(BufWriter::new(
+            std::fs::File::create(path).map_err(|e| anyhow!("Failed to create {}: {}", path, e))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    let total = entries.len();
+    info!(
+        "Enhancing {} prompts with concurrency {}",
+        total, concurrency
+    );
+    let mut results = std::pin::pin!(enhance_batch(entries, concurrency, &provider, |request| {
+        let project_root = resolve_project_root(&request, cwd);
+        async move {
+            enhancer
+                .enhance_simple(
+                    &request.prompt,
+                    &request.conversation_history,
+                    Some(&project_root),
+                )
+                .await
+        }
+    }));
+
+    let mut failed = 0;
+    while let Some(result) = results.next().await {
+        if let Some(ref e) = result.error {
+            failed += 1;
+            warn!("Batch line {} failed: {}", result.line, e);
+        }
+        writeln!(output, "{}", serde_json::to_string(&result)?)?;
+        output.flush()?;
+    }
+
+    info!(
+        "Batch complete: {} succeeded, {} failed",
+        total - failed,
+        failed
+    );
+    if failed > 0 {
+        drop(output);
+        std::process::exit(2);
+    }
+    Ok(())
+}
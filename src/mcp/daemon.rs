@@ -0,0 +1,455 @@
+//! Daemon transport: one long-lived process serving every editor window
+//!
+//! `--daemon` serves MCP on a Unix domain socket from a single process that owns every
+//! `IndexManager`, so editors working on the same project never race on
+//! `.ace-tool/index.bin`. `--use-daemon` runs a thin stdio front-end that relays each
+//! message to the daemon, starting the daemon first when nothing listens on the socket.
+//!
+//! - The socket carries newline-delimited JSON-RPC. Each connection is one MCP session
+//!   with its own negotiated protocol revision.
+//! - The socket path is derived from the server options and the working directory, so
+//!   front-ends started with the same options in the same project share one daemon. The
+//!   daemon inherits the working directory of the front-end that started it, which is the
+//!   default project for prompts and resources. A lock file next to the socket keeps a single daemon
+//!   per path; the socket and lock are only accessible to the current user.
+//! - The daemon exits once it has had no connections for the idle timeout.
+
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{watch, RwLock};
+use tracing::{debug, error, info, warn};
+
+use super::server::{
+    read_message, write_message, McpServer, McpSession, TransportMode, MAX_LINE_LENGTH,
+};
+use super::types::{JsonRpcRequest, JsonRpcResponse};
+
+/// Default seconds without connections before the daemon exits
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 600;
+
+/// Environment variable overriding the daemon socket path
+pub const ENV_DAEMON_SOCKET: &str = "ACE_TOOL_DAEMON_SOCKET";
+
+/// Flag running the daemon itself
+pub const DAEMON_FLAG: &str = "--daemon";
+
+/// Flag running a stdio front-end of the daemon
+pub const USE_DAEMON_FLAG: &str = "--use-daemon";
+
+/// How long a front-end waits for a daemon it started to accept connections
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest pause between connection attempts while the daemon starts
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Options for the daemon transport
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    pub socket_path: PathBuf,
+    /// Exit after this long without connections
+    pub idle_timeout: Duration,
+}
+
+/// Directory holding daemon sockets
+///
+/// `$XDG_RUNTIME_DIR/ace-tool` when set, `<temp dir>/ace-tool-<uid>` otherwise.
+pub fn socket_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|v| !v.is_empty()) {
+        return PathBuf::from(dir).join("ace-tool");
+    }
+    std::env::temp_dir().join(format!("ace-tool-{}", current_uid()))
+}
+
+/// Effective user ID of this process
+fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+/// Command-line arguments with the daemon mode flags removed
+///
+/// These are the server options a front-end and its daemon have in common.
+pub fn server_args<I>(args: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    args.into_iter()
+        .filter(|arg| arg != DAEMON_FLAG && arg != USE_DAEMON_FLAG)
+        .collect()
+}
+
+/// Socket of the daemon started with `server_args` in the working directory `cwd`
+pub fn socket_path_for(server_args: &[OsString], cwd: &Path) -> PathBuf {
+    let mut hasher = Sha256::new();
+    for arg in server_args {
+        hasher.update(arg.as_encoded_bytes());
+        hasher.update([0u8]);
+    }
+    hasher.update(cwd.as_os_str().as_encoded_bytes());
+    let digest = hex::encode(hasher.finalize());
+    socket_dir().join(format!("daemon-{}.sock", &digest[..16]))
+}
+
+/// Command starting a daemon with `server_args`, with its log next to the socket
+pub fn daemon_command(server_args: &[OsString], socket_path: &Path) -> Result<Command> {
+    let exe = std::env::current_exe()
+        .map_err(|e| anyhow!("Failed to locate the ace-tool executable: {}", e))?;
+
+    let log = create_private_dir(socket_path)
+        .and_then(|_| {
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(socket_path.with_extension("log"))
+                .map_err(Into::into)
+        })
+        .map(Stdio::from)
+        .unwrap_or_else(|_| Stdio::null());
+
+    let mut command = Command::new(exe);
+    // The flag goes first: it is not accepted after a subcommand such as `serve`
+    command
+        .arg(DAEMON_FLAG)
+        .args(server_args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log)
+        // Detach from the editor's process group so its signals do not reach the daemon
+        .process_group(0);
+    Ok(command)
+}
+
+/// Create the parent directory of `path`, readable only by the current user
+///
+/// The directory may already exist, e.g. created in a shared temp dir by someone else,
+/// so it is only accepted when it is a real directory owned by this user with mode 0700.
+fn create_private_dir(path: &Path) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid daemon socket path: {:?}", path))?;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| anyhow!("Failed to create {:?}: {}", dir, e))?;
+
+    let metadata =
+        fs::symlink_metadata(dir).map_err(|e| anyhow!("Failed to stat {:?}: {}", dir, e))?;
+    if !metadata.file_type().is_dir() {
+        return Err(anyhow!(
+            "Daemon socket directory {:?} is not a directory",
+            dir
+        ));
+    }
+    if metadata.uid() != current_uid() {
+        return Err(anyhow!(
+            "Daemon socket directory {:?} is owned by another user (uid {})",
+            dir,
+            metadata.uid()
+        ));
+    }
+    if metadata.mode() & 0o777 != 0o700 {
+        return Err(anyhow!(
+            "Daemon socket directory {:?} must have mode 0700, has {:o}",
+            dir,
+            metadata.mode() & 0o777
+        ));
+    }
+    Ok(())
+}
+
+/// Take the single-instance lock of the daemon at `socket_path`
+fn acquire_daemon_lock(socket_path: &Path) -> Result<fs::File> {
+    create_private_dir(socket_path)?;
+    let lock_path = socket_path.with_extension("lock");
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| anyhow!("Failed to open daemon lock {:?}: {}", lock_path, e))?;
+
+    match lock.try_lock() {
+        Ok(()) => Ok(lock),
+        Err(fs::TryLockError::WouldBlock) => Err(anyhow!(
+            "Another ace-tool daemon is already serving {:?}",
+            socket_path
+        )),
+        Err(fs::TryLockError::Error(e)) => Err(anyhow!("Failed to lock {:?}: {}", lock_path, e)),
+    }
+}
+
+/// Counts a connection as active until dropped
+struct ActiveConnection(Arc<watch::Sender<usize>>);
+
+impl ActiveConnection {
+    fn new(active: Arc<watch::Sender<usize>>) -> Self {
+        active.send_modify(|count| *count += 1);
+        Self(active)
+    }
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Resolve once no connection has been open for `timeout`
+async fn wait_idle(mut active: watch::Receiver<usize>, timeout: Duration) {
+    loop {
+        let idle = *active.borrow_and_update() == 0;
+        if idle {
+            tokio::select! {
+                _ = tokio::time::sleep(timeout) => return,
+                changed = active.changed() => {
+                    if changed.is_err() {
+                        return std::future::pending().await;
+                    }
+                }
+            }
+        } else if active.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+    }
+}
+
+/// MCP server shared by every front-end over a Unix domain socket
+pub struct McpDaemon {
+    server: Arc<McpServer>,
+    options: DaemonOptions,
+}
+
+impl McpDaemon {
+    pub fn new(server: McpServer, options: DaemonOptions) -> Self {
+        Self {
+            server: Arc::new(server),
+            options,
+        }
+    }
+
+    /// Serve until idle for the idle timeout, or until Ctrl-C
+    pub async fn run(&self) -> Result<()> {
+        let socket_path = &self.options.socket_path;
+        let _lock = acquire_daemon_lock(socket_path)?;
+
+        // We hold the lock, so any socket left behind belongs to a daemon that died
+        if socket_path.exists() {
+            fs::remove_file(socket_path)
+                .map_err(|e| anyhow!("Failed to remove stale socket {:?}: {}", socket_path, e))?;
+        }
+        let listener = UnixListener::bind(socket_path)
+            .map_err(|e| anyhow!("Failed to bind to {:?}: {}", socket_path, e))?;
+        fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))?;
+
+        info!(
+            "ace-tool daemon listening on {:?} (idle timeout {}s)",
+            socket_path,
+            self.options.idle_timeout.as_secs()
+        );
+
+        let (active_tx, active_rx) = watch::channel(0usize);
+        let active_tx = Arc::new(active_tx);
+
+        let result = loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let server = self.server.clone();
+                        let active = ActiveConnection::new(active_tx.clone());
+                        tokio::spawn(async move {
+                            if let Err(e) = serve_connection(&server, stream).await {
+                                warn!("Daemon connection closed with error: {}", e);
+                            }
+                            drop(active);
+                        });
+                    }
+                    Err(e) => error!("Failed to accept connection: {}", e),
+                },
+                _ = wait_idle(active_rx.clone(), self.options.idle_timeout) => {
+                    info!("No connections for {}s, shutting down daemon", self.options.idle_timeout.as_secs());
+                    break Ok(());
+                }
+                signal = tokio::signal::ctrl_c() => {
+                    info!("Shutting down daemon");
+                    break signal.map_err(Into::into);
+                }
+            }
+        };
+
+        let _ = fs::remove_file(socket_path);
+        result
+    }
+}
+
+/// Serve one front-end: a session answering newline-delimited JSON-RPC in order
+async fn serve_connection(server: &McpServer, stream: UnixStream) -> Result<()> {
+    let session = McpSession::new();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    loop {
+        let mut line = String::new();
+        let bytes = reader.read_line(&mut line).await?;
+        if bytes == 0 {
+            return Ok(());
+        }
+
+        // Protect against DoS from extremely long lines
+        if line.len() > MAX_LINE_LENGTH {
+            return Err(anyhow!(
+                "Line length {} exceeds maximum allowed size of {} bytes",
+                line.len(),
+                MAX_LINE_LENGTH
+            ));
+        }
+
+        let message = line.trim();
+        if message.is_empty() {
+            continue;
+        }
+        debug!("Received over daemon socket: {}", message);
+
+        let response = match serde_json::from_str::<JsonRpcRequest>(message) {
+            Ok(request) => server.handle_session_request(&session, request).await,
+            Err(e) => {
+                error!("Failed to parse request: {}", e);
+                Some(JsonRpcResponse::error(
+                    None,
+                    -32700,
+                    format!("Parse error: {}", e),
+                ))
+            }
+        };
+
+        if let Some(response) = response {
+            let mut payload = serde_json::to_string(&response)?;
+            payload.push('\n');
+            write_half.write_all(payload.as_bytes()).await?;
+        }
+    }
+}
+
+/// Connect to the daemon, starting it with `start` when nothing listens yet
+pub async fn connect(socket_path: &Path, start: Option<Command>) -> Result<UnixStream> {
+    // Never talk to a socket in a directory another user could have planted it in
+    create_private_dir(socket_path)?;
+    if let Ok(stream) = UnixStream::connect(socket_path).await {
+        return Ok(stream);
+    }
+
+    let mut command =
+        start.ok_or_else(|| anyhow!("No ace-tool daemon is listening on {:?}", socket_path))?;
+    info!("Starting ace-tool daemon on {:?}", socket_path);
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("Failed to start ace-tool daemon: {}", e))?;
+    // Reap the daemon if it exits while this front-end is still running
+    std::thread::spawn(move || child.wait());
+
+    // A concurrent front-end may have won the race; its daemon serves us just as well
+    let deadline = Instant::now() + START_TIMEOUT;
+    let mut backoff = Duration::from_millis(20);
+    loop {
+        match UnixStream::connect(socket_path).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) if Instant::now() >= deadline => {
+                return Err(anyhow!(
+                    "ace-tool daemon did not start listening on {:?}: {}",
+                    socket_path,
+                    e
+                ));
+            }
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Relay stdio MCP messages to the daemon at `socket_path` until stdin closes
+///
+/// Replies go back to stdout in the framing detected on stdin (or `transport_mode`).
+pub async fn run_proxy(
+    socket_path: &Path,
+    transport_mode: Option<TransportMode>,
+    start: Option<Command>,
+) -> Result<()> {
+    let stream = connect(socket_path, start).await?;
+    info!("Relaying MCP messages to daemon on {:?}", socket_path);
+
+    let (read_half, mut write_half) = stream.into_split();
+    let reply_mode = Arc::new(RwLock::new(transport_mode));
+
+    // Daemon -> stdout
+    let replies = {
+        let reply_mode = reply_mode.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(read_half);
+            let mut stdout = tokio::io::stdout();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await? == 0 {
+                    return Ok::<(), anyhow::Error>(());
+                }
+                let payload = line.trim_end_matches(&['\r', '\n'][..]);
+                if payload.is_empty() {
+                    continue;
+                }
+                let mode = reply_mode.read().await.unwrap_or(TransportMode::Line);
+                write_message(&mut stdout, mode, payload).await?;
+            }
+        })
+    };
+
+    // Stdin -> daemon
+    let mut reader = BufReader::new(tokio::io::stdin());
+    let mut detected_mode = transport_mode;
+    loop {
+        let message = match read_message(&mut reader, &mut detected_mode).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                error!("Failed to read message: {}", e);
+                continue;
+            }
+        };
+
+        if detected_mode.is_some() {
+            let mut mode = reply_mode.write().await;
+            if mode.is_none() {
+                *mode = detected_mode;
+            }
+        }
+
+        // JSON strings cannot hold raw line breaks, so replacing them keeps the message
+        // intact while fitting it on one line
+        let mut line = message.replace(['\r', '\n'], " ");
+        if line.trim().is_empty() {
+            continue;
+        }
+        line.push('\n');
+        write_half
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| anyhow!("Lost connection to ace-tool daemon: {}", e))?;
+    }
+
+    // Let the daemon finish the requests in flight, then stop once it closes the socket
+    write_half.shutdown().await?;
+    replies
+        .await
+        .map_err(|e| anyhow!("Daemon relay task failed: {}", e))?
+}
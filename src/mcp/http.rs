@@ -0,0 +1,565 @@
+//! Streamable HTTP transport for the MCP server
+//! Lets several editors or remote agents share one ace-tool instance
+//!
+//! - `POST /mcp` carries one JSON-RPC message or a batch. Notifications get `202 Accepted`;
+//!   requests get an `application/json` response, or an SSE stream (`text/event-stream`)
+//!   for `tools/call` when the client accepts it, with keep-alive comments while tools run.
+//! - The `initialize` response carries an `Mcp-Session-Id` header that later requests must
+//!   send back; `DELETE /mcp` ends the session. Each session keeps the protocol revision it
+//!   negotiated; an unsupported `MCP-Protocol-Version` request header is rejected with `400`.
+//! - Sessions idle for longer than the idle timeout expire, and the least recently used
+//!   session is evicted once the session cap is reached; either way the client gets `404`
+//!   and must initialize again.
+//! - `GET /mcp` (server-initiated streams) is not offered and returns `405`.
+//! - With a bearer token configured, every request needs `Authorization: Bearer <token>`.
+//!   Browser requests are accepted only from loopback origins or the configured origin
+//!   allowlist. On a loopback bind, requests whose `Host` is not loopback are rejected,
+//!   so a page that rebinds its DNS name to 127.0.0.1 cannot reach the server.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full, Limited, StreamBody};
+use hyper::body::{Bytes, Frame, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use super::protocol::ProtocolVersion;
+use super::server::{McpServer, McpSession};
+use super::types::{JsonRpcRequest, JsonRpcResponse};
+use crate::enhancer::server::secret_eq;
+
+/// Path of the MCP endpoint
+pub const MCP_HTTP_PATH: &str = "/mcp";
+
+/// Header carrying the MCP session id
+pub const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+
+/// Header carrying the negotiated protocol revision on requests after `initialize`
+pub const PROTOCOL_VERSION_HEADER: &str = "MCP-Protocol-Version";
+
+/// Default bind address for `--transport http`
+pub const DEFAULT_HTTP_ADDR: &str = "127.0.0.1:8765";
+
+/// Environment variable holding the bearer token (keeps it out of the process list)
+pub const ENV_HTTP_TOKEN: &str = "ACE_MCP_HTTP_TOKEN";
+
+/// Maximum request body size (10MB, same as a stdio message)
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Default time a session may stay unused before it expires
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Default number of live sessions kept before the least recently used one is evicted
+pub const DEFAULT_MAX_SESSIONS: usize = 256;
+
+/// Interval between SSE keep-alive comments while a request is running
+const SSE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+type HttpBody = BoxBody<Bytes, Infallible>;
+
+/// Options for the Streamable HTTP transport
+#[derive(Debug, Clone)]
+pub struct HttpTransportOptions {
+    pub bind_addr: SocketAddr,
+    /// Required `Authorization: Bearer` token; `None` accepts unauthenticated requests
+    pub bearer_token: Option<String>,
+    /// Time a session may stay unused before it expires
+    pub session_idle_timeout: Duration,
+    /// Live sessions kept before the least recently used one is evicted
+    pub max_sessions: usize,
+    /// Browser origins accepted in addition to loopback ones (e.g. `https://app.example.com`)
+    pub allowed_origins: Vec<String>,
+}
+
+/// A live session and when its client last used it
+struct HttpSession {
+    session: Arc<McpSession>,
+    last_seen: Instant,
+}
+
+/// Shared state for request handlers
+struct HttpContext {
+    server: Arc<McpServer>,
+    bearer_token: Option<String>,
+    sessions: RwLock<HashMap<String, HttpSession>>,
+    session_idle_timeout: Duration,
+    max_sessions: usize,
+    allowed_origins: Vec<String>,
+    /// Bound to a loopback address: only loopback `Host` headers are accepted
+    loopback_only: bool,
+}
+
+/// MCP server over Streamable HTTP
+pub struct McpHttpServer {
+    ctx: Arc<HttpContext>,
+    bind_addr: SocketAddr,
+}
+
+impl McpHttpServer {
+    pub fn new(server: McpServer, options: HttpTransportOptions) -> Self {
+        Self {
+            ctx: Arc::new(HttpContext {
+                server: Arc::new(server),
+                bearer_token: options.bearer_token.filter(|t| !t.is_empty()),
+                sessions: RwLock::new(HashMap::new()),
+                session_idle_timeout: options.session_idle_timeout,
+                max_sessions: options.max_sessions.max(1),
+                allowed_origins: options
+                    .allowed_origins
+                    .iter()
+                    .map(|origin| normalize_origin(origin))
+                    .collect(),
+                loopback_only: options.bind_addr.ip().is_loopback(),
+            }),
+            bind_addr: options.bind_addr,
+        }
+    }
+
+    /// Bind and serve in the background; returns the bound address
+    pub async fn start(&self) -> Result<SocketAddr> {
+        if !self.bind_addr.ip().is_loopback() && self.ctx.bearer_token.is_none() {
+            warn!(
+                "Serving MCP on non-loopback address {} without a bearer token; \
+                 anyone who can reach it can call tools. Set --http-token or {}.",
+                self.bind_addr, ENV_HTTP_TOKEN
+            );
+        }
+
+        let listener = TcpListener::bind(self.bind_addr)
+            .await
+            .map_err(|e| anyhow!("Failed to bind to {}: {}", self.bind_addr, e))?;
+        let local_addr = listener.local_addr()?;
+
+        let ctx = self.ctx.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        error!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(|req| {
+                        let ctx = ctx.clone();
+                        async move { Ok::<_, Infallible>(handle_http_request(req, &ctx).await) }
+                    });
+
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        if !e.to_string().contains("connection closed") {
+                            error!("Error serving connection: {}", e);
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(local_addr)
+    }
+
+    /// Serve until Ctrl-C
+    pub async fn run(&self) -> Result<()> {
+        let addr = self.start().await?;
+        info!(
+            "MCP Streamable HTTP server listening on http://{}{}",
+            addr, MCP_HTTP_PATH
+        );
+        tokio::signal::ctrl_c().await?;
+        info!("Shutting down MCP HTTP server");
+        Ok(())
+    }
+}
+
+async fn handle_http_request(req: Request<Incoming>, ctx: &HttpContext) -> Response<HttpBody> {
+    if req.uri().path() != MCP_HTTP_PATH {
+        return rpc_error_response(StatusCode::NOT_FOUND, -32601, "Not found");
+    }
+
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+
+    if ctx.loopback_only
+        && !header("Host").is_some_and(|host| is_loopback_host(authority_host(host)))
+    {
+        warn!(
+            "Rejected MCP request for non-loopback host {:?}",
+            header("Host")
+        );
+        return rpc_error_response(StatusCode::FORBIDDEN, -32600, "Host not allowed");
+    }
+
+    if let Some(origin) = header("Origin") {
+        if !origin_allowed(origin, &ctx.allowed_origins) {
+            warn!("Rejected MCP request from origin {}", origin);
+            return rpc_error_response(StatusCode::FORBIDDEN, -32600, "Origin not allowed");
+        }
+    }
+
+    if let Some(expected) = &ctx.bearer_token {
+        let presented = header("Authorization").and_then(|v| v.strip_prefix("Bearer "));
+        if !presented.is_some_and(|token| secret_eq(expected, token.trim())) {
+            let mut response =
+                rpc_error_response(StatusCode::UNAUTHORIZED, -32600, "Invalid bearer token");
+            response
+                .headers_mut()
+                .insert("WWW-Authenticate", "Bearer".parse().unwrap());
+            return response;
+        }
+    }
+
+    let session_id = header(SESSION_ID_HEADER).map(str::to_string);
+
+    match *req.method() {
+        Method::POST => handle_post(req, session_id, ctx).await,
+        Method::DELETE => match session_id {
+            Some(id) if ctx.sessions.write().await.remove(&id).is_some() => {
+                debug!("MCP session {} ended", id);
+                empty_response(StatusCode::OK)
+            }
+            Some(_) => unknown_session_response(),
+            None => missing_session_response(),
+        },
+        _ => {
+            let mut response =
+                rpc_error_response(StatusCode::METHOD_NOT_ALLOWED, -32600, "Method not allowed");
+            response
+                .headers_mut()
+                .insert("Allow", "POST, DELETE".parse().unwrap());
+            response
+        }
+    }
+}
+
+async fn handle_post(
+    req: Request<Incoming>,
+    session_id: Option<String>,
+    ctx: &HttpContext,
+) -> Response<HttpBody> {
+    let accept = req
+        .headers()
+        .get("Accept")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let accepts_sse = accept.contains("text/event-stream");
+    let requested_version = req
+        .headers()
+        .get(PROTOCOL_VERSION_HEADER)
+        .map(|v| v.to_str().unwrap_or_default().to_string());
+    let accepts_json =
+        accept.is_empty() || accept.contains("application/json") || accept.contains("*/*");
+
+    let body = match Limited::new(req.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => {
+            return rpc_error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                -32600,
+                &format!("Failed to read request body: {}", e),
+            )
+        }
+    };
+
+    let (messages, is_batch) = match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Array(items)) if !items.is_empty() => (items, true),
+        Ok(Value::Array(_)) => {
+            return rpc_error_response(StatusCode::BAD_REQUEST, -32600, "Empty batch")
+        }
+        Ok(value) => (vec![value], false),
+        Err(e) => {
+            return rpc_error_response(
+                StatusCode::BAD_REQUEST,
+                -32700,
+                &format!("Parse error: {}", e),
+            )
+        }
+    };
+
+    let is_initialize = messages.iter().any(|m| method_of(m) == Some("initialize"));
+
+    let (session, new_session) = if is_initialize {
+        let id = Uuid::new_v4().simple().to_string();
+        let session = Arc::new(McpSession::new());
+        let now = Instant::now();
+        let mut sessions = ctx.sessions.write().await;
+        sessions.retain(|_, entry| now.duration_since(entry.last_seen) < ctx.session_idle_timeout);
+        if sessions.len() >= ctx.max_sessions {
+            if let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_seen)
+                .map(|(id, _)| id.clone())
+            {
+                sessions.remove(&oldest);
+                debug!("MCP session {} evicted to make room", oldest);
+            }
+        }
+        sessions.insert(
+            id.clone(),
+            HttpSession {
+                session: session.clone(),
+                last_seen: now,
+            },
+        );
+        drop(sessions);
+        debug!("MCP session {} started", id);
+        (session, Some(id))
+    } else {
+        let session = match &session_id {
+            Some(id) => {
+                let now = Instant::now();
+                let mut sessions = ctx.sessions.write().await;
+                match sessions.get_mut(id) {
+                    Some(entry)
+                        if now.duration_since(entry.last_seen) < ctx.session_idle_timeout =>
+                    {
+                        entry.last_seen = now;
+                        entry.session.clone()
+                    }
+                    Some(_) => {
+                        sessions.remove(id);
+                        debug!("MCP session {} expired", id);
+                        return unknown_session_response();
+                    }
+                    None => return unknown_session_response(),
+                }
+            }
+            None => return missing_session_response(),
+        };
+        if let Some(version) = &requested_version {
+            if ProtocolVersion::parse(version).is_none() {
+                return rpc_error_response(
+                    StatusCode::BAD_REQUEST,
+                    -32600,
+                    &format!("Unsupported {}: {}", PROTOCOL_VERSION_HEADER, version),
+                );
+            }
+        }
+        (session, None)
+    };
+
+    // Client responses (no `method`) need no reply; requests have an id
+    let expects_response = messages
+        .iter()
+        .any(|m| method_of(m).is_some() && m.get("id").is_some_and(|id| !id.is_null()));
+    let has_tool_call = messages.iter().any(|m| method_of(m) == Some("tools/call"));
+
+    let server = ctx.server.clone();
+    let pending: FuturesUnordered<_> = messages
+        .into_iter()
+        .enumerate()
+        .filter(|(_, message)| method_of(message).is_some())
+        .map(|(index, message)| {
+            let server = server.clone();
+            let session = session.clone();
+            async move { (index, dispatch(&server, &session, message).await) }
+        })
+        .collect();
+
+    if !expects_response {
+        pending.collect::<Vec<_>>().await;
+        return empty_response(StatusCode::ACCEPTED);
+    }
+
+    let mut response = if accepts_sse && (has_tool_call || !accepts_json) {
+        sse_response(pending)
+    } else {
+        let mut results: Vec<(usize, JsonRpcResponse)> = pending
+            .filter_map(|(index, response)| async move { response.map(|r| (index, r)) })
+            .collect()
+            .await;
+        results.sort_by_key(|(index, _)| *index);
+        let responses: Vec<JsonRpcResponse> = results.into_iter().map(|(_, r)| r).collect();
+
+        let body = if is_batch {
+            serde_json::to_string(&responses)
+        } else {
+            match responses.first() {
+                Some(response) => serde_json::to_string(response),
+                None => return empty_response(StatusCode::ACCEPTED),
+            }
+        };
+        match body {
+            Ok(body) => full_response(StatusCode::OK, "application/json", body),
+            Err(e) => {
+                return rpc_error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    -32603,
+                    &format!("Internal error: {}", e),
+                )
+            }
+        }
+    };
+
+    if let Some(id) = new_session {
+        if let Ok(value) = id.parse() {
+            response.headers_mut().insert(SESSION_ID_HEADER, value);
+        }
+    }
+    response
+}
+
+fn method_of(message: &Value) -> Option<&str> {
+    message.get("method").and_then(Value::as_str)
+}
+
+/// Dispatch one message through the session's request handler
+async fn dispatch(
+    server: &McpServer,
+    session: &McpSession,
+    message: Value,
+) -> Option<JsonRpcResponse> {
+    let id = message.get("id").cloned().filter(|id| !id.is_null());
+    match serde_json::from_value::<JsonRpcRequest>(message) {
+        Ok(request) => {
+            debug!("Received over HTTP: {}", request.method);
+            server.handle_session_request(session, request).await
+        }
+        Err(e) => {
+            id.map(|id| JsonRpcResponse::error(Some(id), -32600, format!("Invalid Request: {}", e)))
+        }
+    }
+}
+
+/// Stream responses as SSE `message` events as they complete, with keep-alive comments
+fn sse_response<S>(mut pending: S) -> Response<HttpBody>
+where
+    S: futures::Stream<Item = (usize, Option<JsonRpcResponse>)> + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<Bytes>(16);
+
+    tokio::spawn(async move {
+        let mut keepalive = tokio::time::interval(SSE_KEEPALIVE_INTERVAL);
+        keepalive.tick().await;
+        loop {
+            tokio::select! {
+                next = pending.next() => match next {
+                    Some((_, Some(response))) => {
+                        let Ok(json) = serde_json::to_string(&response) else {
+                            continue;
+                        };
+                        let event = format!("event: message\ndata: {}\n\n", json);
+                        if tx.send(Bytes::from(event)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some((_, None)) => {}
+                    None => break,
+                },
+                _ = keepalive.tick() => {
+                    if tx.send(Bytes::from_static(b": keepalive\n\n")).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let frames = stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|bytes| (Ok::<_, Infallible>(Frame::data(bytes)), rx))
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .body(BodyExt::boxed(StreamBody::new(frames)))
+        .unwrap()
+}
+
+/// Allow loopback origins and origins on the configured allowlist
+///
+/// The Host header is deliberately not consulted: with DNS rebinding an attacker
+/// controls both the Origin and the Host.
+fn origin_allowed(origin: &str, allowed_origins: &[String]) -> bool {
+    let origin = normalize_origin(origin);
+    if allowed_origins.contains(&origin) {
+        return true;
+    }
+    match origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+    {
+        Some(authority) => is_loopback_host(authority_host(authority)),
+        None => false,
+    }
+}
+
+/// Compare origins case-insensitively and without a trailing slash
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+/// Host part of a `host[:port]` authority, keeping brackets around IPv6 literals
+fn authority_host(authority: &str) -> &str {
+    if authority.starts_with('[') {
+        authority.split_inclusive(']').next().unwrap_or(authority)
+    } else {
+        authority.split(':').next().unwrap_or(authority)
+    }
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+fn full_response(status: StatusCode, content_type: &str, body: String) -> Response<HttpBody> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", content_type)
+        .body(Full::new(Bytes::from(body)).boxed())
+        .unwrap()
+}
+
+fn empty_response(status: StatusCode) -> Response<HttpBody> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::new()).boxed())
+        .unwrap()
+}
+
+/// HTTP error carrying a JSON-RPC error body without an id
+fn rpc_error_response(status: StatusCode, code: i32, message: &str) -> Response<HttpBody> {
+    let body = serde_json::to_string(&JsonRpcResponse::error(None, code, message.to_string()))
+        .unwrap_or_default();
+    full_response(status, "application/json", body)
+}
+
+fn missing_session_response() -> Response<HttpBody> {
+    rpc_error_response(
+        StatusCode::BAD_REQUEST,
+        -32600,
+        "Missing Mcp-Session-Id header; send initialize first",
+    )
+}
+
+fn unknown_session_response() -> Response<HttpBody> {
+    rpc_error_response(StatusCode::NOT_FOUND, -32001, "Session not found")
+}
@@ -1,8 +1,17 @@
 //! MCP (Model Context Protocol) module
 
+#[cfg(unix)]
+pub mod daemon;
+pub mod http;
+pub mod prompts;
+pub mod protocol;
+pub mod resources;
 pub mod server;
 pub mod types;
 
+#[cfg(unix)]
+pub use daemon::{DaemonOptions, McpDaemon};
+pub use http::{HttpTransportOptions, McpHttpServer};
 pub use server::{
-    is_header_line, parse_content_length, McpServer, TransportMode, MAX_HEADER_COUNT,
+    is_header_line, parse_content_length, McpServer, McpSession, TransportMode, MAX_HEADER_COUNT,
 };
@@ -0,0 +1,453 @@
+//! MCP prompts - reusable prompts that embed `search_context` results
+//!
+//! Prompts are Markdown files with an optional front matter block:
+//!
+//! ```text
+//! ---
+//! description: Review error handling in a module
+//! arg: module - Module or directory to review
+//! arg: focus? - Optional aspect to focus on
+//! search: error handling in {module}
+//! ---
+//! Review the error handling in {module}. {focus}
+//!
+//! {search_context}
+//! ```
+//!
+//! `arg: name` declares a required argument and `arg: name?` an optional one. The body
+//! and the `search` query use `{name}` placeholders like enhancement templates (`{{`/`}}`
+//! for literal braces; an unterminated `{name` is an error); the body may also use
+//! `{search_context}` (the search result for the rendered `search` query) and
+//! `{project_root}`. Prompts with a `search` query take an optional
+//! `project_root_path` argument, defaulting to the current directory.
+//!
+//! Prompts are loaded from `.ace-tool/prompts/*.md` in the project, then `prompts/*.md`
+//! in the user config directory, then the built-ins; the first prompt of a name wins.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tracing::warn;
+
+use super::types::{GetPromptResult, Prompt, PromptArgument, PromptMessage, TextContent};
+use crate::config::Config;
+use crate::enhancer::templates::{parse_placeholders, PlaceholderSegment as Segment};
+use crate::index::IndexRegistry;
+use crate::tools::search_context::SearchContextArgs;
+use crate::tools::SearchContextTool;
+use crate::utils::project_detector::{ace_dir_path, get_user_config_dir};
+
+/// Prompt directory name (inside `.ace-tool/` and the user config directory)
+pub const PROMPTS_DIR_NAME: &str = "prompts";
+
+/// Argument naming the project to search, implied by prompts with a `search` query
+pub const PROJECT_ROOT_ARG: &str = "project_root_path";
+
+/// Placeholder filled with the search result
+const SEARCH_CONTEXT_VAR: &str = "search_context";
+
+/// Placeholder filled with the project root
+const PROJECT_ROOT_VAR: &str = "project_root";
+
+/// Built-in prompts as (name, source)
+const BUILTIN_PROMPTS: &[(&str, &str)] = &[
+    (
+        "explain_code",
+        r#"---
+description: Explain how an area of the codebase works, grounded in retrieved code
+arg: topic - Feature, module or behaviour to explain
+search: {topic}
+---
+Explain how {topic} works in this codebase. Describe the main components, how they interact and where the entry points are, citing file paths.
+
+Relevant code retrieved from the codebase:
+
+{search_context}"#,
+    ),
+    (
+        "plan_change",
+        r#"---
+description: Plan a code change step by step before implementing it
+arg: change - The change to make
+search: {change}
+---
+I want to make the following change:
+
+{change}
+
+Using the relevant code below, write a step-by-step implementation plan: the files to modify, the new code needed, risks and edge cases, and the tests to add. Do not write the full implementation yet.
+
+Relevant code retrieved from the codebase:
+
+{search_context}"#,
+    ),
+    (
+        "enhance_request",
+        r#"---
+description: Rewrite a request into a clear, specific prompt grounded in the codebase
+arg: request - The request to improve
+search: {request}
+---
+Rewrite my request below into a clearer, more specific and less ambiguous prompt for a coding assistant. Use the relevant code to name the actual files, types and functions involved, and correct any mistakes. Keep code in triple backticks unchanged. Reply with only the rewritten prompt.
+
+My request:
+
+{request}
+
+Relevant code retrieved from the codebase:
+
+{search_context}"#,
+    ),
+];
+
+/// A parsed prompt definition
+#[derive(Debug, Clone)]
+pub struct PromptDef {
+    pub name: String,
+    pub description: Option<String>,
+    /// Declared arguments, plus `project_root_path` for prompts with a search query
+    pub arguments: Vec<PromptArgument>,
+    search: Option<Vec<Segment>>,
+    body: Vec<Segment>,
+    source: Option<PathBuf>,
+}
+
+impl PromptDef {
+    /// Parse a prompt file's text
+    pub fn parse(name: &str, text: &str) -> Result<Self> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(anyhow!(
+                "Invalid prompt name '{}' (use letters, digits, '_' and '-')",
+                name
+            ));
+        }
+
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let (front_matter, body) = split_front_matter(text)?;
+
+        let mut description = None;
+        let mut arguments: Vec<PromptArgument> = Vec::new();
+        let mut search = None;
+        for line in front_matter.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid front matter line '{}'", line))?;
+            let value = value.trim();
+            match key.trim() {
+                "description" => description = Some(value.to_string()),
+                "arg" => {
+                    let argument = parse_argument(value)?;
+                    if argument.name == SEARCH_CONTEXT_VAR
+                        || argument.name == PROJECT_ROOT_VAR
+                        || arguments.iter().any(|a| a.name == argument.name)
+                    {
+                        return Err(anyhow!(
+                            "Duplicate or reserved argument '{}'",
+                            argument.name
+                        ));
+                    }
+                    arguments.push(argument);
+                }
+                "search" => search = Some(value.to_string()),
+                other => return Err(anyhow!("Unknown front matter key '{}'", other)),
+            }
+        }
+
+        let declared = |name: &str| arguments.iter().any(|a| a.name == name);
+        let search = match search {
+            Some(query) => {
+                let segments = parse_placeholders(&query)?;
+                check_vars(&segments, |v| declared(v) || v == PROJECT_ROOT_ARG)?;
+                Some(segments)
+            }
+            None => None,
+        };
+        let body = parse_placeholders(body.trim())?;
+        check_vars(&body, |v| {
+            declared(v)
+                || v == PROJECT_ROOT_VAR
+                || v == PROJECT_ROOT_ARG
+                || (v == SEARCH_CONTEXT_VAR && search.is_some())
+        })?;
+
+        if search.is_some() && !declared(PROJECT_ROOT_ARG) {
+            arguments.push(PromptArgument {
+                name: PROJECT_ROOT_ARG.to_string(),
+                description: Some(
+                    "Absolute path of the project to search (defaults to the current directory)"
+                        .to_string(),
+                ),
+                required: Some(false),
+            });
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            description,
+            arguments,
+            search,
+            body,
+            source: None,
+        })
+    }
+
+    /// Load a prompt file, named after its file stem
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read prompt {}: {}", path.display(), e))?;
+        let mut prompt = Self::parse(&name, &text)
+            .map_err(|e| anyhow!("Invalid prompt {}: {}", path.display(), e))?;
+        prompt.source = Some(path.to_path_buf());
+        Ok(prompt)
+    }
+
+    /// File the prompt was loaded from (`None` for built-ins)
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// Whether the prompt runs `search_context`
+    pub fn uses_search(&self) -> bool {
+        self.search.is_some()
+            && self
+                .body
+                .iter()
+                .any(|s| matches!(s, Segment::Placeholder(v) if v == SEARCH_CONTEXT_VAR))
+    }
+
+    /// MCP listing entry
+    pub fn to_prompt(&self) -> Prompt {
+        Prompt {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            arguments: self.arguments.clone(),
+        }
+    }
+
+    /// Check required arguments and reject undeclared ones
+    pub fn validate_arguments(&self, args: &HashMap<String, String>) -> Result<()> {
+        for argument in &self.arguments {
+            if argument.required == Some(true)
+                && args.get(&argument.name).is_none_or(|v| v.trim().is_empty())
+            {
+                return Err(anyhow!(
+                    "Missing required argument '{}' for prompt '{}'",
+                    argument.name,
+                    self.name
+                ));
+            }
+        }
+        if let Some(unknown) = args
+            .keys()
+            .find(|k| *k != PROJECT_ROOT_ARG && !self.arguments.iter().any(|a| &a.name == *k))
+        {
+            return Err(anyhow!(
+                "Unknown argument '{}' for prompt '{}'",
+                unknown,
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Search query rendered from the arguments, when the prompt embeds search results
+    pub fn render_search_query(&self, args: &HashMap<String, String>) -> Option<String> {
+        if !self.uses_search() {
+            return None;
+        }
+        let search = self.search.as_ref()?;
+        let query = render(search, |v| args.get(v).cloned().unwrap_or_default());
+        Some(query.trim().to_string())
+    }
+
+    /// Render the prompt body
+    pub fn render(
+        &self,
+        args: &HashMap<String, String>,
+        project_root: &str,
+        search_context: &str,
+    ) -> String {
+        let text = render(&self.body, |v| match v {
+            SEARCH_CONTEXT_VAR => search_context.to_string(),
+            PROJECT_ROOT_VAR | PROJECT_ROOT_ARG => project_root.to_string(),
+            _ => args.get(v).cloned().unwrap_or_default(),
+        });
+        text.trim().to_string()
+    }
+}
+
+/// Split `---` front matter from the body; text without front matter is all body
+fn split_front_matter(text: &str) -> Result<(&str, &str)> {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return Ok(("", text));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Ok((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    Err(anyhow!("Unterminated front matter (missing closing '---')"))
+}
+
+/// Parse `name - description` or `name? - description`
+fn parse_argument(value: &str) -> Result<PromptArgument> {
+    let (name, description) = match value.split_once(" - ") {
+        Some((name, description)) => (name.trim(), Some(description.trim().to_string())),
+        None => (value.trim(), None),
+    };
+    let (name, required) = match name.strip_suffix('?') {
+        Some(name) => (name, false),
+        None => (name, true),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(anyhow!("Invalid argument name '{}'", name));
+    }
+    Ok(PromptArgument {
+        name: name.to_string(),
+        description: description.filter(|d| !d.is_empty()),
+        required: Some(required),
+    })
+}
+
+fn check_vars(segments: &[Segment], known: impl Fn(&str) -> bool) -> Result<()> {
+    for segment in segments {
+        if let Segment::Placeholder(name) = segment {
+            if !known(name) {
+                return Err(anyhow!("Unknown placeholder '{{{}}}'", name));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn render(segments: &[Segment], value: impl Fn(&str) -> String) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => out.push_str(text),
+            Segment::Placeholder(name) => out.push_str(&value(name)),
+        }
+    }
+    out
+}
+
+/// The built-in prompts
+pub fn builtin_prompts() -> Vec<PromptDef> {
+    BUILTIN_PROMPTS
+        .iter()
+        .map(|(name, text)| PromptDef::parse(name, text).expect("built-in prompt is valid"))
+        .collect()
+}
+
+/// Prompt files in a directory, sorted by name; invalid files are skipped with a warning
+fn load_prompt_dir(dir: &Path) -> Vec<PromptDef> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = read_dir
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| match PromptDef::from_file(path) {
+            Ok(prompt) => Some(prompt),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// All prompts for a project: project files, then user files, then built-ins
+pub fn load_prompts(project_root: Option<&Path>) -> Vec<PromptDef> {
+    let dirs = project_root
+        .map(|root| ace_dir_path(root).join(PROMPTS_DIR_NAME))
+        .into_iter()
+        .chain(get_user_config_dir().map(|dir| dir.join(PROMPTS_DIR_NAME)));
+
+    let mut prompts: Vec<PromptDef> = Vec::new();
+    for prompt in dirs
+        .flat_map(|dir| load_prompt_dir(&dir))
+        .chain(builtin_prompts())
+    {
+        if !prompts.iter().any(|p| p.name == prompt.name) {
+            prompts.push(prompt);
+        }
+    }
+    prompts
+}
+
+/// Resolve and render a prompt, running `search_context` when it embeds search results
+///
+/// Searches go through the server's `registry`, so they share its per-project index
+/// managers and indexing locks. A failed search returns its `ToolError`.
+pub async fn get_prompt(
+    config: Arc<Config>,
+    registry: Arc<IndexRegistry>,
+    name: &str,
+    args: HashMap<String, String>,
+) -> Result<GetPromptResult> {
+    let project_root = match args.get(PROJECT_ROOT_ARG).filter(|v| !v.trim().is_empty()) {
+        Some(root) => root.replace('\\', "/"),
+        None => std::env::current_dir()
+            .map(|d| d.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default(),
+    };
+
+    let prompt = load_prompts(Some(Path::new(&project_root)))
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow!("Unknown prompt: {}", name))?;
+    prompt.validate_arguments(&args)?;
+
+    let search_context = match prompt.render_search_query(&args) {
+        Some(query) => {
+            let tool = SearchContextTool::with_registry(config, registry);
+            let result = tool
+                .execute(SearchContextArgs {
+                    project_root_path: Some(project_root.clone()),
+                    query: Some(query),
+                })
+                .await;
+            // A failed search is an error, not "relevant code" for the prompt
+            if let Some(error) = result.error {
+                return Err(error.into());
+            }
+            result.text
+        }
+        None => String::new(),
+    };
+
+    Ok(GetPromptResult {
+        description: prompt.description.clone(),
+        messages: vec![PromptMessage {
+            role: "user".to_string(),
+            content: TextContent::new(prompt.render(&args, &project_root, &search_context)),
+        }],
+    })
+}
@@ -0,0 +1,127 @@
+//! MCP protocol revisions, version negotiation and list pagination
+//!
+//! `initialize` answers with the client's requested revision when it is supported, and
+//! with the latest supported revision otherwise. Responses then follow the negotiated
+//! revision:
+//!
+//! | Revision     | Tool fields added                    | Tool results          |
+//! |--------------|--------------------------------------|-----------------------|
+//! | `2024-11-05` | `name`, `description`, `inputSchema` | `content`             |
+//! | `2025-03-26` | `annotations`                        | `content`             |
+//! | `2025-06-18` | `title`, `outputSchema`              | + `structuredContent` |
+//!
+//! List methods page their results with opaque `cursor` / `nextCursor` values in every
+//! revision.
+
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+use super::types::{CallToolResult, Tool};
+
+/// Maximum number of items per `*/list` page
+pub const LIST_PAGE_SIZE: usize = 100;
+
+/// A supported MCP protocol revision, ordered oldest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtocolVersion {
+    #[default]
+    V2024_11_05,
+    V2025_03_26,
+    V2025_06_18,
+}
+
+impl ProtocolVersion {
+    /// Every supported revision, oldest first
+    pub const ALL: [ProtocolVersion; 3] = [
+        ProtocolVersion::V2024_11_05,
+        ProtocolVersion::V2025_03_26,
+        ProtocolVersion::V2025_06_18,
+    ];
+
+    /// Newest supported revision, offered when the client asks for an unknown one
+    pub const LATEST: ProtocolVersion = ProtocolVersion::V2025_06_18;
+
+    /// Revision assumed before `initialize`, or when the client names none
+    pub const FALLBACK: ProtocolVersion = ProtocolVersion::V2024_11_05;
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProtocolVersion::V2024_11_05 => "2024-11-05",
+            ProtocolVersion::V2025_03_26 => "2025-03-26",
+            ProtocolVersion::V2025_06_18 => "2025-06-18",
+        }
+    }
+
+    /// Parse a supported revision string
+    pub fn parse(version: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.as_str() == version)
+    }
+
+    /// Pick the revision to answer an `initialize` request with
+    pub fn negotiate(requested: Option<&str>) -> Self {
+        match requested {
+            Some(version) => Self::parse(version).unwrap_or(Self::LATEST),
+            None => Self::FALLBACK,
+        }
+    }
+
+    /// Tool `annotations` (readOnlyHint, openWorldHint, ...)
+    pub fn has_tool_annotations(self) -> bool {
+        self >= ProtocolVersion::V2025_03_26
+    }
+
+    /// Tool `title`, `outputSchema` and `structuredContent` results
+    pub fn has_structured_output(self) -> bool {
+        self >= ProtocolVersion::V2025_06_18
+    }
+
+    /// Strip tool fields the revision does not define
+    pub fn shape_tool(self, mut tool: Tool) -> Tool {
+        if !self.has_tool_annotations() {
+            tool.annotations = None;
+        }
+        if !self.has_structured_output() {
+            tool.title = None;
+            tool.output_schema = None;
+        }
+        tool
+    }
+
+    /// Strip result fields the revision does not define
+    pub fn shape_call_result(self, mut result: CallToolResult) -> CallToolResult {
+        if !self.has_structured_output() {
+            result.structured_content = None;
+        }
+        result
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Return the page of `items` starting at `cursor`, and the cursor of the next page
+///
+/// Cursors are opaque to clients; an unknown cursor is an error (`-32602`).
+pub fn paginate<T>(
+    items: Vec<T>,
+    cursor: Option<&str>,
+    page_size: usize,
+) -> Result<(Vec<T>, Option<String>)> {
+    let start = match cursor {
+        None => 0,
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .ok()
+            .filter(|offset| *offset <= items.len())
+            .ok_or_else(|| anyhow!("Invalid cursor: {}", cursor))?,
+    };
+
+    let end = start.saturating_add(page_size.max(1)).min(items.len());
+    let next_cursor = (end < items.len()).then(|| end.to_string());
+    let page = items.into_iter().skip(start).take(end - start).collect();
+    Ok((page, next_cursor))
+}
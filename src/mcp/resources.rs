@@ -0,0 +1,312 @@
+//! MCP resources - browse indexed files, chunks and index statistics
+//!
+//! URIs take the form `ace://<root>/<path>` for an indexed file,
+//! `ace://<root>/<path>#chunkN` for its N-th blob chunk (1-based) and `ace://<root>`
+//! for the index statistics of a project. `<root>` is the absolute project root with
+//! forward slashes; the project is found by walking up from the URI path to the nearest
+//! directory holding `.ace-tool/index.bin`. Only paths present in the index are served,
+//! and only for the projects the session offers (see [`read_resource`]).
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+
+use super::types::{Resource, ResourceTemplate, TextResourceContents};
+use crate::config::Config;
+use crate::index::IndexManager;
+use crate::utils::path_normalizer::{normalize_path, RuntimeEnv};
+use crate::utils::project_detector::{ace_dir_path, INDEX_FILE_NAME};
+
+/// URI scheme prefix for ace-tool resources
+pub const RESOURCE_SCHEME: &str = "ace://";
+
+/// MIME type of the index statistics resource
+const STATS_MIME_TYPE: &str = "application/json";
+
+/// A parsed resource URI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceUri {
+    /// Absolute path named by the URI (project root, or a file inside it)
+    pub path: PathBuf,
+    /// 1-based chunk number from a `#chunkN` fragment
+    pub chunk: Option<usize>,
+}
+
+impl ResourceUri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let rest = uri.strip_prefix(RESOURCE_SCHEME).ok_or_else(|| {
+            anyhow!(
+                "Unsupported resource URI (expected {}): {}",
+                RESOURCE_SCHEME,
+                uri
+            )
+        })?;
+
+        let (path, fragment) = match rest.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (rest, None),
+        };
+
+        let chunk = match fragment {
+            None => None,
+            Some(fragment) => {
+                let n = fragment
+                    .strip_prefix("chunk")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| anyhow!("Invalid chunk fragment '#{}' in {}", fragment, uri))?;
+                Some(n)
+            }
+        };
+
+        let path = PathBuf::from(decode_uri_path(path)?);
+        if !path.is_absolute() {
+            return Err(anyhow!("Resource URI must name an absolute path: {}", uri));
+        }
+        if path
+            .components()
+            .any(|c| matches!(c, Component::ParentDir | Component::CurDir))
+        {
+            return Err(anyhow!(
+                "Resource URI must not contain '.' or '..': {}",
+                uri
+            ));
+        }
+
+        Ok(Self { path, chunk })
+    }
+}
+
+/// URI of an indexed file, or of one of its chunks
+pub fn file_uri(project_root: &Path, rel_path: &str, chunk: Option<usize>) -> String {
+    let mut uri = format!(
+        "{}/{}",
+        project_uri(project_root),
+        encode_uri_path(rel_path)
+    );
+    if let Some(n) = chunk {
+        uri.push_str(&format!("#chunk{}", n));
+    }
+    uri
+}
+
+/// URI of a project's index statistics
+pub fn project_uri(project_root: &Path) -> String {
+    let root = project_root.to_string_lossy().replace('\\', "/");
+    format!(
+        "{}{}",
+        RESOURCE_SCHEME,
+        encode_uri_path(root.trim_end_matches('/'))
+    )
+}
+
+/// Whether a directory has an index file (without creating `.ace-tool/`)
+pub fn has_index(dir: &Path) -> bool {
+    ace_dir_path(dir).join(INDEX_FILE_NAME).is_file()
+}
+
+/// Nearest ancestor of `path` (inclusive) that has an index
+fn find_indexed_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| has_index(dir))
+        .map(Path::to_path_buf)
+}
+
+/// Resource templates advertised by `resources/templates/list`
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    vec![
+        ResourceTemplate {
+            uri_template: "ace://{root}/{+path}".to_string(),
+            name: "Indexed file".to_string(),
+            description: Some(
+                "Current content of an indexed file, decoded as during indexing".to_string(),
+            ),
+            mime_type: None,
+        },
+        ResourceTemplate {
+            uri_template: "ace://{root}/{+path}#chunk{n}".to_string(),
+            name: "Indexed file chunk".to_string(),
+            description: Some(
+                "The n-th blob chunk (1-based) of an indexed file, as uploaded to the index"
+                    .to_string(),
+            ),
+            mime_type: None,
+        },
+        ResourceTemplate {
+            uri_template: "ace://{root}".to_string(),
+            name: "Index statistics".to_string(),
+            description: Some("File, blob and size totals of a project's index".to_string()),
+            mime_type: Some(STATS_MIME_TYPE.to_string()),
+        },
+    ]
+}
+
+/// Resources for every indexed project among `roots`: its statistics, then its files
+pub fn list_resources(config: &Arc<Config>, roots: &[PathBuf]) -> Vec<Resource> {
+    let mut resources = Vec::new();
+    for root in roots {
+        if !has_index(root) {
+            continue;
+        }
+        let Ok(manager) = IndexManager::new(config.clone(), root.clone()) else {
+            continue;
+        };
+        let root = manager.project_root();
+        let index = manager.load_index();
+
+        resources.push(Resource {
+            uri: project_uri(root),
+            name: format!("Index statistics ({})", root.display()),
+            description: Some(format!("{} indexed files", index.entries.len())),
+            mime_type: Some(STATS_MIME_TYPE.to_string()),
+            size: None,
+        });
+
+        let mut paths: Vec<_> = index.entries.iter().collect();
+        paths.sort_by(|a, b| a.0.cmp(b.0));
+        for (rel_path, entry) in paths {
+            let chunks = entry.blob_hashes.len();
+            resources.push(Resource {
+                uri: file_uri(root, rel_path, None),
+                name: rel_path.clone(),
+                description: (chunks > 1).then(|| format!("{} chunks", chunks)),
+                mime_type: Some(guess_mime_type(rel_path).to_string()),
+                size: Some(entry.size),
+            });
+        }
+    }
+    resources
+}
+
+/// Read a resource by URI
+///
+/// Only projects among `roots` are served, the same set `resources/list` offers; an indexed
+/// project elsewhere on disk is refused.
+pub fn read_resource(
+    config: &Arc<Config>,
+    roots: &[PathBuf],
+    uri: &str,
+) -> Result<TextResourceContents> {
+    let target = ResourceUri::parse(uri)?;
+    let root = find_indexed_root(&target.path)
+        .ok_or_else(|| anyhow!("No indexed project contains {}", target.path.display()))?;
+    let env = RuntimeEnv::detect();
+    let local_root = normalize_path(&root, env).local;
+    if !roots
+        .iter()
+        .any(|allowed| normalize_path(allowed, env).local == local_root)
+    {
+        return Err(anyhow!(
+            "Project {} is not offered by this session",
+            root.display()
+        ));
+    }
+    let manager = IndexManager::new(config.clone(), root.clone())?;
+    let index = manager.load_index();
+
+    if target.path == root {
+        if target.chunk.is_some() {
+            return Err(anyhow!("Index statistics have no chunks: {}", uri));
+        }
+        return Ok(TextResourceContents {
+            uri: uri.to_string(),
+            mime_type: Some(STATS_MIME_TYPE.to_string()),
+            text: serde_json::to_string_pretty(&index.summary(manager.project_root()))?,
+        });
+    }
+
+    let rel_path = target
+        .path
+        .strip_prefix(&root)
+        .map_err(|_| anyhow!("Resource path is outside {}", root.display()))?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let entry = index
+        .entries
+        .get(&rel_path)
+        .ok_or_else(|| anyhow!("File is not indexed: {}", rel_path))?;
+
+    let content = manager.read_indexable_content(&rel_path)?;
+    let text = match target.chunk {
+        None => content,
+        Some(n) => {
+            let mut blobs = manager.split_file_content(&rel_path, &content);
+            if n > blobs.len() {
+                return Err(anyhow!(
+                    "Chunk {} out of range: {} has {} chunks (index records {})",
+                    n,
+                    rel_path,
+                    blobs.len(),
+                    entry.blob_hashes.len()
+                ));
+            }
+            blobs.swap_remove(n - 1).content
+        }
+    };
+
+    Ok(TextResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some(guess_mime_type(&rel_path).to_string()),
+        text,
+    })
+}
+
+/// Best-effort MIME type from a file extension
+fn guess_mime_type(path: &str) -> &'static str {
+    let ext = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "ts" | "tsx" => "text/x-typescript",
+        "go" => "text/x-go",
+        "java" => "text/x-java",
+        "c" | "h" => "text/x-c",
+        "cpp" | "cc" | "hpp" => "text/x-c++",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        _ => "text/plain",
+    }
+}
+
+/// Percent-encode the characters that would change a URI's structure
+fn encode_uri_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '%' | '#' | '?' | ' ' => out.push_str(&format!("%{:02X}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes
+fn decode_uri_path(path: &str) -> Result<String> {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = path
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid percent-encoding in resource URI: {}", path))?;
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| anyhow!("Resource URI is not valid UTF-8: {}", path))
+}
@@ -1,5 +1,6 @@
 //! MCP server implementation
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
@@ -8,10 +9,16 @@ use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
 
+use super::protocol::{paginate, ProtocolVersion, LIST_PAGE_SIZE};
+use super::{prompts, resources};
 use crate::config::Config;
+use crate::index::IndexRegistry;
 use crate::tools::enhance_prompt::{EnhancePromptArgs, EnhancePromptToolDef, ENHANCE_PROMPT_TOOL};
+use crate::tools::enhancement_result::{
+    EnhancementResultArgs, EnhancementResultToolDef, ENHANCEMENT_RESULT_TOOL,
+};
 use crate::tools::search_context::{SearchContextArgs, SearchContextToolDef, SEARCH_CONTEXT_TOOL};
-use crate::tools::{EnhancePromptTool, SearchContextTool};
+use crate::tools::{EnhancePromptTool, EnhancementResultTool, SearchContextTool, ToolError};
 
 /// Map tool name aliases to canonical names
 fn normalize_tool_name(name: &str) -> &str {
@@ -23,6 +30,40 @@ fn normalize_tool_name(name: &str) -> &str {
 
 use super::types::*;
 
+/// Build a `tools/call` result; failures get `isError` and their code in `_meta.error`
+fn tool_call_result(text: String, meta: Option<Value>, error: Option<ToolError>) -> CallToolResult {
+    let (is_error, meta) = match error {
+        Some(error) => {
+            let mut error_meta = error.meta();
+            if let (Some(Value::Object(extra)), Value::Object(merged)) = (meta, &mut error_meta) {
+                for (key, value) in extra {
+                    merged.entry(key).or_insert(value);
+                }
+            }
+            (Some(true), Some(error_meta))
+        }
+        None => (None, meta),
+    };
+    CallToolResult {
+        content: vec![TextContent::new(text)],
+        structured_content: None,
+        is_error,
+        meta,
+    }
+}
+
+/// Read the `cursor` of a paginated `*/list` request
+fn parse_cursor(params: Option<Value>) -> Result<Option<String>> {
+    match params
+        .filter(|p| !p.is_null())
+        .map(serde_json::from_value::<PaginatedParams>)
+    {
+        Some(Ok(p)) => Ok(p.cursor),
+        Some(Err(e)) => Err(anyhow!("Invalid params: {}", e)),
+        None => Ok(None),
+    }
+}
+
 /// Check if the enhance_prompt tool is enabled.
 /// The tool is disabled when PROMPT_ENHANCER env var is set to "disabled", "false", "0", or "off".
 /// By default (env var not set or set to other values), the tool is enabled.
@@ -72,7 +113,7 @@ pub fn parse_content_length(line: &str) -> Result<Option<usize>> {
 }
 
 /// Maximum line length for Line mode to prevent DoS (10MB)
-const MAX_LINE_LENGTH: usize = 10 * 1024 * 1024;
+pub(crate) const MAX_LINE_LENGTH: usize = 10 * 1024 * 1024;
 
 async fn read_line_message(reader: &mut BufReader<tokio::io::Stdin>) -> Result<Option<String>> {
     loop {
@@ -176,7 +217,7 @@ async fn read_lsp_message(
     Ok(Some(message))
 }
 
-async fn read_message(
+pub(crate) async fn read_message(
     reader: &mut BufReader<tokio::io::Stdin>,
     mode: &mut Option<TransportMode>,
 ) -> Result<Option<String>> {
@@ -215,7 +256,7 @@ async fn read_message(
     }
 }
 
-async fn write_message(
+pub(crate) async fn write_message(
     stdout: &mut tokio::io::Stdout,
     mode: TransportMode,
     payload: &str,
@@ -239,22 +280,74 @@ async fn write_message(
     Ok(())
 }
 
+/// Protocol state negotiated with one client (the stdio peer or an HTTP session)
+#[derive(Debug, Default)]
+pub struct McpSession {
+    protocol_version: RwLock<ProtocolVersion>,
+    /// Project roots searched in this session, offered by `resources/list`
+    project_roots: RwLock<Vec<PathBuf>>,
+}
+
+impl McpSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revision negotiated by `initialize` ([`ProtocolVersion::FALLBACK`] before it)
+    pub async fn protocol_version(&self) -> ProtocolVersion {
+        *self.protocol_version.read().await
+    }
+
+    /// Record a searched project root for `resources/list`
+    async fn remember_project_root(&self, root: &str) {
+        let root = PathBuf::from(root.replace('\\', "/"));
+        if !root.is_dir() {
+            return;
+        }
+        let mut roots = self.project_roots.write().await;
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+}
+
 /// MCP Server
 pub struct McpServer {
     config: Arc<Config>,
     initial_transport_mode: Option<TransportMode>,
     active_transport_mode: Arc<RwLock<Option<TransportMode>>>,
+    /// Session of the stdio client
+    session: Arc<McpSession>,
+    /// One index manager per project, shared by every session
+    registry: Arc<IndexRegistry>,
+    /// Project assumed when a request names none (default: the working directory)
+    default_root: Option<PathBuf>,
 }
 
 impl McpServer {
     pub fn new(config: Arc<Config>, transport_mode: Option<TransportMode>) -> Self {
         Self {
-            config,
             initial_transport_mode: transport_mode,
             active_transport_mode: Arc::new(RwLock::new(transport_mode)),
+            session: Arc::new(McpSession::new()),
+            registry: Arc::new(IndexRegistry::new(config.clone())),
+            config,
+            default_root: None,
         }
     }
 
+    /// Use `root` instead of the working directory when a request names no project
+    pub fn with_project_root(mut self, root: PathBuf) -> Self {
+        self.default_root = Some(root);
+        self
+    }
+
+    fn default_root(&self) -> Option<PathBuf> {
+        self.default_root
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+    }
+
     /// Run the MCP server (stdio transport)
     pub async fn run(&self) -> Result<()> {
         let stdin = tokio::io::stdin();
@@ -312,8 +405,22 @@ impl McpServer {
         Ok(())
     }
 
-    /// Handle a JSON-RPC request
-    async fn handle_request(&self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+    /// Protocol state of the stdio client
+    pub fn session(&self) -> &McpSession {
+        &self.session
+    }
+
+    /// Handle a JSON-RPC request from the stdio client
+    pub async fn handle_request(&self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        self.handle_session_request(&self.session, request).await
+    }
+
+    /// Handle a JSON-RPC request within a client session
+    pub async fn handle_session_request(
+        &self,
+        session: &McpSession,
+        request: JsonRpcRequest,
+    ) -> Option<JsonRpcResponse> {
         // Per JSON-RPC spec, requests without an id are notifications and must not receive a response
         if request.id.is_none() {
             // Handle known notification side effects silently
@@ -329,11 +436,37 @@ impl McpServer {
             return None;
         }
 
+        if request.method == "initialize" {
+            return Some(
+                self.handle_initialize(session, request.id, request.params)
+                    .await,
+            );
+        }
+
+        let version = session.protocol_version().await;
         match request.method.as_str() {
-            "initialize" => Some(self.handle_initialize(request.id)),
             "initialized" => None, // Notification, no response
-            "tools/list" => Some(self.handle_list_tools(request.id)),
-            "tools/call" => Some(self.handle_call_tool(request.id, request.params).await),
+            "tools/list" => Some(self.handle_list_tools(request.id, request.params, version)),
+            "tools/call" => Some(
+                self.handle_call_tool(session, request.id, request.params, version)
+                    .await,
+            ),
+            "resources/list" => Some(
+                self.handle_list_resources(session, request.id, request.params)
+                    .await,
+            ),
+            "resources/templates/list" => {
+                Some(self.handle_list_resource_templates(request.id, request.params))
+            }
+            "resources/read" => Some(
+                self.handle_read_resource(session, request.id, request.params)
+                    .await,
+            ),
+            "prompts/list" => Some(self.handle_list_prompts(request.id, request.params).await),
+            "prompts/get" => Some(
+                self.handle_get_prompt(session, request.id, request.params)
+                    .await,
+            ),
             "ping" => Some(JsonRpcResponse::success(request.id, json!({}))),
             _ => Some(JsonRpcResponse::error(
                 request.id,
@@ -344,11 +477,42 @@ impl McpServer {
     }
 
     /// Handle initialize request
-    fn handle_initialize(&self, id: Option<Value>) -> JsonRpcResponse {
+    ///
+    /// Negotiates the protocol revision for the session; later responses follow it.
+    async fn handle_initialize(
+        &self,
+        session: &McpSession,
+        id: Option<Value>,
+        params: Option<Value>,
+    ) -> JsonRpcResponse {
+        let params: InitializeParams = match params.map(serde_json::from_value) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e));
+            }
+            None => InitializeParams::default(),
+        };
+
+        let version = ProtocolVersion::negotiate(params.protocol_version.as_deref());
+        *session.protocol_version.write().await = version;
+        info!(
+            "Client {} requested protocol {}, using {}",
+            params
+                .client_info
+                .as_ref()
+                .map(|c| format!("{} {}", c.name, c.version).trim().to_string())
+                .unwrap_or_else(|| "(unknown)".to_string()),
+            params.protocol_version.as_deref().unwrap_or("(none)"),
+            version
+        );
+        debug!("Client capabilities: {}", params.capabilities);
+
         let result = InitializeResult {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: version.as_str().to_string(),
             capabilities: ServerCapabilities {
                 tools: Some(ToolsCapability {}),
+                resources: Some(ResourcesCapability {}),
+                prompts: Some(PromptsCapability {}),
                 logging: None,
             },
             server_info: ServerInfo {
@@ -364,23 +528,68 @@ impl McpServer {
     }
 
     /// Handle list tools request
-    fn handle_list_tools(&self, id: Option<Value>) -> JsonRpcResponse {
+    fn handle_list_tools(
+        &self,
+        id: Option<Value>,
+        params: Option<Value>,
+        version: ProtocolVersion,
+    ) -> JsonRpcResponse {
+        let cursor = match parse_cursor(params) {
+            Ok(cursor) => cursor,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+
         let mut tools = vec![Tool {
             name: SEARCH_CONTEXT_TOOL.name.to_string(),
+            title: Some("Search Codebase Context".to_string()),
             description: SEARCH_CONTEXT_TOOL.description.to_string(),
             input_schema: SearchContextToolDef::get_input_schema(),
+            output_schema: Some(SearchContextToolDef::get_output_schema()),
+            annotations: Some(ToolAnnotations {
+                read_only_hint: Some(true),
+                destructive_hint: None,
+                idempotent_hint: Some(true),
+                // Uploads changed files to the remote index
+                open_world_hint: Some(true),
+            }),
         }];
 
         // Only expose enhance_prompt tool if not disabled
         if is_enhance_prompt_enabled() {
             tools.push(Tool {
                 name: ENHANCE_PROMPT_TOOL.name.to_string(),
+                title: Some("Enhance Prompt".to_string()),
                 description: ENHANCE_PROMPT_TOOL.description.to_string(),
                 input_schema: EnhancePromptToolDef::get_input_schema(),
+                output_schema: None,
+                annotations: Some(ToolAnnotations {
+                    read_only_hint: Some(true),
+                    destructive_hint: None,
+                    idempotent_hint: Some(false),
+                    open_world_hint: Some(true),
+                }),
+            });
+            tools.push(Tool {
+                name: ENHANCEMENT_RESULT_TOOL.name.to_string(),
+                title: Some("Get Enhancement Result".to_string()),
+                description: ENHANCEMENT_RESULT_TOOL.description.to_string(),
+                input_schema: EnhancementResultToolDef::get_input_schema(),
+                output_schema: None,
+                annotations: Some(ToolAnnotations {
+                    read_only_hint: Some(true),
+                    destructive_hint: None,
+                    idempotent_hint: Some(true),
+                    open_world_hint: Some(false),
+                }),
             });
         }
 
-        let result = ListToolsResult { tools };
+        let tools = tools.into_iter().map(|t| version.shape_tool(t)).collect();
+        let (tools, next_cursor) = match paginate(tools, cursor.as_deref(), LIST_PAGE_SIZE) {
+            Ok(page) => page,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+        let result = ListToolsResult { tools, next_cursor };
 
         match serde_json::to_value(result) {
             Ok(value) => JsonRpcResponse::success(id, value),
@@ -389,7 +598,13 @@ impl McpServer {
     }
 
     /// Handle tool call request
-    async fn handle_call_tool(&self, id: Option<Value>, params: Option<Value>) -> JsonRpcResponse {
+    async fn handle_call_tool(
+        &self,
+        session: &McpSession,
+        id: Option<Value>,
+        params: Option<Value>,
+        version: ProtocolVersion,
+    ) -> JsonRpcResponse {
         let params = match params {
             Some(p) => p,
             None => {
@@ -422,14 +637,22 @@ impl McpServer {
                     None => SearchContextArgs::default(),
                 };
 
-                let tool = SearchContextTool::new(self.config.clone());
+                if let Some(root) = args.project_root_path.as_deref() {
+                    session.remember_project_root(root).await;
+                }
+
+                let tool =
+                    SearchContextTool::with_registry(self.config.clone(), self.registry.clone());
                 let result = tool.execute(args).await;
 
-                let call_result = CallToolResult {
-                    content: vec![TextContent::new(result.text)],
-                };
+                let structured = result
+                    .error
+                    .is_none()
+                    .then(|| json!({ "context": result.text.clone() }));
+                let mut call_result = tool_call_result(result.text, None, result.error);
+                call_result.structured_content = structured;
 
-                match serde_json::to_value(call_result) {
+                match serde_json::to_value(version.shape_call_result(call_result)) {
                     Ok(value) => JsonRpcResponse::success(id, value),
                     Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
                 }
@@ -458,14 +681,47 @@ impl McpServer {
                     None => EnhancePromptArgs::default(),
                 };
 
-                let tool = EnhancePromptTool::new(self.config.clone());
+                let tool =
+                    EnhancePromptTool::with_registry(self.config.clone(), self.registry.clone());
                 let result = tool.execute(args).await;
 
-                let call_result = CallToolResult {
-                    content: vec![TextContent::new(result.text)],
+                let call_result = tool_call_result(result.text, result.meta, result.error);
+
+                match serde_json::to_value(version.shape_call_result(call_result)) {
+                    Ok(value) => JsonRpcResponse::success(id, value),
+                    Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
+                }
+            }
+            "get_enhancement_result" => {
+                // Only available alongside enhance_prompt
+                if !is_enhance_prompt_enabled() {
+                    return JsonRpcResponse::error(
+                        id,
+                        -32602,
+                        "Tool 'get_enhancement_result' is disabled".to_string(),
+                    );
+                }
+
+                let args: EnhancementResultArgs = match call_params.arguments {
+                    Some(args) => match serde_json::from_value(args) {
+                        Ok(a) => a,
+                        Err(e) => {
+                            return JsonRpcResponse::error(
+                                id,
+                                -32602,
+                                format!("Invalid arguments: {}", e),
+                            );
+                        }
+                    },
+                    None => EnhancementResultArgs::default(),
                 };
 
-                match serde_json::to_value(call_result) {
+                let tool = EnhancementResultTool::new(self.config.clone());
+                let result = tool.execute(args).await;
+
+                let call_result = tool_call_result(result.text, result.meta, result.error);
+
+                match serde_json::to_value(version.shape_call_result(call_result)) {
                     Ok(value) => JsonRpcResponse::success(id, value),
                     Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
                 }
@@ -474,6 +730,218 @@ impl McpServer {
         }
     }
 
+    /// Projects offered as resources: the working directory (or `--project-root`) and every
+    /// project searched in `session`
+    async fn resource_roots(&self, session: &McpSession) -> Vec<PathBuf> {
+        let mut roots: Vec<PathBuf> = self.default_root().into_iter().collect();
+        for root in session.project_roots.read().await.iter() {
+            if !roots.contains(root) {
+                roots.push(root.clone());
+            }
+        }
+        roots
+    }
+
+    /// Handle list resources request
+    ///
+    /// Lists the working directory and every project searched in this session, when indexed.
+    async fn handle_list_resources(
+        &self,
+        session: &McpSession,
+        id: Option<Value>,
+        params: Option<Value>,
+    ) -> JsonRpcResponse {
+        let cursor = match parse_cursor(params) {
+            Ok(cursor) => cursor,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+
+        let roots = self.resource_roots(session).await;
+        let config = self.config.clone();
+        let resources =
+            tokio::task::spawn_blocking(move || resources::list_resources(&config, &roots))
+                .await
+                .unwrap_or_default();
+
+        let (resources, next_cursor) = match paginate(resources, cursor.as_deref(), LIST_PAGE_SIZE)
+        {
+            Ok(page) => page,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+
+        match serde_json::to_value(ListResourcesResult {
+            resources,
+            next_cursor,
+        }) {
+            Ok(value) => JsonRpcResponse::success(id, value),
+            Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
+        }
+    }
+
+    /// Handle list resource templates request
+    fn handle_list_resource_templates(
+        &self,
+        id: Option<Value>,
+        params: Option<Value>,
+    ) -> JsonRpcResponse {
+        let page = parse_cursor(params).and_then(|cursor| {
+            paginate(
+                resources::resource_templates(),
+                cursor.as_deref(),
+                LIST_PAGE_SIZE,
+            )
+        });
+        let (resource_templates, next_cursor) = match page {
+            Ok(page) => page,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+        let result = ListResourceTemplatesResult {
+            resource_templates,
+            next_cursor,
+        };
+
+        match serde_json::to_value(result) {
+            Ok(value) => JsonRpcResponse::success(id, value),
+            Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
+        }
+    }
+
+    /// Handle read resource request
+    ///
+    /// Serves the same projects `resources/list` offers.
+    async fn handle_read_resource(
+        &self,
+        session: &McpSession,
+        id: Option<Value>,
+        params: Option<Value>,
+    ) -> JsonRpcResponse {
+        let params: ReadResourceParams = match params.map(serde_json::from_value) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e));
+            }
+            None => return JsonRpcResponse::error(id, -32602, "Missing params".to_string()),
+        };
+
+        if let Err(e) = resources::ResourceUri::parse(&params.uri) {
+            return JsonRpcResponse::error(id, -32602, e.to_string());
+        }
+
+        let roots = self.resource_roots(session).await;
+        let config = self.config.clone();
+        let uri = params.uri.clone();
+        let read =
+            tokio::task::spawn_blocking(move || resources::read_resource(&config, &roots, &uri))
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("Resource read failed: {}", e)));
+        let contents = match read {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!("Failed to read resource {}: {}", params.uri, e);
+                return JsonRpcResponse::error(
+                    id,
+                    -32002,
+                    format!("Resource not found: {} ({})", params.uri, e),
+                );
+            }
+        };
+
+        match serde_json::to_value(ReadResourceResult {
+            contents: vec![contents],
+        }) {
+            Ok(value) => JsonRpcResponse::success(id, value),
+            Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
+        }
+    }
+
+    /// Handle list prompts request
+    ///
+    /// Lists the built-in prompts plus prompt files of the working directory and user config.
+    async fn handle_list_prompts(
+        &self,
+        id: Option<Value>,
+        params: Option<Value>,
+    ) -> JsonRpcResponse {
+        let cursor = match parse_cursor(params) {
+            Ok(cursor) => cursor,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+
+        let root = self.default_root();
+        let prompts = tokio::task::spawn_blocking(move || {
+            prompts::load_prompts(root.as_deref())
+                .iter()
+                .map(prompts::PromptDef::to_prompt)
+                .collect()
+        })
+        .await
+        .unwrap_or_default();
+
+        let (prompts, next_cursor) = match paginate(prompts, cursor.as_deref(), LIST_PAGE_SIZE) {
+            Ok(page) => page,
+            Err(e) => return JsonRpcResponse::error(id, -32602, e.to_string()),
+        };
+
+        match serde_json::to_value(ListPromptsResult {
+            prompts,
+            next_cursor,
+        }) {
+            Ok(value) => JsonRpcResponse::success(id, value),
+            Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
+        }
+    }
+
+    /// Handle get prompt request
+    async fn handle_get_prompt(
+        &self,
+        session: &McpSession,
+        id: Option<Value>,
+        params: Option<Value>,
+    ) -> JsonRpcResponse {
+        let params: GetPromptParams = match params.map(serde_json::from_value) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e));
+            }
+            None => return JsonRpcResponse::error(id, -32602, "Missing params".to_string()),
+        };
+
+        let mut args = params.arguments.unwrap_or_default();
+        if let Some(root) = args.get(prompts::PROJECT_ROOT_ARG) {
+            session.remember_project_root(root).await;
+        } else if let Some(root) = &self.default_root {
+            args.insert(
+                prompts::PROJECT_ROOT_ARG.to_string(),
+                root.to_string_lossy().replace('\\', "/"),
+            );
+        }
+
+        match prompts::get_prompt(
+            self.config.clone(),
+            self.registry.clone(),
+            &params.name,
+            args,
+        )
+        .await
+        {
+            Ok(result) => match serde_json::to_value(result) {
+                Ok(value) => JsonRpcResponse::success(id, value),
+                Err(e) => JsonRpcResponse::error(id, -32603, format!("Internal error: {}", e)),
+            },
+            // Tool failures keep their code in `error.data`, like `_meta.error` of tools/call
+            Err(e) => match e.downcast_ref::<ToolError>() {
+                Some(error) => {
+                    let code = match error {
+                        ToolError::InvalidArguments(_) | ToolError::PathNotFound(_) => -32602,
+                        _ => -32603,
+                    };
+                    JsonRpcResponse::error(id, code, e.to_string()).with_data(error.meta())
+                }
+                None => JsonRpcResponse::error(id, -32602, e.to_string()),
+            },
+        }
+    }
+
     /// Send a log notification to the client
     #[allow(dead_code)]
     pub async fn send_log(&self, level: &str, message: &str) -> Result<()> {
@@ -9,10 +9,11 @@ use uuid::Uuid;
 
 use crate::config::Config;
 use crate::http_logger::{self, HttpRequestLog, HttpResponseLog};
+use crate::tools::error::ToolError;
 use crate::USER_AGENT;
 
 use super::common::{
-    is_chinese_text, parse_chat_history, render_enhance_prompt, replace_tool_names, ChatMessage,
+    is_chinese_text, replace_tool_names, to_user_assistant_turns, ChatMessage, EnhanceInput,
 };
 
 /// Default model for prompt enhancement API
@@ -24,6 +25,9 @@ pub const NODE_ID_NEW: i32 = 0;
 /// Node ID for OLD endpoint
 pub const NODE_ID_OLD: i32 = 1;
 
+/// Node ID for the search context node sent to the NEW endpoint
+pub const NODE_ID_SEARCH_CONTEXT: i32 = 2;
+
 /// Redacted token placeholder for logging
 const REDACTED_TOKEN: &str = "<redacted>";
 
@@ -48,6 +52,12 @@ struct PromptEnhancerRequestNew {
     conversation_id: Option<String>,
     model: String,
     mode: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    user_guidelines: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    workspace_guidelines: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<String>,
 }
 
 /// Request payload for OLD chat-stream endpoint (full request with blobs)
@@ -117,23 +127,39 @@ struct PromptEnhancerResponse {
 pub async fn call_new_endpoint(
     client: &Client,
     config: &Config,
-    original_prompt: &str,
-    conversation_history: &str,
+    input: &EnhanceInput,
 ) -> Result<String> {
-    let chat_history = parse_chat_history(conversation_history);
+    let chat_history = to_user_assistant_turns(input.conversation_history.to_messages());
 
-    let payload = PromptEnhancerRequestNew {
-        nodes: vec![PromptNode {
-            id: NODE_ID_NEW,
+    let mut nodes = vec![PromptNode {
+        id: NODE_ID_NEW,
+        node_type: 0,
+        text_node: TextNode {
+            content: input.original_prompt.clone(),
+        },
+    }];
+    if let Some(search_context) = &input.search_context {
+        nodes.push(PromptNode {
+            id: NODE_ID_SEARCH_CONTEXT,
             node_type: 0,
             text_node: TextNode {
-                content: original_prompt.to_string(),
+                content: format!(
+                    "Relevant codebase context for the request above. Use it only as project background, not as the requested output.\n\n<codebase_context>\n{}\n</codebase_context>",
+                    search_context
+                ),
             },
-        }],
+        });
+    }
+
+    let payload = PromptEnhancerRequestNew {
+        nodes,
         chat_history,
         conversation_id: None,
         model: DEFAULT_MODEL.to_string(),
         mode: "CHAT".to_string(),
+        user_guidelines: input.guidelines.user.clone(),
+        workspace_guidelines: input.guidelines.workspace.clone(),
+        rules: input.guidelines.rules.clone(),
     };
 
     let url = format!("{}/prompt-enhancer", config.base_url);
@@ -195,7 +221,7 @@ pub async fn call_new_endpoint(
             if let Some(ref req_log) = http_request_log {
                 http_logger::log_request(None, req_log, None, duration_ms, Some(&error_msg));
             }
-            Err(anyhow!("Request failed: {}", error_msg))
+            Err(ToolError::request_failed("Request failed", &e))
         }
     }
 }
@@ -204,20 +230,24 @@ pub async fn call_new_endpoint(
 pub async fn call_old_endpoint(
     client: &Client,
     config: &Config,
-    original_prompt: &str,
-    conversation_history: &str,
+    input: &EnhanceInput,
     blob_names: &[String],
 ) -> Result<String> {
-    let chat_history = parse_chat_history(conversation_history);
+    let chat_history = to_user_assistant_turns(input.conversation_history.to_messages());
 
-    let final_prompt = render_enhance_prompt(original_prompt)?;
+    let final_prompt = input.render_prompt();
 
-    let is_chinese = is_chinese_text(original_prompt);
+    let is_chinese = is_chinese_text(&input.original_prompt);
     let language_guideline = if is_chinese {
-        "Please respond in Chinese (Simplified Chinese). 请用中文回复。".to_string()
+        "Please respond in Chinese (Simplified Chinese). 请用中文回复。"
     } else {
-        String::new()
+        ""
     };
+    let user_guidelines = [language_guideline, input.guidelines.user.as_str()]
+        .into_iter()
+        .filter(|g| !g.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
 
     let mut sorted_blob_names = blob_names.to_vec();
     sorted_blob_names.sort();
@@ -240,8 +270,8 @@ pub async fn call_old_endpoint(
         context_code_exchange_request_id: None,
         external_source_ids: Vec::new(),
         disable_auto_external_sources: None,
-        user_guidelines: language_guideline,
-        workspace_guidelines: String::new(),
+        user_guidelines,
+        workspace_guidelines: input.guidelines.workspace.clone(),
         feature_detection_flags: FeatureDetectionFlags::default(),
         third_party_override: None,
         tool_definitions: Vec::new(),
@@ -255,7 +285,7 @@ pub async fn call_old_endpoint(
         mode: "CHAT".to_string(),
         agent_memories: None,
         persona_type: None,
-        rules: Vec::new(),
+        rules: input.guidelines.rules.clone(),
         silent: None,
         enable_parallel_tool_use: None,
         conversation_id: None,
@@ -321,7 +351,7 @@ pub async fn call_old_endpoint(
             if let Some(ref req_log) = http_request_log {
                 http_logger::log_request(None, req_log, None, duration_ms, Some(&error_msg));
             }
-            Err(anyhow!("Request failed: {}", error_msg))
+            Err(ToolError::request_failed("Request failed", &e))
         }
     }
 }
@@ -329,17 +359,19 @@ pub async fn call_old_endpoint(
 /// Handle API response text
 fn handle_response_text(status: u16, body_text: &str, is_old_endpoint: bool) -> Result<String> {
     if status == 401 {
-        return Err(anyhow!("Token invalid or expired"));
+        return Err(ToolError::AuthFailed("Token invalid or expired".to_string()).into());
     }
     if status == 403 {
-        return Err(anyhow!("Access denied, token may be disabled"));
+        return Err(
+            ToolError::AuthFailed("Access denied, token may be disabled".to_string()).into(),
+        );
     }
     if !(200..300).contains(&status) {
-        return Err(anyhow!(
+        return Err(ToolError::Upstream(format!(
             "Prompt enhancer API failed: {} - {}",
-            status,
-            body_text
-        ));
+            status, body_text
+        ))
+        .into());
     }
 
     let enhanced_text = if is_old_endpoint {
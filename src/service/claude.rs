@@ -7,9 +7,11 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use crate::tools::error::ToolError;
+
 use super::common::{
-    build_third_party_prompt, extract_enhanced_prompt, map_auth_error, parse_chat_history,
-    replace_tool_names, ThirdPartyConfig,
+    build_system_prompt, extract_enhanced_prompt, map_auth_error, replace_tool_names,
+    to_user_assistant_turns, ChatMessage, EnhanceInput, ThirdPartyConfig,
 };
 
 /// Claude API request structure
@@ -17,7 +19,22 @@ use super::common::{
 struct ClaudeApiRequest {
     model: String,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
     messages: Vec<ClaudeMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<ClaudeThinking>,
+}
+
+#[derive(Debug, Serialize)]
+struct ClaudeThinking {
+    #[serde(rename = "type")]
+    thinking_type: String,
+    budget_tokens: u32,
 }
 
 #[derive(Debug, Serialize)]
@@ -47,13 +64,17 @@ fn build_claude_url(base_url: &str) -> String {
 pub async fn call_claude_endpoint(
     client: &Client,
     config: &ThirdPartyConfig,
-    original_prompt: &str,
-    conversation_history: &str,
+    input: &EnhanceInput,
 ) -> Result<String> {
-    let final_prompt = build_third_party_prompt(original_prompt)?;
-    let chat_history = parse_chat_history(conversation_history);
+    let final_prompt = input.render_prompt();
+    let mut chat_history = input.conversation_history.to_messages();
+    chat_history.push(ChatMessage {
+        role: "user".to_string(),
+        content: final_prompt,
+    });
 
-    let mut messages: Vec<ClaudeMessage> = chat_history
+    // Messages API only has user/assistant turns
+    let messages: Vec<ClaudeMessage> = to_user_assistant_turns(chat_history)
         .into_iter()
         .map(|m| ClaudeMessage {
             role: m.role,
@@ -61,15 +82,24 @@ pub async fn call_claude_endpoint(
         })
         .collect();
 
-    messages.push(ClaudeMessage {
-        role: "user".to_string(),
-        content: final_prompt,
-    });
+    let generation = &config.generation;
+    // Extended thinking spends its budget out of max_tokens, so reserve room for the answer
+    let thinking = generation
+        .reasoning_budget_tokens()
+        .map(|budget_tokens| ClaudeThinking {
+            thinking_type: "enabled".to_string(),
+            budget_tokens,
+        });
+    let max_tokens = generation.max_tokens() + thinking.as_ref().map_or(0, |t| t.budget_tokens);
 
     let payload = ClaudeApiRequest {
         model: config.model.clone(),
-        max_tokens: 4096,
+        max_tokens,
+        system: build_system_prompt(config, input),
         messages,
+        temperature: generation.temperature,
+        top_p: generation.top_p,
+        thinking,
     };
 
     let url = build_claude_url(&config.base_url);
@@ -122,7 +152,7 @@ pub async fn call_claude_endpoint(
 
             Ok(enhanced_text)
         }
-        Err(e) => Err(anyhow!("Claude API request failed: {}", e)),
+        Err(e) => Err(ToolError::request_failed("Claude API request failed", &e)),
     }
 }
 
@@ -8,9 +8,11 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::info;
 
+use crate::tools::error::ToolError;
+
 use super::common::{
-    build_third_party_prompt, extract_enhanced_prompt, map_auth_error, parse_chat_history,
-    replace_tool_names, ThirdPartyConfig,
+    build_system_prompt, extract_enhanced_prompt, map_auth_error, replace_tool_names, EnhanceInput,
+    ThirdPartyConfig,
 };
 
 /// Codex API request structure (OpenAI Responses API)
@@ -23,6 +25,17 @@ struct CodexApiRequest {
     instructions: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<CodexReasoning>,
+}
+
+#[derive(Debug, Serialize)]
+struct CodexReasoning {
+    effort: String,
 }
 
 /// Codex API response structure
@@ -142,11 +155,10 @@ fn extract_output_text(api_response: &CodexApiResponse) -> Result<String> {
 pub async fn call_codex_endpoint(
     client: &Client,
     config: &ThirdPartyConfig,
-    original_prompt: &str,
-    conversation_history: &str,
+    input: &EnhanceInput,
 ) -> Result<String> {
-    let final_prompt = build_third_party_prompt(original_prompt)?;
-    let chat_history = parse_chat_history(conversation_history);
+    let final_prompt = input.render_prompt();
+    let chat_history = input.conversation_history.to_messages();
 
     // Build input as array of message objects with explicit structure
     let mut messages: Vec<Value> = chat_history
@@ -159,8 +171,15 @@ pub async fn call_codex_endpoint(
     let payload = CodexApiRequest {
         model: config.model.clone(),
         input: Some(Value::Array(messages)),
-        instructions: None,
-        max_output_tokens: Some(4096),
+        instructions: build_system_prompt(config, input),
+        max_output_tokens: Some(config.generation.max_tokens()),
+        temperature: config.generation.temperature,
+        top_p: config.generation.top_p,
+        reasoning: config
+            .generation
+            .reasoning_effort
+            .clone()
+            .map(|effort| CodexReasoning { effort }),
     };
 
     let url = build_codex_url(&config.base_url);
@@ -202,7 +221,7 @@ pub async fn call_codex_endpoint(
 
             Ok(enhanced_text)
         }
-        Err(e) => Err(anyhow!("Codex API request failed: {}", e)),
+        Err(e) => Err(ToolError::request_failed("Codex API request failed", &e)),
     }
 }
 
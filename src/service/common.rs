@@ -1,10 +1,16 @@
 //! Common types and utilities for service modules
 
+use std::borrow::Cow;
+
 use anyhow::{anyhow, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::enhancer::templates::ENHANCE_PROMPT_TEMPLATE;
+use crate::enhancer::guidelines::ProjectGuidelines;
+use crate::enhancer::templates::{EnhanceTemplate, TemplateVars};
+use crate::tools::error::ToolError;
+
+use super::generation::GenerationSettings;
 
 /// Environment variable for custom prompt enhancer base URL
 pub const ENV_ENHANCER_BASE_URL: &str = "PROMPT_ENHANCER_BASE_URL";
@@ -74,15 +80,21 @@ impl EnhancerEndpoint {
 }
 
 /// Configuration for third-party API endpoints
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ThirdPartyConfig {
     pub base_url: String,
     pub token: String,
     pub model: String,
+    pub generation: GenerationSettings,
 }
 
 /// Get third-party API configuration from environment variables
-pub fn get_third_party_config(endpoint: EnhancerEndpoint) -> Result<ThirdPartyConfig> {
+///
+/// `generation` holds the user config file's settings; `PROMPT_ENHANCER_*` variables override it.
+pub fn get_third_party_config(
+    endpoint: EnhancerEndpoint,
+    generation: &GenerationSettings,
+) -> Result<ThirdPartyConfig> {
     let base_url = std::env::var(ENV_ENHANCER_BASE_URL).map_err(|_| {
         anyhow!(
             "{} environment variable is required for '{}' endpoint",
@@ -140,25 +152,129 @@ pub fn get_third_party_config(endpoint: EnhancerEndpoint) -> Result<ThirdPartyCo
     // Normalize base URL
     let base_url = base_url.trim_end_matches('/').to_string();
 
+    let generation = generation.with_env_overrides()?;
+    generation.validate_for(endpoint)?;
+
     Ok(ThirdPartyConfig {
         base_url,
         token: token.to_string(),
         model,
+        generation,
     })
 }
 
 /// Chat message for conversation history
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
 }
 
-/// Parse conversation history into ChatMessage format
+/// Conversation history as supplied by callers: `User:`/`Assistant:` text or a JSON
+/// array of `{role, content}` messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConversationHistory {
+    Text(String),
+    Messages(Vec<ChatMessage>),
+}
+
+impl Default for ConversationHistory {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl From<&str> for ConversationHistory {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<String> for ConversationHistory {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl ConversationHistory {
+    /// Normalize message roles; fails on roles other than user, assistant or system
+    pub fn normalized(self) -> Result<Self> {
+        match self {
+            Self::Text(text) => Ok(Self::Text(text)),
+            Self::Messages(messages) => {
+                let messages = messages
+                    .into_iter()
+                    .map(|message| {
+                        let role = normalize_chat_role(&message.role).ok_or_else(|| {
+                            anyhow!(
+                                "Unsupported conversation_history role '{}' (expected user, assistant or system)",
+                                message.role
+                            )
+                        })?;
+                        Ok(ChatMessage {
+                            role: role.to_string(),
+                            content: message.content,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Self::Messages(messages))
+            }
+        }
+    }
+
+    /// Messages for chat APIs: text is split with [`parse_chat_history`]
+    pub fn to_messages(&self) -> Vec<ChatMessage> {
+        match self {
+            Self::Text(text) => parse_chat_history(text),
+            Self::Messages(messages) => messages.clone(),
+        }
+    }
+
+    /// History text for templates: messages are rendered as labelled lines
+    pub fn display(&self) -> Cow<'_, str> {
+        match self {
+            Self::Text(text) => Cow::Borrowed(text),
+            Self::Messages(messages) => Cow::Owned(
+                messages
+                    .iter()
+                    .map(format_chat_message)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
+}
+
+/// Map role aliases onto `user`, `assistant` or `system`
+pub fn normalize_chat_role(role: &str) -> Option<&'static str> {
+    match role.trim().to_lowercase().as_str() {
+        "user" | "human" => Some("user"),
+        "assistant" | "ai" | "model" | "bot" => Some("assistant"),
+        "system" | "developer" => Some("system"),
+        _ => None,
+    }
+}
+
+/// Format one message as a labelled `User:`/`Assistant:`/`System:` line
+pub fn format_chat_message(message: &ChatMessage) -> String {
+    let label = match message.role.as_str() {
+        "assistant" => "Assistant",
+        "system" => "System",
+        _ => "User",
+    };
+    format!("{}: {}", label, message.content)
+}
+
+/// Parse text conversation history into ChatMessage format
+///
+/// Messages are split on `User:`/`AI:`/`Assistant:`/`用户:`/`助手:` line prefixes; lines
+/// inside fenced code blocks never start a new message.
 pub fn parse_chat_history(conversation_history: &str) -> Vec<ChatMessage> {
     let mut chat_history = Vec::new();
     let mut current_role: Option<String> = None;
     let mut current_lines: Vec<String> = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
 
     for line in conversation_history.lines() {
         let trimmed = line.trim();
@@ -170,6 +286,14 @@ pub fn parse_chat_history(conversation_history: &str) -> Vec<ChatMessage> {
             continue;
         }
 
+        if fence.is_some() {
+            if current_role.is_some() {
+                current_lines.push(line.to_string());
+            }
+            fence = update_code_fence(fence, trimmed);
+            continue;
+        }
+
         if let Some((role, content)) = parse_history_line(trimmed) {
             if let Some(prev_role) = current_role.take() {
                 chat_history.push(ChatMessage {
@@ -179,9 +303,13 @@ pub fn parse_chat_history(conversation_history: &str) -> Vec<ChatMessage> {
             }
             current_role = Some(role);
             current_lines.clear();
+            fence = update_code_fence(fence, &content);
             current_lines.push(content);
-        } else if current_role.is_some() {
-            current_lines.push(line.to_string());
+        } else {
+            if current_role.is_some() {
+                current_lines.push(line.to_string());
+            }
+            fence = update_code_fence(fence, trimmed);
         }
     }
 
@@ -195,6 +323,29 @@ pub fn parse_chat_history(conversation_history: &str) -> Vec<ChatMessage> {
     chat_history
 }
 
+/// Track fenced code blocks: a run of 3+ backticks or tildes opens a fence, and a run of
+/// the same character at least as long (with nothing after it) closes it
+fn update_code_fence(fence: Option<(char, usize)>, line: &str) -> Option<(char, usize)> {
+    let Some(marker) = line.chars().next().filter(|c| *c == '`' || *c == '~') else {
+        return fence;
+    };
+    let len = line.chars().take_while(|c| *c == marker).count();
+    if len < 3 {
+        return fence;
+    }
+    match fence {
+        None => Some((marker, len)),
+        Some((open, open_len)) if open == marker && len >= open_len => {
+            if line[len * marker.len_utf8()..].trim().is_empty() {
+                None
+            } else {
+                fence
+            }
+        }
+        Some(_) => fence,
+    }
+}
+
 fn parse_history_line(line: &str) -> Option<(String, String)> {
     let user_prefixes = ["User:", "用户:"];
     for prefix in user_prefixes {
@@ -213,6 +364,31 @@ fn parse_history_line(line: &str) -> Option<(String, String)> {
     None
 }
 
+/// Shape messages for APIs whose conversations only have user and assistant turns
+/// (Claude, Gemini, Augment): system messages become labelled user turns, and consecutive
+/// turns from the same role are merged so roles alternate
+pub fn to_user_assistant_turns(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let mut turns: Vec<ChatMessage> = Vec::with_capacity(messages.len());
+    for message in messages {
+        let message = if message.role == "system" {
+            ChatMessage {
+                role: "user".to_string(),
+                content: format_chat_message(&message),
+            }
+        } else {
+            message
+        };
+        match turns.last_mut() {
+            Some(last) if last.role == message.role => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&message.content);
+            }
+            _ => turns.push(message),
+        }
+    }
+    turns
+}
+
 /// Extract enhanced prompt from XML-like response
 /// Looks for content between <augment-enhanced-prompt> and </augment-enhanced-prompt> tags
 pub fn extract_enhanced_prompt(text: &str) -> Option<String> {
@@ -262,31 +438,73 @@ pub fn replace_tool_names(text: &str) -> String {
 }
 
 /// Render the enhance prompt template safely without corrupting user input
-/// Uses split+concat instead of replace to avoid replacing placeholders
-/// that may appear in user content
+/// Placeholders are resolved from the parsed template, so placeholder-like text
+/// in user content is never substituted
 pub fn render_enhance_prompt(original_prompt: &str) -> Result<String> {
-    let (before, after) = ENHANCE_PROMPT_TEMPLATE
-        .split_once("{original_prompt}")
-        .ok_or_else(|| anyhow!("ENHANCE_PROMPT_TEMPLATE missing {{original_prompt}}"))?;
-
-    let mut rendered = String::with_capacity(before.len() + original_prompt.len() + after.len());
-    rendered.push_str(before);
-    rendered.push_str(original_prompt);
-    rendered.push_str(after);
-    Ok(rendered)
+    Ok(EnhanceInput::new(original_prompt, ConversationHistory::default()).render_prompt())
 }
 
-/// Build the full prompt for third-party APIs using the template
-pub fn build_third_party_prompt(original_prompt: &str) -> Result<String> {
-    let enhanced_prompt = render_enhance_prompt(original_prompt)?;
+/// Inputs for a single prompt enhancement call, shared by all providers
+#[derive(Debug, Clone, Default)]
+pub struct EnhanceInput {
+    pub original_prompt: String,
+    pub conversation_history: ConversationHistory,
+    /// Retrieved codebase context for templates that use `{search_context}`
+    pub search_context: Option<String>,
+    pub project_name: Option<String>,
+    pub template: EnhanceTemplate,
+    /// Project guidelines and rules the enhanced prompt should respect
+    pub guidelines: ProjectGuidelines,
+}
 
-    let language_hint = if is_chinese_text(original_prompt) {
-        "\n\n请用中文回复。"
-    } else {
-        ""
-    };
+impl EnhanceInput {
+    /// Create an input using the built-in template
+    pub fn new(original_prompt: &str, conversation_history: ConversationHistory) -> Self {
+        Self {
+            original_prompt: original_prompt.to_string(),
+            conversation_history,
+            ..Default::default()
+        }
+    }
 
-    Ok(format!("{}{}", enhanced_prompt, language_hint))
+    /// Render the enhancement template for this input
+    pub fn render_prompt(&self) -> String {
+        let language = if is_chinese_text(&self.original_prompt) {
+            "Chinese"
+        } else {
+            "English"
+        };
+
+        self.template.render(&TemplateVars {
+            original_prompt: &self.original_prompt,
+            search_context: self.search_context.as_deref().unwrap_or_default(),
+            language,
+            project_name: self.project_name.as_deref().unwrap_or_default(),
+            conversation_history: &self.conversation_history.display(),
+        })
+    }
+}
+
+/// Build the system prompt for third-party APIs from configured settings, project guidelines
+/// and the response language
+pub fn build_system_prompt(config: &ThirdPartyConfig, input: &EnhanceInput) -> Option<String> {
+    let language_hint = is_chinese_text(&input.original_prompt)
+        .then(|| "Please respond in Chinese (Simplified Chinese). 请用中文回复。".to_string());
+
+    let sections: Vec<String> = [
+        config.generation.system_prompt.clone(),
+        input.guidelines.to_system_prompt(),
+        language_hint,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n\n"))
+    }
 }
 
 /// Build API URL by joining base URL with a resource path, deduplicating version segments.
@@ -337,11 +555,16 @@ fn strip_version_prefix(path: &str) -> &str {
 /// Map common authentication errors to consistent error messages
 pub fn map_auth_error(status: u16, provider: &str) -> Option<anyhow::Error> {
     match status {
-        401 => Some(anyhow!("{} API key invalid or expired", provider)),
-        403 => Some(anyhow!(
-            "{} access denied, API key may be disabled",
-            provider
-        )),
+        401 => {
+            Some(ToolError::AuthFailed(format!("{} API key invalid or expired", provider)).into())
+        }
+        403 => Some(
+            ToolError::AuthFailed(format!(
+                "{} access denied, API key may be disabled",
+                provider
+            ))
+            .into(),
+        ),
         _ => None,
     }
 }
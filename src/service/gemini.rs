@@ -7,14 +7,18 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use crate::tools::error::ToolError;
+
 use super::common::{
-    build_third_party_prompt, extract_enhanced_prompt, map_auth_error, parse_chat_history,
-    replace_tool_names, ThirdPartyConfig,
+    build_system_prompt, extract_enhanced_prompt, map_auth_error, replace_tool_names,
+    to_user_assistant_turns, ChatMessage, EnhanceInput, ThirdPartyConfig,
 };
 
 /// Gemini API request structure
 #[derive(Debug, Serialize)]
 struct GeminiApiRequest {
+    #[serde(rename = "systemInstruction", skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiSystemInstruction>,
     contents: Vec<GeminiContent>,
     #[serde(rename = "generationConfig", skip_serializing_if = "Option::is_none")]
     generation_config: Option<GeminiGenerationConfig>,
@@ -26,6 +30,11 @@ struct GeminiContent {
     parts: Vec<GeminiPart>,
 }
 
+#[derive(Debug, Serialize)]
+struct GeminiSystemInstruction {
+    parts: Vec<GeminiPart>,
+}
+
 #[derive(Debug, Serialize)]
 struct GeminiPart {
     text: String,
@@ -35,6 +44,18 @@ struct GeminiPart {
 struct GeminiGenerationConfig {
     #[serde(rename = "maxOutputTokens")]
     max_output_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(rename = "topP", skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(rename = "thinkingConfig", skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<GeminiThinkingConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiThinkingConfig {
+    #[serde(rename = "thinkingBudget")]
+    thinking_budget: u32,
 }
 
 /// Gemini API response structure
@@ -67,13 +88,17 @@ fn build_gemini_url(base_url: &str, model: &str) -> String {
 pub async fn call_gemini_endpoint(
     client: &Client,
     config: &ThirdPartyConfig,
-    original_prompt: &str,
-    conversation_history: &str,
+    input: &EnhanceInput,
 ) -> Result<String> {
-    let final_prompt = build_third_party_prompt(original_prompt)?;
-    let chat_history = parse_chat_history(conversation_history);
+    let final_prompt = input.render_prompt();
+    let mut chat_history = input.conversation_history.to_messages();
+    chat_history.push(ChatMessage {
+        role: "user".to_string(),
+        content: final_prompt,
+    });
 
-    let mut contents: Vec<GeminiContent> = chat_history
+    // Gemini only has user/model turns
+    let contents: Vec<GeminiContent> = to_user_assistant_turns(chat_history)
         .into_iter()
         .map(|m| {
             let role = if m.role == "assistant" {
@@ -88,15 +113,22 @@ pub async fn call_gemini_endpoint(
         })
         .collect();
 
-    contents.push(GeminiContent {
-        role: "user".to_string(),
-        parts: vec![GeminiPart { text: final_prompt }],
-    });
-
     let payload = GeminiApiRequest {
+        system_instruction: build_system_prompt(config, input).map(|text| {
+            GeminiSystemInstruction {
+                parts: vec![GeminiPart { text }],
+            }
+        }),
         contents,
         generation_config: Some(GeminiGenerationConfig {
-            max_output_tokens: 4096,
+            // Thinking tokens count against maxOutputTokens, so reserve room for the answer
+            max_output_tokens: config.generation.reserved_output_tokens(),
+            temperature: config.generation.temperature,
+            top_p: config.generation.top_p,
+            thinking_config: config
+                .generation
+                .reasoning_budget_tokens()
+                .map(|thinking_budget| GeminiThinkingConfig { thinking_budget }),
         }),
     };
 
@@ -144,7 +176,7 @@ pub async fn call_gemini_endpoint(
 
             Ok(enhanced_text)
         }
-        Err(e) => Err(anyhow!("Gemini API request failed: {}", e)),
+        Err(e) => Err(ToolError::request_failed("Gemini API request failed", &e)),
     }
 }
 
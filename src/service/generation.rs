@@ -0,0 +1,168 @@
+//! Provider-neutral generation settings for third-party enhancer calls
+//! Read from the `enhancer` section of the user config file, with
+//! `PROMPT_ENHANCER_*` environment variables taking precedence
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::common::EnhancerEndpoint;
+
+/// Environment variable for the maximum number of output tokens
+pub const ENV_ENHANCER_MAX_TOKENS: &str = "PROMPT_ENHANCER_MAX_TOKENS";
+
+/// Environment variable for the sampling temperature
+pub const ENV_ENHANCER_TEMPERATURE: &str = "PROMPT_ENHANCER_TEMPERATURE";
+
+/// Environment variable for nucleus sampling (top_p)
+pub const ENV_ENHANCER_TOP_P: &str = "PROMPT_ENHANCER_TOP_P";
+
+/// Environment variable for reasoning effort (minimal, low, medium, high)
+pub const ENV_ENHANCER_REASONING_EFFORT: &str = "PROMPT_ENHANCER_REASONING_EFFORT";
+
+/// Environment variable for an extra system prompt
+pub const ENV_ENHANCER_SYSTEM_PROMPT: &str = "PROMPT_ENHANCER_SYSTEM_PROMPT";
+
+/// Default maximum output tokens when none is configured
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Supported reasoning effort levels
+pub const REASONING_EFFORTS: &[&str] = &["minimal", "low", "medium", "high"];
+
+/// Generation settings shared by all third-party providers
+///
+/// Every field is optional; unset fields keep each provider's API defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationSettings {
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub reasoning_effort: Option<String>,
+    pub system_prompt: Option<String>,
+}
+
+impl GenerationSettings {
+    /// Maximum output tokens, falling back to `DEFAULT_MAX_TOKENS`
+    pub fn max_tokens(&self) -> u32 {
+        self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+    }
+
+    /// Output tokens to reserve in the context window: the answer plus any thinking budget
+    pub fn reserved_output_tokens(&self) -> u32 {
+        self.max_tokens() + self.reasoning_budget_tokens().unwrap_or(0)
+    }
+
+    /// Thinking budget in tokens for providers that take a budget instead of an effort level
+    pub fn reasoning_budget_tokens(&self) -> Option<u32> {
+        match self.reasoning_effort.as_deref()? {
+            "minimal" => Some(1024),
+            "low" => Some(2048),
+            "medium" => Some(8192),
+            "high" => Some(16384),
+            _ => None,
+        }
+    }
+
+    /// Fill unset fields from `fallback`
+    pub fn or(self, fallback: GenerationSettings) -> Self {
+        Self {
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            temperature: self.temperature.or(fallback.temperature),
+            top_p: self.top_p.or(fallback.top_p),
+            reasoning_effort: self.reasoning_effort.or(fallback.reasoning_effort),
+            system_prompt: self.system_prompt.or(fallback.system_prompt),
+        }
+    }
+
+    /// Check value ranges and normalize the reasoning effort
+    pub fn validate(mut self) -> Result<Self> {
+        if self.max_tokens == Some(0) {
+            return Err(anyhow!("max_tokens must be greater than 0"));
+        }
+        if let Some(t) = self.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                return Err(anyhow!("temperature must be between 0 and 2, got {}", t));
+            }
+        }
+        if let Some(p) = self.top_p {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(anyhow!("top_p must be between 0 and 1, got {}", p));
+            }
+        }
+        if let Some(effort) = self.reasoning_effort.take() {
+            let effort = effort.trim().to_lowercase();
+            if !REASONING_EFFORTS.contains(&effort.as_str()) {
+                return Err(anyhow!(
+                    "reasoning_effort must be one of {}, got '{}'",
+                    REASONING_EFFORTS.join(", "),
+                    effort
+                ));
+            }
+            self.reasoning_effort = Some(effort);
+        }
+        self.system_prompt = self
+            .system_prompt
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        Ok(self)
+    }
+
+    /// Check the settings against the limits of one provider's API
+    ///
+    /// Claude only accepts a temperature up to 1 and rejects custom sampling
+    /// when extended thinking is on.
+    pub fn validate_for(&self, endpoint: EnhancerEndpoint) -> Result<()> {
+        if endpoint != EnhancerEndpoint::Claude {
+            return Ok(());
+        }
+        if let Some(t) = self.temperature {
+            if !(0.0..=1.0).contains(&t) {
+                return Err(anyhow!(
+                    "temperature must be between 0 and 1 for the '{}' endpoint, got {}",
+                    endpoint,
+                    t
+                ));
+            }
+        }
+        if self.reasoning_effort.is_some() && (self.temperature.is_some() || self.top_p.is_some()) {
+            return Err(anyhow!(
+                "temperature and top_p can't be combined with reasoning_effort for the '{}' endpoint",
+                endpoint
+            ));
+        }
+        Ok(())
+    }
+
+    /// Read settings from `PROMPT_ENHANCER_*` environment variables
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            max_tokens: parse_env(ENV_ENHANCER_MAX_TOKENS)?,
+            temperature: parse_env(ENV_ENHANCER_TEMPERATURE)?,
+            top_p: parse_env(ENV_ENHANCER_TOP_P)?,
+            reasoning_effort: env_string(ENV_ENHANCER_REASONING_EFFORT),
+            system_prompt: env_string(ENV_ENHANCER_SYSTEM_PROMPT),
+        })
+    }
+
+    /// Apply environment variable overrides to settings from the user config file
+    pub fn with_env_overrides(&self) -> Result<Self> {
+        Self::from_env()?.or(self.clone()).validate()
+    }
+}
+
+fn env_string(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str) -> Result<Option<T>> {
+    match env_string(name) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("Invalid value for {}: '{}'", name, value)),
+        None => Ok(None),
+    }
+}
@@ -7,9 +7,11 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use crate::tools::error::ToolError;
+
 use super::common::{
-    build_third_party_prompt, extract_enhanced_prompt, map_auth_error, parse_chat_history,
-    replace_tool_names, ThirdPartyConfig,
+    build_system_prompt, extract_enhanced_prompt, map_auth_error, replace_tool_names, EnhanceInput,
+    ThirdPartyConfig,
 };
 
 /// OpenAI API request structure
@@ -18,6 +20,12 @@ struct OpenAIApiRequest {
     model: String,
     messages: Vec<OpenAIMessage>,
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -50,20 +58,24 @@ fn build_openai_url(base_url: &str) -> String {
 pub async fn call_openai_endpoint(
     client: &Client,
     config: &ThirdPartyConfig,
-    original_prompt: &str,
-    conversation_history: &str,
+    input: &EnhanceInput,
 ) -> Result<String> {
-    let final_prompt = build_third_party_prompt(original_prompt)?;
-    let chat_history = parse_chat_history(conversation_history);
+    let final_prompt = input.render_prompt();
+    let chat_history = input.conversation_history.to_messages();
 
-    let mut messages: Vec<OpenAIMessage> = chat_history
-        .into_iter()
-        .map(|m| OpenAIMessage {
-            role: m.role,
-            content: m.content,
+    let mut messages: Vec<OpenAIMessage> = build_system_prompt(config, input)
+        .map(|content| OpenAIMessage {
+            role: "system".to_string(),
+            content,
         })
+        .into_iter()
         .collect();
 
+    messages.extend(chat_history.into_iter().map(|m| OpenAIMessage {
+        role: m.role,
+        content: m.content,
+    }));
+
     messages.push(OpenAIMessage {
         role: "user".to_string(),
         content: final_prompt,
@@ -72,7 +84,10 @@ pub async fn call_openai_endpoint(
     let payload = OpenAIApiRequest {
         model: config.model.clone(),
         messages,
-        max_tokens: Some(4096),
+        max_tokens: Some(config.generation.max_tokens()),
+        temperature: config.generation.temperature,
+        top_p: config.generation.top_p,
+        reasoning_effort: config.generation.reasoning_effort.clone(),
     };
 
     let url = build_openai_url(&config.base_url);
@@ -118,7 +133,7 @@ pub async fn call_openai_endpoint(
 
             Ok(enhanced_text)
         }
-        Err(e) => Err(anyhow!("OpenAI API request failed: {}", e)),
+        Err(e) => Err(ToolError::request_failed("OpenAI API request failed", &e)),
     }
 }
 
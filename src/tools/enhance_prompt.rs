@@ -3,12 +3,16 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::json;
 use tracing::{error, info};
 
 use crate::config::Config;
 use crate::enhancer::PromptEnhancer;
+use crate::index::IndexRegistry;
+use crate::service::ConversationHistory;
+
+use super::error::ToolError;
 
 /// Tool definition for MCP
 pub struct EnhancePromptToolDef {
@@ -56,8 +60,20 @@ impl EnhancePromptToolDef {
                     "description": "The original prompt to enhance"
                 },
                 "conversation_history": {
-                    "type": "string",
-                    "description": "Recent conversation history (5-10 rounds) to help understand user intent and project context. Format: 'User: xxx\\nAssistant: yyy'"
+                    "type": ["string", "array"],
+                    "description": "Recent conversation history (5-10 rounds) to help understand user intent and project context. Either text in the format 'User: xxx\\nAssistant: yyy', or an array of {role, content} messages with role 'user', 'assistant' or 'system'",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "role": { "type": "string", "enum": ["user", "assistant", "system"] },
+                            "content": { "type": "string" }
+                        },
+                        "required": ["role", "content"]
+                    }
+                },
+                "no_cache": {
+                    "type": "boolean",
+                    "description": "Skip the enhancement cache and always call the enhancer (optional, defaults to false)"
                 }
             },
             "required": ["prompt", "conversation_history"]
@@ -70,23 +86,74 @@ impl EnhancePromptToolDef {
 pub struct EnhancePromptArgs {
     pub project_root_path: Option<String>,
     pub prompt: Option<String>,
-    pub conversation_history: Option<String>,
+    /// Text history or an array of `{role, content}` messages, with roles normalized
+    #[serde(default, deserialize_with = "deserialize_conversation_history")]
+    pub conversation_history: Option<ConversationHistory>,
+    /// Skip the on-disk enhancement cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_cache: Option<bool>,
+}
+
+/// Accept `conversation_history` as text or as an array of `{role, content}` messages
+fn deserialize_conversation_history<'de, D>(
+    deserializer: D,
+) -> Result<Option<ConversationHistory>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<ConversationHistory>::deserialize(deserializer)?
+        .map(|history| history.normalized().map_err(de::Error::custom))
+        .transpose()
 }
 
 /// Tool result
 #[derive(Debug, Clone)]
 pub struct ToolResult {
     pub text: String,
+    /// Extra details for the MCP result's `_meta` (e.g. enhancement cache status)
+    pub meta: Option<serde_json::Value>,
+    /// Set when the tool failed; `text` then reads `Error: <message>`
+    pub error: Option<ToolError>,
+}
+
+impl ToolResult {
+    pub fn success(text: String, meta: Option<serde_json::Value>) -> Self {
+        Self {
+            text,
+            meta,
+            error: None,
+        }
+    }
+
+    pub fn failure(error: ToolError) -> Self {
+        Self {
+            text: format!("Error: {}", error),
+            meta: None,
+            error: Some(error),
+        }
+    }
 }
 
 /// Enhance prompt tool
 pub struct EnhancePromptTool {
     config: Arc<Config>,
+    registry: Option<Arc<IndexRegistry>>,
 }
 
 impl EnhancePromptTool {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        Self {
+            config,
+            registry: None,
+        }
+    }
+
+    /// Inject search context through the shared managers of a long-lived server
+    pub fn with_registry(config: Arc<Config>, registry: Arc<IndexRegistry>) -> Self {
+        Self {
+            config,
+            registry: Some(registry),
+        }
     }
 
     /// Execute the tool
@@ -94,16 +161,13 @@ impl EnhancePromptTool {
         let prompt = match &args.prompt {
             Some(p) if !p.is_empty() => p.clone(),
             _ => {
-                return ToolResult {
-                    text: "Error: prompt is required".to_string(),
-                };
+                return ToolResult::failure(ToolError::InvalidArguments(
+                    "prompt is required".to_string(),
+                ));
             }
         };
 
-        let conversation_history = match &args.conversation_history {
-            Some(h) => h.clone(),
-            None => String::new(),
-        };
+        let conversation_history = args.conversation_history.clone().unwrap_or_default();
 
         // Determine project root
         let project_root = args
@@ -118,12 +182,16 @@ impl EnhancePromptTool {
 
         // Create enhancer and execute
         let enhancer = match PromptEnhancer::new(self.config.clone()) {
-            Ok(e) => e,
+            Ok(e) => {
+                let e = e.with_cache(!args.no_cache.unwrap_or(false));
+                match &self.registry {
+                    Some(registry) => e.with_registry(registry.clone()),
+                    None => e,
+                }
+            }
             Err(e) => {
                 error!("Failed to create PromptEnhancer: {}", e);
-                return ToolResult {
-                    text: format!("Error: {}", e),
-                };
+                return ToolResult::failure(ToolError::classify(&e, ToolError::Internal));
             }
         };
 
@@ -138,12 +206,15 @@ impl EnhancePromptTool {
         };
 
         match result {
-            Ok(enhanced) => ToolResult { text: enhanced },
+            Ok(enhanced) => ToolResult::success(
+                enhanced,
+                enhancer
+                    .cache_status()
+                    .map(|status| json!({ "cache": status })),
+            ),
             Err(e) => {
                 error!("Enhancement failed: {}", e);
-                ToolResult {
-                    text: format!("Error: {}", e),
-                }
+                ToolResult::failure(ToolError::classify(&e, ToolError::Upstream))
             }
         }
     }
@@ -0,0 +1,108 @@
+//! get_enhancement_result tool implementation
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::enhancer::PromptEnhancer;
+
+use super::enhance_prompt::ToolResult;
+use super::error::ToolError;
+
+/// Tool definition for MCP
+pub struct EnhancementResultToolDef {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Static tool definition
+pub static ENHANCEMENT_RESULT_TOOL: EnhancementResultToolDef = EnhancementResultToolDef {
+    name: "get_enhancement_result",
+    description: r#"Fetches the result of an enhance_prompt review session that timed out.
+
+Use this tool ONLY when an enhance_prompt result ends with an "[ace-tool] The review timed out" note that names a session_id, and the user says they have finished the review (or asks for the reviewed prompt).
+
+Returns the prompt the reviewer submitted. If the review is still open, returns the latest enhanced prompt again with a note and a link to finish the review."#,
+};
+
+impl EnhancementResultToolDef {
+    pub fn get_input_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "session_id": {
+                    "type": "string",
+                    "description": "Session ID from the enhance_prompt timeout note"
+                },
+                "project_root_path": {
+                    "type": "string",
+                    "description": "Absolute path to the project root directory used for enhance_prompt (optional, defaults to current working directory)"
+                }
+            },
+            "required": ["session_id"]
+        })
+    }
+}
+
+/// Tool arguments
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnhancementResultArgs {
+    pub session_id: Option<String>,
+    pub project_root_path: Option<String>,
+}
+
+/// Enhancement result tool
+pub struct EnhancementResultTool {
+    config: Arc<Config>,
+}
+
+impl EnhancementResultTool {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config }
+    }
+
+    /// Execute the tool
+    pub async fn execute(&self, args: EnhancementResultArgs) -> ToolResult {
+        let session_id = match &args.session_id {
+            Some(id) if !id.trim().is_empty() => id.trim().to_string(),
+            _ => {
+                return ToolResult::failure(ToolError::InvalidArguments(
+                    "session_id is required".to_string(),
+                ));
+            }
+        };
+
+        let project_root = args
+            .project_root_path
+            .as_ref()
+            .map(|p| PathBuf::from(p.replace('\\', "/")));
+
+        info!(
+            "Executing get_enhancement_result for session {}",
+            session_id
+        );
+
+        let enhancer = match PromptEnhancer::new(self.config.clone()) {
+            Ok(e) => e,
+            Err(e) => {
+                error!("Failed to create PromptEnhancer: {}", e);
+                return ToolResult::failure(ToolError::classify(&e, ToolError::Internal));
+            }
+        };
+
+        match enhancer
+            .resume_session(&session_id, project_root.as_deref())
+            .await
+        {
+            Ok(text) => ToolResult::success(text, None),
+            Err(e) => {
+                error!("Failed to fetch enhancement result: {}", e);
+                ToolResult::failure(ToolError::classify(&e, ToolError::Internal))
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Typed tool failures
+//!
+//! Tools report failures as MCP results with `isError: true` and a machine-readable
+//! `_meta.error = { "code", "retryable" }`, so agents can tell a bad argument from a
+//! transient upstream problem. Lower layers (API clients, indexing, the review flow)
+//! raise these variants inside `anyhow` errors; [`ToolError::classify`] recovers them.
+
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// A tool failure, carrying the message shown to the client
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ToolError {
+    /// Missing or malformed tool arguments
+    #[error("{0}")]
+    InvalidArguments(String),
+    /// The project path does not exist or is not a directory
+    #[error("{0}")]
+    PathNotFound(String),
+    /// Indexing or uploading the project failed
+    #[error("{0}")]
+    IndexFailed(String),
+    /// The API rejected the token or key (HTTP 401/403)
+    #[error("{0}")]
+    AuthFailed(String),
+    /// An upstream API request timed out
+    #[error("{0}")]
+    UpstreamTimeout(String),
+    /// An upstream API request failed or returned an error status
+    #[error("{0}")]
+    Upstream(String),
+    /// The user cancelled an interactive review
+    #[error("{0}")]
+    Cancelled(String),
+    /// Any other failure
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ToolError {
+    /// Stable error code reported in `_meta.error.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ToolError::InvalidArguments(_) => "invalid_arguments",
+            ToolError::PathNotFound(_) => "path_not_found",
+            ToolError::IndexFailed(_) => "index_failed",
+            ToolError::AuthFailed(_) => "auth_failed",
+            ToolError::UpstreamTimeout(_) => "upstream_timeout",
+            ToolError::Upstream(_) => "upstream_error",
+            ToolError::Cancelled(_) => "cancelled",
+            ToolError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Whether the same call may succeed when retried unchanged
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            ToolError::IndexFailed(_) | ToolError::UpstreamTimeout(_) | ToolError::Upstream(_)
+        )
+    }
+
+    /// The `_meta` object of an error result
+    pub fn meta(&self) -> Value {
+        json!({
+            "error": {
+                "code": self.code(),
+                "retryable": self.retryable(),
+            }
+        })
+    }
+
+    /// Same kind of error with a different message
+    fn with_message(&self, message: String) -> Self {
+        match self {
+            ToolError::InvalidArguments(_) => ToolError::InvalidArguments(message),
+            ToolError::PathNotFound(_) => ToolError::PathNotFound(message),
+            ToolError::IndexFailed(_) => ToolError::IndexFailed(message),
+            ToolError::AuthFailed(_) => ToolError::AuthFailed(message),
+            ToolError::UpstreamTimeout(_) => ToolError::UpstreamTimeout(message),
+            ToolError::Upstream(_) => ToolError::Upstream(message),
+            ToolError::Cancelled(_) => ToolError::Cancelled(message),
+            ToolError::Internal(_) => ToolError::Internal(message),
+        }
+    }
+
+    /// Classify an error by the first `ToolError` or timed-out request in its chain,
+    /// falling back to `fallback`; the message is the error's outermost message
+    pub fn classify(err: &anyhow::Error, fallback: fn(String) -> ToolError) -> ToolError {
+        let message = err.to_string();
+        for cause in err.chain() {
+            if let Some(tool_error) = cause.downcast_ref::<ToolError>() {
+                return tool_error.with_message(message);
+            }
+            if let Some(request_error) = cause.downcast_ref::<reqwest::Error>() {
+                if request_error.is_timeout() {
+                    return ToolError::UpstreamTimeout(message);
+                }
+            }
+        }
+        fallback(message)
+    }
+
+    /// Wrap a failed HTTP request (`context: error`), marking timeouts
+    pub fn request_failed(context: &str, err: &reqwest::Error) -> anyhow::Error {
+        let message = format!("{}: {}", context, err);
+        if err.is_timeout() {
+            ToolError::UpstreamTimeout(message).into()
+        } else {
+            ToolError::Upstream(message).into()
+        }
+    }
+}
@@ -1,7 +1,11 @@
 //! Tools module
 
 pub mod enhance_prompt;
+pub mod enhancement_result;
+pub mod error;
 pub mod search_context;
 
 pub use enhance_prompt::EnhancePromptTool;
+pub use enhancement_result::EnhancementResultTool;
+pub use error::ToolError;
 pub use search_context::SearchContextTool;
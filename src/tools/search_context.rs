@@ -8,7 +8,9 @@ use serde_json::json;
 use tracing::{error, info};
 
 use crate::config::Config;
-use crate::index::IndexManager;
+use crate::index::{IndexManager, IndexRegistry};
+
+use super::error::ToolError;
 
 /// Tool definition for MCP
 pub struct SearchContextToolDef {
@@ -77,6 +79,20 @@ Examples:
             "required": ["project_root_path", "query"]
         })
     }
+
+    /// Schema of the `structuredContent` returned alongside the text result
+    pub fn get_output_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "context": {
+                    "type": "string",
+                    "description": "Retrieved code snippets with their file paths, or an error message"
+                }
+            },
+            "required": ["context"]
+        })
+    }
 }
 
 /// Tool arguments
@@ -90,16 +106,44 @@ pub struct SearchContextArgs {
 #[derive(Debug, Clone)]
 pub struct ToolResult {
     pub text: String,
+    /// Set when the tool failed; `text` then reads `Error: <message>`
+    pub error: Option<ToolError>,
+}
+
+impl ToolResult {
+    pub fn success(text: String) -> Self {
+        Self { text, error: None }
+    }
+
+    pub fn failure(error: ToolError) -> Self {
+        Self {
+            text: format!("Error: {}", error),
+            error: Some(error),
+        }
+    }
 }
 
 /// Search context tool
 pub struct SearchContextTool {
     config: Arc<Config>,
+    /// Shared per-project managers; `None` builds a fresh manager per call
+    registry: Option<Arc<IndexRegistry>>,
 }
 
 impl SearchContextTool {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        Self {
+            config,
+            registry: None,
+        }
+    }
+
+    /// Search through the shared managers of a long-lived server
+    pub fn with_registry(config: Arc<Config>, registry: Arc<IndexRegistry>) -> Self {
+        Self {
+            config,
+            registry: Some(registry),
+        }
     }
 
     /// Execute the tool
@@ -107,18 +151,18 @@ impl SearchContextTool {
         let query = match &args.query {
             Some(q) if !q.is_empty() => q.clone(),
             _ => {
-                return ToolResult {
-                    text: "Error: query is required".to_string(),
-                };
+                return ToolResult::failure(ToolError::InvalidArguments(
+                    "query is required".to_string(),
+                ));
             }
         };
 
         let project_root_path = match &args.project_root_path {
             Some(p) if !p.is_empty() => p.clone(),
             _ => {
-                return ToolResult {
-                    text: "Error: project_root_path is required".to_string(),
-                };
+                return ToolResult::failure(ToolError::InvalidArguments(
+                    "project_root_path is required".to_string(),
+                ));
             }
         };
 
@@ -128,38 +172,48 @@ impl SearchContextTool {
 
         // Validate path exists
         if !project_path.exists() {
-            return ToolResult {
-                text: format!("Error: Project path does not exist: {}", project_root),
-            };
+            return ToolResult::failure(ToolError::PathNotFound(format!(
+                "Project path does not exist: {}",
+                project_root
+            )));
         }
 
         // Validate is directory
         if !project_path.is_dir() {
-            return ToolResult {
-                text: format!("Error: Project path is not a directory: {}", project_root),
-            };
+            return ToolResult::failure(ToolError::PathNotFound(format!(
+                "Project path is not a directory: {}",
+                project_root
+            )));
         }
 
         info!("Executing search_context for: {}", project_root);
 
-        // Create index manager and execute search
-        let manager = match IndexManager::new(self.config.clone(), project_path) {
-            Ok(m) => m,
-            Err(e) => {
-                error!("Failed to create IndexManager: {}", e);
-                return ToolResult {
-                    text: format!("Error: {}", e),
+        let search = match &self.registry {
+            Some(registry) => {
+                if let Err(e) = registry.manager(&project_path) {
+                    error!("Failed to create IndexManager: {}", e);
+                    return ToolResult::failure(ToolError::classify(&e, ToolError::Internal));
+                }
+                registry.search_context(&project_path, &query).await
+            }
+            None => {
+                // Create index manager and execute search
+                let manager = match IndexManager::new(self.config.clone(), project_path) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        error!("Failed to create IndexManager: {}", e);
+                        return ToolResult::failure(ToolError::classify(&e, ToolError::Internal));
+                    }
                 };
+                manager.search_context(&query).await
             }
         };
 
-        match manager.search_context(&query).await {
-            Ok(result) => ToolResult { text: result },
+        match search {
+            Ok(result) => ToolResult::success(result),
             Err(e) => {
                 error!("Search failed: {}", e);
-                ToolResult {
-                    text: format!("Error: {}", e),
-                }
+                ToolResult::failure(ToolError::classify(&e, ToolError::Upstream))
             }
         }
     }
@@ -3,10 +3,21 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Name of the per-project data directory
+pub const ACE_DIR_NAME: &str = ".ace-tool";
+
+/// Index file name inside the `.ace-tool` directory
+pub const INDEX_FILE_NAME: &str = "index.bin";
+
+/// Get the .ace-tool directory path for a project, without creating it
+pub fn ace_dir_path(project_root: &Path) -> PathBuf {
+    project_root.join(ACE_DIR_NAME)
+}
+
 /// Get the .ace-tool directory path for a project
 /// Creates the directory if it doesn't exist
 pub fn get_ace_dir(project_root: &Path) -> PathBuf {
-    let ace_dir = project_root.join(".ace-tool");
+    let ace_dir = ace_dir_path(project_root);
 
     if !ace_dir.exists() {
         if let Err(e) = fs::create_dir_all(&ace_dir) {
@@ -57,12 +68,39 @@ fn gitignore_has_ace_tool(content: &str) -> bool {
             return false;
         }
         let entry = line.split('#').next().unwrap_or(line).trim();
-        entry == ".ace-tool" || entry == ".ace-tool/"
+        entry.strip_suffix('/').unwrap_or(entry) == ACE_DIR_NAME
     })
 }
 
 /// Get index file path
 pub fn get_index_file_path(project_root: &Path) -> PathBuf {
     let ace_dir = get_ace_dir(project_root);
-    ace_dir.join("index.bin")
+    ace_dir.join(INDEX_FILE_NAME)
+}
+
+/// Environment variable overriding the per-user ace-tool config directory
+pub const ENV_CONFIG_DIR: &str = "ACE_TOOL_CONFIG_DIR";
+
+/// Get the per-user ace-tool config directory (not created)
+///
+/// Resolution order: `ACE_TOOL_CONFIG_DIR`, then `%APPDATA%\ace-tool` on Windows,
+/// `$XDG_CONFIG_HOME/ace-tool` or `$HOME/.config/ace-tool` elsewhere.
+pub fn get_user_config_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+
+    if let Some(dir) = non_empty(ENV_CONFIG_DIR) {
+        return Some(PathBuf::from(dir));
+    }
+
+    #[cfg(windows)]
+    {
+        non_empty("APPDATA").map(|d| PathBuf::from(d).join("ace-tool"))
+    }
+    #[cfg(not(windows))]
+    {
+        if let Some(dir) = non_empty("XDG_CONFIG_HOME") {
+            return Some(PathBuf::from(dir).join("ace-tool"));
+        }
+        non_empty("HOME").map(|d| PathBuf::from(d).join(".config").join("ace-tool"))
+    }
 }
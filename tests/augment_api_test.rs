@@ -0,0 +1,131 @@
+//! Tests for Augment NEW/OLD enhancer endpoints
+//! Uses wiremock to mock HTTP responses
+
+use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::service::{
+    call_new_endpoint, call_old_endpoint, ConversationHistory, EnhanceInput, NODE_ID_NEW,
+    NODE_ID_SEARCH_CONTEXT,
+};
+use reqwest::Client;
+use serde_json::Value;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn create_test_client() -> Client {
+    Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+/// Config pointing at the mock server (Config::new forces https)
+fn create_test_config(base_url: String) -> Config {
+    let mut config = (*Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions::default(),
+    )
+    .unwrap())
+    .clone();
+    config.base_url = base_url;
+    config
+}
+
+#[tokio::test]
+async fn test_new_endpoint_sends_single_node_without_search_context() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/prompt-enhancer"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let nodes = body["nodes"].as_array().unwrap();
+            assert_eq!(nodes.len(), 1);
+            assert_eq!(nodes[0]["id"], NODE_ID_NEW);
+            assert_eq!(nodes[0]["text_node"]["content"], "Add login");
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "text": "Enhanced" }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = create_test_config(mock_server.uri());
+
+    let result = call_new_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Add login", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_new_endpoint_sends_search_context_node() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/prompt-enhancer"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let nodes = body["nodes"].as_array().unwrap();
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(nodes[0]["text_node"]["content"], "Add login");
+            assert_eq!(nodes[1]["id"], NODE_ID_SEARCH_CONTEXT);
+            let context = nodes[1]["text_node"]["content"].as_str().unwrap();
+            assert!(context.contains("<codebase_context>\nPath: src/auth.rs\n</codebase_context>"));
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "text": "Enhanced" }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = create_test_config(mock_server.uri());
+    let input = EnhanceInput {
+        search_context: Some("Path: src/auth.rs".to_string()),
+        ..EnhanceInput::new("Add login", ConversationHistory::default())
+    };
+
+    let result = call_new_endpoint(&client, &config, &input).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_old_endpoint_renders_search_context_into_message() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat-stream"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let message = body["message"].as_str().unwrap();
+            assert!(message.contains("Add login"));
+            assert!(message.contains("Context: src/auth.rs"));
+
+            ResponseTemplate::new(200).set_body_string(
+                "{\"text\":\"<augment-enhanced-prompt>Enhanced</augment-enhanced-prompt>\"}\n",
+            )
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = create_test_config(mock_server.uri());
+    let input = EnhanceInput {
+        search_context: Some("src/auth.rs".to_string()),
+        template: ace_tool::enhancer::EnhanceTemplate::parse(
+            "{original_prompt}\nContext: {search_context}",
+        )
+        .unwrap(),
+        ..EnhanceInput::new("Add login", ConversationHistory::default())
+    };
+
+    let result = call_old_endpoint(&client, &config, &input, &[]).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
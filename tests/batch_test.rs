@@ -0,0 +1,215 @@
+//! Tests for batch prompt enhancement
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ace_tool::enhancer::batch::{
+    enhance_batch, parse_batch, read_prompt, resolve_project_root, BatchProvider, BatchRequest,
+    BatchResult,
+};
+use ace_tool::service::ConversationHistory;
+use anyhow::anyhow;
+use futures::StreamExt;
+
+fn provider() -> BatchProvider {
+    BatchProvider {
+        provider: "claude".to_string(),
+        model: Some("claude-sonnet-4-5".to_string()),
+    }
+}
+
+fn request(prompt: &str) -> BatchRequest {
+    BatchRequest {
+        prompt: prompt.to_string(),
+        conversation_history: ConversationHistory::default(),
+        project_root: None,
+        id: None,
+    }
+}
+
+#[test]
+fn test_parse_batch_reads_fields_and_skips_blank_lines() {
+    let input = r#"{"prompt": "add login", "conversation_history": "User: hi", "project_root": "/repo", "id": 7}
+
+{"prompt": "fix bug"}
+"#;
+    let entries = parse_batch(input);
+    assert_eq!(entries.len(), 2);
+
+    let (line, first) = &entries[0];
+    assert_eq!(*line, 1);
+    let first = first.as_ref().unwrap();
+    assert_eq!(first.prompt, "add login");
+    assert_eq!(first.conversation_history, "User: hi".into());
+    assert_eq!(first.project_root, Some(PathBuf::from("/repo")));
+    assert_eq!(first.id, Some(serde_json::json!(7)));
+
+    let (line, second) = &entries[1];
+    assert_eq!(*line, 3);
+    assert_eq!(second.as_ref().unwrap(), &request("fix bug"));
+}
+
+#[test]
+fn test_parse_batch_accepts_structured_history() {
+    let entries = parse_batch(
+        r#"{"prompt": "p", "conversation_history": [{"role": "user", "content": "hi"}, {"role": "assistant", "content": "hello"}]}
+{"prompt": "p", "conversation_history": [{"role": "tool", "content": "x"}]}
+"#,
+    );
+    let request = entries[0].1.as_ref().unwrap();
+    let messages = request.conversation_history.to_messages();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1].role, "assistant");
+
+    assert!(entries[1]
+        .1
+        .as_ref()
+        .unwrap_err()
+        .contains("Unsupported conversation_history role"));
+}
+
+#[test]
+fn test_parse_batch_keeps_invalid_lines_as_errors() {
+    let entries = parse_batch("not json\n{\"prompt\": \"  \"}\n{\"history\": \"x\"}\n");
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0]
+        .1
+        .as_ref()
+        .unwrap_err()
+        .contains("Invalid batch line"));
+    assert_eq!(entries[1].1.as_ref().unwrap_err(), "Prompt is empty");
+    assert!(entries[2].1.as_ref().unwrap_err().contains("prompt"));
+}
+
+#[test]
+fn test_resolve_project_root() {
+    let cwd = Path::new("/work");
+    assert_eq!(
+        resolve_project_root(&request("p"), cwd),
+        PathBuf::from("/work")
+    );
+
+    let mut relative = request("p");
+    relative.project_root = Some(PathBuf::from("sub/project"));
+    assert_eq!(
+        resolve_project_root(&relative, cwd),
+        PathBuf::from("/work/sub/project")
+    );
+
+    let mut absolute = request("p");
+    absolute.project_root = Some(PathBuf::from("/other"));
+    assert_eq!(
+        resolve_project_root(&absolute, cwd),
+        PathBuf::from("/other")
+    );
+}
+
+#[test]
+fn test_read_prompt_from_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("prompt.txt");
+    std::fs::write(&path, "\n  add a login page \n").unwrap();
+    assert_eq!(
+        read_prompt(path.to_str().unwrap()).unwrap(),
+        "add a login page"
+    );
+
+    std::fs::write(&path, "   \n").unwrap();
+    assert!(read_prompt(path.to_str().unwrap())
+        .unwrap_err()
+        .to_string()
+        .contains("No prompt found"));
+
+    assert!(read_prompt(dir.path().join("missing.txt").to_str().unwrap()).is_err());
+}
+
+#[tokio::test]
+async fn test_enhance_batch_keeps_input_order_and_records_results() {
+    let entries = parse_batch(
+        "{\"prompt\": \"slow\", \"id\": \"a\"}\nbad line\n{\"prompt\": \"fail\"}\n{\"prompt\": \"fast\"}\n",
+    );
+    let provider = provider();
+    let results: Vec<BatchResult> = enhance_batch(entries, 4, &provider, |request| async move {
+        match request.prompt.as_str() {
+            "slow" => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok("enhanced slow".to_string())
+            }
+            "fail" => Err(anyhow!("API error")),
+            other => Ok(format!("enhanced {}", other)),
+        }
+    })
+    .collect()
+    .await;
+
+    let lines: Vec<usize> = results.iter().map(|r| r.line).collect();
+    assert_eq!(lines, vec![1, 2, 3, 4]);
+
+    assert_eq!(results[0].id, Some(serde_json::json!("a")));
+    assert_eq!(results[0].prompt, "slow");
+    assert_eq!(results[0].enhanced.as_deref(), Some("enhanced slow"));
+    assert!(results[0].duration_ms >= 90);
+    assert_eq!(results[0].provider, "claude");
+    assert_eq!(results[0].model.as_deref(), Some("claude-sonnet-4-5"));
+
+    assert!(results[1].enhanced.is_none());
+    assert!(results[1]
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("Invalid batch line"));
+
+    assert_eq!(results[2].error.as_deref(), Some("API error"));
+    assert_eq!(results[3].enhanced.as_deref(), Some("enhanced fast"));
+}
+
+#[tokio::test]
+async fn test_enhance_batch_bounds_concurrency() {
+    let input: String = (0..10)
+        .map(|i| format!("{{\"prompt\": \"p{}\"}}\n", i))
+        .collect();
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let provider = provider();
+
+    let results: Vec<BatchResult> = enhance_batch(parse_batch(&input), 3, &provider, |request| {
+        let in_flight = in_flight.clone();
+        let max_in_flight = max_in_flight.clone();
+        async move {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(request.prompt)
+        }
+    })
+    .collect()
+    .await;
+
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|r| r.error.is_none()));
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_batch_result_serializes_as_jsonl_record() {
+    let result = BatchResult {
+        line: 2,
+        id: None,
+        prompt: "p".to_string(),
+        enhanced: Some("e".to_string()),
+        error: None,
+        provider: "new".to_string(),
+        model: None,
+        duration_ms: 12,
+    };
+    let json = serde_json::to_string(&result).unwrap();
+    assert!(!json.contains('\n'));
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["line"], 2);
+    assert_eq!(value["duration_ms"], 12);
+    assert!(value.get("id").is_none());
+    assert!(value["error"].is_null());
+}
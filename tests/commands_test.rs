@@ -0,0 +1,542 @@
+//! Tests for the search, status, clean and ls-files subcommands and the indexing dry run
+
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+
+use ace_tool::commands;
+use ace_tool::config::Config;
+use ace_tool::index::{
+    FileEntry, IndexData, IndexManager, SecretScanSettings, SkipReason, UploadPolicySettings,
+    INDEX_LOCK_FILE_NAME,
+};
+use ace_tool::utils::project_detector::ACE_DIR_NAME;
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn create_project() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+    fs::create_dir_all(root.join("build")).unwrap();
+    fs::write(root.join(".gitignore"), "build/\n*.log\n").unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(
+        root.join("node_modules/pkg/index.js"),
+        "module.exports = 1;\n",
+    )
+    .unwrap();
+    fs::write(root.join("build/out.rs"), "fn out() {}\n").unwrap();
+    fs::write(root.join("debug.log"), "log line\n").unwrap();
+    fs::write(root.join("image.png"), [0u8; 16]).unwrap();
+    fs::write(root.join("data.unknownext"), "data\n").unwrap();
+    fs::write(root.join("big.txt"), "a".repeat(200 * 1024)).unwrap();
+    temp_dir
+}
+
+fn reason_for<'a>(report: &'a commands::LsFilesReport, path: &str) -> Option<&'a SkipReason> {
+    report
+        .files
+        .iter()
+        .find(|entry| entry.path == path)
+        .unwrap_or_else(|| panic!("{} not listed", path))
+        .skipped
+        .as_ref()
+}
+
+// ============================================================================
+// ls-files
+// ============================================================================
+
+#[test]
+fn test_ls_files_reports_reasons() {
+    let project = create_project();
+    let config = Config::new_local(
+        None,
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let report = commands::ls_files(&config, project.path()).unwrap();
+
+    assert_eq!(reason_for(&report, "src/main.rs"), None);
+    assert_eq!(reason_for(&report, ".gitignore"), None);
+    assert!(matches!(
+        reason_for(&report, "build"),
+        Some(SkipReason::Ignored { pattern, .. }) if pattern == "build/"
+    ));
+    assert!(matches!(
+        reason_for(&report, "debug.log"),
+        Some(SkipReason::Ignored { pattern, .. }) if pattern == "*.log"
+    ));
+    assert!(matches!(
+        reason_for(&report, "node_modules"),
+        Some(SkipReason::ExcludePattern { .. })
+    ));
+    assert!(matches!(
+        reason_for(&report, "image.png"),
+        Some(SkipReason::ExcludePattern { .. })
+    ));
+    assert_eq!(
+        reason_for(&report, "data.unknownext"),
+        Some(&SkipReason::UnsupportedType)
+    );
+    assert!(matches!(
+        reason_for(&report, "big.txt"),
+        Some(SkipReason::TooLarge { size }) if *size == 200 * 1024
+    ));
+
+    // Excluded directories are listed once, without their contents
+    assert!(report.files.iter().all(|e| !e.path.starts_with("build/")));
+    assert!(report
+        .files
+        .iter()
+        .all(|e| !e.path.starts_with("node_modules/")));
+    assert_eq!(report.included, 2);
+    assert_eq!(report.excluded, report.files.len() - 2);
+
+    // Listing never creates the data directory
+    assert!(!project.path().join(ACE_DIR_NAME).exists());
+}
+
+#[test]
+fn test_ls_files_json_shape() {
+    let project = create_project();
+    let config = Config::new_local(
+        None,
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let report =
+        serde_json::to_value(commands::ls_files(&config, project.path()).unwrap()).unwrap();
+
+    assert_eq!(report["included"], 2);
+    let files = report["files"].as_array().unwrap();
+    let build = files.iter().find(|f| f["path"] == "build").unwrap();
+    assert_eq!(build["is_dir"], true);
+    assert_eq!(build["included"], false);
+    assert_eq!(build["skipped"]["reason"], "ignored");
+    assert_eq!(build["skipped"]["pattern"], "build/");
+
+    let main = files.iter().find(|f| f["path"] == "src/main.rs").unwrap();
+    assert_eq!(main["included"], true);
+    assert!(main.get("skipped").is_none());
+
+    let big = files.iter().find(|f| f["path"] == "big.txt").unwrap();
+    assert_eq!(big["skipped"]["reason"], "too_large");
+    assert_eq!(big["skipped"]["size"], 200 * 1024);
+}
+
+// ============================================================================
+// status
+// ============================================================================
+
+#[test]
+fn test_status_without_index() {
+    let project = create_project();
+    let config = Config::new_local(
+        None,
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let report = commands::status(&config, project.path()).unwrap();
+
+    assert!(!report.indexed);
+    assert!(report.index.is_none());
+    assert!(report.to_string().contains("Not indexed yet"));
+    assert!(!project.path().join(ACE_DIR_NAME).exists());
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["indexed"], false);
+    assert!(json.get("index").is_none());
+}
+
+#[test]
+fn test_status_with_index() {
+    let project = create_project();
+    let config = Config::new_local(
+        None,
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let manager = IndexManager::new(config.clone(), project.path().to_path_buf()).unwrap();
+
+    let mut entries = HashMap::new();
+    entries.insert(
+        "src/main.rs".to_string(),
+        FileEntry {
+            mtime_secs: 1_700_000_000,
+            mtime_nanos: 0,
+            size: 13,
+            blob_hashes: vec!["a".to_string()],
+        },
+    );
+    entries.insert(
+        "src/lib.rs".to_string(),
+        FileEntry {
+            mtime_secs: 1_700_000_100,
+            mtime_nanos: 0,
+            size: 4000,
+            blob_hashes: vec!["b".to_string(), "c".to_string()],
+        },
+    );
+    manager
+        .save_index(&IndexData {
+            version: 2,
+            config_hash: manager.config_hash().to_string(),
+            entries,
+        })
+        .unwrap();
+
+    let report = commands::status(&config, project.path()).unwrap();
+    assert!(report.indexed);
+    let index = report.index.as_ref().unwrap();
+    assert_eq!(index.files, 2);
+    assert_eq!(index.blobs, 3);
+    assert_eq!(index.chunked_files, 1);
+    assert_eq!(index.total_bytes, 4013);
+    assert_eq!(index.last_modified_secs, Some(1_700_000_100));
+    assert_eq!(index.extensions.get("rs"), Some(&2));
+
+    let text = report.to_string();
+    assert!(
+        text.contains("Indexed files: 2 (3 blobs, 1 chunked files)"),
+        "{}",
+        text
+    );
+    assert!(text.contains("Extensions: rs 2"), "{}", text);
+}
+
+// ============================================================================
+// clean
+// ============================================================================
+
+fn populate_ace_dir(project: &TempDir) {
+    let ace_dir = project.path().join(ACE_DIR_NAME);
+    fs::create_dir_all(ace_dir.join("prompts")).unwrap();
+    fs::create_dir_all(ace_dir.join("sessions")).unwrap();
+    fs::write(ace_dir.join("sessions/pending.json"), "{}").unwrap();
+    fs::write(ace_dir.join("index.bin"), "index").unwrap();
+    fs::write(ace_dir.join("prompts/review.md"), "Review").unwrap();
+    fs::write(ace_dir.join("enhance_template.md"), "template").unwrap();
+}
+
+#[test]
+fn test_clean_keeps_hand_written_files_and_sessions() {
+    let project = create_project();
+    populate_ace_dir(&project);
+    let ace_dir = project.path().join(ACE_DIR_NAME);
+
+    let report = commands::clean(project.path(), false).unwrap();
+    assert_eq!(report.removed, vec![".ace-tool/index.bin"]);
+    assert_eq!(
+        report.kept,
+        vec![
+            ".ace-tool/enhance_template.md",
+            ".ace-tool/prompts",
+            ".ace-tool/sessions"
+        ]
+    );
+    assert!(!ace_dir.join("index.bin").exists());
+    assert!(ace_dir.join("prompts/review.md").exists());
+    assert!(ace_dir.join("enhance_template.md").exists());
+    assert!(ace_dir.join("sessions/pending.json").exists());
+
+    // A second run has nothing left to remove
+    let report = commands::clean(project.path(), false).unwrap();
+    assert!(report.removed.is_empty());
+    assert!(report.to_string().starts_with("Nothing to clean"));
+}
+
+#[test]
+fn test_clean_all_removes_everything_but_the_lock() {
+    let project = create_project();
+    populate_ace_dir(&project);
+    let ace_dir = project.path().join(ACE_DIR_NAME);
+
+    let report = commands::clean(project.path(), true).unwrap();
+    assert!(report.kept.is_empty());
+    assert!(report.removed.contains(&".ace-tool/sessions".to_string()));
+    let remaining: Vec<_> = fs::read_dir(&ace_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(remaining, vec![INDEX_LOCK_FILE_NAME]);
+
+    // Other project files are untouched
+    assert!(project.path().join("src/main.rs").exists());
+    assert!(commands::clean(project.path(), true)
+        .unwrap()
+        .removed
+        .is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_clean_never_unlinks_the_index_lock() {
+    use std::os::unix::fs::MetadataExt;
+
+    let project = create_project();
+    populate_ace_dir(&project);
+    let lock_path = project.path().join(ACE_DIR_NAME).join(INDEX_LOCK_FILE_NAME);
+    fs::write(&lock_path, "").unwrap();
+    let inode = fs::metadata(&lock_path).unwrap().ino();
+
+    for all in [false, true] {
+        let report = commands::clean(project.path(), all).unwrap();
+        assert!(!report
+            .removed
+            .iter()
+            .any(|path| path.ends_with(INDEX_LOCK_FILE_NAME)));
+        assert_eq!(fs::metadata(&lock_path).unwrap().ino(), inode);
+    }
+}
+
+#[test]
+fn test_clean_refuses_while_the_index_lock_is_held() {
+    let project = create_project();
+    populate_ace_dir(&project);
+    let ace_dir = project.path().join(ACE_DIR_NAME);
+
+    let held = IndexManager::try_lock_index(&ace_dir.join("index.bin"))
+        .unwrap()
+        .unwrap();
+    let err = commands::clean(project.path(), true).unwrap_err();
+    assert!(
+        err.to_string().contains("another ace-tool process"),
+        "{}",
+        err
+    );
+    assert!(ace_dir.join("index.bin").exists());
+    assert!(ace_dir.join("sessions/pending.json").exists());
+
+    drop(held);
+    assert!(commands::clean(project.path(), false).is_ok());
+    assert!(!ace_dir.join("index.bin").exists());
+}
+
+// ============================================================================
+// Dry run
+// ============================================================================
+
+fn dry_run_entry<'a>(
+    report: &'a commands::DryRunReport,
+    path: &str,
+) -> &'a ace_tool::index::DryRunEntry {
+    report
+        .files
+        .iter()
+        .find(|entry| entry.path == path)
+        .unwrap_or_else(|| panic!("{} not listed", path))
+}
+
+#[test]
+fn test_dry_run_reports_processing_reasons() {
+    let project = create_project();
+    let root = project.path();
+    fs::write(root.join("src/long.rs"), "let x = 1;\n".repeat(2000)).unwrap();
+    fs::write(root.join("src/blob.rs"), [0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+    fs::write(root.join("src/latin.txt"), b"caf\xe9 na\xefve\n").unwrap();
+    // 100KB of GBK on disk grows to 150KB once decoded to UTF-8
+    let gbk_line = [0xD6u8, 0xD0].repeat(50);
+    let gbk: Vec<u8> = (0..1000)
+        .flat_map(|_| gbk_line.iter().copied().chain([b'\n']))
+        .collect();
+    fs::write(root.join("src/wide.txt"), &gbk).unwrap();
+
+    let config = Config::new_local(
+        None,
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let report = commands::index_dry_run(&config, root).unwrap();
+
+    let main = dry_run_entry(&report, "src/main.rs");
+    assert!(main.included);
+    assert_eq!(main.chunks, 1);
+    assert_eq!(main.bytes, 13);
+    assert_eq!(main.encoding, Some("UTF-8"));
+
+    let long = dry_run_entry(&report, "src/long.rs");
+    assert_eq!(long.chunks, 3);
+
+    let latin = dry_run_entry(&report, "src/latin.txt");
+    assert!(latin.included);
+    assert_eq!(latin.encoding, Some("windows-1252"));
+
+    assert_eq!(
+        dry_run_entry(&report, "src/blob.rs").skipped,
+        Some(SkipReason::Binary)
+    );
+    assert!(matches!(
+        dry_run_entry(&report, "src/wide.txt").skipped,
+        Some(SkipReason::TooLarge { size }) if size > 128 * 1024 && size > gbk.len() as u64
+    ));
+    assert!(matches!(
+        dry_run_entry(&report, "big.txt").skipped,
+        Some(SkipReason::TooLarge { size }) if size == 200 * 1024
+    ));
+
+    // Paths excluded before reading keep their reasons
+    assert!(matches!(
+        dry_run_entry(&report, "build").skipped,
+        Some(SkipReason::Ignored { .. })
+    ));
+    assert_eq!(
+        dry_run_entry(&report, "data.unknownext").skipped,
+        Some(SkipReason::UnsupportedType)
+    );
+
+    assert_eq!(report.included, 4);
+    assert_eq!(report.chunks, 1 + 1 + 3 + 1);
+    assert_eq!(report.by_reason.get("binary"), Some(&1));
+    assert_eq!(report.by_reason.get("too_large"), Some(&2));
+    assert_eq!(report.by_reason.get("ignored"), Some(&2));
+    assert_eq!(report.by_reason.values().sum::<usize>(), report.skipped);
+
+    // Nothing is uploaded or written
+    assert!(!root.join(ACE_DIR_NAME).exists());
+}
+
+#[test]
+fn test_dry_run_respects_max_lines_per_blob() {
+    let project = create_project();
+    fs::write(project.path().join("src/long.rs"), "x\n".repeat(250)).unwrap();
+
+    let config = Config::new_local(
+        Some(100),
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let report = commands::index_dry_run(&config, project.path()).unwrap();
+    assert_eq!(dry_run_entry(&report, "src/long.rs").chunks, 3);
+}
+
+#[test]
+fn test_dry_run_csv_export() {
+    let project = create_project();
+    fs::write(project.path().join("src/a,b.rs"), "fn a() {}\n").unwrap();
+
+    let config = Config::new_local(
+        None,
+        SecretScanSettings::default(),
+        UploadPolicySettings::default(),
+    );
+    let csv = commands::index_dry_run(&config, project.path())
+        .unwrap()
+        .to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "path,included,chunks,bytes,encoding,redacted,reason,detail"
+    );
+    assert!(lines.contains(&"src/main.rs,true,1,13,UTF-8,0,,"));
+    assert!(lines.contains(&"\"src/a,b.rs\",true,1,10,UTF-8,0,,"));
+    assert!(lines.contains(&"build/,false,0,0,,0,ignored,ignored by .gitignore rule 'build/'"));
+    assert!(lines.contains(&"big.txt,false,0,0,,0,too_large,\"too large (200KB, limit 128KB)\""));
+}
+
+// ============================================================================
+// search
+// ============================================================================
+
+#[tokio::test]
+async fn test_search_reports_tool_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("missing");
+    let config = Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        Default::default(),
+    )
+    .unwrap();
+
+    let report = commands::search(config, &missing, "where is main").await;
+    assert!(report.context.is_none());
+    let error = report.error.as_ref().unwrap();
+    assert_eq!(error.code, "path_not_found");
+    assert!(!error.retryable);
+    assert!(report.to_string().starts_with("Error: "));
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["query"], "where is main");
+    assert_eq!(json["error"]["code"], "path_not_found");
+    assert!(json.get("context").is_none());
+}
+
+// ============================================================================
+// Command line
+// ============================================================================
+
+fn run_cli(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_ace-tool-rs"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli_ls_files_json_with_project_root() {
+    let project = create_project();
+    let root = project.path().to_str().unwrap();
+    let output = run_cli(&["ls-files", "--json", "--project-root", root]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["included"], 2);
+    let expected = project.path().canonicalize().unwrap();
+    assert_eq!(
+        report["project_root"],
+        expected.to_string_lossy().replace('\\', "/")
+    );
+}
+
+#[test]
+fn test_cli_status_needs_no_credentials() {
+    let project = create_project();
+    let root = project.path().to_str().unwrap();
+    let output = run_cli(&["--project-root", root, "--json", "status"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["indexed"], false);
+    assert!(!project.path().join(ACE_DIR_NAME).exists());
+}
+
+#[test]
+fn test_cli_dry_run_needs_no_credentials() {
+    let project = create_project();
+    let root = project.path().to_str().unwrap();
+    let output = run_cli(&[
+        "--index-only",
+        "--dry-run",
+        "--json",
+        "--project-root",
+        root,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["included"], 2);
+    assert_eq!(report["by_reason"]["too_large"], 1);
+    assert!(!project.path().join(ACE_DIR_NAME).exists());
+
+    let output = run_cli(&["--index-only", "--dry-run", "--csv", "--project-root", root]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("path,included,"));
+
+    // --dry-run only applies to --index-only
+    assert!(!run_cli(&["--dry-run", "--project-root", root])
+        .status
+        .success());
+}
+
+#[test]
+fn test_cli_rejects_missing_project_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("missing");
+    let output = run_cli(&["status", "--project-root", missing.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--project-root"));
+}
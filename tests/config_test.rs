@@ -77,6 +77,12 @@ fn test_config_with_custom_values() {
             no_webbrowser_enhance_prompt: true,
             force_xdg_open: false,
             webui_addr: None,
+            webui_tls_cert: None,
+            webui_tls_key: None,
+            webui_timeout: Some(600),
+            secret_scan: None,
+            upload_policy: None,
+            generation: None,
         },
     )
     .unwrap();
@@ -86,6 +92,7 @@ fn test_config_with_custom_values() {
     assert!(config.no_webbrowser_enhance_prompt);
     assert_eq!(config.cli_overrides.upload_timeout_secs, Some(60));
     assert_eq!(config.cli_overrides.upload_concurrency, Some(4));
+    assert_eq!(config.webui_timeout_secs, 600);
 }
 
 #[test]
@@ -249,3 +256,16 @@ fn test_default_exclude_patterns_contains_common_dirs() {
     assert!(patterns.contains(&"__pycache__".to_string()));
     assert!(patterns.contains(&".ace-tool".to_string()));
 }
+
+#[test]
+fn test_config_requires_tls_cert_and_key_together() {
+    let result = Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions {
+            webui_tls_cert: Some("cert.pem".to_string()),
+            ..Default::default()
+        },
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,119 @@
+//! Tests for terminal review via $EDITOR
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ace_tool::enhancer::editor_review::{resolve_editor, review_in_editor};
+
+/// Serializes tests that modify editor environment variables
+static ENV_MUTEX: Mutex<()> = Mutex::new(());
+
+/// Fake editor: runs `script` with the review file as `$1`
+#[cfg(unix)]
+fn shell_editor(script: &str) -> Vec<String> {
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        script.to_string(),
+        "sh".to_string(),
+    ]
+}
+
+#[test]
+fn test_resolve_editor_prefers_visual() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    std::env::set_var("VISUAL", "code --wait");
+    std::env::set_var("EDITOR", "nano");
+    assert_eq!(resolve_editor(), vec!["code", "--wait"]);
+
+    std::env::remove_var("VISUAL");
+    assert_eq!(resolve_editor(), vec!["nano"]);
+
+    std::env::set_var("EDITOR", "   ");
+    assert!(!resolve_editor().is_empty());
+    std::env::remove_var("EDITOR");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_returns_saved_text() {
+    let editor = shell_editor(r#"printf 'edited prompt\n' > "$1""#);
+    let result = review_in_editor(&editor, "enhanced prompt", Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(result, "edited prompt");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_keeps_unchanged_text() {
+    let editor = shell_editor("true");
+    let result = review_in_editor(&editor, "enhanced prompt", Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(result, "enhanced prompt");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_empty_file_cancels() {
+    let editor = shell_editor(r#": > "$1""#);
+    let err = review_in_editor(&editor, "enhanced prompt", Duration::from_secs(10))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("cancelled"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_editor_failure_cancels() {
+    let editor = shell_editor("exit 1");
+    let err = review_in_editor(&editor, "enhanced prompt", Duration::from_secs(10))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("review cancelled"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_timeout_keeps_enhanced_prompt() {
+    let editor = shell_editor("sleep 5");
+    let result = review_in_editor(&editor, "enhanced prompt", Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert!(result.starts_with("enhanced prompt\n\n---\n"));
+    assert!(result.contains("timed out"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_timeout_keeps_last_saved_text() {
+    let editor = shell_editor(r#"printf 'half edited\n' > "$1"; sleep 5"#);
+    let result = review_in_editor(&editor, "enhanced prompt", Duration::from_millis(500))
+        .await
+        .unwrap();
+    assert!(result.starts_with("half edited\n\n---\n"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_review_file_is_private() {
+    let editor = shell_editor(
+        r#"mode() { stat -c '%a' "$1" 2>/dev/null || stat -f '%Lp' "$1"; }
+           printf '%s\n%s' "$(mode "$1")" "$(mode "$(dirname "$1")")" > "$1.mode"
+           mv "$1.mode" "$1""#,
+    );
+    let result = review_in_editor(&editor, "enhanced prompt", Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(result, "600\n700");
+}
+
+#[tokio::test]
+async fn test_review_missing_editor() {
+    let editor = vec!["ace-tool-no-such-editor".to_string()];
+    let err = review_in_editor(&editor, "enhanced prompt", Duration::from_secs(1))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Failed to start editor"));
+}
@@ -0,0 +1,393 @@
+//! Tests for the on-disk enhancement cache
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::enhancer::cache::{
+    CacheEntry, CacheKeyInput, CacheStatus, EnhanceCache, CACHE_DIR_NAME,
+};
+use ace_tool::enhancer::prompt_enhancer::ENV_ENHANCER_CONTEXT_TOKENS;
+use ace_tool::enhancer::{EnhanceTemplate, ProjectGuidelines, PromptEnhancer};
+use ace_tool::index::{
+    calculate_config_hash, FileEntry, IndexData, IndexManager, SecretScanSettings,
+};
+use ace_tool::service::{ChatMessage, ConversationHistory, GenerationSettings};
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn cache_in(dir: &TempDir, ttl: Duration, max_bytes: u64) -> EnhanceCache {
+    EnhanceCache::new(dir.path().join(CACHE_DIR_NAME), ttl, max_bytes)
+}
+
+fn entry(key: &str, prompt: &str) -> CacheEntry {
+    CacheEntry::new(
+        key.to_string(),
+        prompt.to_string(),
+        "claude".to_string(),
+        Some("claude-sonnet-4-5".to_string()),
+    )
+}
+
+#[test]
+fn test_cache_key_changes_with_every_input() {
+    let guidelines = ProjectGuidelines::default();
+    let blobs = vec!["blob-a".to_string()];
+    let template = EnhanceTemplate::builtin().fingerprint();
+    let index_hash = calculate_config_hash(800, &SecretScanSettings::default());
+    let history = ConversationHistory::from("User: hi");
+    let base = CacheKeyInput {
+        prompt: "add login",
+        conversation_history: &history,
+        endpoint: "claude",
+        base_url: "https://api.example.com",
+        model: "claude-sonnet-4-5",
+        generation: None,
+        template: &template,
+        guidelines: &guidelines,
+        index_config_hash: &index_hash,
+        blob_names: &blobs,
+        include_search_context: false,
+        budget_tokens: 100_000,
+        search_budget: 4_000,
+    };
+    let key = base.key();
+    assert_eq!(key.len(), 64);
+    assert_eq!(key, base.key());
+
+    let other_guidelines = ProjectGuidelines {
+        workspace: "Use tabs".to_string(),
+        ..Default::default()
+    };
+    let other_blobs = vec!["blob-a".to_string(), "blob-b".to_string()];
+    let other_index_hash = calculate_config_hash(400, &SecretScanSettings::default());
+    let empty_history = ConversationHistory::default();
+    let structured_history = ConversationHistory::Messages(vec![ChatMessage {
+        role: "user".to_string(),
+        content: "hi".to_string(),
+    }]);
+    let generation = GenerationSettings {
+        temperature: Some(0.2),
+        ..Default::default()
+    };
+    let other_generation = GenerationSettings {
+        reasoning_effort: Some("high".to_string()),
+        ..generation.clone()
+    };
+    let variants = [
+        CacheKeyInput {
+            prompt: "add logout",
+            ..base
+        },
+        CacheKeyInput {
+            conversation_history: &empty_history,
+            ..base
+        },
+        CacheKeyInput {
+            conversation_history: &structured_history,
+            ..base
+        },
+        CacheKeyInput {
+            endpoint: "openai",
+            ..base
+        },
+        CacheKeyInput {
+            base_url: "https://proxy.example.com",
+            ..base
+        },
+        CacheKeyInput {
+            model: "claude-opus-4-1",
+            ..base
+        },
+        CacheKeyInput {
+            generation: Some(&generation),
+            ..base
+        },
+        CacheKeyInput {
+            generation: Some(&other_generation),
+            ..base
+        },
+        CacheKeyInput {
+            template: "custom",
+            ..base
+        },
+        CacheKeyInput {
+            guidelines: &other_guidelines,
+            ..base
+        },
+        CacheKeyInput {
+            index_config_hash: &other_index_hash,
+            ..base
+        },
+        CacheKeyInput {
+            blob_names: &other_blobs,
+            ..base
+        },
+        CacheKeyInput {
+            include_search_context: true,
+            ..base
+        },
+        CacheKeyInput {
+            budget_tokens: 50_000,
+            ..base
+        },
+        CacheKeyInput {
+            search_budget: 2_000,
+            ..base
+        },
+    ];
+    for variant in variants {
+        assert_ne!(variant.key(), key, "{:?}", variant);
+    }
+}
+
+#[test]
+fn test_cache_key_is_not_ambiguous_across_fields() {
+    let guidelines = ProjectGuidelines::default();
+    let history = ConversationHistory::from("c");
+    let shifted_history = ConversationHistory::from("bc");
+    let base = CacheKeyInput {
+        prompt: "ab",
+        conversation_history: &history,
+        endpoint: "new",
+        base_url: "",
+        model: "",
+        generation: None,
+        template: "",
+        guidelines: &guidelines,
+        index_config_hash: "",
+        blob_names: &[],
+        include_search_context: false,
+        budget_tokens: 0,
+        search_budget: 0,
+    };
+    let shifted = CacheKeyInput {
+        prompt: "a",
+        conversation_history: &shifted_history,
+        ..base
+    };
+    assert_ne!(base.key(), shifted.key());
+}
+
+#[test]
+fn test_template_fingerprint_tracks_content() {
+    let builtin = EnhanceTemplate::builtin();
+    assert_eq!(
+        builtin.fingerprint(),
+        EnhanceTemplate::builtin().fingerprint()
+    );
+
+    let custom = EnhanceTemplate::parse("Improve: {original_prompt}").unwrap();
+    let literal = EnhanceTemplate::parse("Improve: {{original_prompt}} {original_prompt}").unwrap();
+    assert_ne!(custom.fingerprint(), builtin.fingerprint());
+    assert_ne!(custom.fingerprint(), literal.fingerprint());
+}
+
+#[test]
+fn test_cache_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let cache = cache_in(&dir, Duration::from_secs(60), 1024 * 1024);
+    assert!(cache.get("k1").is_none());
+
+    let stored = entry("k1", "enhanced login");
+    cache.put(&stored).unwrap();
+    assert!(cache.dir().join("k1.json").is_file());
+
+    let hit = cache.get("k1").unwrap();
+    assert_eq!(hit.entry, stored);
+    assert!(hit.age < Duration::from_secs(5));
+    assert!(cache.get("k2").is_none());
+}
+
+#[test]
+fn test_cache_expired_entry_is_removed() {
+    let dir = TempDir::new().unwrap();
+    let cache = cache_in(&dir, Duration::from_secs(60), 1024 * 1024);
+
+    let mut old = entry("old", "stale");
+    old.created_at -= 61_000;
+    cache.put(&old).unwrap();
+
+    assert!(cache.get("old").is_none());
+    assert!(!cache.dir().join("old.json").exists());
+}
+
+#[test]
+fn test_cache_invalid_entry_is_removed() {
+    let dir = TempDir::new().unwrap();
+    let cache = cache_in(&dir, Duration::from_secs(60), 1024 * 1024);
+    cache.put(&entry("k1", "x")).unwrap();
+
+    fs::write(cache.dir().join("k1.json"), "not json").unwrap();
+    assert!(cache.get("k1").is_none());
+    assert!(!cache.dir().join("k1.json").exists());
+
+    // An entry stored under another key's file name is not served
+    cache.put(&entry("k2", "x")).unwrap();
+    fs::rename(cache.dir().join("k2.json"), cache.dir().join("k3.json")).unwrap();
+    assert!(cache.get("k3").is_none());
+}
+
+#[test]
+fn test_cache_prunes_oldest_entries_over_size_limit() {
+    let dir = TempDir::new().unwrap();
+    let probe = cache_in(&dir, Duration::from_secs(60), u64::MAX);
+    probe.put(&entry("probe", &"x".repeat(100))).unwrap();
+    let entry_size = fs::metadata(probe.dir().join("probe.json")).unwrap().len();
+    fs::remove_file(probe.dir().join("probe.json")).unwrap();
+
+    let cache = cache_in(&dir, Duration::from_secs(60), entry_size * 2);
+    for key in ["a1", "a2", "a3"] {
+        cache.put(&entry(key, &"x".repeat(100))).unwrap();
+        // Keep modification times distinct so eviction order is deterministic
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    assert!(cache.get("a1").is_none());
+    assert!(cache.get("a2").is_some());
+    assert!(cache.get("a3").is_some());
+}
+
+#[test]
+fn test_cache_status_serialization() {
+    let hit = CacheStatus::Hit {
+        key: "abc".to_string(),
+        age_secs: 12,
+    };
+    assert_eq!(
+        serde_json::to_value(&hit).unwrap(),
+        serde_json::json!({ "status": "hit", "key": "abc", "age_secs": 12 })
+    );
+    assert_eq!(
+        serde_json::to_value(CacheStatus::Bypassed).unwrap(),
+        serde_json::json!({ "status": "bypassed" })
+    );
+}
+
+/// Serializes the tests that enhance through the cache, since they read budget env vars
+static ENV_MUTEX: Mutex<()> = Mutex::const_new(());
+
+/// Config whose `/prompt-enhancer` requests go to the mock server
+fn mock_config(mock_server: &MockServer) -> Arc<Config> {
+    // Config::new forces https; point the copy at the mock server
+    let mut config = (*Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions::default(),
+    )
+    .unwrap())
+    .clone();
+    config.base_url = mock_server.uri();
+    Arc::new(config)
+}
+
+/// Write a real `index.bin` whose single file has the given blob hashes
+fn save_index(config: &Arc<Config>, project: &TempDir, blob_hashes: &[&str]) {
+    let manager = IndexManager::new(config.clone(), project.path().to_path_buf()).unwrap();
+    let mut entries = HashMap::new();
+    entries.insert(
+        "src/main.rs".to_string(),
+        FileEntry {
+            mtime_secs: 1_700_000_000,
+            mtime_nanos: 0,
+            size: 13,
+            blob_hashes: blob_hashes.iter().map(|h| h.to_string()).collect(),
+        },
+    );
+    manager
+        .save_index(&IndexData {
+            version: 2,
+            config_hash: manager.config_hash().to_string(),
+            entries,
+        })
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_rebuilt_index_misses_the_cache() {
+    let _guard = ENV_MUTEX.lock().await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/prompt-enhancer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "text": "Enhanced"
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config = mock_config(&mock_server);
+    let project = TempDir::new().unwrap();
+    save_index(&config, &project, &["blob-a"]);
+
+    let enhancer = PromptEnhancer::new(config.clone()).unwrap();
+    let history = ConversationHistory::default();
+    let enhance = || async {
+        enhancer
+            .enhance_simple("add login", &history, Some(project.path()))
+            .await
+            .unwrap();
+        enhancer.cache_status().unwrap()
+    };
+
+    assert!(matches!(enhance().await, CacheStatus::Miss { .. }));
+    let CacheStatus::Hit { key, .. } = enhance().await else {
+        panic!("unchanged index should hit the cache");
+    };
+
+    save_index(&config, &project, &["blob-a", "blob-b"]);
+    let CacheStatus::Miss { key: rebuilt_key } = enhance().await else {
+        panic!("rebuilt index should miss the cache");
+    };
+    assert_ne!(key, rebuilt_key);
+}
+
+#[tokio::test]
+async fn test_changed_token_budget_misses_the_cache() {
+    let _guard = ENV_MUTEX.lock().await;
+    let original = std::env::var(ENV_ENHANCER_CONTEXT_TOKENS).ok();
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/prompt-enhancer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "text": "Enhanced"
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config = mock_config(&mock_server);
+    let project = TempDir::new().unwrap();
+    save_index(&config, &project, &["blob-a"]);
+
+    let enhancer = PromptEnhancer::new(config).unwrap();
+    let history = ConversationHistory::from("User: earlier request\nAssistant: earlier answer");
+    let enhance = || async {
+        enhancer
+            .enhance_simple("add login", &history, Some(project.path()))
+            .await
+            .unwrap();
+        enhancer.cache_status().unwrap()
+    };
+
+    std::env::set_var(ENV_ENHANCER_CONTEXT_TOKENS, "100000");
+    let first = enhance().await;
+    let hit = enhance().await;
+    std::env::set_var(ENV_ENHANCER_CONTEXT_TOKENS, "50000");
+    let resized = enhance().await;
+    match original {
+        Some(value) => std::env::set_var(ENV_ENHANCER_CONTEXT_TOKENS, value),
+        None => std::env::remove_var(ENV_ENHANCER_CONTEXT_TOKENS),
+    }
+
+    assert!(matches!(first, CacheStatus::Miss { .. }));
+    assert!(matches!(hit, CacheStatus::Hit { .. }));
+    assert!(
+        matches!(resized, CacheStatus::Miss { .. }),
+        "a smaller context window should miss the cache"
+    );
+}
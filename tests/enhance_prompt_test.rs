@@ -72,13 +72,23 @@ fn test_get_input_schema_has_prompt() {
 #[test]
 fn test_get_input_schema_has_conversation_history() {
     let schema = EnhancePromptToolDef::get_input_schema();
-    assert!(schema["properties"]["conversation_history"].is_object());
+    let history = &schema["properties"]["conversation_history"];
+    assert!(history.is_object());
+    assert_eq!(history["type"], serde_json::json!(["string", "array"]));
     assert_eq!(
-        schema["properties"]["conversation_history"]["type"],
-        "string"
+        history["items"]["required"],
+        serde_json::json!(["role", "content"])
     );
 }
 
+#[test]
+fn test_get_input_schema_has_optional_no_cache() {
+    let schema = EnhancePromptToolDef::get_input_schema();
+    assert_eq!(schema["properties"]["no_cache"]["type"], "boolean");
+    let required = schema["required"].as_array().unwrap();
+    assert!(!required.iter().any(|v| v == "no_cache"));
+}
+
 #[test]
 fn test_get_input_schema_required_fields() {
     let schema = EnhancePromptToolDef::get_input_schema();
@@ -111,7 +121,8 @@ fn test_enhance_prompt_args_serialization() {
     let args = EnhancePromptArgs {
         project_root_path: Some("/path/to/project".to_string()),
         prompt: Some("Add login feature".to_string()),
-        conversation_history: Some("User: Hello\nAssistant: Hi".to_string()),
+        conversation_history: Some("User: Hello\nAssistant: Hi".into()),
+        no_cache: None,
     };
 
     let json = serde_json::to_string(&args).unwrap();
@@ -131,7 +142,44 @@ fn test_enhance_prompt_args_deserialization() {
     let args: EnhancePromptArgs = serde_json::from_str(json).unwrap();
     assert_eq!(args.project_root_path, Some("/test/path".to_string()));
     assert_eq!(args.prompt, Some("test prompt".to_string()));
-    assert_eq!(args.conversation_history, Some("User: test".to_string()));
+    assert_eq!(args.conversation_history, Some("User: test".into()));
+}
+
+#[test]
+fn test_enhance_prompt_args_no_cache() {
+    let args: EnhancePromptArgs =
+        serde_json::from_str(r#"{"prompt": "p", "no_cache": true}"#).unwrap();
+    assert_eq!(args.no_cache, Some(true));
+
+    let json = serde_json::to_string(&EnhancePromptArgs::default()).unwrap();
+    assert!(!json.contains("no_cache"));
+}
+
+#[test]
+fn test_enhance_prompt_args_structured_history() {
+    let json = r#"{
+        "prompt": "fix it",
+        "conversation_history": [
+            {"role": "user", "content": "User: not a prefix"},
+            {"role": "AI", "content": "ok"}
+        ]
+    }"#;
+    let args: EnhancePromptArgs = serde_json::from_str(json).unwrap();
+    let history = args.conversation_history.unwrap();
+
+    let messages = history.to_messages();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].content, "User: not a prefix");
+    assert_eq!(messages[1].role, "assistant");
+}
+
+#[test]
+fn test_enhance_prompt_args_rejects_unknown_history_role() {
+    let json = r#"{"prompt": "p", "conversation_history": [{"role": "tool", "content": "x"}]}"#;
+    let err = serde_json::from_str::<EnhancePromptArgs>(json).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Unsupported conversation_history role"));
 }
 
 #[test]
@@ -160,7 +208,8 @@ fn test_enhance_prompt_args_with_unicode() {
     let args = EnhancePromptArgs {
         project_root_path: Some("/路径/项目".to_string()),
         prompt: Some("添加登录功能".to_string()),
-        conversation_history: Some("用户: 你好\n助手: 你好！".to_string()),
+        conversation_history: Some("用户: 你好\n助手: 你好！".into()),
+        no_cache: None,
     };
 
     let json = serde_json::to_string(&args).unwrap();
@@ -176,7 +225,8 @@ fn test_enhance_prompt_args_with_special_characters() {
     let args = EnhancePromptArgs {
         project_root_path: Some("/path/with spaces/project".to_string()),
         prompt: Some("Add feature with \"quotes\" and 'apostrophes'".to_string()),
-        conversation_history: Some("User: Line1\nLine2\tTabbed".to_string()),
+        conversation_history: Some("User: Line1\nLine2\tTabbed".into()),
+        no_cache: None,
     };
 
     let json = serde_json::to_string(&args).unwrap();
@@ -190,7 +240,8 @@ fn test_enhance_prompt_args_clone() {
     let args = EnhancePromptArgs {
         project_root_path: Some("/test".to_string()),
         prompt: Some("test".to_string()),
-        conversation_history: Some("history".to_string()),
+        conversation_history: Some("history".into()),
+        no_cache: None,
     };
 
     let cloned = args.clone();
@@ -205,6 +256,7 @@ fn test_enhance_prompt_args_debug() {
         project_root_path: Some("/test".to_string()),
         prompt: Some("test".to_string()),
         conversation_history: None,
+        no_cache: None,
     };
 
     let debug_str = format!("{:?}", args);
@@ -222,6 +274,7 @@ fn test_enhance_prompt_args_with_windows_path() {
         project_root_path: Some("C:\\Users\\test\\project".to_string()),
         prompt: Some("test".to_string()),
         conversation_history: None,
+        no_cache: None,
     };
 
     let json = serde_json::to_string(&args).unwrap();
@@ -239,6 +292,7 @@ fn test_enhance_prompt_args_with_mixed_path_separators() {
         project_root_path: Some("C:/Users\\test/project".to_string()),
         prompt: Some("test".to_string()),
         conversation_history: None,
+        no_cache: None,
     };
 
     assert!(args.project_root_path.is_some());
@@ -255,6 +309,7 @@ fn test_enhance_prompt_args_with_large_prompt() {
         project_root_path: None,
         prompt: Some(large_prompt.clone()),
         conversation_history: None,
+        no_cache: None,
     };
 
     let json = serde_json::to_string(&args).unwrap();
@@ -273,13 +328,18 @@ fn test_enhance_prompt_args_with_large_conversation_history() {
     let args = EnhancePromptArgs {
         project_root_path: None,
         prompt: Some("test".to_string()),
-        conversation_history: Some(large_history.clone()),
+        conversation_history: Some(large_history.into()),
+        no_cache: None,
     };
 
     let json = serde_json::to_string(&args).unwrap();
     let parsed: EnhancePromptArgs = serde_json::from_str(&json).unwrap();
 
-    assert!(parsed.conversation_history.unwrap().contains("Message 99"));
+    assert!(parsed
+        .conversation_history
+        .unwrap()
+        .display()
+        .contains("Message 99"));
 }
 
 // ============================================================================
@@ -291,12 +351,13 @@ fn test_enhance_prompt_args_empty_strings() {
     let args = EnhancePromptArgs {
         project_root_path: Some("".to_string()),
         prompt: Some("".to_string()),
-        conversation_history: Some("".to_string()),
+        conversation_history: Some("".into()),
+        no_cache: None,
     };
 
     assert_eq!(args.project_root_path, Some("".to_string()));
     assert_eq!(args.prompt, Some("".to_string()));
-    assert_eq!(args.conversation_history, Some("".to_string()));
+    assert_eq!(args.conversation_history, Some("".into()));
 }
 
 #[test]
@@ -304,7 +365,8 @@ fn test_enhance_prompt_args_whitespace_only() {
     let args = EnhancePromptArgs {
         project_root_path: Some("   ".to_string()),
         prompt: Some("\t\n".to_string()),
-        conversation_history: Some("  \n  ".to_string()),
+        conversation_history: Some("  \n  ".into()),
+        no_cache: None,
     };
 
     assert!(args.prompt.is_some());
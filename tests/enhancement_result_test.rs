@@ -0,0 +1,147 @@
+//! Tests for get_enhancement_result tool and session persistence
+
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use ace_tool::config::Config;
+use ace_tool::enhancer::server::SessionStatus;
+use ace_tool::enhancer::session_store::{
+    get_sessions_dir, load_session, prune_sessions, remove_session, save_session, PersistedSession,
+};
+use ace_tool::tools::enhancement_result::{
+    EnhancementResultArgs, EnhancementResultToolDef, ENHANCEMENT_RESULT_TOOL,
+};
+use ace_tool::tools::EnhancementResultTool;
+use ace_tool::utils::project_detector::ACE_DIR_NAME;
+use tempfile::TempDir;
+
+fn persisted(id: &str, result: Option<&str>) -> PersistedSession {
+    PersistedSession {
+        id: id.to_string(),
+        token: "secret".to_string(),
+        status: if result.is_some() {
+            SessionStatus::Completed
+        } else {
+            SessionStatus::Timeout
+        },
+        original_prompt: "add login".to_string(),
+        enhanced_prompt: "add a login page".to_string(),
+        conversation_history: Default::default(),
+        blob_names: Vec::new(),
+        revisions: Vec::new(),
+        selected_revision: None,
+        result: result.map(str::to_string),
+        created_at: 0,
+    }
+}
+
+const SESSION_ID: &str = "3f1c1d4e-8a8b-4c5f-9a53-0b1f2f0d9a11";
+
+#[test]
+fn test_enhancement_result_tool_definition() {
+    assert_eq!(ENHANCEMENT_RESULT_TOOL.name, "get_enhancement_result");
+    let schema = EnhancementResultToolDef::get_input_schema();
+    assert_eq!(schema["required"], serde_json::json!(["session_id"]));
+    assert!(schema["properties"]["project_root_path"].is_object());
+}
+
+#[test]
+fn test_session_store_round_trip() {
+    let project = TempDir::new().unwrap();
+    let dir = get_sessions_dir(project.path());
+    assert!(dir.ends_with(".ace-tool/sessions"));
+    assert!(!dir.exists());
+
+    save_session(&dir, &persisted(SESSION_ID, Some("final prompt"))).unwrap();
+    let loaded = load_session(&dir, SESSION_ID).unwrap().unwrap();
+    assert_eq!(loaded.status, SessionStatus::Completed);
+    assert_eq!(loaded.result.as_deref(), Some("final prompt"));
+
+    let missing = "00000000-0000-4000-8000-000000000000";
+    assert!(load_session(&dir, missing).unwrap().is_none());
+}
+
+#[test]
+fn test_prune_sessions_removes_stale_files() {
+    let project = TempDir::new().unwrap();
+    let dir = get_sessions_dir(project.path());
+    let fresh = "00000000-0000-4000-8000-000000000000";
+    save_session(&dir, &persisted(SESSION_ID, None)).unwrap();
+    save_session(&dir, &persisted(fresh, None)).unwrap();
+
+    let stale = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+    fs::File::options()
+        .write(true)
+        .open(dir.join(format!("{}.json", SESSION_ID)))
+        .unwrap()
+        .set_modified(stale)
+        .unwrap();
+
+    let removed = prune_sessions(&dir, Duration::from_secs(24 * 60 * 60)).unwrap();
+    assert_eq!(removed, 1);
+    assert!(load_session(&dir, SESSION_ID).unwrap().is_none());
+    assert!(load_session(&dir, fresh).unwrap().is_some());
+
+    remove_session(&dir, fresh).unwrap();
+    assert!(load_session(&dir, fresh).unwrap().is_none());
+    // Removing a missing session is not an error
+    remove_session(&dir, fresh).unwrap();
+    assert_eq!(
+        prune_sessions(&project.path().join("missing"), Duration::ZERO).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn test_session_store_rejects_non_uuid_ids() {
+    let project = TempDir::new().unwrap();
+    let dir = get_sessions_dir(project.path());
+    assert!(load_session(&dir, "../../etc/passwd").is_err());
+    assert!(save_session(&dir, &persisted("../escape", None)).is_err());
+}
+
+#[tokio::test]
+async fn test_get_enhancement_result_returns_submitted_prompt() {
+    let project = TempDir::new().unwrap();
+    save_session(
+        &get_sessions_dir(project.path()),
+        &persisted(SESSION_ID, Some("reviewed prompt")),
+    )
+    .unwrap();
+
+    let tool = EnhancementResultTool::new(Config::new_for_third_party_enhancer());
+    let result = tool
+        .execute(EnhancementResultArgs {
+            session_id: Some(SESSION_ID.to_string()),
+            project_root_path: Some(project.path().to_string_lossy().to_string()),
+        })
+        .await;
+    assert_eq!(result.text, "reviewed prompt");
+
+    // The session (and its token) is deleted once the result is picked up
+    let dir = get_sessions_dir(project.path());
+    assert!(load_session(&dir, SESSION_ID).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_get_enhancement_result_unknown_session() {
+    let project = TempDir::new().unwrap();
+    let tool = EnhancementResultTool::new(Config::new_for_third_party_enhancer());
+    let result = tool
+        .execute(EnhancementResultArgs {
+            session_id: Some("00000000-0000-4000-8000-000000000000".to_string()),
+            project_root_path: Some(project.path().to_string_lossy().to_string()),
+        })
+        .await;
+    assert!(result.text.starts_with("Error: Session not found"));
+    assert_eq!(result.error.unwrap().code(), "invalid_arguments");
+    // Looking a session up must not create `.ace-tool/` in the project
+    assert!(!project.path().join(ACE_DIR_NAME).exists());
+}
+
+#[tokio::test]
+async fn test_get_enhancement_result_requires_session_id() {
+    let tool = EnhancementResultTool::new(Config::new_for_third_party_enhancer());
+    let result = tool.execute(EnhancementResultArgs::default()).await;
+    assert_eq!(result.text, "Error: session_id is required");
+}
@@ -0,0 +1,302 @@
+//! Tests for the versioned Web UI JSON API used by editor plugins
+
+use std::time::{Duration, Instant};
+
+use ace_tool::enhancer::api::{
+    read_discovery_file, write_discovery_file, DiffRequest, DiffResponse, DiscoveryInfo,
+    ReEnhanceRequest, SessionListResponse, SessionResponse, SubmitRequest, SuccessResponse,
+    API_VERSION, API_VERSION_HEADER,
+};
+use ace_tool::enhancer::server::{EnhancerServer, SessionStatus, SESSION_TOKEN_HEADER};
+use ace_tool::service::ConversationHistory;
+use tokio::sync::oneshot;
+
+async fn start_test_server() -> (EnhancerServer, String) {
+    let server = EnhancerServer::new();
+    server.set_bind_addr("127.0.0.1:0".parse().unwrap()).await;
+    server.start().await.unwrap();
+    let base = format!("http://127.0.0.1:{}", server.get_port().await);
+    (server, base)
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder().no_proxy().build().unwrap()
+}
+
+async fn create_session(
+    server: &EnhancerServer,
+    original: &str,
+) -> (String, String, oneshot::Receiver<String>) {
+    let (id, rx) = server
+        .create_session(
+            format!("enhanced {}", original),
+            original.to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&id).await.unwrap().token;
+    (id, token, rx)
+}
+
+async fn dashboard_token(server: &EnhancerServer) -> String {
+    server.discovery_info().await.unwrap().token
+}
+
+#[tokio::test]
+async fn test_v1_session_endpoint_returns_typed_response() {
+    let (server, base) = start_test_server().await;
+    let (id, token, _rx) = create_session(&server, "add login").await;
+
+    let resp = client()
+        .get(format!("{}/api/v1/session?session={}", base, id))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        resp.headers()[API_VERSION_HEADER].to_str().unwrap(),
+        API_VERSION.to_string()
+    );
+
+    let session: SessionResponse = resp.json().await.unwrap();
+    assert_eq!(session.enhanced_prompt, "enhanced add login");
+    assert_eq!(session.status, SessionStatus::Pending);
+    assert_eq!(session.revisions.len(), 1);
+}
+
+#[tokio::test]
+async fn test_v1_submit_accepts_typed_request() {
+    let (server, base) = start_test_server().await;
+    let (id, token, _rx) = create_session(&server, "add login").await;
+
+    let request = SubmitRequest {
+        session_id: id.clone(),
+        content: "reviewed".to_string(),
+        action: None,
+        revision_index: Some(0),
+    };
+    let resp: SuccessResponse = client()
+        .post(format!("{}/api/v1/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&request)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(resp.success);
+
+    let session = server.get_session(&id).await.unwrap();
+    assert_eq!(session.result.as_deref(), Some("reviewed"));
+    assert_eq!(session.selected_revision, Some(0));
+}
+
+#[tokio::test]
+async fn test_v1_diff_round_trips_typed_types() {
+    let (server, base) = start_test_server().await;
+    let (id, token, _rx) = create_session(&server, "add login").await;
+
+    let request = DiffRequest {
+        session_id: id,
+        current_prompt: Some("add OAuth login".to_string()),
+        base_revision: None,
+    };
+    let resp: DiffResponse = client()
+        .post(format!("{}/api/v1/diff", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&request)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(resp.original, "add login");
+    assert_eq!(resp.enhanced, "add OAuth login");
+    assert!(!resp.segments.is_empty());
+}
+
+#[test]
+fn test_request_types_use_camel_case() {
+    let request = ReEnhanceRequest {
+        session_id: "id".to_string(),
+        current_prompt: "text".to_string(),
+    };
+    assert_eq!(
+        serde_json::to_value(&request).unwrap(),
+        serde_json::json!({ "sessionId": "id", "currentPrompt": "text" })
+    );
+
+    let submit: SubmitRequest =
+        serde_json::from_str(r#"{"sessionId":"id","content":"text"}"#).unwrap();
+    assert_eq!(submit.action, None);
+    assert_eq!(submit.revision_index, None);
+}
+
+#[tokio::test]
+async fn test_unknown_v1_path_is_not_found() {
+    let (server, base) = start_test_server().await;
+    let resp = client()
+        .get(format!("{}/api/v1/unknown", base))
+        .header(SESSION_TOKEN_HEADER, dashboard_token(&server).await)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_sessions_wait_returns_when_session_created() {
+    let (server, base) = start_test_server().await;
+    let dashboard = dashboard_token(&server).await;
+
+    let initial: SessionListResponse = client()
+        .get(format!("{}/api/v1/sessions", base))
+        .header(SESSION_TOKEN_HEADER, &dashboard)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(initial.sessions.is_empty());
+
+    let server = std::sync::Arc::new(server);
+    let creator = {
+        let server = server.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let (id, _, rx) = create_session(&server, "new prompt").await;
+            (id, rx)
+        })
+    };
+
+    let started = Instant::now();
+    let changed: SessionListResponse = client()
+        .get(format!(
+            "{}/api/v1/sessions/wait?cursor={}&timeout=10",
+            base, initial.cursor
+        ))
+        .header(SESSION_TOKEN_HEADER, &dashboard)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let (id, _rx) = creator.await.unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(changed.cursor > initial.cursor);
+    assert_eq!(changed.sessions.len(), 1);
+    assert_eq!(changed.sessions[0].id, id);
+    assert_eq!(changed.sessions[0].original_prompt, "new prompt");
+}
+
+#[tokio::test]
+async fn test_sessions_wait_times_out_unchanged() {
+    let (server, base) = start_test_server().await;
+    let dashboard = dashboard_token(&server).await;
+    let (_, _, _rx) = create_session(&server, "existing").await;
+
+    // A stale cursor returns immediately with the current list
+    let resp: SessionListResponse = client()
+        .get(format!("{}/api/v1/sessions/wait?cursor=0", base))
+        .header(SESSION_TOKEN_HEADER, &dashboard)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(resp.sessions.len(), 1);
+
+    // An up-to-date cursor waits for the timeout and returns the same cursor
+    let started = Instant::now();
+    let again: SessionListResponse = client()
+        .get(format!(
+            "{}/api/v1/sessions/wait?cursor={}&timeout=1",
+            base, resp.cursor
+        ))
+        .header(SESSION_TOKEN_HEADER, &dashboard)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(900));
+    assert_eq!(again.cursor, resp.cursor);
+}
+
+#[tokio::test]
+async fn test_sessions_wait_requires_dashboard_token() {
+    let (server, base) = start_test_server().await;
+    let (_, token, _rx) = create_session(&server, "prompt").await;
+
+    let resp = client()
+        .get(format!("{}/api/v1/sessions/wait?timeout=1", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let resp = client()
+        .get(format!("{}/api/v1/sessions/wait?cursor=abc", base))
+        .header(SESSION_TOKEN_HEADER, dashboard_token(&server).await)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_discovery_info_reflects_running_server() {
+    let server = EnhancerServer::new();
+    assert!(server.discovery_info().await.is_none());
+
+    server.set_bind_addr("127.0.0.1:0".parse().unwrap()).await;
+    server.start().await.unwrap();
+    let info = server.discovery_info().await.unwrap();
+    assert_eq!(info.api_version, API_VERSION);
+    assert_eq!(info.host, "127.0.0.1");
+    assert_eq!(info.port, server.get_port().await);
+    assert_eq!(info.url, format!("http://127.0.0.1:{}", info.port));
+    assert!(!info.tls);
+    assert_eq!(info.pid, std::process::id());
+    assert!(server.dashboard_url().await.ends_with(&info.token));
+}
+
+#[test]
+fn test_discovery_file_round_trip() {
+    let project = tempfile::TempDir::new().unwrap();
+    assert!(read_discovery_file(project.path()).unwrap().is_none());
+
+    let info = DiscoveryInfo {
+        api_version: API_VERSION,
+        url: "http://127.0.0.1:3000".to_string(),
+        host: "127.0.0.1".to_string(),
+        port: 3000,
+        tls: false,
+        token: "secret".to_string(),
+        pid: 42,
+    };
+    let path = write_discovery_file(project.path(), &info).unwrap();
+    assert_eq!(path, project.path().join(".ace-tool").join("webui.json"));
+    assert_eq!(read_discovery_file(project.path()).unwrap(), Some(info));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json["apiVersion"], API_VERSION);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -1,15 +1,23 @@
 //! Tests for enhancer server module
 
-use std::time::Instant;
-
-use ace_tool::enhancer::server::{EnhancerServer, SessionData, SessionStatus};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ace_tool::enhancer::diff::{word_diff, DiffOp, DiffSegment};
+use ace_tool::enhancer::server::{
+    EnhanceCallback, EnhancerServer, SessionData, SessionOutcome, SessionStatus,
+};
+use ace_tool::enhancer::session_store::load_session;
+use ace_tool::service::ConversationHistory;
 use http_body_util::Full;
 use hyper::body::Bytes;
 use hyper::{Response, StatusCode};
 
 // Import the internal functions we need to test
 // These need to be made pub in the source file
-use ace_tool::enhancer::server::{cors_response, json_response, serve_enhancer_ui};
+use ace_tool::enhancer::server::{
+    cors_response, json_response, serve_enhancer_ui, SESSION_TOKEN_HEADER,
+};
 
 // ========================================================================
 // SessionStatus Tests
@@ -47,6 +55,7 @@ fn test_session_status_all_variants_different() {
         SessionStatus::Pending,
         SessionStatus::Completed,
         SessionStatus::Timeout,
+        SessionStatus::Cancelled,
     ];
 
     for i in 0..variants.len() {
@@ -70,17 +79,22 @@ fn test_session_data_creation() {
         id: "test-id".to_string(),
         enhanced_prompt: "enhanced".to_string(),
         original_prompt: "original".to_string(),
-        conversation_history: "history".to_string(),
+        conversation_history: "history".into(),
         blob_names: vec!["blob1".to_string()],
         status: SessionStatus::Pending,
         created_at: Instant::now(),
         created_at_ms: 1234567890,
+        token: "secret".to_string(),
+        revisions: Vec::new(),
+        selected_revision: None,
+        result: None,
+        store_dir: None,
     };
 
     assert_eq!(data.id, "test-id");
     assert_eq!(data.enhanced_prompt, "enhanced");
     assert_eq!(data.original_prompt, "original");
-    assert_eq!(data.conversation_history, "history");
+    assert_eq!(data.conversation_history, "history".into());
     assert_eq!(data.blob_names.len(), 1);
     assert_eq!(data.status, SessionStatus::Pending);
 }
@@ -91,11 +105,16 @@ fn test_session_data_clone() {
         id: "test-id".to_string(),
         enhanced_prompt: "enhanced".to_string(),
         original_prompt: "original".to_string(),
-        conversation_history: "history".to_string(),
+        conversation_history: "history".into(),
         blob_names: vec!["blob1".to_string(), "blob2".to_string()],
         status: SessionStatus::Pending,
         created_at: Instant::now(),
         created_at_ms: 1234567890,
+        token: "secret".to_string(),
+        revisions: Vec::new(),
+        selected_revision: None,
+        result: None,
+        store_dir: None,
     };
 
     let cloned = data.clone();
@@ -112,11 +131,16 @@ fn test_session_data_with_empty_blobs() {
         id: "test".to_string(),
         enhanced_prompt: "enhanced".to_string(),
         original_prompt: "original".to_string(),
-        conversation_history: "".to_string(),
+        conversation_history: "".into(),
         blob_names: vec![],
         status: SessionStatus::Pending,
         created_at: Instant::now(),
         created_at_ms: 0,
+        token: "secret".to_string(),
+        revisions: Vec::new(),
+        selected_revision: None,
+        result: None,
+        store_dir: None,
     };
 
     assert!(data.blob_names.is_empty());
@@ -128,15 +152,20 @@ fn test_session_data_with_unicode() {
         id: "测试-id".to_string(),
         enhanced_prompt: "增强的提示".to_string(),
         original_prompt: "原始提示".to_string(),
-        conversation_history: "用户: 你好\n助手: 你好！".to_string(),
+        conversation_history: "用户: 你好\n助手: 你好！".into(),
         blob_names: vec!["文件.rs".to_string()],
         status: SessionStatus::Pending,
         created_at: Instant::now(),
         created_at_ms: 1234567890,
+        token: "secret".to_string(),
+        revisions: Vec::new(),
+        selected_revision: None,
+        result: None,
+        store_dir: None,
     };
 
     assert_eq!(data.enhanced_prompt, "增强的提示");
-    assert!(data.conversation_history.contains("你好"));
+    assert!(data.conversation_history.display().contains("你好"));
 }
 
 // ========================================================================
@@ -170,7 +199,7 @@ async fn test_enhancer_server_create_session() {
         .create_session(
             "enhanced".to_string(),
             "original".to_string(),
-            "history".to_string(),
+            "history".into(),
             vec!["blob".to_string()],
         )
         .await;
@@ -188,7 +217,7 @@ async fn test_enhancer_server_create_multiple_sessions() {
         .create_session(
             "enhanced1".to_string(),
             "original1".to_string(),
-            "history1".to_string(),
+            "history1".into(),
             vec![],
         )
         .await;
@@ -197,7 +226,7 @@ async fn test_enhancer_server_create_multiple_sessions() {
         .create_session(
             "enhanced2".to_string(),
             "original2".to_string(),
-            "history2".to_string(),
+            "history2".into(),
             vec![],
         )
         .await;
@@ -210,7 +239,12 @@ async fn test_enhancer_server_create_multiple_sessions() {
 async fn test_enhancer_server_create_session_with_empty_data() {
     let server = EnhancerServer::new();
     let (session_id, _rx) = server
-        .create_session("".to_string(), "".to_string(), "".to_string(), vec![])
+        .create_session(
+            "".to_string(),
+            "".to_string(),
+            ConversationHistory::default(),
+            vec![],
+        )
         .await;
 
     assert!(!session_id.is_empty());
@@ -226,7 +260,7 @@ async fn test_enhancer_server_create_session_with_large_data() {
         .create_session(
             large_prompt.clone(),
             large_prompt,
-            "history".to_string(),
+            "history".into(),
             many_blobs,
         )
         .await;
@@ -292,7 +326,7 @@ fn test_cors_response_adds_headers() {
         .body(Full::new(Bytes::new()))
         .unwrap();
 
-    let cors_resp = cors_response(response);
+    let cors_resp = cors_response(response, "http://localhost:3000");
 
     assert!(cors_resp
         .headers()
@@ -306,18 +340,18 @@ fn test_cors_response_adds_headers() {
 }
 
 #[test]
-fn test_cors_response_allows_localhost_origin() {
+fn test_cors_response_allows_given_origin() {
     let response = Response::builder()
         .status(StatusCode::OK)
         .body(Full::new(Bytes::new()))
         .unwrap();
 
-    let cors_resp = cors_response(response);
+    let cors_resp = cors_response(response, "http://localhost:3000");
     let origin = cors_resp
         .headers()
         .get("Access-Control-Allow-Origin")
         .unwrap();
-    assert_eq!(origin, "http://localhost");
+    assert_eq!(origin, "http://localhost:3000");
 }
 
 #[test]
@@ -327,7 +361,7 @@ fn test_cors_response_allows_required_methods() {
         .body(Full::new(Bytes::new()))
         .unwrap();
 
-    let cors_resp = cors_response(response);
+    let cors_resp = cors_response(response, "http://localhost:3000");
     let methods = cors_resp
         .headers()
         .get("Access-Control-Allow-Methods")
@@ -346,7 +380,7 @@ fn test_cors_response_preserves_status() {
         .body(Full::new(Bytes::new()))
         .unwrap();
 
-    let cors_resp = cors_response(response);
+    let cors_resp = cors_response(response, "http://localhost:3000");
     assert_eq!(cors_resp.status(), StatusCode::NOT_FOUND);
 }
 
@@ -585,3 +619,947 @@ async fn test_concurrent_start_calls() {
     let port = server.get_port().await;
     assert_ne!(port, 0, "Port must be non-zero after concurrent starts");
 }
+
+// ========================================================================
+// Diff Tests
+// ========================================================================
+
+fn diff_text(segments: &[DiffSegment], op: DiffOp) -> Vec<&str> {
+    segments
+        .iter()
+        .filter(|s| s.op == op)
+        .map(|s| s.text.as_str())
+        .collect()
+}
+
+#[test]
+fn test_word_diff_identical() {
+    let segments = word_diff("add login page", "add login page");
+    assert_eq!(
+        segments,
+        vec![DiffSegment {
+            op: DiffOp::Equal,
+            text: "add login page".to_string()
+        }]
+    );
+}
+
+#[test]
+fn test_word_diff_insert_and_delete_words() {
+    let segments = word_diff("add a login page", "add an OAuth login page with tests");
+    assert_eq!(diff_text(&segments, DiffOp::Delete), vec!["a"]);
+    assert_eq!(
+        diff_text(&segments, DiffOp::Insert),
+        vec!["an", "OAuth ", " with tests"]
+    );
+}
+
+#[test]
+fn test_word_diff_reconstructs_both_sides() {
+    let original = "Fix the bug in auth.rs, then run tests.";
+    let enhanced = "Fix the session bug in src/auth.rs and run all tests.\nReport results.";
+    let segments = word_diff(original, enhanced);
+
+    let rebuilt_original: String = segments
+        .iter()
+        .filter(|s| s.op != DiffOp::Insert)
+        .map(|s| s.text.as_str())
+        .collect();
+    let rebuilt_enhanced: String = segments
+        .iter()
+        .filter(|s| s.op != DiffOp::Delete)
+        .map(|s| s.text.as_str())
+        .collect();
+    assert_eq!(rebuilt_original, original);
+    assert_eq!(rebuilt_enhanced, enhanced);
+
+    // Adjacent segments never share an operation
+    assert!(segments.windows(2).all(|w| w[0].op != w[1].op));
+}
+
+#[test]
+fn test_word_diff_cjk_per_character() {
+    let segments = word_diff("添加登录", "添加用户登录");
+    assert_eq!(diff_text(&segments, DiffOp::Insert), vec!["用户"]);
+    assert!(diff_text(&segments, DiffOp::Delete).is_empty());
+}
+
+#[test]
+fn test_word_diff_empty_sides() {
+    assert!(word_diff("", "").is_empty());
+    assert_eq!(
+        word_diff("", "new text"),
+        vec![DiffSegment {
+            op: DiffOp::Insert,
+            text: "new text".to_string()
+        }]
+    );
+    assert_eq!(
+        word_diff("old text", ""),
+        vec![DiffSegment {
+            op: DiffOp::Delete,
+            text: "old text".to_string()
+        }]
+    );
+}
+
+#[test]
+fn test_diff_segment_serialization() {
+    let json = serde_json::to_value(DiffSegment {
+        op: DiffOp::Insert,
+        text: "x".to_string(),
+    })
+    .unwrap();
+    assert_eq!(json, serde_json::json!({"op": "insert", "text": "x"}));
+}
+
+async fn start_test_server() -> (EnhancerServer, String) {
+    let server = EnhancerServer::new();
+    server.set_bind_addr("127.0.0.1:0".parse().unwrap()).await;
+    server.start().await.unwrap();
+    let base = format!("http://127.0.0.1:{}", server.get_port().await);
+    (server, base)
+}
+
+#[tokio::test]
+async fn test_api_diff_uses_session_prompts() {
+    let (server, base) = start_test_server().await;
+    let (session_id, _rx) = server
+        .create_session(
+            "add OAuth login page".to_string(),
+            "add login page".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp: serde_json::Value = client
+        .post(format!("{}/api/diff", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(resp["original"], "add login page");
+    assert_eq!(resp["enhanced"], "add OAuth login page");
+    assert_eq!(
+        resp["segments"],
+        serde_json::json!([
+            {"op": "equal", "text": "add "},
+            {"op": "insert", "text": "OAuth "},
+            {"op": "equal", "text": "login page"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_api_diff_uses_current_prompt_edits() {
+    let (server, base) = start_test_server().await;
+    let (session_id, _rx) = server
+        .create_session(
+            "add OAuth login page".to_string(),
+            "add login page".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp: serde_json::Value = client
+        .post(format!("{}/api/diff", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({
+            "sessionId": session_id,
+            "currentPrompt": "add login form"
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(resp["enhanced"], "add login form");
+    assert_eq!(
+        resp["segments"][1],
+        serde_json::json!({"op": "delete", "text": "page"})
+    );
+    assert_eq!(
+        resp["segments"][2],
+        serde_json::json!({"op": "insert", "text": "form"})
+    );
+}
+
+#[tokio::test]
+async fn test_api_diff_unknown_session() {
+    let (_server, base) = start_test_server().await;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .post(format!("{}/api/diff", base))
+        .header(SESSION_TOKEN_HEADER, "any-token")
+        .json(&serde_json::json!({ "sessionId": "missing" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+// ========================================================================
+// Revision History Tests
+// ========================================================================
+
+/// Enhance callback that tags the prompt so each round is distinguishable
+fn tagging_callback() -> EnhanceCallback {
+    Arc::new(|prompt, _history, _blobs| {
+        Box::pin(async move { Ok(format!("{} (enhanced)", prompt)) })
+    })
+}
+
+#[tokio::test]
+async fn test_create_session_records_initial_revision() {
+    let server = EnhancerServer::new();
+    let (session_id, _rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+
+    let session = server.get_session(&session_id).await.unwrap();
+    assert_eq!(session.revisions.len(), 1);
+    assert_eq!(session.revisions[0].text, "enhanced");
+    assert!(session.revisions[0].user_edit.is_none());
+    assert!(!session.revisions[0].provider.is_empty());
+    assert!(session.selected_revision.is_none());
+}
+
+#[tokio::test]
+async fn test_api_re_enhance_appends_revisions() {
+    let (server, base) = start_test_server().await;
+    server.set_enhance_callback(tagging_callback()).await;
+    let (session_id, _rx) = server
+        .create_session(
+            "first".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+    // Unchanged text: no user edit recorded
+    let resp: serde_json::Value = client
+        .post(format!("{}/api/re-enhance", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "currentPrompt": "first" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(resp["enhancedPrompt"], "first (enhanced)");
+    assert_eq!(resp["revisionIndex"], 1);
+    assert!(resp["revision"]["userEdit"].is_null());
+
+    // Edited text: the edit is kept with the new revision
+    let resp: serde_json::Value = client
+        .post(format!("{}/api/re-enhance", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "currentPrompt": "my edit" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(resp["revisionIndex"], 2);
+    assert_eq!(resp["revision"]["userEdit"], "my edit");
+
+    let session: serde_json::Value = client
+        .get(format!("{}/api/session?session={}", base, session_id))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let texts: Vec<&str> = session["revisions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["text"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        texts,
+        vec!["first", "first (enhanced)", "my edit (enhanced)"]
+    );
+    assert_eq!(session["enhancedPrompt"], "my edit (enhanced)");
+}
+
+#[tokio::test]
+async fn test_api_diff_against_revision() {
+    let (server, base) = start_test_server().await;
+    server.set_enhance_callback(tagging_callback()).await;
+    let (session_id, _rx) = server
+        .create_session(
+            "add login page".to_string(),
+            "login".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    client
+        .post(format!("{}/api/re-enhance", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "currentPrompt": "add login page" }))
+        .send()
+        .await
+        .unwrap();
+
+    let resp: serde_json::Value = client
+        .post(format!("{}/api/diff", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "baseRevision": 0 }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(resp["original"], "add login page");
+    assert_eq!(resp["enhanced"], "add login page (enhanced)");
+
+    let resp = client
+        .post(format!("{}/api/diff", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "baseRevision": 5 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_api_submit_records_selected_revision() {
+    let (server, base) = start_test_server().await;
+    server.set_enhance_callback(tagging_callback()).await;
+    let (session_id, rx) = server
+        .create_session(
+            "first".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    client
+        .post(format!("{}/api/re-enhance", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "currentPrompt": "first" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Out-of-range index is rejected and leaves the session pending
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({
+            "sessionId": session_id,
+            "content": "first",
+            "action": "send",
+            "revisionIndex": 7
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({
+            "sessionId": session_id,
+            "content": "first",
+            "action": "send",
+            "revisionIndex": 0
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert_eq!(rx.await.unwrap(), "first");
+
+    let session = server.get_session(&session_id).await.unwrap();
+    assert_eq!(session.selected_revision, Some(0));
+    assert_eq!(session.status, SessionStatus::Completed);
+}
+
+// ========================================================================
+// Session Token / TLS Tests
+// ========================================================================
+
+#[tokio::test]
+async fn test_session_url_contains_token() {
+    let (server, base) = start_test_server().await;
+    let (session_id, _rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+    assert_eq!(token.len(), 64);
+
+    let url = server.session_url(&session_id).await.unwrap();
+    assert_eq!(
+        url,
+        format!("{}/enhance?session={}&token={}", base, session_id, token)
+    );
+    assert!(server.session_url("missing").await.is_none());
+}
+
+#[tokio::test]
+async fn test_api_requires_session_token() {
+    let (server, base) = start_test_server().await;
+    let (session_id, _rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let submit = serde_json::json!({ "sessionId": session_id, "content": "hijacked" });
+
+    // Missing token
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .json(&submit)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // Wrong token
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, "0".repeat(64))
+        .json(&submit)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // A token from another session is rejected too
+    let (other_id, _other_rx) = server
+        .create_session(
+            "x".to_string(),
+            "y".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let other_token = server.get_session(&other_id).await.unwrap().token;
+    let resp = client
+        .get(format!("{}/api/session?session={}", base, session_id))
+        .header(SESSION_TOKEN_HEADER, &other_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // Token in the query string is accepted
+    let resp = client
+        .get(format!(
+            "{}/api/session?session={}&token={}",
+            base, session_id, token
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+    let session = server.get_session(&session_id).await.unwrap();
+    assert_eq!(session.status, SessionStatus::Pending);
+}
+
+#[tokio::test]
+async fn test_ui_page_does_not_require_token() {
+    let (_server, base) = start_test_server().await;
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .get(format!("{}/enhance", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_cors_reflects_bind_host() {
+    let (_server, base) = start_test_server().await;
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+    // Cross-origin requests only see the bind origin
+    let resp = client
+        .get(format!("{}/enhance", base))
+        .header("Origin", "http://evil.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()["Access-Control-Allow-Origin"], base.as_str());
+
+    // Same-origin requests via another hostname (e.g. a devcontainer) are reflected
+    let port = base.rsplit(':').next().unwrap();
+    let origin = format!("http://devbox:{}", port);
+    let resp = client
+        .get(format!("{}/enhance", base))
+        .header("Host", format!("devbox:{}", port))
+        .header("Origin", &origin)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.headers()["Access-Control-Allow-Origin"],
+        origin.as_str()
+    );
+}
+
+#[tokio::test]
+async fn test_tls_server_serves_https() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls");
+    let server = EnhancerServer::new();
+    server
+        .set_tls(&fixtures.join("cert.pem"), &fixtures.join("key.pem"))
+        .await
+        .unwrap();
+    server.set_bind_addr("127.0.0.1:0".parse().unwrap()).await;
+    server.start().await.unwrap();
+    assert!(server.is_tls().await);
+
+    let (session_id, _rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let url = server.session_url(&session_id).await.unwrap();
+    assert!(url.starts_with("https://127.0.0.1:"));
+
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_set_tls_rejects_missing_files() {
+    let server = EnhancerServer::new();
+    let err = server
+        .set_tls(
+            std::path::Path::new("/nonexistent/cert.pem"),
+            std::path::Path::new("/nonexistent/key.pem"),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("TLS certificate"));
+    assert!(!server.is_tls().await);
+}
+
+// ========================================================================
+// Timeout / Persistence Tests
+// ========================================================================
+
+#[tokio::test]
+async fn test_timeout_returns_latest_prompt_and_keeps_session_open() {
+    let server = EnhancerServer::with_timeout(50);
+    server.set_bind_addr("127.0.0.1:0".parse().unwrap()).await;
+    server.start().await.unwrap();
+    let base = format!("http://127.0.0.1:{}", server.get_port().await);
+
+    let store = tempfile::TempDir::new().unwrap();
+    let (session_id, rx) = server
+        .create_session(
+            "latest".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    server
+        .persist_session(&session_id, store.path().to_path_buf())
+        .await
+        .unwrap();
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let outcome = server
+        .wait_for_session_with_receiver(&session_id, rx)
+        .await
+        .unwrap();
+    assert_eq!(outcome, SessionOutcome::TimedOut("latest".to_string()));
+    let persisted = load_session(store.path(), &session_id).unwrap().unwrap();
+    assert_eq!(persisted.status, SessionStatus::Timeout);
+
+    // A late reviewer can still submit; the result is persisted for the agent
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "content": "late edit" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+    let persisted = load_session(store.path(), &session_id).unwrap().unwrap();
+    assert_eq!(persisted.status, SessionStatus::Completed);
+    assert_eq!(persisted.result.as_deref(), Some("late edit"));
+
+    // ...but only once
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "content": "again" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_completed_session_file_is_removed_once_picked_up() {
+    let (server, base) = start_test_server().await;
+    let store = tempfile::TempDir::new().unwrap();
+    let (session_id, rx) = server
+        .create_session(
+            "latest".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    server
+        .persist_session(&session_id, store.path().to_path_buf())
+        .await
+        .unwrap();
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "content": "final" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+    let outcome = server
+        .wait_for_session_with_receiver(&session_id, rx)
+        .await
+        .unwrap();
+    assert_eq!(outcome, SessionOutcome::Completed("final".to_string()));
+    assert!(server.get_session(&session_id).await.is_none());
+    assert!(load_session(store.path(), &session_id).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_restore_session_reopens_persisted_review() {
+    let (server, base) = start_test_server().await;
+    let store = tempfile::TempDir::new().unwrap();
+    let (session_id, _rx) = server
+        .create_session(
+            "latest".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    server
+        .persist_session(&session_id, store.path().to_path_buf())
+        .await
+        .unwrap();
+    let persisted = load_session(store.path(), &session_id).unwrap().unwrap();
+
+    // A fresh server (e.g. after a restart) picks the session up from disk
+    let (restarted, restarted_base) = start_test_server().await;
+    assert_ne!(base, restarted_base);
+    restarted
+        .restore_session(persisted, store.path().to_path_buf())
+        .await;
+    let url = restarted.session_url(&session_id).await.unwrap();
+    assert!(url.starts_with(&restarted_base));
+
+    let session = restarted.get_session(&session_id).await.unwrap();
+    assert_eq!(session.enhanced_prompt, "latest");
+    assert_eq!(session.store_dir.as_deref(), Some(store.path()));
+}
+
+#[test]
+fn test_with_timeout_sets_timeout() {
+    assert_eq!(EnhancerServer::with_timeout(1234).timeout_ms, 1234);
+}
+
+async fn dashboard_token(server: &EnhancerServer) -> String {
+    let url = server.dashboard_url().await;
+    url.split("token=").nth(1).unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_dashboard_page_is_served() {
+    let (_server, base) = start_test_server().await;
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client.get(format!("{}/", base)).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert!(resp.text().await.unwrap().contains("Pending Reviews"));
+}
+
+#[tokio::test]
+async fn test_api_sessions_lists_open_sessions_with_project() {
+    let (server, base) = start_test_server().await;
+    let project = tempfile::TempDir::new().unwrap();
+    let (first, _rx1) = server
+        .create_session(
+            "enhanced one".to_string(),
+            "first prompt".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    server
+        .persist_session(&first, project.path().join(".ace-tool").join("sessions"))
+        .await
+        .unwrap();
+    let (second, _rx2) = server
+        .create_session(
+            "enhanced two".to_string(),
+            "second prompt".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp: serde_json::Value = client
+        .get(format!("{}/api/sessions", base))
+        .header(SESSION_TOKEN_HEADER, dashboard_token(&server).await)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let sessions = resp["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    let entry = |id: &str| sessions.iter().find(|s| s["id"] == id).unwrap().clone();
+    let first_entry = entry(&first);
+    assert_eq!(first_entry["originalPrompt"], "first prompt");
+    assert_eq!(first_entry["status"], "pending");
+    assert_eq!(first_entry["project"], project.path().display().to_string());
+    assert!(first_entry["ageMs"].is_u64());
+    let token = server.get_session(&first).await.unwrap().token;
+    assert_eq!(
+        first_entry["url"],
+        format!("/enhance?session={}&token={}", first, token)
+    );
+    assert!(entry(&second)["project"].is_null());
+}
+
+#[tokio::test]
+async fn test_api_sessions_rejects_session_token() {
+    let (server, base) = start_test_server().await;
+    let (session_id, _rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let token = server.get_session(&session_id).await.unwrap().token;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .get(format!("{}/api/sessions", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let resp = client
+        .get(format!("{}/api/sessions", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_cancel_notifies_waiter_and_hides_session() {
+    let (server, base) = start_test_server().await;
+    let (session_id, rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    let dashboard = dashboard_token(&server).await;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .post(format!("{}/api/cancel", base))
+        .header(SESSION_TOKEN_HEADER, &dashboard)
+        .json(&serde_json::json!({ "sessionId": session_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+    // The waiting tool call receives an empty result, i.e. a cancellation
+    assert_eq!(rx.await.unwrap(), "");
+    let session = server.get_session(&session_id).await.unwrap();
+    assert_eq!(session.status, SessionStatus::Cancelled);
+
+    let resp: serde_json::Value = client
+        .get(format!("{}/api/sessions", base))
+        .header(SESSION_TOKEN_HEADER, &dashboard)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(resp["sessions"].as_array().unwrap().is_empty());
+
+    // A cancelled session cannot be submitted or cancelled again
+    let token = session.token;
+    let resp = client
+        .post(format!("{}/api/submit", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id, "content": "late" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+    let resp = client
+        .post(format!("{}/api/cancel", base))
+        .header(SESSION_TOKEN_HEADER, &token)
+        .json(&serde_json::json!({ "sessionId": session_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_api_cancel_requires_matching_token() {
+    let (server, base) = start_test_server().await;
+    let (session_id, _rx) = server
+        .create_session(
+            "enhanced".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let resp = client
+        .post(format!("{}/api/cancel", base))
+        .header(SESSION_TOKEN_HEADER, "wrong-token")
+        .json(&serde_json::json!({ "sessionId": session_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+    assert_eq!(
+        server.get_session(&session_id).await.unwrap().status,
+        SessionStatus::Pending
+    );
+}
+
+#[tokio::test]
+async fn test_sweep_expires_pending_sessions_and_frees_responders() {
+    let server = EnhancerServer::with_timeout(50);
+    let store = tempfile::TempDir::new().unwrap();
+    let (session_id, rx) = server
+        .create_session(
+            "latest".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+    server
+        .persist_session(&session_id, store.path().to_path_buf())
+        .await
+        .unwrap();
+
+    assert_eq!(server.sweep_expired_sessions().await, 0);
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(server.sweep_expired_sessions().await, 1);
+
+    // The responder was dropped and the session kept open as timed out
+    assert!(rx.await.is_err());
+    let session = server.get_session(&session_id).await.unwrap();
+    assert_eq!(session.status, SessionStatus::Timeout);
+    let persisted = load_session(store.path(), &session_id).unwrap().unwrap();
+    assert_eq!(persisted.status, SessionStatus::Timeout);
+    assert_eq!(server.sweep_expired_sessions().await, 0);
+}
+
+#[tokio::test]
+async fn test_waiter_reports_timeout_when_sweeper_expires_first() {
+    let server = EnhancerServer::with_timeout(200);
+    let (session_id, rx) = server
+        .create_session(
+            "latest".to_string(),
+            "original".to_string(),
+            ConversationHistory::default(),
+            Vec::new(),
+        )
+        .await;
+
+    // Simulate the sweeper running just before the waiter's own timeout
+    tokio::time::sleep(Duration::from_millis(220)).await;
+    server.sweep_expired_sessions().await;
+
+    let outcome = server
+        .wait_for_session_with_receiver(&session_id, rx)
+        .await
+        .unwrap();
+    assert_eq!(outcome, SessionOutcome::TimedOut("latest".to_string()));
+}
@@ -0,0 +1,165 @@
+//! Tests for third-party generation settings
+
+use std::fs;
+use std::sync::Mutex;
+
+use ace_tool::config::{UserConfigFile, USER_CONFIG_FILE_NAME};
+use ace_tool::service::common::EnhancerEndpoint;
+use ace_tool::service::generation::{
+    GenerationSettings, DEFAULT_MAX_TOKENS, ENV_ENHANCER_MAX_TOKENS, ENV_ENHANCER_REASONING_EFFORT,
+    ENV_ENHANCER_SYSTEM_PROMPT, ENV_ENHANCER_TEMPERATURE, ENV_ENHANCER_TOP_P,
+};
+use ace_tool::utils::project_detector::ENV_CONFIG_DIR;
+use tempfile::TempDir;
+
+/// Serializes tests that modify generation environment variables
+static ENV_MUTEX: Mutex<()> = Mutex::new(());
+
+const GENERATION_ENV_VARS: &[&str] = &[
+    ENV_ENHANCER_MAX_TOKENS,
+    ENV_ENHANCER_TEMPERATURE,
+    ENV_ENHANCER_TOP_P,
+    ENV_ENHANCER_REASONING_EFFORT,
+    ENV_ENHANCER_SYSTEM_PROMPT,
+];
+
+fn clear_env() {
+    for name in GENERATION_ENV_VARS {
+        std::env::remove_var(name);
+    }
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_generation_settings_defaults() {
+    let settings = GenerationSettings::default();
+    assert_eq!(settings.max_tokens(), DEFAULT_MAX_TOKENS);
+    assert!(settings.reasoning_budget_tokens().is_none());
+}
+
+#[test]
+fn test_generation_settings_validate_ranges() {
+    let settings = GenerationSettings {
+        temperature: Some(2.5),
+        ..Default::default()
+    };
+    assert!(settings.validate().is_err());
+
+    let settings = GenerationSettings {
+        top_p: Some(1.5),
+        ..Default::default()
+    };
+    assert!(settings.validate().is_err());
+
+    let settings = GenerationSettings {
+        max_tokens: Some(0),
+        ..Default::default()
+    };
+    assert!(settings.validate().is_err());
+
+    let settings = GenerationSettings {
+        reasoning_effort: Some("extreme".to_string()),
+        ..Default::default()
+    };
+    let err = settings.validate().unwrap_err();
+    assert!(err.to_string().contains("reasoning_effort"));
+}
+
+#[test]
+fn test_generation_settings_validate_for_claude() {
+    let hot = GenerationSettings {
+        temperature: Some(1.5),
+        ..Default::default()
+    };
+    assert!(hot.validate_for(EnhancerEndpoint::OpenAI).is_ok());
+    let err = hot.validate_for(EnhancerEndpoint::Claude).unwrap_err();
+    assert!(err.to_string().contains("between 0 and 1"), "{}", err);
+
+    let thinking = GenerationSettings {
+        top_p: Some(0.9),
+        reasoning_effort: Some("low".to_string()),
+        ..Default::default()
+    };
+    assert!(thinking.validate_for(EnhancerEndpoint::Gemini).is_ok());
+    let err = thinking.validate_for(EnhancerEndpoint::Claude).unwrap_err();
+    assert!(err.to_string().contains("reasoning_effort"), "{}", err);
+
+    let sampling_only = GenerationSettings {
+        temperature: Some(0.5),
+        top_p: Some(0.9),
+        ..Default::default()
+    };
+    assert!(sampling_only.validate_for(EnhancerEndpoint::Claude).is_ok());
+}
+
+#[test]
+fn test_generation_settings_validate_normalizes() {
+    let settings = GenerationSettings {
+        reasoning_effort: Some(" High ".to_string()),
+        system_prompt: Some("   ".to_string()),
+        ..Default::default()
+    }
+    .validate()
+    .unwrap();
+
+    assert_eq!(settings.reasoning_effort.as_deref(), Some("high"));
+    assert_eq!(settings.reasoning_budget_tokens(), Some(16384));
+    assert_eq!(
+        settings.reserved_output_tokens(),
+        DEFAULT_MAX_TOKENS + 16384
+    );
+    assert!(settings.system_prompt.is_none());
+}
+
+#[test]
+fn test_generation_settings_env_overrides_file() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    clear_env();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+    fs::write(
+        user_dir.path().join(USER_CONFIG_FILE_NAME),
+        r#"{"enhancer": {"max_tokens": 2048, "temperature": 0.2, "system_prompt": "Be concise."}}"#,
+    )
+    .unwrap();
+    std::env::set_var(ENV_ENHANCER_TEMPERATURE, "0.7");
+    std::env::set_var(ENV_ENHANCER_REASONING_EFFORT, "low");
+
+    let settings = UserConfigFile::load()
+        .unwrap()
+        .enhancer
+        .with_env_overrides()
+        .unwrap();
+    assert_eq!(settings.max_tokens, Some(2048));
+    assert_eq!(settings.temperature, Some(0.7));
+    assert_eq!(settings.reasoning_effort.as_deref(), Some("low"));
+    assert_eq!(settings.system_prompt.as_deref(), Some("Be concise."));
+
+    clear_env();
+}
+
+#[test]
+fn test_generation_settings_invalid_env_value() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    clear_env();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+    std::env::set_var(ENV_ENHANCER_MAX_TOKENS, "lots");
+
+    let err = GenerationSettings::default()
+        .with_env_overrides()
+        .unwrap_err();
+    assert!(err.to_string().contains(ENV_ENHANCER_MAX_TOKENS));
+
+    clear_env();
+}
+
+#[test]
+fn test_generation_settings_rejects_unknown_file_keys() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(USER_CONFIG_FILE_NAME);
+    fs::write(&path, r#"{"enhancer": {"max_token": 10}}"#).unwrap();
+
+    let err = UserConfigFile::from_file(&path).unwrap_err();
+    assert!(err.to_string().contains("Invalid config file"));
+}
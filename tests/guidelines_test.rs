@@ -0,0 +1,114 @@
+//! Tests for project guidelines discovery
+
+use std::fs;
+use std::sync::Mutex;
+
+use ace_tool::enhancer::guidelines::{
+    load_project_guidelines, ProjectGuidelines, ENV_ENHANCER_INCLUDE_GUIDELINES, RULES_DIR,
+    USER_GUIDELINES_FILE_NAME,
+};
+use ace_tool::utils::project_detector::ENV_CONFIG_DIR;
+use tempfile::TempDir;
+
+/// Serializes tests that modify guideline-related environment variables
+static ENV_MUTEX: Mutex<()> = Mutex::new(());
+
+#[test]
+fn test_load_project_guidelines_discovers_files() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+    fs::write(
+        user_dir.path().join(USER_GUIDELINES_FILE_NAME),
+        "Prefer short answers.\n",
+    )
+    .unwrap();
+
+    let project = TempDir::new().unwrap();
+    let root = project.path();
+    fs::write(root.join("AGENTS.md"), "Run cargo fmt.").unwrap();
+    fs::write(root.join("CLAUDE.md"), "Use anyhow for errors.").unwrap();
+    fs::write(root.join(".cursorrules"), "No unwrap in library code.").unwrap();
+    fs::create_dir_all(root.join(RULES_DIR)).unwrap();
+    fs::write(root.join(RULES_DIR).join("b.md"), "Rule B").unwrap();
+    fs::write(root.join(RULES_DIR).join("a.md"), "Rule A").unwrap();
+    fs::write(root.join(RULES_DIR).join("ignored.txt"), "Not a rule").unwrap();
+
+    let guidelines = load_project_guidelines(Some(root));
+    assert_eq!(guidelines.user, "Prefer short answers.");
+    assert_eq!(
+        guidelines.workspace,
+        "# AGENTS.md\nRun cargo fmt.\n\n# CLAUDE.md\nUse anyhow for errors."
+    );
+    assert_eq!(
+        guidelines.rules,
+        vec!["No unwrap in library code.", "Rule A", "Rule B"]
+    );
+
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_load_project_guidelines_empty_project() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+
+    let project = TempDir::new().unwrap();
+    let guidelines = load_project_guidelines(Some(project.path()));
+    assert!(guidelines.is_empty());
+    assert!(guidelines.to_system_prompt().is_none());
+
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_load_project_guidelines_truncates_large_files() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+
+    let project = TempDir::new().unwrap();
+    fs::write(project.path().join("AGENTS.md"), "é".repeat(20 * 1024)).unwrap();
+
+    let guidelines = load_project_guidelines(Some(project.path()));
+    assert!(guidelines
+        .workspace
+        .ends_with("[guidelines truncated for length]"));
+    assert!(guidelines.workspace.len() < 17 * 1024);
+
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_load_project_guidelines_can_be_disabled() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+    std::env::set_var(ENV_ENHANCER_INCLUDE_GUIDELINES, "off");
+
+    let project = TempDir::new().unwrap();
+    fs::write(project.path().join("AGENTS.md"), "Run cargo fmt.").unwrap();
+
+    let guidelines = load_project_guidelines(Some(project.path()));
+    assert!(guidelines.is_empty());
+
+    std::env::remove_var(ENV_ENHANCER_INCLUDE_GUIDELINES);
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_guidelines_system_prompt_sections() {
+    let guidelines = ProjectGuidelines {
+        user: "Be terse.".to_string(),
+        workspace: "# AGENTS.md\nRun tests.".to_string(),
+        rules: vec!["Rule one".to_string()],
+    };
+
+    let prompt = guidelines.to_system_prompt().unwrap();
+    assert!(prompt.contains("<user_guidelines>\nBe terse.\n</user_guidelines>"));
+    assert!(
+        prompt.contains("<workspace_guidelines>\n# AGENTS.md\nRun tests.\n</workspace_guidelines>")
+    );
+    assert!(prompt.contains("<rule>\nRule one\n</rule>"));
+}
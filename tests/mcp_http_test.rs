@@ -0,0 +1,556 @@
+//! Tests for the MCP Streamable HTTP transport
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::mcp::http::{
+    DEFAULT_MAX_SESSIONS, DEFAULT_SESSION_IDLE_TIMEOUT, MCP_HTTP_PATH, PROTOCOL_VERSION_HEADER,
+    SESSION_ID_HEADER,
+};
+use ace_tool::mcp::{HttpTransportOptions, McpHttpServer, McpServer};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+const TOKEN: &str = "test-http-token";
+
+async fn start_server(bearer_token: Option<&str>) -> (McpHttpServer, String) {
+    start_server_with(HttpTransportOptions {
+        bind_addr: "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        bearer_token: bearer_token.map(str::to_string),
+        session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+        max_sessions: DEFAULT_MAX_SESSIONS,
+        allowed_origins: Vec::new(),
+    })
+    .await
+}
+
+async fn start_server_with(options: HttpTransportOptions) -> (McpHttpServer, String) {
+    let config = Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions::default(),
+    )
+    .unwrap();
+    let server = McpHttpServer::new(McpServer::new(config, None), options);
+    let addr = server.start().await.unwrap();
+    (server, format!("http://{}{}", addr, MCP_HTTP_PATH))
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder().no_proxy().build().unwrap()
+}
+
+fn post(url: &str, body: Value) -> reqwest::RequestBuilder {
+    client()
+        .post(url)
+        .bearer_auth(TOKEN)
+        .header("Accept", "application/json, text/event-stream")
+        .json(&body)
+}
+
+fn initialize_request() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" }
+        }
+    })
+}
+
+async fn initialize(url: &str) -> String {
+    let resp = post(url, initialize_request()).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    resp.headers()[SESSION_ID_HEADER]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_initialize_returns_session_id_and_json_result() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+
+    let resp = post(&url, initialize_request()).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    assert!(!resp.headers()[SESSION_ID_HEADER].is_empty());
+
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["id"], 1);
+    assert_eq!(body["result"]["serverInfo"]["name"], "ace-tool");
+}
+
+#[tokio::test]
+async fn test_requests_require_bearer_token() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+
+    let resp = client()
+        .post(&url)
+        .json(&initialize_request())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()["www-authenticate"], "Bearer");
+
+    let resp = client()
+        .post(&url)
+        .bearer_auth("wrong")
+        .json(&initialize_request())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_no_token_configured_allows_requests() {
+    let (_server, url) = start_server(None).await;
+    let resp = client()
+        .post(&url)
+        .json(&initialize_request())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_session_id_is_required_after_initialize() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+
+    let resp = post(&url, list.clone()).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = post(&url, list.clone())
+        .header(SESSION_ID_HEADER, "unknown")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let session = initialize(&url).await;
+    let resp = post(&url, list)
+        .header(SESSION_ID_HEADER, &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await.unwrap();
+    let tools = body["result"]["tools"].as_array().unwrap();
+    assert!(tools.iter().any(|t| t["name"] == "search_context"));
+}
+
+#[tokio::test]
+async fn test_notifications_are_accepted_without_body() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let session = initialize(&url).await;
+
+    let resp = post(
+        &url,
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .header(SESSION_ID_HEADER, &session)
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    assert!(resp.bytes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_tool_call_streams_sse_response() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let session = initialize(&url).await;
+
+    let resp = post(
+        &url,
+        json!({
+            "jsonrpc": "2.0",
+            "id": "call-1",
+            "method": "tools/call",
+            "params": { "name": "no_such_tool", "arguments": {} }
+        }),
+    )
+    .header(SESSION_ID_HEADER, &session)
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+
+    let body = resp.text().await.unwrap();
+    let data = body
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    assert!(body.starts_with("event: message\n"));
+    let message: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(message["id"], "call-1");
+    assert!(message["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Unknown tool"));
+}
+
+#[tokio::test]
+async fn test_tool_call_returns_json_without_sse_accept() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let session = initialize(&url).await;
+
+    let resp = client()
+        .post(&url)
+        .bearer_auth(TOKEN)
+        .header("Accept", "application/json")
+        .header(SESSION_ID_HEADER, &session)
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": { "name": "no_such_tool" }
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["id"], 3);
+}
+
+#[tokio::test]
+async fn test_batch_returns_responses_in_order() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let session = initialize(&url).await;
+
+    let resp = post(
+        &url,
+        json!([
+            { "jsonrpc": "2.0", "id": "a", "method": "ping" },
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "id": "b", "method": "tools/list" }
+        ]),
+    )
+    .header(SESSION_ID_HEADER, &session)
+    .send()
+    .await
+    .unwrap();
+    let body: Value = resp.json().await.unwrap();
+    let responses = body.as_array().unwrap();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["id"], "a");
+    assert_eq!(responses[1]["id"], "b");
+}
+
+#[tokio::test]
+async fn test_invalid_json_is_parse_error() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let resp = client()
+        .post(&url)
+        .bearer_auth(TOKEN)
+        .header("Content-Type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], -32700);
+}
+
+#[tokio::test]
+async fn test_delete_ends_session() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let session = initialize(&url).await;
+
+    let resp = client()
+        .delete(&url)
+        .bearer_auth(TOKEN)
+        .header(SESSION_ID_HEADER, &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post(&url, json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }))
+        .header(SESSION_ID_HEADER, &session)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+async fn ping(url: &str, session: &str) -> StatusCode {
+    post(url, json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }))
+        .header(SESSION_ID_HEADER, session)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_idle_sessions_expire() {
+    let (_server, url) = start_server_with(HttpTransportOptions {
+        bind_addr: "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        bearer_token: Some(TOKEN.to_string()),
+        session_idle_timeout: Duration::from_millis(300),
+        max_sessions: DEFAULT_MAX_SESSIONS,
+        allowed_origins: Vec::new(),
+    })
+    .await;
+    let idle = initialize(&url).await;
+    let active = initialize(&url).await;
+
+    for _ in 0..3 {
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(ping(&url, &active).await, StatusCode::OK);
+    }
+    assert_eq!(ping(&url, &idle).await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_least_recently_used_session_is_evicted_at_cap() {
+    let (_server, url) = start_server_with(HttpTransportOptions {
+        bind_addr: "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        bearer_token: Some(TOKEN.to_string()),
+        session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+        max_sessions: 2,
+        allowed_origins: Vec::new(),
+    })
+    .await;
+    let first = initialize(&url).await;
+    let second = initialize(&url).await;
+    assert_eq!(ping(&url, &first).await, StatusCode::OK);
+
+    let third = initialize(&url).await;
+    assert_eq!(ping(&url, &second).await, StatusCode::NOT_FOUND);
+    assert_eq!(ping(&url, &first).await, StatusCode::OK);
+    assert_eq!(ping(&url, &third).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_foreign_origin_is_rejected() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+
+    let resp = post(&url, initialize_request())
+        .header("Origin", "https://evil.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = post(&url, initialize_request())
+        .header("Origin", "http://localhost:5173")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rebound_origin_matching_host_is_rejected() {
+    let (_server, url) = start_server(None).await;
+    let port = url.split(':').nth(2).unwrap().split('/').next().unwrap();
+
+    let resp = post(&url, initialize_request())
+        .header("Host", format!("evil.example.com:{}", port))
+        .header("Origin", format!("http://evil.example.com:{}", port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = post(&url, initialize_request())
+        .header("Host", "evil.example.com")
+        .header("Origin", "http://evil.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_non_loopback_host_is_rejected_on_loopback_bind() {
+    let (_server, url) = start_server(None).await;
+
+    let resp = post(&url, initialize_request())
+        .header("Host", "evil.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = post(&url, initialize_request())
+        .header("Host", "localhost")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_allowlisted_origin_is_accepted() {
+    let (_server, url) = start_server_with(HttpTransportOptions {
+        bind_addr: "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        bearer_token: Some(TOKEN.to_string()),
+        session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+        max_sessions: DEFAULT_MAX_SESSIONS,
+        allowed_origins: vec!["https://app.example.com/".to_string()],
+    })
+    .await;
+
+    let resp = post(&url, initialize_request())
+        .header("Origin", "https://App.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post(&url, initialize_request())
+        .header("Origin", "https://other.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_get_and_unknown_paths() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+
+    let resp = client().get(&url).bearer_auth(TOKEN).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(resp.headers()["allow"], "POST, DELETE");
+
+    let other = url.replace(MCP_HTTP_PATH, "/other");
+    let resp = client()
+        .post(other)
+        .bearer_auth(TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_resources_methods_are_served() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let resp = post(&url, initialize_request()).send().await.unwrap();
+    let session = resp.headers()[SESSION_ID_HEADER]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body: Value = resp.json().await.unwrap();
+    assert!(body["result"]["capabilities"]["resources"].is_object());
+
+    let resp = post(
+        &url,
+        json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "resources/templates/list" },
+            { "jsonrpc": "2.0", "id": 2, "method": "resources/read", "params": { "uri": "file:///etc/passwd" } },
+            { "jsonrpc": "2.0", "id": 3, "method": "resources/read", "params": { "uri": "ace:///no/such/project" } }
+        ]),
+    )
+    .header(SESSION_ID_HEADER, &session)
+    .send()
+    .await
+    .unwrap();
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(
+        body[0]["result"]["resourceTemplates"][0]["uriTemplate"],
+        "ace://{root}/{+path}"
+    );
+    assert_eq!(body[1]["error"]["code"], -32602);
+    assert_eq!(body[2]["error"]["code"], -32002);
+}
+
+#[tokio::test]
+async fn test_prompts_methods_are_served() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+    let resp = post(&url, initialize_request()).send().await.unwrap();
+    let session = resp.headers()[SESSION_ID_HEADER]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body: Value = resp.json().await.unwrap();
+    assert!(body["result"]["capabilities"]["prompts"].is_object());
+
+    let resp = post(
+        &url,
+        json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "prompts/list" },
+            { "jsonrpc": "2.0", "id": 2, "method": "prompts/get", "params": { "name": "no_such_prompt" } },
+            { "jsonrpc": "2.0", "id": 3, "method": "prompts/get", "params": { "name": "explain_code", "arguments": {} } },
+            {
+                "jsonrpc": "2.0",
+                "id": 4,
+                "method": "prompts/get",
+                "params": {
+                    "name": "explain_code",
+                    "arguments": { "topic": "login", "project_root_path": "/no/such/project" }
+                }
+            }
+        ]),
+    )
+    .header(SESSION_ID_HEADER, &session)
+    .send()
+    .await
+    .unwrap();
+    let body: Value = resp.json().await.unwrap();
+    let prompts = body[0]["result"]["prompts"].as_array().unwrap();
+    assert!(prompts.iter().any(|p| p["name"] == "explain_code"));
+    assert!(prompts.iter().any(|p| p["name"] == "plan_change"));
+    assert_eq!(body[1]["error"]["code"], -32602);
+    assert_eq!(body[2]["error"]["code"], -32602);
+    // A failed search is reported as an error with the tool's error code
+    assert_eq!(body[3]["error"]["code"], -32602);
+    assert_eq!(body[3]["error"]["data"]["error"]["code"], "path_not_found");
+}
+
+#[tokio::test]
+async fn test_sessions_negotiate_protocol_versions_independently() {
+    let (_server, url) = start_server(Some(TOKEN)).await;
+
+    let mut sessions = Vec::new();
+    for version in ["2024-11-05", "2025-06-18"] {
+        let mut request = initialize_request();
+        request["params"]["protocolVersion"] = json!(version);
+        let resp = post(&url, request).send().await.unwrap();
+        let session = resp.headers()[SESSION_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["result"]["protocolVersion"], version);
+        sessions.push(session);
+    }
+
+    let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+    let mut has_title = Vec::new();
+    for session in &sessions {
+        let body: Value = post(&url, list.clone())
+            .header(SESSION_ID_HEADER, session)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        has_title.push(body["result"]["tools"][0].get("title").is_some());
+    }
+    assert_eq!(has_title, vec![false, true]);
+
+    let resp = post(&url, list)
+        .header(SESSION_ID_HEADER, &sessions[1])
+        .header(PROTOCOL_VERSION_HEADER, "1999-01-01")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
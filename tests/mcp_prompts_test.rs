@@ -0,0 +1,257 @@
+//! Tests for MCP prompts
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::index::IndexRegistry;
+use ace_tool::mcp::prompts::{
+    builtin_prompts, get_prompt, load_prompts, PromptDef, PROJECT_ROOT_ARG,
+};
+use ace_tool::tools::ToolError;
+use tempfile::TempDir;
+
+fn test_config() -> Arc<Config> {
+    Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions::default(),
+    )
+    .unwrap()
+}
+
+fn test_registry() -> Arc<IndexRegistry> {
+    Arc::new(IndexRegistry::new(test_config()))
+}
+
+fn args(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn project_with_prompts(files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let prompts_dir = dir.path().join(".ace-tool").join("prompts");
+    fs::create_dir_all(&prompts_dir).unwrap();
+    for (name, text) in files {
+        fs::write(prompts_dir.join(name), text).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_builtin_prompts() {
+    let prompts = builtin_prompts();
+    let names: Vec<&str> = prompts.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["explain_code", "plan_change", "enhance_request"]
+    );
+
+    for prompt in &prompts {
+        assert!(prompt.uses_search());
+        assert!(prompt.description.is_some());
+        let root_arg = prompt.arguments.last().unwrap();
+        assert_eq!(root_arg.name, PROJECT_ROOT_ARG);
+        assert_eq!(root_arg.required, Some(false));
+    }
+    assert_eq!(prompts[0].arguments[0].name, "topic");
+    assert_eq!(prompts[0].arguments[0].required, Some(true));
+}
+
+#[test]
+fn test_parse_front_matter_and_render() {
+    let prompt = PromptDef::parse(
+        "review",
+        "---\ndescription: Review a module\narg: module - Module to review\narg: focus? - Aspect\nsearch: errors in {module}\n---\nReview {module} in {project_root}. {focus}\n\n{search_context}\n",
+    )
+    .unwrap();
+
+    assert_eq!(prompt.description.as_deref(), Some("Review a module"));
+    let names: Vec<&str> = prompt.arguments.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["module", "focus", PROJECT_ROOT_ARG]);
+    assert_eq!(prompt.arguments[1].required, Some(false));
+    assert_eq!(prompt.arguments[1].description.as_deref(), Some("Aspect"));
+
+    let values = args(&[("module", "src/index")]);
+    assert_eq!(
+        prompt.render_search_query(&values).as_deref(),
+        Some("errors in src/index")
+    );
+    assert_eq!(
+        prompt.render(&values, "/repo", "CONTEXT"),
+        "Review src/index in /repo. \n\nCONTEXT"
+    );
+}
+
+#[test]
+fn test_parse_without_front_matter_and_escapes() {
+    let prompt = PromptDef::parse("plain", "Use {{braces}} and { spaces }").unwrap();
+    assert!(prompt.arguments.is_empty());
+    assert!(!prompt.uses_search());
+    assert_eq!(prompt.render_search_query(&HashMap::new()), None);
+    assert_eq!(
+        prompt.render(&HashMap::new(), "/repo", ""),
+        "Use {braces} and { spaces }"
+    );
+}
+
+#[test]
+fn test_parse_rejects_invalid_prompts() {
+    let cases = [
+        ("bad name", "text"),
+        ("p", "Hello {who}"),
+        ("p", "{search_context}"),
+        ("p", "---\narg: x\n"),
+        ("p", "---\nunknown: value\n---\nbody"),
+        ("p", "---\narg: x\narg: x\n---\n{x}"),
+        ("p", "---\narg: search_context\n---\nbody"),
+        ("p", "---\nsearch: {missing}\n---\nbody"),
+        ("p", "---\narg: x\n---\nReview {x"),
+        ("p", "---\narg: x\nsearch: {x in\n---\n{x}"),
+    ];
+    for (name, text) in cases {
+        assert!(
+            PromptDef::parse(name, text).is_err(),
+            "expected error for {:?}",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_validate_arguments() {
+    let prompt = PromptDef::parse("p", "---\narg: a\narg: b?\n---\n{a}{b}").unwrap();
+
+    assert!(prompt.validate_arguments(&args(&[("a", "x")])).is_ok());
+    assert!(prompt
+        .validate_arguments(&args(&[("a", "x"), (PROJECT_ROOT_ARG, "/repo")]))
+        .is_ok());
+
+    let err = prompt.validate_arguments(&args(&[("b", "y")])).unwrap_err();
+    assert!(err.to_string().contains("Missing required argument 'a'"));
+    let err = prompt.validate_arguments(&args(&[("a", " ")])).unwrap_err();
+    assert!(err.to_string().contains("Missing required argument"));
+    let err = prompt
+        .validate_arguments(&args(&[("a", "x"), ("c", "z")]))
+        .unwrap_err();
+    assert!(err.to_string().contains("Unknown argument 'c'"));
+}
+
+#[test]
+fn test_project_prompts_load_and_override_builtins() {
+    let dir = project_with_prompts(&[
+        ("explain_code.md", "---\narg: topic\n---\nCustom {topic}"),
+        ("zz-notes.md", "Notes for {project_root}"),
+        ("broken.md", "---\nunterminated"),
+        ("ignored.txt", "not a prompt"),
+    ]);
+
+    let prompts = load_prompts(Some(dir.path()));
+    let explain = prompts.iter().find(|p| p.name == "explain_code").unwrap();
+    assert!(explain.source().is_some());
+    assert!(!explain.uses_search());
+
+    assert!(prompts.iter().any(|p| p.name == "zz-notes"));
+    assert!(prompts.iter().any(|p| p.name == "plan_change"));
+    assert!(!prompts
+        .iter()
+        .any(|p| p.name == "broken" || p.name == "ignored"));
+    assert_eq!(
+        prompts.iter().filter(|p| p.name == "explain_code").count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_get_prompt_renders_project_prompt() {
+    let dir = project_with_prompts(&[(
+        "greet.md",
+        "---\ndescription: Greet\narg: who\n---\nHello {who} from {project_root}",
+    )]);
+    let root = dir.path().to_string_lossy().replace('\\', "/");
+
+    let result = get_prompt(
+        test_config(),
+        test_registry(),
+        "greet",
+        args(&[("who", "world"), (PROJECT_ROOT_ARG, &root)]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.description.as_deref(), Some("Greet"));
+    assert_eq!(result.messages.len(), 1);
+    assert_eq!(result.messages[0].role, "user");
+    assert_eq!(
+        result.messages[0].content.text,
+        format!("Hello world from {}", root)
+    );
+
+    let json = serde_json::to_value(&result).unwrap();
+    assert_eq!(json["messages"][0]["content"]["type"], "text");
+}
+
+#[tokio::test]
+async fn test_get_prompt_errors() {
+    let dir = project_with_prompts(&[]);
+    let root = dir.path().to_string_lossy().to_string();
+
+    let err = get_prompt(
+        test_config(),
+        test_registry(),
+        "no_such_prompt",
+        args(&[(PROJECT_ROOT_ARG, &root)]),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("Unknown prompt"));
+
+    let err = get_prompt(
+        test_config(),
+        test_registry(),
+        "plan_change",
+        args(&[(PROJECT_ROOT_ARG, &root)]),
+    )
+    .await
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Missing required argument 'change'"));
+}
+
+#[tokio::test]
+async fn test_get_prompt_searches_through_the_registry() {
+    let dir = project_with_prompts(&[]);
+    let root = dir.path().to_string_lossy().replace('\\', "/");
+    let registry = test_registry();
+
+    // The project has no files to index, so the search fails
+    let err = get_prompt(
+        test_config(),
+        registry.clone(),
+        "explain_code",
+        args(&[("topic", "login"), (PROJECT_ROOT_ARG, &root)]),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(registry.len(), 1);
+    assert!(err.downcast_ref::<ToolError>().is_some(), "{}", err);
+}
+
+#[tokio::test]
+async fn test_get_prompt_reports_search_errors() {
+    let err = get_prompt(
+        test_config(),
+        test_registry(),
+        "explain_code",
+        args(&[("topic", "login"), (PROJECT_ROOT_ARG, "/no/such/project")]),
+    )
+    .await
+    .unwrap_err();
+    let error = err.downcast_ref::<ToolError>().unwrap();
+    assert_eq!(error.code(), "path_not_found");
+    assert!(err.to_string().contains("/no/such/project"), "{}", err);
+}
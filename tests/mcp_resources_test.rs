@@ -0,0 +1,293 @@
+//! Tests for MCP resources over indexed projects
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::index::{FileEntry, IndexData, IndexManager};
+use ace_tool::mcp::resources::{
+    file_uri, list_resources, project_uri, read_resource, resource_templates, ResourceUri,
+};
+use ace_tool::mcp::types::JsonRpcRequest;
+use ace_tool::mcp::{McpServer, McpSession};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+fn test_config() -> Arc<Config> {
+    Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions {
+            max_lines_per_blob: Some(2),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+/// Write files and an index covering `indexed` of them
+fn indexed_project(files: &[(&str, &[u8])], indexed: &[&str]) -> (TempDir, Arc<Config>) {
+    let dir = TempDir::new().unwrap();
+    for (path, content) in files {
+        let full = dir.path().join(path);
+        fs::create_dir_all(full.parent().unwrap()).unwrap();
+        fs::write(full, content).unwrap();
+    }
+
+    let config = test_config();
+    let manager = IndexManager::new(config.clone(), dir.path().to_path_buf()).unwrap();
+    let mut entries = HashMap::new();
+    for path in indexed {
+        let content = manager.read_indexable_content(path).unwrap();
+        let blob_hashes = manager
+            .split_file_content(path, &content)
+            .iter()
+            .map(|b| IndexManager::calculate_blob_name(&b.path, &b.content))
+            .collect();
+        entries.insert(
+            path.to_string(),
+            FileEntry {
+                mtime_secs: 1_700_000_000,
+                mtime_nanos: 0,
+                size: fs::metadata(dir.path().join(path)).unwrap().len(),
+                blob_hashes,
+            },
+        );
+    }
+    manager
+        .save_index(&IndexData {
+            version: 2,
+            config_hash: manager.config_hash().to_string(),
+            entries,
+        })
+        .unwrap();
+    (dir, config)
+}
+
+#[test]
+fn test_resource_uri_round_trip() {
+    let root = Path::new("/home/user/my project");
+    let uri = file_uri(root, "src/a#b.rs", Some(2));
+    assert_eq!(uri, "ace:///home/user/my%20project/src/a%23b.rs#chunk2");
+
+    let parsed = ResourceUri::parse(&uri).unwrap();
+    assert_eq!(parsed.path, root.join("src/a#b.rs"));
+    assert_eq!(parsed.chunk, Some(2));
+
+    assert_eq!(project_uri(root), "ace:///home/user/my%20project");
+}
+
+#[test]
+fn test_resource_uri_rejects_invalid_forms() {
+    assert!(ResourceUri::parse("file:///etc/passwd").is_err());
+    assert!(ResourceUri::parse("ace://relative/path").is_err());
+    assert!(ResourceUri::parse("ace:///repo/../etc/passwd").is_err());
+    assert!(ResourceUri::parse("ace:///repo/a.rs#chunk0").is_err());
+    assert!(ResourceUri::parse("ace:///repo/a.rs#lines").is_err());
+    assert!(ResourceUri::parse("ace:///repo/a%2").is_err());
+}
+
+#[test]
+fn test_list_resources_includes_stats_and_sorted_files() {
+    let (dir, config) = indexed_project(
+        &[
+            ("src/main.rs", b"fn main() {}\n"),
+            ("README.md", b"# Title\n"),
+            ("notes.txt", b"not indexed\n"),
+        ],
+        &["src/main.rs", "README.md"],
+    );
+    let missing = TempDir::new().unwrap();
+
+    let resources = list_resources(
+        &config,
+        &[dir.path().to_path_buf(), missing.path().to_path_buf()],
+    );
+    assert_eq!(resources.len(), 3);
+    assert_eq!(resources[0].uri, project_uri(dir.path()));
+    assert_eq!(resources[0].mime_type.as_deref(), Some("application/json"));
+    assert_eq!(resources[1].name, "README.md");
+    assert_eq!(resources[1].mime_type.as_deref(), Some("text/markdown"));
+    assert_eq!(resources[2].name, "src/main.rs");
+    assert_eq!(resources[2].size, Some(13));
+    assert_eq!(resources[2].uri, file_uri(dir.path(), "src/main.rs", None));
+}
+
+#[test]
+fn test_read_file_and_chunks() {
+    let (dir, config) = indexed_project(&[("lib.rs", b"one\ntwo\nthree\n")], &["lib.rs"]);
+    let root = dir.path();
+
+    let file = read_resource(
+        &config,
+        &[root.to_path_buf()],
+        &file_uri(root, "lib.rs", None),
+    )
+    .unwrap();
+    assert_eq!(file.text, "one\ntwo\nthree\n");
+    assert_eq!(file.mime_type.as_deref(), Some("text/x-rust"));
+
+    let chunk = read_resource(
+        &config,
+        &[root.to_path_buf()],
+        &file_uri(root, "lib.rs", Some(2)),
+    )
+    .unwrap();
+    assert_eq!(chunk.text, "three");
+
+    let err = read_resource(
+        &config,
+        &[root.to_path_buf()],
+        &file_uri(root, "lib.rs", Some(3)),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("out of range"));
+}
+
+#[test]
+fn test_read_decodes_like_indexing() {
+    // "中文" in GBK
+    let gbk: &[u8] = &[0xD6, 0xD0, 0xCE, 0xC4, b'\n'];
+    let (dir, config) = indexed_project(&[("gbk.txt", gbk)], &["gbk.txt"]);
+
+    let file = read_resource(
+        &config,
+        &[dir.path().to_path_buf()],
+        &file_uri(dir.path(), "gbk.txt", None),
+    )
+    .unwrap();
+    assert_eq!(file.text, "中文\n");
+}
+
+#[test]
+fn test_read_only_serves_indexed_files() {
+    let (dir, config) =
+        indexed_project(&[("a.rs", b"a\n"), ("secret.txt", b"secret\n")], &["a.rs"]);
+    let root = dir.path();
+
+    let err = read_resource(
+        &config,
+        &[root.to_path_buf()],
+        &file_uri(root, "secret.txt", None),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("not indexed"));
+
+    let outside = TempDir::new().unwrap();
+    let err = read_resource(
+        &config,
+        &[outside.path().to_path_buf()],
+        &project_uri(outside.path()),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("No indexed project"));
+}
+
+#[test]
+fn test_read_only_serves_offered_projects() {
+    let (dir, config) = indexed_project(&[("a.rs", b"a\n")], &["a.rs"]);
+    let other = TempDir::new().unwrap();
+
+    let err = read_resource(
+        &config,
+        &[other.path().to_path_buf()],
+        &file_uri(dir.path(), "a.rs", None),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("not offered"), "{}", err);
+
+    let err = read_resource(&config, &[], &project_uri(dir.path())).unwrap_err();
+    assert!(err.to_string().contains("not offered"), "{}", err);
+}
+
+#[test]
+fn test_read_index_statistics() {
+    let (dir, config) = indexed_project(
+        &[("a.rs", b"1\n2\n3\n"), ("b.rs", b"x\n"), ("c.md", b"# c\n")],
+        &["a.rs", "b.rs", "c.md"],
+    );
+
+    let stats = read_resource(
+        &config,
+        &[dir.path().to_path_buf()],
+        &project_uri(dir.path()),
+    )
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&stats.text).unwrap();
+    assert_eq!(value["files"], 3);
+    assert_eq!(value["blobs"], 4);
+    assert_eq!(value["chunked_files"], 1);
+    assert_eq!(value["total_bytes"], 6 + 2 + 4);
+    assert_eq!(value["index_version"], 2);
+    assert_eq!(value["extensions"]["rs"], 2);
+    assert_eq!(value["extensions"]["md"], 1);
+}
+
+#[test]
+fn test_resource_templates() {
+    let templates = resource_templates();
+    let uris: Vec<&str> = templates.iter().map(|t| t.uri_template.as_str()).collect();
+    assert_eq!(
+        uris,
+        vec![
+            "ace://{root}/{+path}",
+            "ace://{root}/{+path}#chunk{n}",
+            "ace://{root}"
+        ]
+    );
+    let json = serde_json::to_value(&templates[0]).unwrap();
+    assert!(json.get("uriTemplate").is_some());
+}
+
+async fn session_call(
+    server: &McpServer,
+    session: &McpSession,
+    method: &str,
+    params: Value,
+) -> Value {
+    let response = server
+        .handle_session_request(
+            session,
+            JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: Some(json!(1)),
+                method: method.to_string(),
+                params: Some(params),
+            },
+        )
+        .await
+        .unwrap();
+    serde_json::to_value(response).unwrap()
+}
+
+#[tokio::test]
+async fn test_searched_roots_stay_in_their_session() {
+    let (dir, config) = indexed_project(&[("src/a.rs", b"fn a()\n")], &["src/a.rs"]);
+    let cwd = TempDir::new().unwrap();
+    let server = McpServer::new(config, None).with_project_root(cwd.path().to_path_buf());
+    let searcher = McpSession::new();
+    let other = McpSession::new();
+
+    // Fails validation before any network access, but still records the root
+    let root = dir.path().to_string_lossy().to_string();
+    session_call(
+        &server,
+        &searcher,
+        "tools/call",
+        json!({ "name": "search_context", "arguments": { "project_root_path": root } }),
+    )
+    .await;
+
+    let uri = project_uri(dir.path());
+    let read = session_call(&server, &searcher, "resources/read", json!({ "uri": uri })).await;
+    assert!(read["result"]["contents"].is_array(), "{}", read);
+    let list = session_call(&server, &searcher, "resources/list", json!({})).await;
+    assert_eq!(list["result"]["resources"][0]["uri"], uri);
+
+    let read = session_call(&server, &other, "resources/read", json!({ "uri": uri })).await;
+    assert_eq!(read["error"]["code"], -32002);
+    let list = session_call(&server, &other, "resources/list", json!({})).await;
+    assert_eq!(list["result"]["resources"], json!([]));
+}
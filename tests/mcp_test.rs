@@ -1,8 +1,10 @@
 //! Tests for MCP types module
 
+use ace_tool::config::{Config, ConfigOptions};
+use ace_tool::mcp::protocol::{paginate, ProtocolVersion};
 use ace_tool::mcp::types::*;
-use ace_tool::mcp::TransportMode;
-use serde_json::json;
+use ace_tool::mcp::{McpServer, TransportMode};
+use serde_json::{json, Value};
 
 #[test]
 fn test_json_rpc_request_serialization() {
@@ -74,11 +76,15 @@ fn test_json_rpc_response_serialization() {
 fn test_server_capabilities() {
     let caps = ServerCapabilities {
         tools: Some(ToolsCapability {}),
+        resources: Some(ResourcesCapability {}),
+        prompts: Some(PromptsCapability {}),
         logging: Some(LoggingCapability {}),
     };
 
     let json = serde_json::to_string(&caps).unwrap();
     assert!(json.contains("\"tools\""));
+    assert!(json.contains("\"resources\""));
+    assert!(json.contains("\"prompts\""));
     assert!(json.contains("\"logging\""));
 }
 
@@ -100,6 +106,8 @@ fn test_initialize_result() {
         protocol_version: "2024-11-05".to_string(),
         capabilities: ServerCapabilities {
             tools: Some(ToolsCapability {}),
+            resources: None,
+            prompts: None,
             logging: None,
         },
         server_info: ServerInfo {
@@ -117,16 +125,22 @@ fn test_initialize_result() {
 fn test_tool_definition() {
     let tool = Tool {
         name: "search_context".to_string(),
+        title: None,
         description: "Search the codebase".to_string(),
         input_schema: json!({
             "type": "object",
             "properties": {}
         }),
+        output_schema: None,
+        annotations: None,
     };
 
     let json = serde_json::to_string(&tool).unwrap();
     assert!(json.contains("\"inputSchema\""));
     assert!(json.contains("search_context"));
+    assert!(!json.contains("\"title\""));
+    assert!(!json.contains("\"outputSchema\""));
+    assert!(!json.contains("\"annotations\""));
 }
 
 #[test]
@@ -135,20 +149,28 @@ fn test_list_tools_result() {
         tools: vec![
             Tool {
                 name: "tool1".to_string(),
+                title: None,
                 description: "First tool".to_string(),
                 input_schema: json!({}),
+                output_schema: None,
+                annotations: None,
             },
             Tool {
                 name: "tool2".to_string(),
+                title: None,
                 description: "Second tool".to_string(),
                 input_schema: json!({}),
+                output_schema: None,
+                annotations: None,
             },
         ],
+        next_cursor: None,
     };
 
     let json = serde_json::to_string(&result).unwrap();
     assert!(json.contains("tool1"));
     assert!(json.contains("tool2"));
+    assert!(!json.contains("nextCursor"));
 }
 
 #[test]
@@ -190,11 +212,17 @@ fn test_call_tool_result() {
             TextContent::new("Result 1".to_string()),
             TextContent::new("Result 2".to_string()),
         ],
+        structured_content: None,
+        is_error: None,
+        meta: None,
     };
 
     let json = serde_json::to_string(&result).unwrap();
     assert!(json.contains("Result 1"));
     assert!(json.contains("Result 2"));
+    assert!(!json.contains("_meta"));
+    assert!(!json.contains("structuredContent"));
+    assert!(!json.contains("isError"));
 }
 
 #[test]
@@ -302,3 +330,229 @@ fn test_transport_mode_unwrap_or_default() {
         TransportMode::Line
     );
 }
+
+// ---------------------------------------------------------------------------
+// Protocol revision conformance
+// ---------------------------------------------------------------------------
+
+fn test_server() -> McpServer {
+    let config = Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions::default(),
+    )
+    .unwrap();
+    McpServer::new(config, None)
+}
+
+async fn call(server: &McpServer, method: &str, params: Option<Value>) -> Value {
+    let response = server
+        .handle_request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            method: method.to_string(),
+            params,
+        })
+        .await
+        .unwrap();
+    serde_json::to_value(response).unwrap()
+}
+
+async fn initialize(server: &McpServer, version: &str) -> Value {
+    call(
+        server,
+        "initialize",
+        Some(json!({
+            "protocolVersion": version,
+            "capabilities": { "roots": { "listChanged": true } },
+            "clientInfo": { "name": "conformance", "version": "1.0" }
+        })),
+    )
+    .await
+}
+
+/// The `search_context` entry of `tools/list`
+async fn search_tool(server: &McpServer) -> Value {
+    let list = call(server, "tools/list", None).await;
+    list["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["name"] == "search_context")
+        .cloned()
+        .unwrap()
+}
+
+/// Result of a `search_context` call that fails validation before any network access
+async fn search_without_query(server: &McpServer) -> Value {
+    call(
+        server,
+        "tools/call",
+        Some(json!({ "name": "search_context", "arguments": {} })),
+    )
+    .await["result"]
+        .clone()
+}
+
+#[test]
+fn test_protocol_version_negotiation() {
+    for version in ProtocolVersion::ALL {
+        assert_eq!(ProtocolVersion::negotiate(Some(version.as_str())), version);
+        assert_eq!(ProtocolVersion::parse(version.as_str()), Some(version));
+    }
+    assert_eq!(
+        ProtocolVersion::negotiate(Some("2099-01-01")),
+        ProtocolVersion::LATEST
+    );
+    assert_eq!(ProtocolVersion::negotiate(None), ProtocolVersion::FALLBACK);
+    assert!(ProtocolVersion::V2024_11_05 < ProtocolVersion::V2025_06_18);
+    assert_eq!(ProtocolVersion::V2025_03_26.to_string(), "2025-03-26");
+}
+
+#[test]
+fn test_paginate() {
+    let items: Vec<u32> = (0..5).collect();
+
+    let (page, next) = paginate(items.clone(), None, 2).unwrap();
+    assert_eq!(page, vec![0, 1]);
+    assert_eq!(next.as_deref(), Some("2"));
+
+    let (page, next) = paginate(items.clone(), next.as_deref(), 2).unwrap();
+    assert_eq!(page, vec![2, 3]);
+    let (page, next) = paginate(items.clone(), next.as_deref(), 2).unwrap();
+    assert_eq!(page, vec![4]);
+    assert_eq!(next, None);
+
+    assert!(paginate(items.clone(), Some("6"), 2).is_err());
+    assert!(paginate(items, Some("not-a-cursor"), 2).is_err());
+}
+
+#[tokio::test]
+async fn test_conformance_2024_11_05() {
+    let server = test_server();
+    let init = initialize(&server, "2024-11-05").await;
+    assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+    assert!(init["result"]["capabilities"]["tools"].is_object());
+
+    let tool = search_tool(&server).await;
+    assert!(tool["inputSchema"].is_object());
+    assert!(tool.get("annotations").is_none());
+    assert!(tool.get("title").is_none());
+    assert!(tool.get("outputSchema").is_none());
+
+    let result = search_without_query(&server).await;
+    assert!(result["content"][0]["text"].is_string());
+    assert!(result.get("structuredContent").is_none());
+}
+
+#[tokio::test]
+async fn test_conformance_2025_03_26() {
+    let server = test_server();
+    let init = initialize(&server, "2025-03-26").await;
+    assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+
+    let tool = search_tool(&server).await;
+    assert_eq!(tool["annotations"]["readOnlyHint"], true);
+    assert_eq!(tool["annotations"]["openWorldHint"], true);
+    assert!(tool.get("title").is_none());
+    assert!(tool.get("outputSchema").is_none());
+
+    let result = search_without_query(&server).await;
+    assert!(result.get("structuredContent").is_none());
+}
+
+#[tokio::test]
+async fn test_conformance_2025_06_18() {
+    let server = test_server();
+    let init = initialize(&server, "2025-06-18").await;
+    assert_eq!(init["result"]["protocolVersion"], "2025-06-18");
+
+    let tool = search_tool(&server).await;
+    assert!(tool["annotations"].is_object());
+    assert_eq!(tool["title"], "Search Codebase Context");
+    assert_eq!(tool["outputSchema"]["required"], json!(["context"]));
+
+    // Failed calls carry no structured output
+    let result = search_without_query(&server).await;
+    assert_eq!(result["isError"], true);
+    assert!(result.get("structuredContent").is_none());
+}
+
+#[test]
+fn test_shape_call_result_per_version() {
+    let result = CallToolResult {
+        content: vec![TextContent::new("found".to_string())],
+        structured_content: Some(json!({ "context": "found" })),
+        is_error: None,
+        meta: None,
+    };
+
+    for version in ProtocolVersion::ALL {
+        let shaped = serde_json::to_value(version.shape_call_result(result.clone())).unwrap();
+        assert_eq!(
+            shaped.get("structuredContent").is_some(),
+            version >= ProtocolVersion::V2025_06_18,
+            "{}",
+            version
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_tool_errors_are_flagged_in_every_revision() {
+    for version in ProtocolVersion::ALL {
+        let server = test_server();
+        initialize(&server, version.as_str()).await;
+
+        let result = search_without_query(&server).await;
+        assert_eq!(result["isError"], true, "{}", version);
+        assert_eq!(result["_meta"]["error"]["code"], "invalid_arguments");
+        assert_eq!(result["_meta"]["error"]["retryable"], false);
+        assert_eq!(result["content"][0]["text"], "Error: query is required");
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_or_missing_protocol_version() {
+    let server = test_server();
+    let init = initialize(&server, "1999-01-01").await;
+    assert_eq!(
+        init["result"]["protocolVersion"],
+        ProtocolVersion::LATEST.as_str()
+    );
+    assert_eq!(
+        server.session().protocol_version().await,
+        ProtocolVersion::LATEST
+    );
+
+    let server = test_server();
+    assert_eq!(
+        server.session().protocol_version().await,
+        ProtocolVersion::FALLBACK
+    );
+    let init = call(&server, "initialize", None).await;
+    assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+
+    let init = call(&server, "initialize", Some(json!({ "protocolVersion": 5 }))).await;
+    assert_eq!(init["error"]["code"], -32602);
+}
+
+#[tokio::test]
+async fn test_list_methods_accept_cursors() {
+    let server = test_server();
+    initialize(&server, "2025-06-18").await;
+
+    let list = call(&server, "tools/list", Some(json!({ "cursor": null }))).await;
+    assert!(list["result"]["tools"].is_array());
+    assert!(list["result"].get("nextCursor").is_none());
+
+    for method in [
+        "tools/list",
+        "resources/list",
+        "resources/templates/list",
+        "prompts/list",
+    ] {
+        let response = call(&server, method, Some(json!({ "cursor": "bogus" }))).await;
+        assert_eq!(response["error"]["code"], -32602, "{}", method);
+    }
+}
@@ -1,16 +1,22 @@
 //! Tests for prompt_enhancer module
 
+use ace_tool::config::{Config, ConfigOptions};
 use ace_tool::enhancer::prompt_enhancer::{
     get_enhancer_endpoint, ENV_ENHANCER_ENDPOINT, ENV_ENHANCER_ENDPOINT_LEGACY,
+    ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT,
 };
+use ace_tool::enhancer::PromptEnhancer;
+use ace_tool::index::IndexRegistry;
 use ace_tool::service::{
     extract_enhanced_prompt, get_third_party_config, is_chinese_text, parse_chat_history,
-    parse_streaming_response, render_enhance_prompt, replace_tool_names, ChatMessage,
-    EnhancerEndpoint, DEFAULT_CLAUDE_MODEL, DEFAULT_CODEX_MODEL, DEFAULT_GEMINI_MODEL,
-    DEFAULT_MODEL, DEFAULT_OPENAI_MODEL, ENV_ENHANCER_BASE_URL, ENV_ENHANCER_MODEL,
-    ENV_ENHANCER_TOKEN, NODE_ID_NEW, NODE_ID_OLD,
+    parse_streaming_response, render_enhance_prompt, replace_tool_names, to_user_assistant_turns,
+    ChatMessage, ConversationHistory, EnhanceInput, EnhancerEndpoint, GenerationSettings,
+    DEFAULT_CLAUDE_MODEL, DEFAULT_CODEX_MODEL, DEFAULT_GEMINI_MODEL, DEFAULT_MODEL,
+    DEFAULT_OPENAI_MODEL, ENV_ENHANCER_BASE_URL, ENV_ENHANCER_MODEL, ENV_ENHANCER_TOKEN,
+    NODE_ID_NEW, NODE_ID_OLD,
 };
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
 
 /// Global mutex for tests that modify environment variables
 /// All env-modifying tests must acquire this lock to prevent race conditions
@@ -405,6 +411,131 @@ fn test_parse_chat_history_long_conversation() {
     assert_eq!(result.len(), 40);
 }
 
+#[test]
+fn test_parse_chat_history_ignores_prefixes_in_code_fences() {
+    let history = "User: Why does this print twice?\n```text\nUser: admin\nAssistant: ready\n```\nAssistant: The loop runs twice.\n~~~~\nAI: not a turn\n~~~\nstill code\n~~~~\nUser: Thanks";
+    let result = parse_chat_history(history);
+
+    assert_eq!(result.len(), 3);
+    assert_eq!(
+        result[0].content,
+        "Why does this print twice?\n```text\nUser: admin\nAssistant: ready\n```"
+    );
+    assert_eq!(result[1].role, "assistant");
+    assert!(result[1].content.contains("AI: not a turn"));
+    assert!(result[1].content.ends_with("still code\n~~~~"));
+    assert_eq!(result[2].role, "user");
+    assert_eq!(result[2].content, "Thanks");
+}
+
+#[test]
+fn test_parse_chat_history_fence_opened_on_prefix_line() {
+    let history = "Assistant: ```\nUser: inside\n```\nUser: outside";
+    let result = parse_chat_history(history);
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].content, "```\nUser: inside\n```");
+    assert_eq!(result[1].content, "outside");
+}
+
+#[test]
+fn test_parse_chat_history_unclosed_fence_keeps_rest() {
+    let history = "User: ```\nAssistant: inside";
+    let result = parse_chat_history(history);
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].content, "```\nAssistant: inside");
+}
+
+#[test]
+fn test_parse_chat_history_json_text_stays_text() {
+    let history = r#"[{"role":"user","content":"hi"}]"#;
+    let result = parse_chat_history(history);
+    assert!(result.is_empty());
+
+    let parsed: ConversationHistory = serde_json::from_value(serde_json::json!(history)).unwrap();
+    assert_eq!(parsed, ConversationHistory::Text(history.to_string()));
+}
+
+#[test]
+fn test_parse_chat_history_text_starting_with_bracket() {
+    let result = parse_chat_history("[draft]\nUser: Hello");
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].content, "Hello");
+}
+
+#[test]
+fn test_conversation_history_normalizes_roles() {
+    let history: ConversationHistory = serde_json::from_str(
+        r#"[{"role":"Human","content":"User: literal\nAI: text"},{"role":"model","content":"b"},{"role":"developer","content":"c"}]"#,
+    )
+    .unwrap();
+    let messages = history.normalized().unwrap().to_messages();
+    let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(roles, vec!["user", "assistant", "system"]);
+    assert_eq!(messages[0].content, "User: literal\nAI: text");
+
+    let text: ConversationHistory = serde_json::from_str(r#""User: hi""#).unwrap();
+    assert_eq!(
+        text.normalized().unwrap(),
+        ConversationHistory::Text("User: hi".to_string())
+    );
+}
+
+#[test]
+fn test_conversation_history_rejects_unknown_role() {
+    let history: ConversationHistory =
+        serde_json::from_str(r#"[{"role":"tool","content":"x"}]"#).unwrap();
+    let err = history.normalized().unwrap_err().to_string();
+    assert!(err.contains("'tool'"));
+}
+
+#[test]
+fn test_conversation_history_display_renders_messages() {
+    let history = ConversationHistory::Messages(vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: "Be terse".to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: "Hi".to_string(),
+        },
+    ]);
+    assert_eq!(history.display(), "System: Be terse\nUser: Hi");
+    assert_eq!(ConversationHistory::from("User: Hi").display(), "User: Hi");
+
+    let mut input = EnhanceInput::new("prompt", history);
+    input.template =
+        ace_tool::enhancer::EnhanceTemplate::parse("{conversation_history}\n{original_prompt}")
+            .unwrap();
+    assert_eq!(input.render_prompt(), "System: Be terse\nUser: Hi\nprompt");
+}
+
+#[test]
+fn test_to_user_assistant_turns_merges_and_maps_system() {
+    let message = |role: &str, content: &str| ChatMessage {
+        role: role.to_string(),
+        content: content.to_string(),
+    };
+    let turns = to_user_assistant_turns(vec![
+        message("system", "Be terse"),
+        message("user", "Hi"),
+        message("assistant", "Hello"),
+        message("assistant", "Anything else?"),
+        message("user", "No"),
+    ]);
+
+    assert_eq!(
+        turns,
+        vec![
+            message("user", "System: Be terse\n\nHi"),
+            message("assistant", "Hello\n\nAnything else?"),
+            message("user", "No"),
+        ]
+    );
+}
+
 // ========================================================================
 // ChatMessage Tests
 // ========================================================================
@@ -482,8 +613,6 @@ fn test_full_workflow_simulation() {
 
 #[test]
 fn test_get_enhancer_endpoint_all_cases() {
-    use std::sync::Mutex;
-    static ENV_MUTEX: Mutex<()> = Mutex::new(());
     let _guard = ENV_MUTEX.lock().unwrap();
 
     let original_value = std::env::var(ENV_ENHANCER_ENDPOINT).ok();
@@ -780,7 +909,7 @@ fn test_get_third_party_config_missing_base_url() {
     std::env::remove_var(ENV_ENHANCER_BASE_URL);
     std::env::set_var(ENV_ENHANCER_TOKEN, "test-token");
 
-    let result = get_third_party_config(EnhancerEndpoint::Claude);
+    let result = get_third_party_config(EnhancerEndpoint::Claude, &GenerationSettings::default());
     assert!(result.is_err());
     assert!(result
         .unwrap_err()
@@ -808,7 +937,7 @@ fn test_get_third_party_config_missing_token() {
     std::env::set_var(ENV_ENHANCER_BASE_URL, "https://api.example.com");
     std::env::remove_var(ENV_ENHANCER_TOKEN);
 
-    let result = get_third_party_config(EnhancerEndpoint::Claude);
+    let result = get_third_party_config(EnhancerEndpoint::Claude, &GenerationSettings::default());
     assert!(result.is_err());
     assert!(result
         .unwrap_err()
@@ -839,17 +968,20 @@ fn test_get_third_party_config_default_models() {
     std::env::remove_var(ENV_ENHANCER_MODEL);
 
     // Test Claude default model
-    let config = get_third_party_config(EnhancerEndpoint::Claude).unwrap();
+    let config =
+        get_third_party_config(EnhancerEndpoint::Claude, &GenerationSettings::default()).unwrap();
     assert_eq!(config.model, DEFAULT_CLAUDE_MODEL);
     assert_eq!(config.base_url, "https://api.example.com"); // trailing slash removed
     assert_eq!(config.token, "test-token");
 
     // Test OpenAI default model
-    let config = get_third_party_config(EnhancerEndpoint::OpenAI).unwrap();
+    let config =
+        get_third_party_config(EnhancerEndpoint::OpenAI, &GenerationSettings::default()).unwrap();
     assert_eq!(config.model, DEFAULT_OPENAI_MODEL);
 
     // Test Gemini default model
-    let config = get_third_party_config(EnhancerEndpoint::Gemini).unwrap();
+    let config =
+        get_third_party_config(EnhancerEndpoint::Gemini, &GenerationSettings::default()).unwrap();
     assert_eq!(config.model, DEFAULT_GEMINI_MODEL);
 
     // Restore
@@ -879,7 +1011,8 @@ fn test_get_third_party_config_custom_model() {
     std::env::set_var(ENV_ENHANCER_TOKEN, "custom-token");
     std::env::set_var(ENV_ENHANCER_MODEL, "custom-model-v1");
 
-    let config = get_third_party_config(EnhancerEndpoint::Claude).unwrap();
+    let config =
+        get_third_party_config(EnhancerEndpoint::Claude, &GenerationSettings::default()).unwrap();
     assert_eq!(config.model, "custom-model-v1");
 
     // Restore
@@ -917,3 +1050,52 @@ fn test_default_model_constants() {
     assert_eq!(DEFAULT_GEMINI_MODEL, "gemini-3-flash-preview");
     assert_eq!(DEFAULT_CODEX_MODEL, "gpt-5.3-codex");
 }
+
+// ========================================================================
+// Search Context Injection Tests
+// ========================================================================
+
+#[test]
+fn test_search_context_injection_uses_the_registry() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+
+    let orig_endpoint = std::env::var(ENV_ENHANCER_ENDPOINT).ok();
+    let orig_include = std::env::var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT).ok();
+    std::env::set_var(ENV_ENHANCER_ENDPOINT, "new");
+    std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, "1");
+
+    let config = Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions::default(),
+    )
+    .unwrap();
+    let registry = Arc::new(IndexRegistry::new(config.clone()));
+    let enhancer = PromptEnhancer::new(config)
+        .unwrap()
+        .with_cache(false)
+        .with_registry(registry.clone());
+
+    // The project has no files to index, so the search fails before any request
+    let project = TempDir::new().unwrap();
+    let result = tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(enhancer.enhance_simple(
+            "add login",
+            &ConversationHistory::default(),
+            Some(project.path()),
+        ));
+
+    // Restore
+    match orig_endpoint {
+        Some(v) => std::env::set_var(ENV_ENHANCER_ENDPOINT, v),
+        None => std::env::remove_var(ENV_ENHANCER_ENDPOINT),
+    }
+    match orig_include {
+        Some(v) => std::env::set_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT, v),
+        None => std::env::remove_var(ENV_ENHANCER_INCLUDE_SEARCH_CONTEXT),
+    }
+
+    assert!(result.is_err());
+    assert_eq!(registry.len(), 1);
+}
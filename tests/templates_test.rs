@@ -1,6 +1,17 @@
 //! Tests for enhancer templates module
 
-use ace_tool::enhancer::templates::{ENHANCER_UI_HTML, ENHANCE_PROMPT_TEMPLATE};
+use std::fs;
+use std::sync::Mutex;
+
+use ace_tool::enhancer::templates::{
+    load_enhance_template, EnhanceTemplate, TemplateVars, ENHANCER_UI_HTML,
+    ENHANCE_PROMPT_TEMPLATE, ENHANCE_TEMPLATE_FILE_NAME,
+};
+use ace_tool::utils::project_detector::ENV_CONFIG_DIR;
+use tempfile::TempDir;
+
+/// Serializes tests that modify the user config dir environment variable
+static ENV_MUTEX: Mutex<()> = Mutex::new(());
 
 // ========================================================================
 // ENHANCE_PROMPT_TEMPLATE Tests
@@ -158,6 +169,12 @@ fn test_enhancer_ui_html_has_re_enhance_endpoint() {
     assert!(ENHANCER_UI_HTML.contains("/api/re-enhance"));
 }
 
+#[test]
+fn test_enhancer_ui_html_has_diff_endpoint() {
+    assert!(ENHANCER_UI_HTML.contains("/api/diff"));
+    assert!(ENHANCER_UI_HTML.contains("function toggleDiff()"));
+}
+
 // ========================================================================
 // JavaScript Function Tests
 // ========================================================================
@@ -348,3 +365,136 @@ fn test_enhancer_ui_html_has_timeout_handling() {
 fn test_enhancer_ui_html_can_close_window() {
     assert!(ENHANCER_UI_HTML.contains("window.close"));
 }
+
+// ========================================================================
+// EnhanceTemplate Tests
+// ========================================================================
+
+#[test]
+fn test_builtin_template_parses() {
+    let template = EnhanceTemplate::builtin();
+    assert!(template.uses("original_prompt"));
+    assert!(!template.uses("search_context"));
+    assert!(template.source().is_none());
+}
+
+#[test]
+fn test_template_renders_all_placeholders() {
+    let template = EnhanceTemplate::parse(
+        "[{project_name}/{language}] {original_prompt}\n{search_context}\n{conversation_history}",
+    )
+    .unwrap();
+    let rendered = template.render(&TemplateVars {
+        original_prompt: "Add login",
+        search_context: "src/auth.rs",
+        language: "English",
+        project_name: "demo",
+        conversation_history: "User: hi",
+    });
+    assert_eq!(rendered, "[demo/English] Add login\nsrc/auth.rs\nUser: hi");
+}
+
+#[test]
+fn test_template_does_not_rescan_substituted_values() {
+    let template = EnhanceTemplate::parse("{original_prompt} / {language}").unwrap();
+    let rendered = template.render(&TemplateVars {
+        original_prompt: "keep {language} literal",
+        language: "English",
+        ..Default::default()
+    });
+    assert_eq!(rendered, "keep {language} literal / English");
+}
+
+#[test]
+fn test_template_brace_escapes_and_literals() {
+    let template = EnhanceTemplate::parse("{{\"a\": 1}} { } {original_prompt}").unwrap();
+    let rendered = template.render(&TemplateVars {
+        original_prompt: "x",
+        ..Default::default()
+    });
+    assert_eq!(rendered, "{\"a\": 1} { } x");
+}
+
+#[test]
+fn test_template_rejects_unknown_placeholder() {
+    let err = EnhanceTemplate::parse("{original_prompt} {test_plan}").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Unknown template placeholder '{test_plan}'"));
+}
+
+#[test]
+fn test_template_rejects_unterminated_placeholder() {
+    let err = EnhanceTemplate::parse("{original_prompt} {language").unwrap_err();
+    assert!(err.to_string().contains("Unterminated"));
+}
+
+#[test]
+fn test_template_requires_original_prompt() {
+    let err = EnhanceTemplate::parse("Only {search_context}").unwrap_err();
+    assert!(err.to_string().contains("{original_prompt}"));
+}
+
+#[test]
+fn test_load_enhance_template_prefers_project_file() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+    fs::write(
+        user_dir.path().join(ENHANCE_TEMPLATE_FILE_NAME),
+        "user: {original_prompt}",
+    )
+    .unwrap();
+
+    let project = TempDir::new().unwrap();
+    fs::create_dir_all(project.path().join(".ace-tool")).unwrap();
+    let project_file = project
+        .path()
+        .join(".ace-tool")
+        .join(ENHANCE_TEMPLATE_FILE_NAME);
+    fs::write(&project_file, "project: {original_prompt}").unwrap();
+
+    let template = load_enhance_template(Some(project.path())).unwrap();
+    assert_eq!(template.source(), Some(project_file.as_path()));
+
+    let other_project = TempDir::new().unwrap();
+    let template = load_enhance_template(Some(other_project.path())).unwrap();
+    let rendered = template.render(&TemplateVars {
+        original_prompt: "x",
+        ..Default::default()
+    });
+    assert_eq!(rendered, "user: x");
+
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_load_enhance_template_falls_back_to_builtin() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+
+    let project = TempDir::new().unwrap();
+    let template = load_enhance_template(Some(project.path())).unwrap();
+    assert!(template.source().is_none());
+    assert!(template.uses("original_prompt"));
+
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
+
+#[test]
+fn test_load_enhance_template_reports_malformed_file() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let user_dir = TempDir::new().unwrap();
+    std::env::set_var(ENV_CONFIG_DIR, user_dir.path());
+    fs::write(
+        user_dir.path().join(ENHANCE_TEMPLATE_FILE_NAME),
+        "{original_prompt} {unknown}",
+    )
+    .unwrap();
+
+    let err = load_enhance_template(None).unwrap_err();
+    assert!(err.to_string().contains("Invalid template"));
+
+    std::env::remove_var(ENV_CONFIG_DIR);
+}
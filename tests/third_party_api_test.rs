@@ -1,9 +1,12 @@
 //! Tests for third-party API endpoints (Claude, OpenAI, Gemini)
 //! Uses wiremock to mock HTTP responses
 
+use ace_tool::enhancer::guidelines::ProjectGuidelines;
+use ace_tool::enhancer::templates::EnhanceTemplate;
+use ace_tool::service::generation::DEFAULT_MAX_TOKENS;
 use ace_tool::service::{
     call_claude_endpoint, call_codex_endpoint, call_gemini_endpoint, call_openai_endpoint,
-    ThirdPartyConfig,
+    ConversationHistory, EnhanceInput, GenerationSettings, ThirdPartyConfig,
 };
 use reqwest::Client;
 use serde_json::Value;
@@ -59,9 +62,15 @@ async fn test_claude_api_success() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Enhanced prompt for testing");
@@ -101,9 +110,15 @@ async fn test_claude_api_success_without_xml_tag() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Plain enhanced prompt without XML tags");
@@ -147,9 +162,15 @@ async fn test_claude_api_multiple_content_blocks() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "First part Second part");
@@ -176,9 +197,15 @@ async fn test_claude_api_error_401() {
         base_url: mock_server.uri(),
         token: "invalid-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     let err = result.unwrap_err().to_string();
@@ -214,9 +241,15 @@ async fn test_claude_api_empty_response() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("empty response"));
@@ -256,10 +289,16 @@ async fn test_claude_api_with_conversation_history() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
     let history = "User: Hello\nAssistant: Hi there!";
-    let result = call_claude_endpoint(&client, &config, "Test prompt", history).await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", history.into()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Enhanced with history");
@@ -300,9 +339,15 @@ async fn test_claude_api_url_normalization() {
         base_url: format!("{}/v1", mock_server.uri()),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
 }
@@ -351,9 +396,15 @@ async fn test_openai_api_success() {
         base_url: mock_server.uri(),
         token: "test-openai-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
-    let result = call_openai_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "OpenAI enhanced prompt");
@@ -397,9 +448,15 @@ async fn test_openai_api_success_without_xml_tag() {
         base_url: mock_server.uri(),
         token: "test-openai-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
-    let result = call_openai_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Plain OpenAI response");
@@ -427,9 +484,15 @@ async fn test_openai_api_error_401() {
         base_url: mock_server.uri(),
         token: "invalid-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
-    let result = call_openai_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     let err = result.unwrap_err().to_string();
@@ -465,9 +528,15 @@ async fn test_openai_api_empty_choices() {
         base_url: mock_server.uri(),
         token: "test-openai-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
-    let result = call_openai_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("empty response"));
@@ -511,10 +580,16 @@ async fn test_openai_api_with_conversation_history() {
         base_url: mock_server.uri(),
         token: "test-openai-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
     let history = "User: What is Rust?\nAssistant: Rust is a systems programming language.";
-    let result = call_openai_endpoint(&client, &config, "Tell me more", history).await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Tell me more", history.into()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Response with history context");
@@ -559,9 +634,15 @@ async fn test_openai_api_url_normalization() {
         base_url: format!("{}/v1", mock_server.uri()),
         token: "test-openai-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
-    let result = call_openai_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
 }
@@ -610,9 +691,15 @@ async fn test_gemini_api_success() {
         base_url: mock_server.uri(),
         token: "test-gemini-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Gemini enhanced prompt");
@@ -656,9 +743,15 @@ async fn test_gemini_api_success_without_xml_tag() {
         base_url: mock_server.uri(),
         token: "test-gemini-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Plain Gemini response");
@@ -686,9 +779,15 @@ async fn test_gemini_api_error_401() {
         base_url: mock_server.uri(),
         token: "invalid-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     let err = result.unwrap_err().to_string();
@@ -720,9 +819,15 @@ async fn test_gemini_api_empty_candidates() {
         base_url: mock_server.uri(),
         token: "test-gemini-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("empty response"));
@@ -766,10 +871,16 @@ async fn test_gemini_api_with_conversation_history() {
         base_url: mock_server.uri(),
         token: "test-gemini-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
     let history = "User: Hello\nAssistant: Hi!";
-    let result = call_gemini_endpoint(&client, &config, "Continue", history).await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Continue", history.into()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Response considering history");
@@ -814,9 +925,15 @@ async fn test_gemini_api_url_normalization() {
         base_url: format!("{}/v1beta", mock_server.uri()),
         token: "test-gemini-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
 }
@@ -861,9 +978,15 @@ async fn test_gemini_api_uses_header_not_query_param() {
         base_url: mock_server.uri(),
         token: "secure-api-key".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
 }
@@ -906,9 +1029,15 @@ async fn test_claude_api_replaces_tool_names() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_ok());
     let text = result.unwrap();
@@ -942,9 +1071,15 @@ async fn test_claude_api_rate_limit_429() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
     };
 
-    let result = call_claude_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     let err = result.unwrap_err().to_string();
@@ -972,9 +1107,15 @@ async fn test_openai_api_server_error_500() {
         base_url: mock_server.uri(),
         token: "test-openai-token".to_string(),
         model: "gpt-4o".to_string(),
+        ..Default::default()
     };
 
-    let result = call_openai_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     let err = result.unwrap_err().to_string();
@@ -997,9 +1138,15 @@ async fn test_gemini_api_invalid_json_response() {
         base_url: mock_server.uri(),
         token: "test-gemini-token".to_string(),
         model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
     };
 
-    let result = call_gemini_endpoint(&client, &config, "Test prompt", "").await;
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
 
     assert!(result.is_err());
     assert!(result
@@ -1008,6 +1155,47 @@ async fn test_gemini_api_invalid_json_response() {
         .contains("Failed to parse Gemini response"));
 }
 
+#[tokio::test]
+async fn test_claude_api_renders_custom_template() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let content = body["messages"][0]["content"].as_str().unwrap();
+            assert!(content.starts_with("House style for demo (English): Add login"));
+            assert!(content.contains("Context: src/auth.rs"));
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "Enhanced"}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
+    };
+    let input = EnhanceInput {
+        search_context: Some("src/auth.rs".to_string()),
+        project_name: Some("demo".to_string()),
+        template: EnhanceTemplate::parse(
+            "House style for {project_name} ({language}): {original_prompt}\nContext: {search_context}",
+        )
+        .unwrap(),
+        ..EnhanceInput::new("Add login", ConversationHistory::default())
+    };
+
+    let result = call_claude_endpoint(&client, &config, &input).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
 // ============================================================================
 // Codex API Tests
 // ============================================================================
@@ -1049,12 +1237,619 @@ async fn test_codex_api_uses_output_text_for_assistant_history() {
         base_url: mock_server.uri(),
         token: "test-token".to_string(),
         model: "gpt-5.3-codex".to_string(),
+        ..Default::default()
     };
 
     let history = "User: Check the startup flow.
 Assistant: OK, I will inspect it.";
-    let result = call_codex_endpoint(&client, &config, "Test prompt", history).await;
+    let result = call_codex_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", history.into()),
+    )
+    .await;
 
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "Enhanced prompt for testing");
 }
+
+// ============================================================================
+// Project Guidelines (System Prompt) Tests
+// ============================================================================
+
+fn input_with_guidelines() -> EnhanceInput {
+    EnhanceInput {
+        guidelines: ProjectGuidelines {
+            workspace: "# AGENTS.md\nRun cargo fmt.".to_string(),
+            ..Default::default()
+        },
+        ..EnhanceInput::new("Test prompt", ConversationHistory::default())
+    }
+}
+
+fn assert_guidelines_prompt(system: &str) {
+    assert!(system
+        .contains("<workspace_guidelines>\n# AGENTS.md\nRun cargo fmt.\n</workspace_guidelines>"));
+}
+
+#[tokio::test]
+async fn test_claude_api_sends_guidelines_as_system() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_guidelines_prompt(body["system"].as_str().unwrap());
+            assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "Enhanced"}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
+    };
+
+    let result = call_claude_endpoint(&client, &config, &input_with_guidelines()).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_claude_api_omits_system_without_guidelines() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert!(body.get("system").is_none());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "Enhanced"}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
+    };
+
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_openai_api_sends_guidelines_as_system_message() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let messages = body["messages"].as_array().unwrap();
+            assert_eq!(messages[0]["role"], "system");
+            assert_guidelines_prompt(messages[0]["content"].as_str().unwrap());
+            assert_eq!(messages[1]["role"], "user");
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "Enhanced"}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "gpt-4o".to_string(),
+        ..Default::default()
+    };
+
+    let result = call_openai_endpoint(&client, &config, &input_with_guidelines()).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_gemini_api_sends_guidelines_as_system_instruction() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash-exp:generateContent"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_guidelines_prompt(
+                body["systemInstruction"]["parts"][0]["text"]
+                    .as_str()
+                    .unwrap(),
+            );
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "candidates": [{"content": {"parts": [{"text": "Enhanced"}], "role": "model"}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "gemini-2.0-flash-exp".to_string(),
+        ..Default::default()
+    };
+
+    let result = call_gemini_endpoint(&client, &config, &input_with_guidelines()).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_codex_api_sends_guidelines_as_instructions() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_guidelines_prompt(body["instructions"].as_str().unwrap());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "output": [{
+                    "type": "message",
+                    "content": [{"type": "output_text", "text": "Enhanced"}]
+                }]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "gpt-5.3-codex".to_string(),
+        ..Default::default()
+    };
+
+    let result = call_codex_endpoint(&client, &config, &input_with_guidelines()).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+// ============================================================================
+// Generation Settings Tests
+// ============================================================================
+
+fn tuned_config(base_url: String, model: &str) -> ThirdPartyConfig {
+    ThirdPartyConfig {
+        base_url,
+        token: "test-token".to_string(),
+        model: model.to_string(),
+        generation: GenerationSettings {
+            max_tokens: Some(1000),
+            temperature: Some(0.3),
+            top_p: Some(0.9),
+            reasoning_effort: Some("low".to_string()),
+            system_prompt: Some("You are a prompt engineer.".to_string()),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_claude_api_maps_generation_settings() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["system"], "You are a prompt engineer.");
+            assert_eq!(body["thinking"]["type"], "enabled");
+            assert_eq!(body["thinking"]["budget_tokens"], 2048);
+            assert_eq!(body["max_tokens"], 3048);
+            assert!(body.get("temperature").is_none());
+            assert!(body.get("top_p").is_none());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [
+                    {"type": "thinking", "thinking": "..."},
+                    {"type": "text", "text": "Enhanced"}
+                ]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let mut config = tuned_config(mock_server.uri(), "claude-sonnet-4-20250514");
+    // Claude rejects custom sampling together with thinking
+    config.generation.temperature = None;
+    config.generation.top_p = None;
+
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_claude_api_sends_sampling_without_thinking() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert!(body.get("thinking").is_none());
+            assert_eq!(body["max_tokens"], 1000);
+            assert!((body["temperature"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+            assert!((body["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "Enhanced"}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let mut config = tuned_config(mock_server.uri(), "claude-sonnet-4-20250514");
+    config.generation.reasoning_effort = None;
+
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_openai_api_maps_generation_settings() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["messages"][0]["role"], "system");
+            assert_eq!(body["messages"][0]["content"], "You are a prompt engineer.");
+            assert_eq!(body["max_tokens"], 1000);
+            assert_eq!(body["reasoning_effort"], "low");
+            assert!(body["temperature"].is_number());
+            assert!(body["top_p"].is_number());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "Enhanced"}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = tuned_config(mock_server.uri(), "gpt-4o");
+
+    let result = call_openai_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_gemini_api_maps_generation_settings() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash-exp:generateContent"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(
+                body["systemInstruction"]["parts"][0]["text"],
+                "You are a prompt engineer."
+            );
+            let generation = &body["generationConfig"];
+            // The thinking budget is added on top of max_tokens
+            assert_eq!(generation["maxOutputTokens"], 3048);
+            assert_eq!(generation["thinkingConfig"]["thinkingBudget"], 2048);
+            assert!(generation["temperature"].is_number());
+            assert!(generation["topP"].is_number());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "candidates": [{"content": {"parts": [{"text": "Enhanced"}], "role": "model"}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = tuned_config(mock_server.uri(), "gemini-2.0-flash-exp");
+
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_gemini_api_reserves_high_thinking_budget() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let generation = &body["generationConfig"];
+            assert_eq!(generation["thinkingConfig"]["thinkingBudget"], 16384);
+            assert_eq!(generation["maxOutputTokens"], DEFAULT_MAX_TOKENS + 16384);
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "candidates": [{"content": {"parts": [{"text": "Enhanced"}], "role": "model"}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "gemini-2.5-pro".to_string(),
+        generation: GenerationSettings {
+            reasoning_effort: Some("high".to_string()),
+            ..Default::default()
+        },
+    };
+
+    let result = call_gemini_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_codex_api_maps_generation_settings() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["instructions"], "You are a prompt engineer.");
+            assert_eq!(body["max_output_tokens"], 1000);
+            assert_eq!(body["reasoning"]["effort"], "low");
+            assert!(body["temperature"].is_number());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "output": [{
+                    "type": "message",
+                    "content": [{"type": "output_text", "text": "Enhanced"}]
+                }]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = tuned_config(mock_server.uri(), "gpt-5.3-codex");
+
+    let result = call_codex_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("Test prompt", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_chinese_prompt_language_hint_in_system_prompt() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert!(body["system"].as_str().unwrap().contains("请用中文回复"));
+            let content = body["messages"][0]["content"].as_str().unwrap();
+            assert!(!content.ends_with("请用中文回复。"));
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "增强后的提示词"}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
+    };
+
+    let result = call_claude_endpoint(
+        &client,
+        &config,
+        &EnhanceInput::new("添加登录功能", ConversationHistory::default()),
+    )
+    .await;
+    assert_eq!(result.unwrap(), "增强后的提示词");
+}
+
+// ============================================================================
+// Structured Conversation History Tests
+// ============================================================================
+
+fn structured_history() -> ConversationHistory {
+    serde_json::from_str(
+        r#"[
+            {"role": "system", "content": "Be terse"},
+            {"role": "user", "content": "Why does this fail?"},
+            {"role": "assistant", "content": "```\nUser: admin\n```"},
+            {"role": "user", "content": "Fix it"}
+        ]"#,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_claude_api_maps_structured_history_to_alternating_turns() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let messages = body["messages"].as_array().unwrap();
+            let roles: Vec<&str> = messages
+                .iter()
+                .map(|m| m["role"].as_str().unwrap())
+                .collect();
+
+            assert_eq!(roles, vec!["user", "assistant", "user"]);
+            assert_eq!(
+                messages[0]["content"],
+                "System: Be terse\n\nWhy does this fail?"
+            );
+            assert_eq!(messages[1]["content"], "```\nUser: admin\n```");
+            let last = messages[2]["content"].as_str().unwrap();
+            assert!(last.starts_with("Fix it\n\n"));
+            assert!(last.contains("Test prompt"));
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{"type": "text", "text": "Enhanced"}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "claude-sonnet-4-20250514".to_string(),
+        ..Default::default()
+    };
+
+    let input = EnhanceInput::new("Test prompt", structured_history());
+    let result = call_claude_endpoint(&client, &config, &input).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_gemini_api_maps_structured_history_to_model_role() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let roles: Vec<&str> = body["contents"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c["role"].as_str().unwrap())
+                .collect();
+            assert_eq!(roles, vec!["user", "model", "user"]);
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "candidates": [{"content": {"parts": [{"text": "Enhanced"}]}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "gemini-2.5-flash".to_string(),
+        ..Default::default()
+    };
+
+    let input = EnhanceInput::new("Test prompt", structured_history());
+    let result = call_gemini_endpoint(&client, &config, &input).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
+
+#[tokio::test]
+async fn test_openai_api_keeps_structured_history_roles() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(|request: &wiremock::Request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let messages = body["messages"].as_array().unwrap();
+            let roles: Vec<&str> = messages
+                .iter()
+                .map(|m| m["role"].as_str().unwrap())
+                .collect();
+
+            assert_eq!(roles, vec!["system", "user", "assistant", "user", "user"]);
+            assert_eq!(messages[0]["content"], "Be terse");
+            assert_eq!(messages[2]["content"], "```\nUser: admin\n```");
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{"message": {"role": "assistant", "content": "Enhanced"}}]
+            }))
+        })
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_test_client();
+    let config = ThirdPartyConfig {
+        base_url: mock_server.uri(),
+        token: "test-token".to_string(),
+        model: "gpt-4o".to_string(),
+        ..Default::default()
+    };
+
+    let input = EnhanceInput::new("Test prompt", structured_history());
+    let result = call_openai_endpoint(&client, &config, &input).await;
+    assert_eq!(result.unwrap(), "Enhanced");
+}
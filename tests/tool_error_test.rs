@@ -0,0 +1,101 @@
+//! Tests for typed tool errors
+
+use std::time::Duration;
+
+use ace_tool::tools::ToolError;
+use anyhow::Context;
+use tokio::net::TcpListener;
+
+#[test]
+fn test_codes_and_retryable() {
+    let cases = [
+        (
+            ToolError::InvalidArguments(String::new()),
+            "invalid_arguments",
+            false,
+        ),
+        (
+            ToolError::PathNotFound(String::new()),
+            "path_not_found",
+            false,
+        ),
+        (ToolError::IndexFailed(String::new()), "index_failed", true),
+        (ToolError::AuthFailed(String::new()), "auth_failed", false),
+        (
+            ToolError::UpstreamTimeout(String::new()),
+            "upstream_timeout",
+            true,
+        ),
+        (ToolError::Upstream(String::new()), "upstream_error", true),
+        (ToolError::Cancelled(String::new()), "cancelled", false),
+        (ToolError::Internal(String::new()), "internal_error", false),
+    ];
+    for (error, code, retryable) in cases {
+        assert_eq!(error.code(), code);
+        assert_eq!(error.retryable(), retryable, "{}", code);
+        assert_eq!(error.meta()["error"]["code"], code);
+        assert_eq!(error.meta()["error"]["retryable"], retryable);
+    }
+}
+
+#[test]
+fn test_classify_finds_tool_error_under_context() {
+    let err = anyhow::Error::from(ToolError::AuthFailed(
+        "Token invalid or expired".to_string(),
+    ))
+    .context("Enhancement failed");
+    let classified = ToolError::classify(&err, ToolError::Internal);
+    assert_eq!(
+        classified,
+        ToolError::AuthFailed("Enhancement failed".to_string())
+    );
+}
+
+#[test]
+fn test_classify_falls_back_for_untyped_errors() {
+    let err = anyhow::anyhow!("Failed to parse response");
+    assert_eq!(
+        ToolError::classify(&err, ToolError::Upstream),
+        ToolError::Upstream("Failed to parse response".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_timed_out_requests_are_upstream_timeouts() {
+    // Accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut open = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            open.push(stream);
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let request_error = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap_err();
+
+    let wrapped = ToolError::request_failed("Search request failed", &request_error);
+    let classified = ToolError::classify(&wrapped, ToolError::Internal);
+    assert_eq!(classified.code(), "upstream_timeout");
+    assert!(classified
+        .to_string()
+        .starts_with("Search request failed: "));
+
+    // Untyped errors keep the timeout in their source chain
+    let untyped = Err::<(), _>(request_error)
+        .context("Request failed")
+        .unwrap_err();
+    assert_eq!(
+        ToolError::classify(&untyped, ToolError::Internal).code(),
+        "upstream_timeout"
+    );
+}
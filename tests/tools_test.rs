@@ -7,6 +7,7 @@ use ace_tool::config::{Config, ConfigOptions};
 use ace_tool::tools::search_context::{
     SearchContextArgs, SearchContextTool, SearchContextToolDef, ToolResult, SEARCH_CONTEXT_TOOL,
 };
+use ace_tool::tools::ToolError;
 
 fn create_test_config() -> Arc<Config> {
     Config::new(
@@ -60,10 +61,13 @@ fn test_search_context_args_serialization() {
 
 #[test]
 fn test_tool_result() {
-    let result = ToolResult {
-        text: "Found some code".to_string(),
-    };
+    let result = ToolResult::success("Found some code".to_string());
     assert_eq!(result.text, "Found some code");
+    assert!(result.error.is_none());
+
+    let result = ToolResult::failure(ToolError::Upstream("Search failed".to_string()));
+    assert_eq!(result.text, "Error: Search failed");
+    assert_eq!(result.error.unwrap().code(), "upstream_error");
 }
 
 #[test]
@@ -85,6 +89,7 @@ async fn test_execute_missing_query() {
     let result = tool.execute(args).await;
     assert!(result.text.contains("Error"));
     assert!(result.text.contains("query is required"));
+    assert!(matches!(result.error, Some(ToolError::InvalidArguments(_))));
 }
 
 #[tokio::test]
@@ -145,6 +150,7 @@ async fn test_execute_nonexistent_path() {
     let result = tool.execute(args).await;
     assert!(result.text.contains("Error"));
     assert!(result.text.contains("does not exist"));
+    assert!(matches!(result.error, Some(ToolError::PathNotFound(_))));
 }
 
 #[tokio::test]
@@ -164,4 +170,5 @@ async fn test_execute_path_is_file_not_directory() {
     let result = tool.execute(args).await;
     assert!(result.text.contains("Error"));
     assert!(result.text.contains("not a directory"));
+    assert!(matches!(result.error, Some(ToolError::PathNotFound(_))));
 }
@@ -0,0 +1,301 @@
+//! Tests for the upload allowlist/denylist and the upload audit log
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use ace_tool::commands;
+use ace_tool::config::{Config, ConfigOptions, UserConfigFile};
+use ace_tool::index::upload_policy::{UploadAuditLog, UPLOAD_AUDIT_LOG_FILE_NAME};
+use ace_tool::index::{
+    dry_run, Blob, IndexManager, SkipReason, UploadAuditSettings, UploadPolicy,
+    UploadPolicySettings,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn policy(allow: &[&str], deny: &[&str]) -> UploadPolicySettings {
+    UploadPolicySettings {
+        allow: allow.iter().map(|p| p.to_string()).collect(),
+        deny: deny.iter().map(|p| p.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn create_config(upload_policy: UploadPolicySettings) -> Arc<Config> {
+    Config::new(
+        "https://api.example.com".to_string(),
+        "test-token".to_string(),
+        ConfigOptions {
+            upload_policy: Some(upload_policy),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+fn create_project() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("src/internal")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/internal/billing.rs"), "pub fn bill() {}\n").unwrap();
+    fs::write(root.join("docs/guide.md"), "# Guide\n").unwrap();
+    fs::write(root.join("notes.txt"), "notes\n").unwrap();
+    temp_dir
+}
+
+fn read_records(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+// ============================================================================
+// Policy
+// ============================================================================
+
+#[test]
+fn test_denylist_wins_over_allowlist() {
+    let policy = UploadPolicy::new(&policy(&["src/"], &["internal/"]), Path::new("")).unwrap();
+
+    assert_eq!(policy.check("src/main.rs"), None);
+    assert_eq!(
+        policy.check("src/internal/billing.rs"),
+        Some(SkipReason::UploadDenied {
+            pattern: "internal/".to_string()
+        })
+    );
+    assert_eq!(
+        policy.check("docs/guide.md"),
+        Some(SkipReason::NotAllowlisted)
+    );
+}
+
+#[test]
+fn test_empty_policy_allows_everything() {
+    let policy = UploadPolicy::new(&UploadPolicySettings::default(), Path::new("")).unwrap();
+    assert_eq!(policy.check("any/path.rs"), None);
+}
+
+#[test]
+fn test_invalid_settings_are_rejected() {
+    let err = UploadPolicy::new(&policy(&[], &["src/[z-a].rs"]), Path::new(""))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("Invalid upload deny pattern"), "{}", err);
+
+    let mut settings = UploadPolicySettings::default();
+    settings.audit.max_bytes = 0;
+    assert!(UploadPolicy::new(&settings, Path::new("")).is_err());
+}
+
+#[test]
+fn test_settings_from_user_config_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.json");
+    fs::write(
+        &path,
+        r#"{
+            "secrets": { "policy": "skip" },
+            "upload": {
+                "allow": ["src/"],
+                "deny": ["*.pem"],
+                "audit": { "max_files": 2 }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let file = UserConfigFile::from_file(&path).unwrap();
+    let settings = file.upload;
+    assert_eq!(settings.allow, vec!["src/"]);
+    assert_eq!(settings.deny, vec!["*.pem"]);
+    assert!(settings.audit.enabled);
+    assert_eq!(settings.audit.max_files, 2);
+    // Other sections are read from the same file
+    assert_eq!(file.secrets.policy, ace_tool::index::SecretPolicy::Skip);
+
+    fs::write(&path, r#"{ "upload": { "allowed": [] } }"#).unwrap();
+    assert!(UserConfigFile::from_file(&path).is_err());
+
+    fs::write(&path, r#"{ "upload": { "audit": { "max_bytes": 0 } } }"#).unwrap();
+    let err = UserConfigFile::from_file(&path).unwrap_err();
+    assert!(format!("{:#}", err).contains("max_bytes"), "{:#}", err);
+}
+
+// ============================================================================
+// Indexing
+// ============================================================================
+
+#[test]
+fn test_collect_files_applies_policy() {
+    let project = create_project();
+    let manager = IndexManager::new(
+        create_config(policy(&["src/", "docs/"], &["internal/"])),
+        project.path().to_path_buf(),
+    )
+    .unwrap();
+
+    let mut paths: Vec<String> = manager
+        .collect_files()
+        .unwrap()
+        .into_iter()
+        .map(|b| b.path)
+        .collect();
+    paths.sort();
+    assert_eq!(paths, vec!["docs/guide.md", "src/main.rs"]);
+}
+
+#[tokio::test]
+async fn test_index_project_refuses_when_nothing_is_allowed() {
+    let project = create_project();
+    let manager = IndexManager::new(
+        create_config(policy(&["vendor/"], &[])),
+        project.path().to_path_buf(),
+    )
+    .unwrap();
+
+    let result = manager.index_project().await;
+    assert_eq!(result.status, "error");
+    assert_eq!(result.message, "No files allowed by the upload policy");
+    assert!(!project.path().join(".ace-tool/index.bin").exists());
+}
+
+#[test]
+fn test_ls_files_and_dry_run_report_policy_reasons() {
+    let project = create_project();
+    let mut config = Config::new_for_third_party_enhancer();
+    Arc::make_mut(&mut config).upload_policy = policy(&["src/"], &["internal/"]);
+
+    let report = commands::ls_files(&config, project.path()).unwrap();
+    let reason = |path: &str| {
+        report
+            .files
+            .iter()
+            .find(|e| e.path == path)
+            .and_then(|e| e.skipped.as_ref())
+            .map(|r| r.code())
+    };
+    assert_eq!(reason("src/main.rs"), None);
+    assert_eq!(reason("src/internal/billing.rs"), Some("upload_denied"));
+    assert_eq!(reason("docs/guide.md"), Some("not_allowlisted"));
+    assert_eq!(report.included, 1);
+
+    let entries = dry_run(&config, project.path()).unwrap();
+    let included: Vec<&str> = entries
+        .iter()
+        .filter(|e| e.included)
+        .map(|e| e.path.as_str())
+        .collect();
+    assert_eq!(included, vec!["src/main.rs"]);
+    let denied = entries
+        .iter()
+        .find(|e| e.path == "src/internal/billing.rs")
+        .unwrap();
+    assert_eq!(
+        denied.skipped.as_ref().unwrap().to_string(),
+        "denied by upload policy 'internal/'"
+    );
+}
+
+// ============================================================================
+// Audit log
+// ============================================================================
+
+#[test]
+fn test_audit_log_records_each_blob() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join(UPLOAD_AUDIT_LOG_FILE_NAME);
+    let log = UploadAuditLog::new(path.clone(), &UploadAuditSettings::default()).unwrap();
+
+    let blobs = vec![
+        Blob {
+            path: "src/main.rs".to_string(),
+            content: "fn main() {}".to_string(),
+        },
+        Blob {
+            path: "src/lib.rs#chunk1of2".to_string(),
+            content: "pub mod a;".to_string(),
+        },
+    ];
+    log.record("https://api.example.com", "req-1", &blobs);
+
+    let records = read_records(&path);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["path"], "src/main.rs");
+    assert_eq!(
+        records[0]["blob"],
+        IndexManager::calculate_blob_name("src/main.rs", "fn main() {}")
+    );
+    assert_eq!(records[0]["base_url"], "https://api.example.com");
+    assert_eq!(records[0]["request_id"], "req-1");
+    assert!(records[0]["timestamp"].is_string());
+    assert_eq!(records[1]["path"], "src/lib.rs#chunk1of2");
+    // File content never reaches the log
+    assert!(!fs::read_to_string(&path).unwrap().contains("fn main"));
+}
+
+#[test]
+fn test_audit_log_rotates_by_size() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join(UPLOAD_AUDIT_LOG_FILE_NAME);
+    let settings = UploadAuditSettings {
+        enabled: true,
+        max_bytes: 300,
+        max_files: 2,
+    };
+    let log = UploadAuditLog::new(path.clone(), &settings).unwrap();
+    let blob = |n: usize| Blob {
+        path: format!("src/file{}.rs", n),
+        content: String::new(),
+    };
+
+    for n in 0..4 {
+        log.record("https://api.example.com", &format!("req-{}", n), &[blob(n)]);
+    }
+
+    let rotated = |n: usize| {
+        temp_dir
+            .path()
+            .join(format!("{}.{}", UPLOAD_AUDIT_LOG_FILE_NAME, n))
+    };
+    assert_eq!(read_records(&path)[0]["request_id"], "req-3");
+    assert_eq!(read_records(&rotated(1))[0]["request_id"], "req-2");
+    assert_eq!(read_records(&rotated(2))[0]["request_id"], "req-1");
+    assert!(!rotated(3).exists());
+}
+
+#[test]
+fn test_audit_log_can_be_disabled() {
+    let settings = UploadAuditSettings {
+        enabled: false,
+        ..Default::default()
+    };
+    assert!(UploadAuditLog::new("audit.log".into(), &settings).is_none());
+}
+
+#[test]
+fn test_clean_keeps_audit_logs() {
+    let project = create_project();
+    let ace_dir = project.path().join(".ace-tool");
+    fs::create_dir_all(&ace_dir).unwrap();
+    fs::write(ace_dir.join("index.bin"), "").unwrap();
+    fs::write(ace_dir.join(UPLOAD_AUDIT_LOG_FILE_NAME), "{}\n").unwrap();
+    fs::write(
+        ace_dir.join(format!("{}.1", UPLOAD_AUDIT_LOG_FILE_NAME)),
+        "{}\n",
+    )
+    .unwrap();
+
+    let report = commands::clean(project.path(), false).unwrap();
+    assert_eq!(report.removed, vec![".ace-tool/index.bin"]);
+    assert_eq!(report.kept.len(), 2);
+
+    commands::clean(project.path(), true).unwrap();
+    assert!(!ace_dir.join(UPLOAD_AUDIT_LOG_FILE_NAME).exists());
+    assert_eq!(fs::read_dir(&ace_dir).unwrap().count(), 1);
+}